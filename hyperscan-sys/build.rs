@@ -3,40 +3,67 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, bail, Context, Result};
 
-fn find_hyperscan() -> Result<PathBuf> {
-    cargo_emit::rerun_if_env_changed!("HYPERSCAN_ROOT");
+#[cfg_attr(feature = "gen", allow(dead_code))]
+#[path = "build/bindings.rs"]
+mod bindings;
+#[path = "build/cxx.rs"]
+mod cxx;
+#[path = "build/paths.rs"]
+mod paths;
+#[cfg_attr(feature = "vendored", allow(dead_code))]
+#[path = "build/pcre.rs"]
+mod pcre;
+#[path = "build/version.rs"]
+mod version;
+
+fn env_path(name: &str) -> Option<PathBuf> {
+    cargo_emit::rerun_if_env_changed!(name);
+
+    env::var_os(name).map(PathBuf::from)
+}
 
+#[cfg_attr(feature = "vendored", allow(dead_code))]
+fn find_hyperscan() -> Result<PathBuf> {
     let link_kind = if cfg!(feature = "static") { "static" } else { "dylib" };
     let static_libstd = cfg!(feature = "contained");
 
-    if let Ok(prefix) = env::var("HYPERSCAN_ROOT") {
-        let prefix = Path::new(&prefix);
-        let inc_path = prefix.join("include/hs");
-        let link_path = prefix.join("lib");
+    let multiarch = paths::multiarch(
+        &env::var("CARGO_CFG_TARGET_ARCH")?,
+        &env::var("CARGO_CFG_TARGET_OS")?,
+        &env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default(),
+        &env::var("CARGO_CFG_TARGET_ENDIAN")?,
+    );
+    let paths_env = paths::Env {
+        root: env_path("HYPERSCAN_ROOT"),
+        include_dir: env_path("HYPERSCAN_INCLUDE_DIR"),
+        library_dir: env_path("HYPERSCAN_LIBRARY_DIR"),
+    };
 
-        if cfg!(feature = "tracing") {
-            cargo_emit::warning!("use HYPERSCAN_ROOT = {}", prefix.display());
-        }
+    if !paths_env.is_empty() {
+        if let Some(ref prefix) = paths_env.root {
+            if cfg!(feature = "tracing") {
+                cargo_emit::warning!("use HYPERSCAN_ROOT = {}", prefix.display());
+            }
 
-        if !prefix.exists() || !prefix.is_dir() {
-            bail!("HYPERSCAN_ROOT should point to a directory that exists.");
+            if !prefix.is_dir() {
+                bail!("HYPERSCAN_ROOT should point to a directory that exists.");
+            }
         }
 
-        if link_path.exists() && link_path.is_dir() {
-            cargo_emit::rustc_link_search!(link_path.to_string_lossy() => "native");
-        } else {
-            bail!("`$HYPERSCAN_ROOT/lib` subdirectory not found.");
-        }
+        let paths::Layout {
+            include_dir: inc_path,
+            library_dir: link_path,
+        } = paths::resolve(&paths_env, multiarch.as_deref(), Path::is_dir)?;
+        let prefix = paths_env.root.unwrap_or_default();
 
         cargo_emit::rustc_link_search!(link_path.to_string_lossy() => "native");
 
-        if cfg!(feature = "static") {
-            let target_os = std::env::var("CARGO_CFG_TARGET_OS").unwrap();
-            let std_link = if target_os == "macos" { "c++" } else { "stdc++" };
-            if static_libstd {
-                cargo_emit::rustc_link_lib!(std_link => "static:-bundle");
-            } else {
-                cargo_emit::rustc_link_lib!(std_link);
+        if cfg!(feature = "chimera") {
+            cargo_emit::rustc_link_lib!("chimera" => "static");
+
+            // Chimera built with the PCRE source tree installs the static PCRE library next to it.
+            if !link_pcre(link_kind, multiarch.as_deref())? {
+                cargo_emit::rustc_link_lib!("pcre" => "static");
             }
         }
 
@@ -46,9 +73,9 @@ fn find_hyperscan() -> Result<PathBuf> {
             cargo_emit::rustc_link_lib!("hs" => link_kind);
         }
 
-        if cfg!(feature = "chimera") {
-            cargo_emit::rustc_link_lib!("chimera" => "static");
-            cargo_emit::rustc_link_lib!("pcre" => "static");
+        // The C++ runtime must follow the static libraries which depend on it.
+        if cfg!(feature = "static") {
+            link_cxx_runtime(static_libstd)?;
         }
 
         if cfg!(feature = "tracing") {
@@ -61,14 +88,26 @@ fn find_hyperscan() -> Result<PathBuf> {
             );
         }
 
+        Ok(inc_path)
+    } else if let Some(inc_path) = find_vcpkg() {
         Ok(inc_path)
     } else {
+        // `libhs.pc` always links `libhs`, the runtime library is linked manually for the runtime-only build.
+        let runtime_only = !cfg!(feature = "compile") && cfg!(feature = "runtime");
         let libhs = pkg_config::Config::new()
             .statik(cfg!(feature = "static"))
-            .cargo_metadata(true)
+            .cargo_metadata(!runtime_only)
             .env_metadata(true)
             .probe("libhs")?;
 
+        if runtime_only {
+            for link_path in &libhs.link_paths {
+                cargo_emit::rustc_link_search!(link_path.to_string_lossy() => "native");
+            }
+
+            cargo_emit::rustc_link_lib!("hs_runtime" => link_kind);
+        }
+
         if cfg!(feature = "tracing") {
             cargo_emit::warning!(
                 "building with Hyperscan {} with {} library, libs={:?}, link_paths={:?}, include_paths={:?}",
@@ -97,6 +136,8 @@ fn find_hyperscan() -> Result<PathBuf> {
                     libch.include_paths
                 );
             }
+
+            link_pcre(link_kind, multiarch.as_deref())?;
         }
 
         libhs
@@ -107,7 +148,379 @@ fn find_hyperscan() -> Result<PathBuf> {
     }
 }
 
-#[cfg(any(feature = "gen", not(target_pointer_width = "64")))]
+/// Locate the PCRE 8.x library required by Chimera with `PCRE_ROOT` or `pkg-config`.
+///
+/// Returns `false` if PCRE was not found, the caller decides the fallback.
+#[cfg_attr(feature = "vendored", allow(dead_code))]
+fn link_pcre(link_kind: &str, multiarch: Option<&str>) -> Result<bool> {
+    if let Some(prefix) = env_path("PCRE_ROOT") {
+        if cfg!(feature = "tracing") {
+            cargo_emit::warning!("use PCRE_ROOT = {}", prefix.display());
+        }
+
+        if !prefix.is_dir() {
+            bail!("PCRE_ROOT should point to a directory that exists.");
+        }
+
+        let header = prefix.join("include").join("pcre.h");
+
+        cargo_emit::rerun_if_changed!(header.to_string_lossy());
+
+        let version = std::fs::read_to_string(&header)
+            .ok()
+            .and_then(|content| pcre::parse_header(&content))
+            .ok_or_else(|| {
+                anyhow!(
+                    "PCRE 8.x header not found @ {}, Chimera doesn't support PCRE2",
+                    header.display()
+                )
+            })?;
+
+        pcre::check(version).map_err(anyhow::Error::msg)?;
+
+        let candidates = paths::library_dirs(&prefix, multiarch);
+        let link_path = candidates
+            .iter()
+            .find(|dir| dir.is_dir())
+            .ok_or_else(|| anyhow!("PCRE library directory not found, tried {:?}", candidates))?;
+
+        cargo_emit::rustc_link_search!(link_path.to_string_lossy() => "native");
+        cargo_emit::rustc_link_lib!("pcre" => link_kind);
+
+        if cfg!(feature = "tracing") {
+            cargo_emit::warning!(
+                "building with PCRE {}.{} with {} library @ {:?}",
+                version.0,
+                version.1,
+                link_kind,
+                link_path
+            );
+        }
+
+        return Ok(true);
+    }
+
+    match pkg_config::Config::new()
+        .statik(cfg!(feature = "static"))
+        .cargo_metadata(true)
+        .env_metadata(true)
+        .probe("libpcre")
+    {
+        Ok(libpcre) => {
+            let version = pcre::parse_version(&libpcre.version)
+                .ok_or_else(|| anyhow!("unknown PCRE version `{}`", libpcre.version))?;
+
+            pcre::check(version).map_err(anyhow::Error::msg)?;
+
+            if cfg!(feature = "tracing") {
+                cargo_emit::warning!(
+                    "building with PCRE {} with {} library, libs={:?}, link_paths={:?}",
+                    libpcre.version,
+                    link_kind,
+                    libpcre.libs,
+                    libpcre.link_paths
+                );
+            }
+
+            Ok(true)
+        }
+        Err(err) => {
+            if cfg!(feature = "tracing") {
+                cargo_emit::warning!("PCRE not found by pkg-config, {}", err);
+            }
+
+            Ok(false)
+        }
+    }
+}
+
+/// Probe Hyperscan installed by `vcpkg`, which emits the link lines.
+#[cfg(windows)]
+fn find_vcpkg() -> Option<PathBuf> {
+    if !is_msvc() {
+        return None;
+    }
+
+    match vcpkg::Config::new().find_package("hyperscan") {
+        Ok(lib) => {
+            if cfg!(feature = "tracing") {
+                cargo_emit::warning!(
+                    "building with Hyperscan from vcpkg, libs={:?}, link_paths={:?}, include_paths={:?}",
+                    lib.found_names,
+                    lib.link_paths,
+                    lib.include_paths
+                );
+            }
+
+            lib.include_paths.first().map(|path| path.join("hs"))
+        }
+        Err(err) => {
+            if cfg!(feature = "tracing") {
+                cargo_emit::warning!("Hyperscan not found by vcpkg, {}", err);
+            }
+
+            None
+        }
+    }
+}
+
+#[cfg(not(windows))]
+fn find_vcpkg() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(any(windows, feature = "gen"))]
+fn is_msvc() -> bool {
+    env::var("CARGO_CFG_TARGET_ENV").is_ok_and(|target_env| target_env == "msvc")
+}
+
+fn link_cxx_runtime(contained: bool) -> Result<()> {
+    cargo_emit::rerun_if_env_changed!("HYPERSCAN_CXX_RUNTIME");
+
+    let os = env::var("CARGO_CFG_TARGET_OS")?;
+    let target_env = env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
+    let features = env::var("CARGO_CFG_TARGET_FEATURE").unwrap_or_default();
+    let overridden = env::var("HYPERSCAN_CXX_RUNTIME").ok();
+
+    let decision = cxx::decide(
+        cxx::Target {
+            os: &os,
+            env: &target_env,
+            features: &features,
+        },
+        overridden.as_deref(),
+        contained,
+    )
+    .map_err(anyhow::Error::msg)?;
+
+    cargo_emit::warning!("{}", decision);
+
+    match (decision.runtime.lib_name(), decision.kind) {
+        (Some(name), Some(kind)) => cargo_emit::rustc_link_lib!(name => kind),
+        (Some(name), None) => cargo_emit::rustc_link_lib!(name),
+        (None, _) => {}
+    }
+
+    Ok(())
+}
+
+/// Build the pinned Hyperscan source tree with `cmake` and link it statically.
+///
+/// The sources are downloaded with `curl`, verified with `sha256sum` or `shasum` and unpacked with `tar`,
+/// and the Hyperscan build itself runs `ragel` and `python`.
+#[cfg(feature = "vendored")]
+mod vendored {
+    use std::env;
+    use std::path::{Path, PathBuf};
+    use std::process::Command;
+
+    use anyhow::{bail, Context, Result};
+
+    const HYPERSCAN_VERSION: &str = "5.4.2";
+    const BOOST_VERSION: &str = "1.76.0";
+    const PCRE_VERSION: &str = "8.45";
+
+    // the SHA-256 of the pinned tarballs, checked before unpacking them
+    const HYPERSCAN_SHA256: &str = "32b0f24b3113bbc46b6bfaa05cf7cf45840b6b59333d078cc1f624e4c40b2b99";
+    const BOOST_SHA256: &str = "7bd7ddceec1a1dfdcbdb3e609b60d01739c38390a5f956385a12f3122049f0ca";
+    const PCRE_SHA256: &str = "4e6ce03e0336e8b4a3d6c2b70b1c5e18590a5673a98186da90d4f33c23defc09";
+
+    /// Returns the source directory from the environment variable,
+    /// the git submodule, or downloads, verifies and unpacks the pinned tarball.
+    fn source_dir(var: &str, submodule: &str, url: &str, sha256: &str, out_dir: &Path) -> Result<PathBuf> {
+        cargo_emit::rerun_if_env_changed!(var);
+
+        if let Ok(dir) = env::var(var) {
+            let dir = PathBuf::from(dir);
+
+            if !dir.is_dir() {
+                bail!("{} should point to a directory that exists.", var);
+            }
+
+            return Ok(dir);
+        }
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join(submodule);
+
+        if dir.join("CMakeLists.txt").is_file() || dir.join("boost").is_dir() {
+            return Ok(dir);
+        }
+
+        let dir = out_dir.join(submodule);
+
+        if !dir.is_dir() {
+            let tarball = out_dir.join(format!("{}.tar.gz", submodule));
+
+            if cfg!(feature = "tracing") {
+                cargo_emit::warning!("downloading {} to {}", url, tarball.display());
+            }
+
+            run(Command::new("curl").arg("-fsSL").arg("-o").arg(&tarball).arg(url))
+                .with_context(|| format!("download {}", url))?;
+
+            verify(&tarball, sha256)?;
+
+            std::fs::create_dir_all(&dir)?;
+
+            run(Command::new("tar")
+                .arg("xzf")
+                .arg(&tarball)
+                .arg("--strip-components=1")
+                .arg("-C")
+                .arg(&dir))
+            .with_context(|| format!("unpack {}", tarball.display()))?;
+        }
+
+        Ok(dir)
+    }
+
+    /// Check the SHA-256 of a downloaded tarball, which is removed when it doesn't match.
+    fn verify(tarball: &Path, sha256: &str) -> Result<()> {
+        let output = Command::new("sha256sum")
+            .arg(tarball)
+            .output()
+            .or_else(|_| Command::new("shasum").arg("-a").arg("256").arg(tarball).output())
+            .with_context(|| format!("compute the SHA-256 of {}", tarball.display()))?;
+
+        if !output.status.success() {
+            bail!(
+                "computing the SHA-256 of {} failed with {}",
+                tarball.display(),
+                output.status
+            );
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let actual = stdout.split_whitespace().next().unwrap_or_default();
+
+        if !actual.eq_ignore_ascii_case(sha256) {
+            let _ = std::fs::remove_file(tarball);
+
+            bail!("{} has the SHA-256 {}, expected {}", tarball.display(), actual, sha256);
+        }
+
+        Ok(())
+    }
+
+    fn run(cmd: &mut Command) -> Result<()> {
+        let status = cmd.status().with_context(|| format!("run {:?}", cmd))?;
+
+        if !status.success() {
+            bail!("{:?} failed with {}", cmd, status);
+        }
+
+        Ok(())
+    }
+
+    /// Build Hyperscan (and Chimera with PCRE) from source, returns the include directory.
+    pub fn build() -> Result<PathBuf> {
+        let out_dir = PathBuf::from(env::var("OUT_DIR")?);
+
+        let src_dir = source_dir(
+            "HYPERSCAN_SOURCE_DIR",
+            "hyperscan",
+            &format!(
+                "https://github.com/intel/hyperscan/archive/refs/tags/v{}.tar.gz",
+                HYPERSCAN_VERSION
+            ),
+            HYPERSCAN_SHA256,
+            &out_dir,
+        )?;
+
+        // Hyperscan only requires the header-only part of Boost.
+        cargo_emit::rerun_if_env_changed!("BOOST_ROOT");
+
+        let boost_root = match env::var("BOOST_ROOT") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => source_dir(
+                "BOOST_SOURCE_DIR",
+                "boost",
+                &format!(
+                    "https://archives.boost.io/release/{0}/source/boost_{1}.tar.gz",
+                    BOOST_VERSION,
+                    BOOST_VERSION.replace('.', "_")
+                ),
+                BOOST_SHA256,
+                &out_dir,
+            )?,
+        };
+
+        let mut cfg = cmake::Config::new(&src_dir);
+
+        cfg.define("BUILD_STATIC_LIBS", "ON")
+            .define("BUILD_SHARED_LIBS", "OFF")
+            .define("BUILD_EXAMPLES", "OFF")
+            .define("BUILD_BENCHMARKS", "OFF")
+            .define("BUILD_UNIT", "OFF")
+            .define("CMAKE_POSITION_INDEPENDENT_CODE", "ON")
+            .define("BOOST_ROOT", &boost_root)
+            .define("CMAKE_INSTALL_LIBDIR", "lib");
+
+        if env::var("CARGO_CFG_TARGET_OS")? == "linux" {
+            cfg.define("FAT_RUNTIME", "ON");
+        }
+
+        if cfg!(feature = "chimera") {
+            let pcre_dir = source_dir(
+                "PCRE_SOURCE_DIR",
+                "pcre",
+                &format!(
+                    "https://sourceforge.net/projects/pcre/files/pcre/{0}/pcre-{0}.tar.gz/download",
+                    PCRE_VERSION
+                ),
+                PCRE_SHA256,
+                &out_dir,
+            )?;
+
+            cfg.define("PCRE_SOURCE", &pcre_dir);
+        }
+
+        // `cmake` passes `NUM_JOBS` through as the parallel level of the build.
+        let dst = cfg.build();
+        let link_path = dst.join("lib");
+
+        if cfg!(feature = "tracing") {
+            cargo_emit::warning!(
+                "building with vendored Hyperscan {} @ {}, boost @ {}",
+                HYPERSCAN_VERSION,
+                dst.display(),
+                boost_root.display()
+            );
+        }
+
+        cargo_emit::rustc_link_search!(link_path.to_string_lossy() => "native");
+
+        if cfg!(feature = "chimera") {
+            // Chimera is built with the PCRE source tree, its static library is left in the build directory.
+            let pcre_path = dst.join("build/lib");
+
+            cargo_emit::rustc_link_search!(pcre_path.to_string_lossy() => "native");
+            cargo_emit::rustc_link_lib!("chimera" => "static");
+            cargo_emit::rustc_link_lib!("pcre" => "static");
+        }
+
+        if !cfg!(feature = "compile") && cfg!(feature = "runtime") {
+            cargo_emit::rustc_link_lib!("hs_runtime" => "static");
+        } else {
+            cargo_emit::rustc_link_lib!("hs" => "static");
+        }
+
+        super::link_cxx_runtime(cfg!(feature = "contained"))?;
+
+        Ok(dst.join("include/hs"))
+    }
+}
+
+#[cfg(feature = "gen")]
+fn clang_args() -> &'static [&'static str] {
+    if is_msvc() {
+        &["-x", "c++", "-std=c++14", "-fms-compatibility", "-fms-extensions"]
+    } else {
+        &["-x", "c++", "-std=c++11"]
+    }
+}
+
+#[cfg(feature = "gen")]
 fn generate_binding(inc_dir: &Path, out_dir: &Path) -> Result<()> {
     let out_file = out_dir.join("hyperscan.rs");
     let inc_file = inc_dir.join("hs.h");
@@ -123,12 +536,13 @@ fn generate_binding(inc_dir: &Path, out_dir: &Path) -> Result<()> {
         .header(inc_file)
         .use_core()
         .ctypes_prefix("::libc")
-        .clang_args(&["-x", "c++", "-std=c++11"])
+        .clang_args(clang_args())
         .allowlist_var("^HS_.*")
         .allowlist_type("^hs_.*")
         .allowlist_function("^hs_.*")
         .blocklist_type("^__darwin_.*")
         .size_t_is_usize(true)
+        .layout_tests(false)
         .derive_copy(true)
         .derive_debug(true)
         .derive_default(true)
@@ -140,14 +554,30 @@ fn generate_binding(inc_dir: &Path, out_dir: &Path) -> Result<()> {
         .with_context(|| "write wrapper")
 }
 
-#[cfg(all(not(feature = "gen"), target_pointer_width = "64"))]
+#[cfg(not(feature = "gen"))]
 fn generate_binding(_: &Path, out_dir: &Path) -> Result<()> {
-    std::fs::copy("src/hyperscan.rs", out_dir.join("hyperscan.rs"))
+    copy_binding("hyperscan.rs", out_dir)
+}
+
+#[cfg_attr(feature = "gen", allow(dead_code))]
+fn copy_binding(name: &str, out_dir: &Path) -> Result<()> {
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH")?;
+    let target_os = env::var("CARGO_CFG_TARGET_OS")?;
+
+    if !bindings::is_pregenerated(&target_arch, &target_os) {
+        bail!(
+            "no pregenerated binding file for `{}-{}` target, please enable the `gen` feature",
+            target_arch,
+            target_os
+        );
+    }
+
+    std::fs::copy(Path::new("src").join(name), out_dir.join(name))
         .map(|_| ())
         .with_context(|| "copy binding file")
 }
 
-#[cfg(all(feature = "chimera", any(feature = "gen", not(target_pointer_width = "64"))))]
+#[cfg(all(feature = "chimera", feature = "gen"))]
 fn generate_chimera_binding(inc_dir: &Path, out_dir: &Path) -> Result<()> {
     let out_file = out_dir.join("chimera.rs");
     let inc_file = inc_dir.join("ch.h");
@@ -163,12 +593,13 @@ fn generate_chimera_binding(inc_dir: &Path, out_dir: &Path) -> Result<()> {
         .header(inc_file)
         .use_core()
         .ctypes_prefix("::libc")
-        .clang_args(&["-x", "c++", "-std=c++11"])
+        .clang_args(clang_args())
         .allowlist_var("^CH_.*")
         .allowlist_type("^ch_.*")
         .allowlist_function("^ch_.*")
         .blocklist_type("^__darwin_.*")
         .size_t_is_usize(true)
+        .layout_tests(false)
         .derive_copy(true)
         .derive_debug(true)
         .derive_default(true)
@@ -180,23 +611,33 @@ fn generate_chimera_binding(inc_dir: &Path, out_dir: &Path) -> Result<()> {
         .with_context(|| "write wrapper")
 }
 
-#[cfg(all(not(feature = "gen"), feature = "chimera", target_pointer_width = "64"))]
+#[cfg(all(feature = "chimera", not(feature = "gen")))]
 fn generate_chimera_binding(_: &Path, out_dir: &Path) -> Result<()> {
-    std::fs::copy("src/chimera.rs", out_dir.join("chimera.rs"))
-        .map(|_| ())
-        .with_context(|| "copy binding file")
+    copy_binding("chimera.rs", out_dir)
 }
 
-#[cfg(all(not(feature = "chimera"), target_pointer_width = "64"))]
+#[cfg(not(feature = "chimera"))]
 fn generate_chimera_binding(_: &Path, _: &Path) -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "vendored")]
+fn locate_hyperscan() -> Result<PathBuf> {
+    vendored::build().with_context(|| {
+        "build the vendored hyperscan, which requires cmake, curl, tar, sha256sum or shasum, ragel and python"
+    })
+}
+
+#[cfg(not(feature = "vendored"))]
+fn locate_hyperscan() -> Result<PathBuf> {
+    find_hyperscan().with_context(|| "please download and install hyperscan from https://www.hyperscan.io/")
+}
+
 fn main() -> Result<()> {
     let inc_dir = if std::env::var("DOCS_RS").is_ok() {
         PathBuf::new()
     } else {
-        find_hyperscan().with_context(|| "please download and install hyperscan from https://www.hyperscan.io/")?
+        locate_hyperscan()?
     };
     let out_dir = env::var("OUT_DIR")?;
     let out_dir = Path::new(&out_dir);
@@ -207,5 +648,78 @@ fn main() -> Result<()> {
         generate_chimera_binding(&inc_dir, out_dir)?;
     }
 
-    Ok(())
+    // docs.rs has no Hyperscan installed, the pregenerated binding of the latest version is documented
+    let version = if std::env::var("DOCS_RS").is_ok() {
+        version::LATEST
+    } else {
+        detect_version(&inc_dir)?
+    };
+
+    emit_version(version);
+    write_build_info(version, &inc_dir, out_dir)
+}
+
+/// Detect the version from the `hs.h` header, instead of the pregenerated binding file.
+///
+/// The version is recorded in the build information and selects the version `cfg`s,
+/// so a header without a version is an error rather than assumed to be the latest one.
+fn detect_version(inc_dir: &Path) -> Result<(u32, u32, u32)> {
+    let header = inc_dir.join("hs.h");
+    let content =
+        std::fs::read_to_string(&header).with_context(|| format!("read Hyperscan header {}", header.display()))?;
+
+    cargo_emit::rerun_if_changed!(header.to_string_lossy());
+
+    version::parse_header(&content)
+        .with_context(|| format!("HS_MAJOR, HS_MINOR and HS_PATCH not found in {}", header.display()))
+}
+
+/// Emit the version `cfg` for this crate, and the version metadata for the dependent crates,
+/// which is available as `DEP_HS_VERSION`, `DEP_HS_VERSION_CFGS` and `DEP_HS_ALL_VERSION_CFGS`.
+fn emit_version(version: (u32, u32, u32)) {
+    let (major, minor, patch) = version;
+    let all_cfgs = version::VERSION_CFGS.iter().map(|&(_, cfg)| cfg).collect::<Vec<_>>();
+    let cfgs = version::cfgs(version).collect::<Vec<_>>();
+
+    for cfg in &all_cfgs {
+        println!("cargo:rustc-check-cfg=cfg({})", cfg);
+    }
+    for cfg in &cfgs {
+        cargo_emit::rustc_cfg!("{}", cfg);
+    }
+
+    println!("cargo:version={}.{}.{}", major, minor, patch);
+    println!("cargo:version_cfgs={}", cfgs.join(","));
+    println!("cargo:all_version_cfgs={}", all_cfgs.join(","));
+
+    if cfg!(feature = "tracing") {
+        cargo_emit::warning!("detected Hyperscan {}.{}.{}, cfgs={:?}", major, minor, patch, cfgs);
+    }
+}
+
+/// Generate the `build_info.rs` file, which describes how the library was built and linked.
+fn write_build_info(version: (u32, u32, u32), inc_dir: &Path, out_dir: &Path) -> Result<()> {
+    let (major, minor, patch) = version;
+    let link_kind = if cfg!(feature = "static") { "static" } else { "dylib" };
+
+    std::fs::write(
+        out_dir.join("build_info.rs"),
+        format!(
+            "/// The `(major, minor, patch)` version of the Hyperscan headers detected at build time.\n\
+             pub const HS_DETECTED_VERSION: (u32, u32, u32) = ({}, {}, {});\n\
+             /// How the Hyperscan library was linked, `static` or `dylib`.\n\
+             pub const HS_LINK_KIND: &str = {:?};\n\
+             /// Whether the Chimera library was linked.\n\
+             pub const HS_CHIMERA: bool = {};\n\
+             /// The directory of the Hyperscan headers used at build time.\n\
+             pub const HS_INCLUDE_DIR: &str = {:?};\n",
+            major,
+            minor,
+            patch,
+            link_kind,
+            cfg!(feature = "chimera"),
+            inc_dir.to_string_lossy()
+        ),
+    )
+    .with_context(|| "write build info file")
 }
@@ -0,0 +1,37 @@
+//! Select the pregenerated binding files for the target.
+
+/// The targets which the pregenerated binding files in `src/` were verified against.
+///
+/// The binding files only use `libc` fixed size integers and pointers, with `size_t` mapped to `usize`,
+/// so one file serves every target listed here; `src/layout.rs` checks the struct layouts at compile time.
+pub const PREGENERATED_TARGETS: &[(&str, &[&str])] = &[
+    ("x86_64", &["linux", "macos", "windows", "freebsd"]),
+    ("aarch64", &["linux", "macos"]),
+    ("arm", &["linux"]),
+    ("powerpc64", &["linux"]),
+];
+
+/// Returns `true` if the pregenerated binding files could be used for the target.
+pub fn is_pregenerated(arch: &str, os: &str) -> bool {
+    PREGENERATED_TARGETS
+        .iter()
+        .any(|&(target_arch, target_os)| target_arch == arch && target_os.contains(&os))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pregenerated_targets() {
+        assert!(is_pregenerated("x86_64", "linux"));
+        assert!(is_pregenerated("x86_64", "windows"));
+        assert!(is_pregenerated("aarch64", "macos"));
+        assert!(is_pregenerated("arm", "linux"));
+        assert!(is_pregenerated("powerpc64", "linux"));
+
+        assert!(!is_pregenerated("aarch64", "windows"));
+        assert!(!is_pregenerated("x86", "linux"));
+        assert!(!is_pregenerated("riscv64", "linux"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Decide the C++ runtime library linked with the static Hyperscan library.
+//!
+//! Shared by the build script and `tests/build_script.rs`, so it must only depend on `std`.
+
+use std::fmt;
+
+/// The C++ runtime library.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Runtime {
+    /// GNU `libstdc++`
+    StdCxx,
+    /// LLVM `libc++`
+    LibCxx,
+    /// Don't link any C++ runtime library.
+    None,
+}
+
+impl Runtime {
+    /// The name of the library to link, `None` for `Runtime::None`.
+    pub fn lib_name(self) -> Option<&'static str> {
+        match self {
+            Runtime::StdCxx => Some("stdc++"),
+            Runtime::LibCxx => Some("c++"),
+            Runtime::None => None,
+        }
+    }
+}
+
+/// How the C++ runtime library should be linked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Decision {
+    /// The C++ runtime library.
+    pub runtime: Runtime,
+    /// The link kind, e.g. `static` or `static:-bundle`, `None` to link it dynamically.
+    pub kind: Option<&'static str>,
+    /// Why the runtime library was chosen.
+    pub reason: String,
+}
+
+impl fmt::Display for Decision {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.runtime.lib_name(), self.kind) {
+            (Some(name), Some(kind)) => write!(f, "linking C++ runtime `{}={}`", kind, name)?,
+            (Some(name), None) => write!(f, "linking C++ runtime `{}`", name)?,
+            (None, _) => f.write_str("not linking any C++ runtime")?,
+        }
+
+        write!(f, ", {}", self.reason)
+    }
+}
+
+/// The target to link.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Target<'a> {
+    /// `CARGO_CFG_TARGET_OS`
+    pub os: &'a str,
+    /// `CARGO_CFG_TARGET_ENV`
+    pub env: &'a str,
+    /// `CARGO_CFG_TARGET_FEATURE`, e.g. `crt-static,fxsr,sse`
+    pub features: &'a str,
+}
+
+/// Decide the C++ runtime library for the target.
+///
+/// The `HYPERSCAN_CXX_RUNTIME` environment variable (`stdc++`, `c++` or `none`) overrides the default,
+/// which is `libc++` on macOS and BSD, none for MSVC, and `libstdc++` on the others.
+/// The runtime is linked statically when the CRT is static (`+crt-static`) or `contained` is enabled.
+pub fn decide(target: Target<'_>, overridden: Option<&str>, contained: bool) -> Result<Decision, String> {
+    let (runtime, reason) = match overridden.map(str::trim) {
+        Some("stdc++") | Some("libstdc++") => (Runtime::StdCxx, "set by HYPERSCAN_CXX_RUNTIME".to_owned()),
+        Some("c++") | Some("libc++") => (Runtime::LibCxx, "set by HYPERSCAN_CXX_RUNTIME".to_owned()),
+        Some("none") | Some("") => (Runtime::None, "set by HYPERSCAN_CXX_RUNTIME".to_owned()),
+        Some(s) => {
+            return Err(format!(
+                "invalid HYPERSCAN_CXX_RUNTIME `{}`, expected `stdc++`, `c++` or `none`",
+                s
+            ))
+        }
+        None if target.env == "msvc" => (
+            Runtime::None,
+            "MSVC links the C++ runtime along with the CRT".to_owned(),
+        ),
+        None => match target.os {
+            "macos" | "ios" | "freebsd" | "openbsd" | "netbsd" | "dragonfly" => (
+                Runtime::LibCxx,
+                format!("libc++ is the default C++ runtime on {}", target.os),
+            ),
+            os => (
+                Runtime::StdCxx,
+                format!("libstdc++ is the default C++ runtime on {}", os),
+            ),
+        },
+    };
+
+    let crt_static = target.features.split(',').any(|feature| feature == "crt-static");
+    let kind = if runtime == Runtime::None {
+        None
+    } else if crt_static && target.env != "msvc" {
+        Some("static")
+    } else if contained {
+        Some("static:-bundle")
+    } else {
+        None
+    };
+    let reason = if kind == Some("static") {
+        reason + ", and the CRT is statically linked"
+    } else {
+        reason
+    };
+
+    Ok(Decision { runtime, kind, reason })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINUX: Target<'static> = Target {
+        os: "linux",
+        env: "gnu",
+        features: "fxsr,sse,sse2",
+    };
+    const MUSL: Target<'static> = Target {
+        os: "linux",
+        env: "musl",
+        features: "crt-static,fxsr,sse,sse2",
+    };
+    const MACOS: Target<'static> = Target {
+        os: "macos",
+        env: "",
+        features: "fxsr,sse,sse2",
+    };
+    const FREEBSD: Target<'static> = Target {
+        os: "freebsd",
+        env: "",
+        features: "",
+    };
+    const MSVC: Target<'static> = Target {
+        os: "windows",
+        env: "msvc",
+        features: "crt-static",
+    };
+
+    fn runtime(target: Target<'_>, overridden: Option<&str>, contained: bool) -> (Runtime, Option<&'static str>) {
+        let decision = decide(target, overridden, contained).unwrap();
+
+        (decision.runtime, decision.kind)
+    }
+
+    #[test]
+    fn test_default_runtime() {
+        assert_eq!(runtime(LINUX, None, false), (Runtime::StdCxx, None));
+        assert_eq!(runtime(LINUX, None, true), (Runtime::StdCxx, Some("static:-bundle")));
+        assert_eq!(runtime(MUSL, None, false), (Runtime::StdCxx, Some("static")));
+        assert_eq!(runtime(MUSL, None, true), (Runtime::StdCxx, Some("static")));
+        assert_eq!(runtime(MACOS, None, false), (Runtime::LibCxx, None));
+        assert_eq!(runtime(MACOS, None, true), (Runtime::LibCxx, Some("static:-bundle")));
+        assert_eq!(runtime(FREEBSD, None, false), (Runtime::LibCxx, None));
+        assert_eq!(runtime(MSVC, None, false), (Runtime::None, None));
+        assert_eq!(runtime(MSVC, None, true), (Runtime::None, None));
+    }
+
+    #[test]
+    fn test_overridden_runtime() {
+        assert_eq!(runtime(LINUX, Some("c++"), false), (Runtime::LibCxx, None));
+        assert_eq!(runtime(LINUX, Some("none"), true), (Runtime::None, None));
+        assert_eq!(runtime(MACOS, Some("stdc++"), false), (Runtime::StdCxx, None));
+        assert_eq!(runtime(MUSL, Some("libc++"), false), (Runtime::LibCxx, Some("static")));
+        assert_eq!(runtime(MSVC, Some("stdc++"), false), (Runtime::StdCxx, None));
+
+        assert_eq!(
+            decide(LINUX, Some("gnustl"), false).unwrap_err(),
+            "invalid HYPERSCAN_CXX_RUNTIME `gnustl`, expected `stdc++`, `c++` or `none`"
+        );
+    }
+
+    #[test]
+    fn test_decision_message() {
+        assert_eq!(
+            decide(LINUX, None, false).unwrap().to_string(),
+            "linking C++ runtime `stdc++`, libstdc++ is the default C++ runtime on linux"
+        );
+        assert_eq!(
+            decide(MUSL, None, false).unwrap().to_string(),
+            "linking C++ runtime `static=stdc++`, libstdc++ is the default C++ runtime on linux, \
+             and the CRT is statically linked"
+        );
+        assert_eq!(
+            decide(MACOS, Some("none"), false).unwrap().to_string(),
+            "not linking any C++ runtime, set by HYPERSCAN_CXX_RUNTIME"
+        );
+    }
+}
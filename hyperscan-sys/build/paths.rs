@@ -0,0 +1,286 @@
+//! Resolve the Hyperscan installation directories from the environment variables.
+//!
+//! Shared by the build script and `tests/build_script.rs`, so it must only depend on `std`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// The directories of a Hyperscan installation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Layout {
+    /// The directory contains `hs.h`.
+    pub include_dir: PathBuf,
+    /// The directory contains `libhs` and `libhs_runtime`.
+    pub library_dir: PathBuf,
+}
+
+/// The environment variables to locate Hyperscan.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Env {
+    /// `HYPERSCAN_ROOT`, the installation prefix.
+    pub root: Option<PathBuf>,
+    /// `HYPERSCAN_INCLUDE_DIR`, overrides the include directory derived from the prefix.
+    pub include_dir: Option<PathBuf>,
+    /// `HYPERSCAN_LIBRARY_DIR`, overrides the library directory derived from the prefix.
+    pub library_dir: Option<PathBuf>,
+}
+
+impl Env {
+    /// Returns `true` if none of the variables is set.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none() && self.include_dir.is_none() && self.library_dir.is_none()
+    }
+}
+
+/// The directory was not found, with all the directories that were tried.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NotFound {
+    /// The kind of directory, e.g. `include` or `library`.
+    pub kind: &'static str,
+    /// The directories that were tried.
+    pub tried: Vec<PathBuf>,
+}
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hyperscan {} directory not found, tried", self.kind)?;
+
+        if self.tried.is_empty() {
+            f.write_str(" nothing, please set HYPERSCAN_ROOT")
+        } else {
+            for (i, dir) in self.tried.iter().enumerate() {
+                write!(f, "{} `{}`", if i == 0 { "" } else { "," }, dir.display())?;
+            }
+
+            Ok(())
+        }
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// The Debian multiarch tuple of the target, e.g. `x86_64-linux-gnu`.
+pub fn multiarch(target_arch: &str, target_os: &str, target_env: &str, target_endian: &str) -> Option<String> {
+    if target_os != "linux" {
+        return None;
+    }
+
+    let arch = match target_arch {
+        "x86" => "i386",
+        "powerpc64" if target_endian == "little" => "powerpc64le",
+        arch => arch,
+    };
+    let env = if target_env.is_empty() { "gnu" } else { target_env };
+
+    Some(format!("{}-linux-{}", arch, env))
+}
+
+/// The library directories of the prefix, `lib`, `lib64` and the multiarch directory (e.g. `lib/x86_64-linux-gnu`).
+pub fn library_dirs(root: &Path, multiarch: Option<&str>) -> Vec<PathBuf> {
+    let lib = root.join("lib");
+
+    vec![lib.clone(), root.join("lib64")]
+        .into_iter()
+        .chain(multiarch.map(|tuple| lib.join(tuple)))
+        .collect()
+}
+
+/// Resolve the Hyperscan directories, `is_dir` checks whether a directory exists.
+///
+/// The `HYPERSCAN_INCLUDE_DIR` and `HYPERSCAN_LIBRARY_DIR` overrides take precedence,
+/// otherwise the directories are searched in the `HYPERSCAN_ROOT` prefix,
+/// the libraries in `lib`, `lib64` and the multiarch directory (e.g. `lib/x86_64-linux-gnu`).
+pub fn resolve<F>(env: &Env, multiarch: Option<&str>, is_dir: F) -> Result<Layout, NotFound>
+where
+    F: Fn(&Path) -> bool,
+{
+    let find = |kind: &'static str, dir: &Option<PathBuf>, candidates: Vec<PathBuf>| {
+        let candidates = match dir {
+            Some(dir) => vec![dir.clone()],
+            None => candidates,
+        };
+
+        candidates.iter().find(|dir| is_dir(dir)).cloned().ok_or(NotFound {
+            kind,
+            tried: candidates,
+        })
+    };
+
+    let include_candidates = env.root.iter().map(|root| root.join("include").join("hs")).collect();
+    let library_candidates = env.root.iter().flat_map(|root| library_dirs(root, multiarch)).collect();
+
+    Ok(Layout {
+        include_dir: find("include", &env.include_dir, include_candidates)?,
+        library_dir: find("library", &env.library_dir, library_candidates)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    fn dirs(dirs: &[&str]) -> impl Fn(&Path) -> bool {
+        let dirs = dirs.iter().map(PathBuf::from).collect::<HashSet<_>>();
+
+        move |dir| dirs.contains(dir)
+    }
+
+    #[test]
+    fn test_multiarch() {
+        assert_eq!(
+            multiarch("x86_64", "linux", "gnu", "little").unwrap(),
+            "x86_64-linux-gnu"
+        );
+        assert_eq!(
+            multiarch("aarch64", "linux", "gnu", "little").unwrap(),
+            "aarch64-linux-gnu"
+        );
+        assert_eq!(multiarch("x86", "linux", "gnu", "little").unwrap(), "i386-linux-gnu");
+        assert_eq!(
+            multiarch("powerpc64", "linux", "gnu", "little").unwrap(),
+            "powerpc64le-linux-gnu"
+        );
+        assert_eq!(
+            multiarch("x86_64", "linux", "musl", "little").unwrap(),
+            "x86_64-linux-musl"
+        );
+        assert_eq!(multiarch("x86_64", "macos", "", "little"), None);
+    }
+
+    #[test]
+    fn test_resolve_root() {
+        let env = Env {
+            root: Some("/opt/hs".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolve(&env, None, dirs(&["/opt/hs/include/hs", "/opt/hs/lib"])).unwrap(),
+            Layout {
+                include_dir: "/opt/hs/include/hs".into(),
+                library_dir: "/opt/hs/lib".into(),
+            }
+        );
+        assert_eq!(
+            resolve(&env, None, dirs(&["/opt/hs/include/hs", "/opt/hs/lib64"]))
+                .unwrap()
+                .library_dir,
+            PathBuf::from("/opt/hs/lib64")
+        );
+        assert_eq!(
+            resolve(
+                &env,
+                Some("x86_64-linux-gnu"),
+                dirs(&["/opt/hs/include/hs", "/opt/hs/lib/x86_64-linux-gnu"])
+            )
+            .unwrap()
+            .library_dir,
+            PathBuf::from("/opt/hs/lib/x86_64-linux-gnu")
+        );
+    }
+
+    #[test]
+    fn test_resolve_overrides() {
+        let env = Env {
+            root: Some("/opt/hs".into()),
+            include_dir: Some("/usr/local/opt/hyperscan/include/hs".into()),
+            library_dir: Some("/custom/lib".into()),
+        };
+        let exists = dirs(&[
+            "/opt/hs/include/hs",
+            "/opt/hs/lib",
+            "/usr/local/opt/hyperscan/include/hs",
+            "/custom/lib",
+        ]);
+
+        assert_eq!(
+            resolve(&env, None, exists).unwrap(),
+            Layout {
+                include_dir: "/usr/local/opt/hyperscan/include/hs".into(),
+                library_dir: "/custom/lib".into(),
+            }
+        );
+
+        let env = Env {
+            root: Some("/opt/hs".into()),
+            library_dir: Some("/custom/lib".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolve(&env, None, dirs(&["/opt/hs/include/hs", "/custom/lib"])).unwrap(),
+            Layout {
+                include_dir: "/opt/hs/include/hs".into(),
+                library_dir: "/custom/lib".into(),
+            }
+        );
+
+        let env = Env {
+            include_dir: Some("/inc".into()),
+            library_dir: Some("/lib".into()),
+            ..Default::default()
+        };
+
+        assert!(resolve(&env, None, dirs(&["/inc", "/lib"])).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_not_found() {
+        let env = Env {
+            root: Some("/opt/hs".into()),
+            ..Default::default()
+        };
+
+        let err = resolve(&env, Some("x86_64-linux-gnu"), dirs(&["/opt/hs/include/hs"])).unwrap_err();
+
+        assert_eq!(
+            err,
+            NotFound {
+                kind: "library",
+                tried: vec![
+                    "/opt/hs/lib".into(),
+                    "/opt/hs/lib64".into(),
+                    "/opt/hs/lib/x86_64-linux-gnu".into(),
+                ],
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Hyperscan library directory not found, tried `/opt/hs/lib`, `/opt/hs/lib64`, \
+             `/opt/hs/lib/x86_64-linux-gnu`"
+        );
+
+        let env = Env {
+            include_dir: Some("/inc".into()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            resolve(&env, None, dirs(&["/inc"])).unwrap_err().to_string(),
+            "Hyperscan library directory not found, tried nothing, please set HYPERSCAN_ROOT"
+        );
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_resolve_windows_paths() {
+        let env = Env {
+            root: Some(r"C:\vcpkg\installed\x64-windows".into()),
+            ..Default::default()
+        };
+
+        let layout = resolve(&env, None, |dir| {
+            dir == Path::new(r"C:\vcpkg\installed\x64-windows\include\hs")
+                || dir == Path::new(r"C:\vcpkg\installed\x64-windows\lib")
+        })
+        .unwrap();
+
+        assert_eq!(
+            layout.include_dir,
+            PathBuf::from(r"C:\vcpkg\installed\x64-windows\include\hs")
+        );
+        assert_eq!(layout.library_dir, PathBuf::from(r"C:\vcpkg\installed\x64-windows\lib"));
+    }
+}
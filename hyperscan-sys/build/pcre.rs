@@ -0,0 +1,99 @@
+//! Check the PCRE library required by Chimera.
+//!
+//! Shared by the build script and `tests/build_script.rs`, so it must only depend on `std`.
+
+/// The minimum PCRE version supported by Chimera.
+pub const MIN_VERSION: (u32, u32) = (8, 41);
+
+/// Parse the `PCRE_MAJOR` and `PCRE_MINOR` macros of the `pcre.h` header.
+pub fn parse_header(header: &str) -> Option<(u32, u32)> {
+    let define = |name: &str| {
+        header.lines().find_map(|line| {
+            let mut tokens = line.split_whitespace();
+
+            match (tokens.next(), tokens.next(), tokens.next()) {
+                (Some("#define"), Some(macro_name), Some(value)) if macro_name == name => value.parse().ok(),
+                _ => None,
+            }
+        })
+    };
+
+    Some((define("PCRE_MAJOR")?, define("PCRE_MINOR")?))
+}
+
+/// Parse the version reported by `pkg-config`, e.g. `8.45`.
+pub fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let mut parts = version.trim().splitn(3, '.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts
+        .next()?
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>()
+        .parse()
+        .ok()?;
+
+    Some((major, minor))
+}
+
+/// Check that the PCRE version is the 8.x series required by Chimera.
+pub fn check((major, minor): (u32, u32)) -> Result<(), String> {
+    if major >= 10 {
+        Err(format!(
+            "Chimera requires PCRE 8.x, but found PCRE2 {}.{}, which has an incompatible API; \
+             please install PCRE {}.{} or later in the 8.x series and set PCRE_ROOT",
+            major, minor, MIN_VERSION.0, MIN_VERSION.1
+        ))
+    } else if major != MIN_VERSION.0 || minor < MIN_VERSION.1 {
+        Err(format!(
+            "Chimera requires PCRE {}.{} or later in the 8.x series, but found PCRE {}.{}",
+            MIN_VERSION.0, MIN_VERSION.1, major, minor
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header(include_str!("../tests/fixtures/pcre-8.45/pcre.h")),
+            Some((8, 45))
+        );
+        assert_eq!(
+            parse_header(include_str!("../tests/fixtures/pcre2-10.42/pcre2.h")),
+            None
+        );
+        assert_eq!(parse_header("#define PCRE_MAJOR 8"), None);
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("8.45"), Some((8, 45)));
+        assert_eq!(parse_version("8.41\n"), Some((8, 41)));
+        assert_eq!(parse_version("10.42"), Some((10, 42)));
+        assert_eq!(parse_version("8.45-RC1"), Some((8, 45)));
+        assert_eq!(parse_version("8"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn test_check() {
+        assert!(check((8, 45)).is_ok());
+        assert!(check((8, 41)).is_ok());
+
+        assert_eq!(
+            check((8, 39)).unwrap_err(),
+            "Chimera requires PCRE 8.41 or later in the 8.x series, but found PCRE 8.39"
+        );
+        assert_eq!(
+            check((7, 8)).unwrap_err(),
+            "Chimera requires PCRE 8.41 or later in the 8.x series, but found PCRE 7.8"
+        );
+        assert!(check((10, 42)).unwrap_err().contains("found PCRE2 10.42"));
+    }
+}
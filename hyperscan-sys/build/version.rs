@@ -0,0 +1,84 @@
+//! Detect the Hyperscan version from the `hs.h` header.
+//!
+//! Shared by the build script and `tests/build_script.rs`, so it must only depend on `std`.
+
+/// The version assumed when the header is not available, e.g. building the documents.
+pub const LATEST: (u32, u32, u32) = (5, 4, 0);
+
+/// The `cfg` emitted when the detected version is greater than or equal to the minimum version.
+pub const VERSION_CFGS: &[((u32, u32), &str)] = &[
+    ((5, 0), "hs_version_gte_5_0"),
+    ((5, 1), "hs_version_gte_5_1"),
+    ((5, 2), "hs_version_gte_5_2"),
+    ((5, 3), "hs_version_gte_5_3"),
+    ((5, 4), "hs_version_gte_5_4"),
+];
+
+/// Parse the `HS_MAJOR`, `HS_MINOR` and `HS_PATCH` macros of the `hs.h` header.
+pub fn parse_header(header: &str) -> Option<(u32, u32, u32)> {
+    let define = |name: &str| {
+        header.lines().find_map(|line| {
+            let mut tokens = line.split_whitespace();
+
+            match (tokens.next(), tokens.next(), tokens.next()) {
+                (Some("#define"), Some(macro_name), Some(value)) if macro_name == name => value.parse().ok(),
+                _ => None,
+            }
+        })
+    };
+
+    Some((
+        define("HS_MAJOR")?,
+        define("HS_MINOR")?,
+        define("HS_PATCH").unwrap_or_default(),
+    ))
+}
+
+/// The `cfg` enabled for the version.
+pub fn cfgs(version: (u32, u32, u32)) -> impl Iterator<Item = &'static str> {
+    VERSION_CFGS
+        .iter()
+        .filter(move |&&(min, _)| (version.0, version.1) >= min)
+        .map(|&(_, cfg)| cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header(include_str!("../tests/fixtures/hs-4.7.0/hs.h")),
+            Some((4, 7, 0))
+        );
+        assert_eq!(
+            parse_header(include_str!("../tests/fixtures/hs-5.4.0/hs.h")),
+            Some((5, 4, 0))
+        );
+        assert_eq!(
+            parse_header("#define HS_MAJOR 5\n#define  HS_MINOR\t2"),
+            Some((5, 2, 0))
+        );
+        assert_eq!(parse_header("#define HS_MAJOR_X 5\n#define HS_MINOR 2"), None);
+        assert_eq!(parse_header(""), None);
+    }
+
+    #[test]
+    fn test_cfgs() {
+        let v4 = parse_header(include_str!("../tests/fixtures/hs-4.7.0/hs.h")).unwrap();
+        let v5 = parse_header(include_str!("../tests/fixtures/hs-5.4.0/hs.h")).unwrap();
+
+        assert_eq!(cfgs(v4).count(), 0);
+        assert!(!cfgs(v4).any(|cfg| cfg == "hs_version_gte_5_2"));
+        assert!(cfgs(v5).any(|cfg| cfg == "hs_version_gte_5_2"));
+        assert_eq!(
+            cfgs(v5).collect::<Vec<_>>(),
+            VERSION_CFGS.iter().map(|&(_, cfg)| cfg).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            cfgs((5, 2, 1)).collect::<Vec<_>>(),
+            vec!["hs_version_gte_5_0", "hs_version_gte_5_1", "hs_version_gte_5_2"]
+        );
+    }
+}
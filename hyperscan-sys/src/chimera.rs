@@ -86,61 +86,6 @@ pub struct hs_platform_info {
     #[doc = " Reserved for future use."]
     pub reserved2: ::libc::c_ulonglong,
 }
-#[test]
-fn bindgen_test_layout_hs_platform_info() {
-    const UNINIT: ::core::mem::MaybeUninit<hs_platform_info> = ::core::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::core::mem::size_of::<hs_platform_info>(),
-        32usize,
-        concat!("Size of: ", stringify!(hs_platform_info))
-    );
-    assert_eq!(
-        ::core::mem::align_of::<hs_platform_info>(),
-        8usize,
-        concat!("Alignment of ", stringify!(hs_platform_info))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).tune) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(tune)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).cpu_features) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(cpu_features)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).reserved1) as usize - ptr as usize },
-        16usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(reserved1)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).reserved2) as usize - ptr as usize },
-        24usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(reserved2)
-        )
-    );
-}
 #[doc = " A type containing information on the target platform which may optionally be\n provided to the compile calls (@ref hs_compile(), @ref hs_compile_multi(),\n @ref hs_compile_ext_multi()).\n\n A hs_platform_info structure may be populated for the current platform by\n using the @ref hs_populate_platform() call."]
 pub type hs_platform_info_t = hs_platform_info;
 #[doc = " A type containing error details that is returned by the compile calls (@ref\n ch_compile() and @ref ch_compile_multi() on failure. The caller may inspect\n the values returned in this type to determine the cause of failure."]
@@ -152,41 +97,6 @@ pub struct ch_compile_error {
     #[doc = " The zero-based number of the expression that caused the error (if this\n can be determined). If the error is not specific to an expression, then\n this value will be less than zero."]
     pub expression: ::libc::c_int,
 }
-#[test]
-fn bindgen_test_layout_ch_compile_error() {
-    const UNINIT: ::core::mem::MaybeUninit<ch_compile_error> = ::core::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::core::mem::size_of::<ch_compile_error>(),
-        16usize,
-        concat!("Size of: ", stringify!(ch_compile_error))
-    );
-    assert_eq!(
-        ::core::mem::align_of::<ch_compile_error>(),
-        8usize,
-        concat!("Alignment of ", stringify!(ch_compile_error))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).message) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(ch_compile_error),
-            "::",
-            stringify!(message)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).expression) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(ch_compile_error),
-            "::",
-            stringify!(expression)
-        )
-    );
-}
 impl Default for ch_compile_error {
     fn default() -> Self {
         let mut s = ::core::mem::MaybeUninit::<Self>::uninit();
@@ -263,36 +173,6 @@ pub struct ch_capture {
     #[doc = " offset at which this capture group ends."]
     pub to: ::libc::c_ulonglong,
 }
-#[test]
-fn bindgen_test_layout_ch_capture() {
-    const UNINIT: ::core::mem::MaybeUninit<ch_capture> = ::core::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::core::mem::size_of::<ch_capture>(),
-        24usize,
-        concat!("Size of: ", stringify!(ch_capture))
-    );
-    assert_eq!(
-        ::core::mem::align_of::<ch_capture>(),
-        8usize,
-        concat!("Alignment of ", stringify!(ch_capture))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).flags) as usize - ptr as usize },
-        0usize,
-        concat!("Offset of field: ", stringify!(ch_capture), "::", stringify!(flags))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).from) as usize - ptr as usize },
-        8usize,
-        concat!("Offset of field: ", stringify!(ch_capture), "::", stringify!(from))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).to) as usize - ptr as usize },
-        16usize,
-        concat!("Offset of field: ", stringify!(ch_capture), "::", stringify!(to))
-    );
-}
 #[doc = " Structure representing a captured subexpression within a match. An array of\n these structures corresponding to capture groups in order is passed to the\n callback on match, with active structures identified by the\n CH_CAPTURE_FLAG_ACTIVE flag."]
 pub type ch_capture_t = ch_capture;
 #[doc = " Definition of the match event callback function type.\n\n A callback function matching the defined type must be provided by the\n application calling the @ref ch_scan()\n\n This callback function will be invoked whenever a match is located in the\n target data during the execution of a scan. The details of the match are\n passed in as parameters to the callback function, and the callback function\n should return a value indicating whether or not matching should continue on\n the target data. If no callbacks are desired from a scan call, NULL may be\n provided in order to suppress match production.\n\n @param id\n      The ID number of the expression that matched. If the expression was a\n      single expression compiled with @ref ch_compile(), this value will be\n      zero.\n\n @param from\n      The offset of the first byte that matches the expression.\n\n @param to\n      The offset after the last byte that matches the expression.\n\n @param flags\n      This is provided for future use and is unused at present.\n\n @param size\n      The number of valid entries pointed to by the captured parameter.\n\n @param captured\n      A pointer to an array of @ref ch_capture_t structures that\n      contain the start and end offsets of entire pattern match and\n      each captured subexpression.\n\n @param ctx\n      The pointer supplied by the user to the @ref ch_scan() function.\n\n @return\n      The callback can return @ref CH_CALLBACK_TERMINATE to stop matching.\n      Otherwise, a return value of @ref CH_CALLBACK_CONTINUE will continue,\n      with the current pattern if configured to produce multiple matches per\n      pattern, while a return value of @ref CH_CALLBACK_SKIP_PATTERN will\n      cease matching this pattern but continue matching the next pattern."]
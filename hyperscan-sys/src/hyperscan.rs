@@ -161,41 +161,6 @@ pub struct hs_compile_error {
     #[doc = " The zero-based number of the expression that caused the error (if this\n can be determined). If the error is not specific to an expression, then\n this value will be less than zero."]
     pub expression: ::libc::c_int,
 }
-#[test]
-fn bindgen_test_layout_hs_compile_error() {
-    const UNINIT: ::core::mem::MaybeUninit<hs_compile_error> = ::core::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::core::mem::size_of::<hs_compile_error>(),
-        16usize,
-        concat!("Size of: ", stringify!(hs_compile_error))
-    );
-    assert_eq!(
-        ::core::mem::align_of::<hs_compile_error>(),
-        8usize,
-        concat!("Alignment of ", stringify!(hs_compile_error))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).message) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_compile_error),
-            "::",
-            stringify!(message)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).expression) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_compile_error),
-            "::",
-            stringify!(expression)
-        )
-    );
-}
 impl Default for hs_compile_error {
     fn default() -> Self {
         let mut s = ::core::mem::MaybeUninit::<Self>::uninit();
@@ -220,61 +185,6 @@ pub struct hs_platform_info {
     #[doc = " Reserved for future use."]
     pub reserved2: ::libc::c_ulonglong,
 }
-#[test]
-fn bindgen_test_layout_hs_platform_info() {
-    const UNINIT: ::core::mem::MaybeUninit<hs_platform_info> = ::core::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::core::mem::size_of::<hs_platform_info>(),
-        32usize,
-        concat!("Size of: ", stringify!(hs_platform_info))
-    );
-    assert_eq!(
-        ::core::mem::align_of::<hs_platform_info>(),
-        8usize,
-        concat!("Alignment of ", stringify!(hs_platform_info))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).tune) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(tune)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).cpu_features) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(cpu_features)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).reserved1) as usize - ptr as usize },
-        16usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(reserved1)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).reserved2) as usize - ptr as usize },
-        24usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_platform_info),
-            "::",
-            stringify!(reserved2)
-        )
-    );
-}
 #[doc = " A type containing information on the target platform which may optionally be\n provided to the compile calls (@ref hs_compile(), @ref hs_compile_multi(),\n @ref hs_compile_ext_multi()).\n\n A hs_platform_info structure may be populated for the current platform by\n using the @ref hs_populate_platform() call."]
 pub type hs_platform_info_t = hs_platform_info;
 #[doc = " A type containing information related to an expression that is returned by\n @ref hs_expression_info() or @ref hs_expression_ext_info."]
@@ -292,71 +202,6 @@ pub struct hs_expr_info {
     #[doc = " Whether this expression can *only* produce matches at end of data (EOD).\n In streaming mode, all matches for this expression are raised during\n @ref hs_close_stream(). Zero if false, non-zero if true."]
     pub matches_only_at_eod: ::libc::c_char,
 }
-#[test]
-fn bindgen_test_layout_hs_expr_info() {
-    const UNINIT: ::core::mem::MaybeUninit<hs_expr_info> = ::core::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::core::mem::size_of::<hs_expr_info>(),
-        12usize,
-        concat!("Size of: ", stringify!(hs_expr_info))
-    );
-    assert_eq!(
-        ::core::mem::align_of::<hs_expr_info>(),
-        4usize,
-        concat!("Alignment of ", stringify!(hs_expr_info))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).min_width) as usize - ptr as usize },
-        0usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_info),
-            "::",
-            stringify!(min_width)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).max_width) as usize - ptr as usize },
-        4usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_info),
-            "::",
-            stringify!(max_width)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).unordered_matches) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_info),
-            "::",
-            stringify!(unordered_matches)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).matches_at_eod) as usize - ptr as usize },
-        9usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_info),
-            "::",
-            stringify!(matches_at_eod)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).matches_only_at_eod) as usize - ptr as usize },
-        10usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_info),
-            "::",
-            stringify!(matches_only_at_eod)
-        )
-    );
-}
 #[doc = " A type containing information related to an expression that is returned by\n @ref hs_expression_info() or @ref hs_expression_ext_info."]
 pub type hs_expr_info_t = hs_expr_info;
 #[doc = " A structure containing additional parameters related to an expression,\n passed in at build time to @ref hs_compile_ext_multi() or @ref\n hs_expression_ext_info.\n\n These parameters allow the set of matches produced by a pattern to be\n constrained at compile time, rather than relying on the application to\n process unwanted matches at runtime."]
@@ -376,76 +221,6 @@ pub struct hs_expr_ext {
     #[doc = " Allow patterns to approximately match within this Hamming distance. To\n use this parameter, set the @ref HS_EXT_FLAG_HAMMING_DISTANCE flag in the\n hs_expr_ext::flags field."]
     pub hamming_distance: ::libc::c_uint,
 }
-#[test]
-fn bindgen_test_layout_hs_expr_ext() {
-    const UNINIT: ::core::mem::MaybeUninit<hs_expr_ext> = ::core::mem::MaybeUninit::uninit();
-    let ptr = UNINIT.as_ptr();
-    assert_eq!(
-        ::core::mem::size_of::<hs_expr_ext>(),
-        40usize,
-        concat!("Size of: ", stringify!(hs_expr_ext))
-    );
-    assert_eq!(
-        ::core::mem::align_of::<hs_expr_ext>(),
-        8usize,
-        concat!("Alignment of ", stringify!(hs_expr_ext))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).flags) as usize - ptr as usize },
-        0usize,
-        concat!("Offset of field: ", stringify!(hs_expr_ext), "::", stringify!(flags))
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).min_offset) as usize - ptr as usize },
-        8usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_ext),
-            "::",
-            stringify!(min_offset)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).max_offset) as usize - ptr as usize },
-        16usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_ext),
-            "::",
-            stringify!(max_offset)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).min_length) as usize - ptr as usize },
-        24usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_ext),
-            "::",
-            stringify!(min_length)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).edit_distance) as usize - ptr as usize },
-        32usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_ext),
-            "::",
-            stringify!(edit_distance)
-        )
-    );
-    assert_eq!(
-        unsafe { ::core::ptr::addr_of!((*ptr).hamming_distance) as usize - ptr as usize },
-        36usize,
-        concat!(
-            "Offset of field: ",
-            stringify!(hs_expr_ext),
-            "::",
-            stringify!(hamming_distance)
-        )
-    );
-}
 #[doc = " A structure containing additional parameters related to an expression,\n passed in at build time to @ref hs_compile_ext_multi() or @ref\n hs_expression_ext_info.\n\n These parameters allow the set of matches produced by a pattern to be\n constrained at compile time, rather than relying on the application to\n process unwanted matches at runtime."]
 pub type hs_expr_ext_t = hs_expr_ext;
 extern "C" {
@@ -0,0 +1,69 @@
+//! Verify the layout of the binding types at compile time.
+//!
+//! The pregenerated binding files are shared by all the supported targets,
+//! a wrong binding file fails to build instead of corrupting memory.
+
+use core::mem::{align_of, size_of};
+
+/// The size of a C pointer.
+const PTR: usize = size_of::<*const u8>();
+
+/// The alignment of C `unsigned long long`, which is 4 bytes on 32-bit x86 except Windows.
+const ULL_ALIGN: usize = if cfg!(all(target_arch = "x86", not(windows))) {
+    4
+} else {
+    8
+};
+
+/// Round up the size to a multiple of the alignment.
+const fn pad(size: usize, align: usize) -> usize {
+    size.div_ceil(align) * align
+}
+
+macro_rules! assert_layout {
+    ($ty:ty, $size:expr, $align:expr) => {
+        const _: () = assert!(
+            size_of::<$ty>() == $size && align_of::<$ty>() == $align,
+            concat!(
+                "unexpected layout of `",
+                stringify!($ty),
+                "`, please regenerate the binding with `gen` feature"
+            )
+        );
+    };
+}
+
+// struct hs_compile_error { char *message; int expression; }
+assert_layout!(crate::hs_compile_error, pad(PTR + 4, PTR), PTR);
+
+// struct hs_platform_info { unsigned int tune; unsigned long long cpu_features, reserved1, reserved2; }
+assert_layout!(
+    crate::hs_platform_info,
+    pad(pad(4, ULL_ALIGN) + 3 * 8, ULL_ALIGN),
+    ULL_ALIGN
+);
+
+// struct hs_expr_info { unsigned int min_width, max_width; char unordered_matches, matches_at_eod, ...; }
+assert_layout!(crate::hs_expr_info, 12, 4);
+
+// struct hs_expr_ext { unsigned long long flags, ..., min_length; unsigned edit_distance, hamming_distance; }
+assert_layout!(crate::hs_expr_ext, pad(4 * 8 + 2 * 4, ULL_ALIGN), ULL_ALIGN);
+
+// struct ch_compile_error { char *message; int expression; }
+#[cfg(feature = "chimera")]
+assert_layout!(crate::chimera::ch_compile_error, pad(PTR + 4, PTR), PTR);
+
+#[cfg(feature = "chimera")]
+assert_layout!(
+    crate::chimera::hs_platform_info,
+    pad(pad(4, ULL_ALIGN) + 3 * 8, ULL_ALIGN),
+    ULL_ALIGN
+);
+
+// struct ch_capture { unsigned int flags; unsigned long long from, to; }
+#[cfg(feature = "chimera")]
+assert_layout!(
+    crate::chimera::ch_capture,
+    pad(pad(4, ULL_ALIGN) + 2 * 8, ULL_ALIGN),
+    ULL_ALIGN
+);
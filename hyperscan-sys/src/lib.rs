@@ -1,9 +1,22 @@
 //! Hyperscan is a software regular expression matching engine
 //! designed with high performance and flexibility in mind.
+//!
+//! # Vendored build
+//!
+//! The `vendored` feature builds a pinned Hyperscan release from source and links it statically.
+//! Besides a C++ toolchain and `cmake`, the build runs the following tools, which must be on the `PATH`:
+//!
+//! - `curl`, `tar` and `sha256sum` (or `shasum`) to download, verify and unpack the Hyperscan, Boost
+//!   and PCRE tarballs, unless `HYPERSCAN_SOURCE_DIR`, `BOOST_ROOT` and `PCRE_SOURCE_DIR` point to local sources;
+//! - `ragel`, which generates the parsers of Hyperscan;
+//! - `python`, which runs the build scripts of Hyperscan.
 #![no_std]
 #![allow(non_camel_case_types, deref_nullptr)]
 
 include!(concat!(env!("OUT_DIR"), "/hyperscan.rs"));
+include!(concat!(env!("OUT_DIR"), "/build_info.rs"));
+
+mod layout;
 
 #[cfg(feature = "chimera")]
 pub mod chimera {
@@ -0,0 +1,21 @@
+//! Tests for the pure functions of the build script.
+
+#[allow(dead_code)]
+#[path = "../build/bindings.rs"]
+mod bindings;
+
+#[allow(dead_code)]
+#[path = "../build/cxx.rs"]
+mod cxx;
+
+#[allow(dead_code)]
+#[path = "../build/paths.rs"]
+mod paths;
+
+#[allow(dead_code)]
+#[path = "../build/pcre.rs"]
+mod pcre;
+
+#[allow(dead_code)]
+#[path = "../build/version.rs"]
+mod version;
@@ -0,0 +1,15 @@
+/*
+ * Trimmed copy of the Hyperscan 5.4.0 `hs.h` header, only the version macros are kept.
+ */
+
+#ifndef HS_H_
+#define HS_H_
+
+#define HS_MAJOR 5
+#define HS_MINOR 4
+#define HS_PATCH 0
+
+#include "hs_compile.h"
+#include "hs_runtime.h"
+
+#endif /* HS_H_ */
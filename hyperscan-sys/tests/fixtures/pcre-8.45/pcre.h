@@ -0,0 +1,13 @@
+/* Excerpt of the header of PCRE 8.45 for the build script tests. */
+
+#ifndef _PCRE_H
+#define _PCRE_H
+
+/* The current PCRE version information. */
+
+#define PCRE_MAJOR          8
+#define PCRE_MINOR          45
+#define PCRE_PRERELEASE
+#define PCRE_DATE           2021-06-15
+
+#endif /* End of pcre.h */
@@ -0,0 +1,13 @@
+/* Excerpt of the header of PCRE2 10.42 for the build script tests. */
+
+#ifndef PCRE2_H_IDEMPOTENT_GUARD
+#define PCRE2_H_IDEMPOTENT_GUARD
+
+/* The current PCRE version information. */
+
+#define PCRE2_MAJOR           10
+#define PCRE2_MINOR           42
+#define PCRE2_PRERELEASE
+#define PCRE2_DATE            2022-12-11
+
+#endif /* PCRE2_H_IDEMPOTENT_GUARD */
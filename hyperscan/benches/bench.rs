@@ -49,14 +49,14 @@ fn hyperscan_bench(c: &mut Criterion) {
     for (&name, &expr) in BENCH_DATA.iter() {
         let pat = Pattern::with_flags(expr, PatternFlags::SOM_LEFTMOST | PatternFlags::MULTILINE).unwrap();
         let db = pat.build::<BlockMode>().unwrap();
-        let s = db.alloc_scratch().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
 
         for &size in BENCH_SIZE.iter() {
             let text = BENCH_TEXT.get(..size).unwrap();
 
             group.throughput(Throughput::Bytes(text.len() as u64));
             group.bench_with_input(BenchmarkId::new(name, size), &(text), |b, text| {
-                b.iter(|| db.scan(text, &s, Matching::Terminate).unwrap())
+                b.iter(|| db.scan(text, &mut s, Matching::Terminate).unwrap())
             });
         }
     }
@@ -91,6 +91,51 @@ fn chimera_bench(c: &mut Criterion) {
 #[cfg(not(feature = "chimera"))]
 fn chimera_bench(c: &mut Criterion) {}
 
+#[cfg(feature = "rayon")]
+fn rayon_bench(c: &mut Criterion) {
+    use hyperscan::{prelude::*, BlockMode, Match, PatternFlags};
+    use rayon::prelude::*;
+
+    let mut group = c.benchmark_group("rayon");
+
+    for (&name, &expr) in BENCH_DATA.iter() {
+        let pat = Pattern::with_flags(expr, PatternFlags::SOM_LEFTMOST | PatternFlags::MULTILINE).unwrap();
+        let db = pat.build::<BlockMode>().unwrap();
+        let pool = ScratchPool::new(&db).unwrap();
+        let docs = BENCH_TEXT.chunks(KB).collect::<Vec<_>>();
+
+        group.throughput(Throughput::Bytes(BENCH_TEXT.len() as u64));
+        group.bench_with_input(BenchmarkId::new("par_scan_matches", name), &docs, |b, docs| {
+            b.iter(|| db.par_scan_matches(docs.par_iter().copied(), &pool).unwrap())
+        });
+        group.bench_with_input(BenchmarkId::new("manual", name), &docs, |b, docs| {
+            b.iter(|| {
+                docs.par_iter()
+                    .map_init(
+                        || db.alloc_scratch().unwrap(),
+                        |s, doc| {
+                            let mut matches = vec![];
+
+                            db.scan(doc, s, |id, from, to, _| {
+                                matches.push(Match { id, from, to });
+                                Matching::Continue
+                            })
+                            .unwrap();
+
+                            matches
+                        },
+                    )
+                    .collect::<Vec<_>>()
+            })
+        });
+    }
+
+    group.finish();
+}
+
+#[cfg(not(feature = "rayon"))]
+fn rayon_bench(_c: &mut Criterion) {}
+
 fn regex_bench(c: &mut Criterion) {
     use std::str;
 
@@ -117,7 +162,7 @@ fn regex_bench(c: &mut Criterion) {
 criterion_group! {
     name = benches;
     config = Criterion::default();
-    targets = hyperscan_bench, chimera_bench, regex_bench
+    targets = hyperscan_bench, chimera_bench, rayon_bench, regex_bench
 }
 
 criterion_main!(benches);
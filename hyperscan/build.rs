@@ -0,0 +1,15 @@
+use std::env;
+
+/// Forward the version `cfg` detected by `hyperscan-sys`, e.g. `hs_version_gte_5_2`.
+fn main() {
+    let all_cfgs = env::var("DEP_HS_ALL_VERSION_CFGS").unwrap_or_default();
+    let cfgs = env::var("DEP_HS_VERSION_CFGS").unwrap_or_default();
+
+    for cfg in all_cfgs.split(',').filter(|cfg| !cfg.is_empty()) {
+        println!("cargo:rustc-check-cfg=cfg({})", cfg);
+    }
+
+    for cfg in cfgs.split(',').filter(|cfg| !cfg.is_empty()) {
+        println!("cargo:rustc-cfg={}", cfg);
+    }
+}
@@ -255,9 +255,6 @@ struct Benchmark {
     /// Map used to construct stream_ids
     sessions: HashMap<Session, usize>,
 
-    // Vector of Hyperscan stream state (used in streaming mode)
-    streams: Vec<Stream>,
-
     // Count of matches found during scanning
     matches: usize,
 }
@@ -306,20 +303,20 @@ impl Benchmark {
     }
 
     /// Open a Hyperscan stream for each stream in stream_ids
-    fn open_streams(&mut self, db: &StreamingDatabase) -> Result<()> {
-        self.streams = (0..self.sessions.len())
+    fn open_streams<'db>(&self, db: &'db StreamingDatabase) -> Result<Vec<Stream<'db>>> {
+        let streams = (0..self.sessions.len())
             .map(|_| db.open_stream())
             .collect::<hyperscan::Result<Vec<_>>>()?;
 
-        Ok(())
+        Ok(streams)
     }
 
     /// Close all open Hyperscan streams (potentially generating any end-anchored matches)
-    fn close_streams(&mut self, scratch: &Scratch) -> Result<()> {
+    fn close_streams(&mut self, streams: Vec<Stream<'_>>, scratch: &mut Scratch) -> Result<()> {
         let matches = &mut self.matches;
 
-        for stream in self.streams.drain(..) {
-            stream.close(&scratch, |_, _, _, _| {
+        for stream in streams {
+            stream.close(scratch, |_, _, _, _| {
                 *matches += 1;
                 Matching::Continue
             })?;
@@ -329,13 +326,13 @@ impl Benchmark {
     }
 
     /// Scan each packet (in the ordering given in the PCAP file) through Hyperscan using the streaming interface.
-    fn scan_streams(&mut self, scratch: &Scratch) -> Result<()> {
+    fn scan_streams(&mut self, streams: &[Stream<'_>], scratch: &mut Scratch) -> Result<()> {
         let matches = &mut self.matches;
 
         for (i, ref packet) in self.packets.iter().enumerate() {
-            let ref stream = self.streams[self.stream_ids[i]];
+            let stream = &streams[self.stream_ids[i]];
 
-            stream.scan(&packet, &scratch, |_, _, _, _| {
+            stream.scan(&packet, scratch, |_, _, _, _| {
                 *matches += 1;
                 Matching::Continue
             })?;
@@ -346,11 +343,11 @@ impl Benchmark {
 
     /// Scan each packet (in the ordering given in the PCAP file) through
     /// Hyperscan using the block-mode interface.
-    fn scan_block(&mut self, db: &BlockDatabase, scratch: &Scratch) -> Result<()> {
+    fn scan_block(&mut self, db: &BlockDatabase, scratch: &mut Scratch) -> Result<()> {
         let matches = &mut self.matches;
 
         for packet in &self.packets {
-            db.scan(packet, &scratch, |_, _, _, _| {
+            db.scan(packet, scratch, |_, _, _, _| {
                 *matches += 1;
                 Matching::Continue
             })?;
@@ -409,7 +406,7 @@ fn eval_set(
         patterns.build::<Block>().map(Either::Right)?
     };
     let compile_time = now.elapsed();
-    let scratch = db.as_ref().either(|db| db.alloc_scratch(), |db| db.alloc_scratch())?;
+    let mut scratch = db.as_ref().either(|db| db.alloc_scratch(), |db| db.alloc_scratch())?;
 
     match criterion {
         ByteCodeSize => db
@@ -436,12 +433,16 @@ fn eval_set(
             for _ in 0..repeats {
                 match db {
                     Either::Left(ref db) => {
-                        bench.open_streams(db).with_context(|| "open stream")?;
-                        bench.scan_streams(&scratch).with_context(|| "scan stream")?;
-                        bench.close_streams(&scratch).with_context(|| "close stream")?;
+                        let streams = bench.open_streams(db).with_context(|| "open stream")?;
+                        bench
+                            .scan_streams(&streams, &mut scratch)
+                            .with_context(|| "scan stream")?;
+                        bench
+                            .close_streams(streams, &mut scratch)
+                            .with_context(|| "close stream")?;
                     }
                     Either::Right(ref db) => {
-                        bench.scan_block(db, &scratch).with_context(|| "scan block")?;
+                        bench.scan_block(db, &mut scratch).with_context(|| "scan block")?;
                     }
                 }
             }
@@ -27,6 +27,7 @@ use std::net::SocketAddrV4;
 use std::path::{Path, PathBuf};
 use std::process::exit;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use anyhow::{Context, Result};
@@ -106,7 +107,7 @@ struct Benchmark {
     sessions: HashMap<Session, usize>,
 
     /// Hyperscan compiled database (streaming mode)
-    streaming_db: StreamingDatabase,
+    streaming_db: Arc<StreamingDatabase>,
 
     /// Hyperscan compiled database (block mode)
     block_db: BlockDatabase,
@@ -115,7 +116,7 @@ struct Benchmark {
     scratch: Scratch,
 
     // Vector of Hyperscan stream state (used in streaming mode)
-    streams: Vec<Stream>,
+    streams: Vec<Stream<'static>>,
 
     // Count of matches found during scanning
     match_count: AtomicUsize,
@@ -131,7 +132,7 @@ impl Benchmark {
             packets: Vec::new(),
             stream_ids: Vec::new(),
             sessions: HashMap::new(),
-            streaming_db: streaming_db,
+            streaming_db: Arc::new(streaming_db),
             block_db: block_db,
             scratch: s,
             streams: Vec::new(),
@@ -222,7 +223,7 @@ impl Benchmark {
 
     // Open a Hyperscan stream for each stream in stream_ids
     fn open_streams(&mut self) -> Result<()> {
-        self.streams = iter::repeat_with(|| self.streaming_db.open_stream())
+        self.streams = iter::repeat_with(|| self.streaming_db.open_shared_stream())
             .take(self.sessions.len())
             .collect::<hyperscan::Result<Vec<_>>>()?;
 
@@ -234,7 +235,7 @@ impl Benchmark {
         for stream in self.streams.drain(..) {
             let match_count = &self.match_count;
             stream
-                .close(&self.scratch, |_, _, _, _| {
+                .close(&mut self.scratch, |_, _, _, _| {
                     match_count.fetch_add(1, Ordering::Relaxed);
 
                     Matching::Continue
@@ -246,10 +247,12 @@ impl Benchmark {
     }
 
     fn reset_streams(&mut self) -> Result<()> {
+        let match_count = &self.match_count;
+
         for ref stream in &self.streams {
             stream
-                .reset(&self.scratch, |_, _, _, _| {
-                    self.match_count.fetch_add(1, Ordering::Relaxed);
+                .reset(&mut self.scratch, |_, _, _, _| {
+                    match_count.fetch_add(1, Ordering::Relaxed);
 
                     Matching::Continue
                 })
@@ -262,12 +265,14 @@ impl Benchmark {
     // Scan each packet (in the ordering given in the PCAP file)
     // through Hyperscan using the streaming interface.
     fn scan_streams(&mut self) -> Result<()> {
+        let match_count = &self.match_count;
+
         for (i, ref packet) in self.packets.iter().enumerate() {
             let ref stream = self.streams[self.stream_ids[i]];
 
             stream
-                .scan(packet.as_ref().as_slice(), &self.scratch, |_, _, _, _| {
-                    self.match_count.fetch_add(1, Ordering::Relaxed);
+                .scan(packet.as_ref().as_slice(), &mut self.scratch, |_, _, _, _| {
+                    match_count.fetch_add(1, Ordering::Relaxed);
 
                     Matching::Continue
                 })
@@ -280,10 +285,12 @@ impl Benchmark {
     // Scan each packet (in the ordering given in the PCAP file)
     // through Hyperscan using the block-mode interface.
     fn scan_block(&mut self) -> Result<()> {
+        let match_count = &self.match_count;
+
         for ref packet in &self.packets {
             self.block_db
-                .scan(packet.as_ref().as_slice(), &self.scratch, |_, _, _, _| {
-                    self.match_count.fetch_add(1, Ordering::Relaxed);
+                .scan(packet.as_ref().as_slice(), &mut self.scratch, |_, _, _, _| {
+                    match_count.fetch_add(1, Ordering::Relaxed);
 
                     Matching::Continue
                 })
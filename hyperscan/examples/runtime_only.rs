@@ -0,0 +1,47 @@
+// Hyperscan example program: runtime_only
+//
+// This example only uses the Hyperscan runtime library: it will load a database
+// serialized by a previous compile, and search a given input file with it.
+// It is intended to demonstrate that the `runtime` feature links against
+// `libhs_runtime` alone, without any of the compile time symbols.
+//
+// Build instructions:
+//
+//     cargo build --example runtime_only --no-default-features --features runtime
+//
+// Usage:
+//
+//     ./runtime_only <serialized database> <input file>
+//
+
+use std::env;
+use std::fs;
+use std::process;
+
+use hyperscan::prelude::*;
+use hyperscan::SerializedDatabase;
+
+fn main() -> hyperscan::Result<()> {
+    let args = env::args().collect::<Vec<_>>();
+
+    if args.len() != 3 {
+        eprintln!("Usage: {} <serialized database> <input file>", args[0]);
+        process::exit(1);
+    }
+
+    let serialized = fs::read(&args[1]).expect("read serialized database");
+    let input_data = fs::read(&args[2]).expect("read input file");
+
+    println!("{}", serialized.info()?);
+
+    let database: BlockDatabase = serialized.deserialize()?;
+    let mut scratch = database.alloc_scratch()?;
+
+    database.scan(&input_data, &mut scratch, |id, from, to, _| {
+        println!("Match for pattern {} at offset {}..{}", id, from, to);
+
+        Matching::Continue
+    })?;
+
+    Ok(())
+}
@@ -79,12 +79,12 @@ fn main() -> Result<()> {
     // match event.
     //
 
-    let scratch = database.alloc_scratch().with_context(|| "allocate scratch space")?;
+    let mut scratch = database.alloc_scratch().with_context(|| "allocate scratch space")?;
 
     println!("Scanning {} bytes with Hyperscan", input_data.len());
 
     database
-        .scan(&input_data, &scratch, |_, from, to, _| {
+        .scan(&input_data, &mut scratch, |_, from, to, _| {
             println!(
                 "Match for pattern \"{}\" at offset {}..{}: {}",
                 pattern.expression,
@@ -95,5 +95,7 @@ fn main() -> Result<()> {
 
             Matching::Continue
         })
-        .with_context(|| "scan input buffer")
+        .with_context(|| "scan input buffer")?;
+
+    Ok(())
 }
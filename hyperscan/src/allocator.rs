@@ -0,0 +1,304 @@
+//! Route the Hyperscan allocations through the Rust global allocator.
+//!
+//! By default, Hyperscan allocates the database bytecode, scratch spaces and stream state with `malloc()`,
+//! which is invisible to a Rust global allocator (for example, jemalloc with its statistics).
+//! [`install`] replaces the database, scratch, stream and misc allocators of Hyperscan with the `GlobalAlloc`,
+//! and [`install_with_accounting`] also tracks the current and peak bytes of each [`Subsystem`].
+//!
+//! The misc allocator is used for the compile errors, the information strings and the serialized databases,
+//! their owners, such as [`MiscBuf`], release them to the allocator which allocated them.
+//!
+//! The allocator must be installed before any database is compiled or deserialized,
+//! or any scratch space or stream is allocated, otherwise `Error::AllocatorInUse` is returned,
+//! since the existing allocations would be released to the wrong allocator.
+//! These calls wait for an allocator being installed by another thread.
+//!
+//! The allocator of Chimera is not covered.
+use alloc::alloc::{self as global, Layout};
+use core::fmt;
+use core::hint;
+use core::ops::Deref;
+use core::ptr;
+use core::slice;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+
+use crate::{error::AsResult, ffi, Error, Result};
+
+/// The alignment and the size of the header which keeps the size of each allocation,
+/// suitable for the largest representable data type as Hyperscan requires.
+const ALIGN: usize = 16;
+
+/// Nothing has been allocated by Hyperscan yet, the allocator may be installed.
+const IDLE: u8 = 0;
+/// The allocator is being installed, the allocations wait for it.
+const INSTALLING: u8 = 1;
+/// Hyperscan has allocated memory with the current allocator.
+const IN_USE: u8 = 2;
+
+static STATE: AtomicU8 = AtomicU8::new(IDLE);
+static ACCOUNTING: AtomicBool = AtomicBool::new(false);
+/// The misc allocator is installed, the misc buffers are released to the global allocator.
+static MISC: AtomicBool = AtomicBool::new(false);
+
+/// The subsystems of Hyperscan which allocate memory.
+#[repr(usize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Subsystem {
+    /// The database bytecode, allocated by the compile calls and the deserialization.
+    Database,
+    /// The scratch spaces.
+    Scratch,
+    /// The stream state, allocated by opening or copying a stream.
+    Stream,
+    /// The compile errors, the information strings and the serialized databases.
+    Misc,
+}
+
+impl Subsystem {
+    /// All the subsystems.
+    pub const ALL: [Subsystem; 4] = [
+        Subsystem::Database,
+        Subsystem::Scratch,
+        Subsystem::Stream,
+        Subsystem::Misc,
+    ];
+}
+
+/// The memory usage of a subsystem.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AllocStats {
+    /// Bytes currently allocated.
+    pub current: usize,
+    /// The peak of the bytes allocated.
+    pub peak: usize,
+    /// Allocations performed.
+    pub allocations: u64,
+}
+
+struct Counters {
+    current: AtomicUsize,
+    peak: AtomicUsize,
+    allocations: AtomicU64,
+}
+
+impl Counters {
+    const fn new() -> Counters {
+        Counters {
+            current: AtomicUsize::new(0),
+            peak: AtomicUsize::new(0),
+            allocations: AtomicU64::new(0),
+        }
+    }
+}
+
+static COUNTERS: [Counters; 4] = [Counters::new(), Counters::new(), Counters::new(), Counters::new()];
+
+/// Record that Hyperscan is about to allocate memory with the current allocator.
+///
+/// Called by the compile, deserialize, information and allocation entry points, it waits for an allocator being installed
+/// by another thread, so nothing is allocated with the old allocator and released to the new one.
+pub(crate) fn mark_in_use() {
+    while let Err(INSTALLING) = STATE.compare_exchange(IDLE, IN_USE, Ordering::Acquire, Ordering::Acquire) {
+        hint::spin_loop();
+    }
+}
+
+/// Allocate the memory of Hyperscan from the Rust global allocator.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{allocator, prelude::*};
+/// allocator::install().unwrap();
+///
+/// let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+///
+/// assert_eq!(allocator::install(), Err(hyperscan::Error::AllocatorInUse));
+/// ```
+pub fn install() -> Result<()> {
+    set_allocators(false)
+}
+
+/// Allocate the memory of Hyperscan from the Rust global allocator, and account the bytes of each subsystem.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{allocator::{self, Subsystem}, prelude::*};
+/// allocator::install_with_accounting().unwrap();
+///
+/// let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+/// let stats = allocator::stats(Subsystem::Database).unwrap();
+///
+/// assert!(stats.current >= db.size().unwrap());
+/// ```
+pub fn install_with_accounting() -> Result<()> {
+    set_allocators(true)
+}
+
+/// The memory usage of the subsystem, or `None` if the accounting is not enabled.
+pub fn stats(subsystem: Subsystem) -> Option<AllocStats> {
+    if ACCOUNTING.load(Ordering::Relaxed) {
+        let counters = &COUNTERS[subsystem as usize];
+
+        Some(AllocStats {
+            current: counters.current.load(Ordering::Relaxed),
+            peak: counters.peak.load(Ordering::Relaxed),
+            allocations: counters.allocations.load(Ordering::Relaxed),
+        })
+    } else {
+        None
+    }
+}
+
+fn set_allocators(accounting: bool) -> Result<()> {
+    // checking nothing was allocated and claiming the installation is a single step,
+    // so a concurrent compile can't allocate in between
+    if STATE
+        .compare_exchange(IDLE, INSTALLING, Ordering::Acquire, Ordering::Acquire)
+        .is_err()
+    {
+        return Err(Error::AllocatorInUse);
+    }
+
+    ACCOUNTING.store(accounting, Ordering::Relaxed);
+
+    let res = install_allocators();
+
+    STATE.store(IDLE, Ordering::Release);
+
+    res
+}
+
+fn install_allocators() -> Result<()> {
+    unsafe {
+        ffi::hs_set_database_allocator(
+            Some(allocate::<{ Subsystem::Database as usize }>),
+            Some(deallocate::<{ Subsystem::Database as usize }>),
+        )
+        .ok()?;
+        ffi::hs_set_scratch_allocator(
+            Some(allocate::<{ Subsystem::Scratch as usize }>),
+            Some(deallocate::<{ Subsystem::Scratch as usize }>),
+        )
+        .ok()?;
+        ffi::hs_set_stream_allocator(
+            Some(allocate::<{ Subsystem::Stream as usize }>),
+            Some(deallocate::<{ Subsystem::Stream as usize }>),
+        )
+        .ok()?;
+        ffi::hs_set_misc_allocator(
+            Some(allocate::<{ Subsystem::Misc as usize }>),
+            Some(deallocate::<{ Subsystem::Misc as usize }>),
+        )
+        .ok()?;
+    }
+
+    MISC.store(true, Ordering::Release);
+
+    Ok(())
+}
+
+/// Release a buffer allocated by the misc allocator of Hyperscan, such as an information string.
+///
+/// The allocator can't be installed while a misc buffer is alive, since its allocation marks the allocator in use,
+/// so the buffer is released to the allocator which allocated it.
+pub(crate) unsafe fn free_misc(p: *mut libc::c_void) {
+    if MISC.load(Ordering::Acquire) {
+        deallocate::<{ Subsystem::Misc as usize }>(p)
+    } else {
+        libc::free(p)
+    }
+}
+
+/// A buffer allocated by the misc allocator of Hyperscan, such as a serialized database.
+///
+/// The buffer is released to the allocator which allocated it, `free()` or the Rust global allocator.
+pub struct MiscBuf {
+    ptr: *mut u8,
+    len: usize,
+}
+
+unsafe impl Send for MiscBuf {}
+unsafe impl Sync for MiscBuf {}
+
+impl MiscBuf {
+    /// Take the ownership of a buffer allocated by the misc allocator.
+    pub(crate) unsafe fn from_raw_parts(ptr: *mut u8, len: usize) -> MiscBuf {
+        MiscBuf { ptr, len }
+    }
+}
+
+impl Deref for MiscBuf {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.ptr.is_null() {
+            &[]
+        } else {
+            unsafe { slice::from_raw_parts(self.ptr, self.len) }
+        }
+    }
+}
+
+impl AsRef<[u8]> for MiscBuf {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl fmt::Debug for MiscBuf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl Drop for MiscBuf {
+    fn drop(&mut self) {
+        unsafe { free_misc(self.ptr.cast()) }
+    }
+}
+
+fn layout(size: usize) -> Option<Layout> {
+    size.checked_add(ALIGN)
+        .and_then(|size| Layout::from_size_align(size, ALIGN).ok())
+}
+
+unsafe extern "C" fn allocate<const S: usize>(size: usize) -> *mut libc::c_void {
+    let layout = match layout(size) {
+        Some(layout) => layout,
+        None => return ptr::null_mut(),
+    };
+    let p = global::alloc(layout);
+
+    if p.is_null() {
+        return ptr::null_mut();
+    }
+
+    p.cast::<usize>().write(size);
+
+    if ACCOUNTING.load(Ordering::Relaxed) {
+        let counters = &COUNTERS[S];
+        let current = counters.current.fetch_add(size, Ordering::Relaxed) + size;
+
+        counters.peak.fetch_max(current, Ordering::Relaxed);
+        counters.allocations.fetch_add(1, Ordering::Relaxed);
+    }
+
+    p.add(ALIGN).cast()
+}
+
+unsafe extern "C" fn deallocate<const S: usize>(p: *mut libc::c_void) {
+    if p.is_null() {
+        return;
+    }
+
+    let p = p.cast::<u8>().sub(ALIGN);
+    let size = p.cast::<usize>().read();
+
+    if ACCOUNTING.load(Ordering::Relaxed) {
+        COUNTERS[S].current.fetch_sub(size, Ordering::Relaxed);
+    }
+
+    global::dealloc(p, Layout::from_size_align_unchecked(size + ALIGN, ALIGN));
+}
@@ -0,0 +1,226 @@
+//! Scan the string and binary columns of Apache Arrow.
+//!
+//! The values are sliced from the value buffer of the array with its offsets, without copying the rows.
+//! The matches are reported with the index of the row, and the offsets relative to the row value.
+//! The null rows are skipped.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use arrow_array::StringArray;
+//! # use hyperscan::{arrow::scan_array, prelude::*};
+//! let db: BlockDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+//! let mut s = db.alloc_scratch().unwrap();
+//! let array = StringArray::from(vec![Some("foo test"), None, Some("test")]);
+//! let mut matches = vec![];
+//!
+//! scan_array(&array, &db, &mut s, |row, m| {
+//!     matches.push((row, m.from, m.to));
+//!     Matching::Continue
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(matches, vec![(0, 4, 8), (2, 0, 4)]);
+//! ```
+use arrow_array::{types::ByteArrayType, Array, GenericByteArray};
+use arrow_buffer::ArrowNativeType;
+
+use crate::{
+    common::{DatabaseRef, Vectored},
+    runtime::{Match, Matching, ScratchRef},
+    BlockDatabase, Result, ScanOutcome,
+};
+
+/// The number of rows written to Hyperscan in a vectored scan.
+const BATCH_ROWS: usize = 1024;
+
+/// Iterate the non-null rows of the array with their index.
+fn rows<T: ByteArrayType>(array: &GenericByteArray<T>) -> impl Iterator<Item = (usize, &[u8])> {
+    let offsets = array.value_offsets();
+    let data = array.value_data();
+
+    (0..array.len())
+        .filter(move |&row| array.is_valid(row))
+        .map(move |row| (row, &data[offsets[row].as_usize()..offsets[row + 1].as_usize()]))
+}
+
+/// Scan each row of the array in block mode, and report the matches with the index of the row.
+///
+/// If the callback returns `Matching::Terminate`, the scan stops and returns `ScanOutcome::Terminated`.
+pub fn scan_array<T, F>(
+    array: &GenericByteArray<T>,
+    db: &BlockDatabase,
+    scratch: &mut ScratchRef,
+    mut on_match: F,
+) -> Result<ScanOutcome>
+where
+    T: ByteArrayType,
+    F: FnMut(usize, Match) -> Matching,
+{
+    for (row, value) in rows(array) {
+        if db
+            .scan(value, scratch, |id, from, to, _| on_match(row, Match { id, from, to }))?
+            .is_terminated()
+        {
+            return Ok(ScanOutcome::Terminated);
+        }
+    }
+
+    Ok(ScanOutcome::Completed)
+}
+
+/// Scan the rows of the array in vectored mode, writing many rows to Hyperscan in each call.
+///
+/// The rows of a batch are scanned as a single stream of data, so a match may span a row boundary,
+/// it is reported on the row where it ends, with `from` clamped to the start of that row.
+/// This is faster than [`scan_array`] with many short rows, and gives the same matches for the patterns
+/// which can't cross a row boundary, for example the patterns without the anchors and the `.*` repeats.
+///
+/// The empty rows are skipped, so a pattern with `ALLOWEMPTY` doesn't match them.
+pub fn scan_array_vectored<T, F>(
+    array: &GenericByteArray<T>,
+    db: &DatabaseRef<Vectored>,
+    scratch: &mut ScratchRef,
+    mut on_match: F,
+) -> Result<ScanOutcome>
+where
+    T: ByteArrayType,
+    F: FnMut(usize, Match) -> Matching,
+{
+    let mut rows = rows(array).filter(|(_, value)| !value.is_empty()).peekable();
+    let mut batch = Vec::with_capacity(BATCH_ROWS);
+    let mut ends = Vec::with_capacity(BATCH_ROWS);
+
+    while rows.peek().is_some() {
+        batch.clear();
+        ends.clear();
+
+        let mut end = 0;
+
+        for (row, value) in rows.by_ref().take(BATCH_ROWS) {
+            end += value.len() as u64;
+            batch.push((row, value));
+            ends.push(end);
+        }
+
+        let outcome = db.scan(
+            batch.iter().map(|&(_, value)| value),
+            scratch,
+            |id, from: u64, to: u64, _| {
+                let i = ends.partition_point(|&end| end < to);
+                let start = ends[i] - batch[i].1.len() as u64;
+
+                on_match(
+                    batch[i].0,
+                    Match {
+                        id,
+                        from: from.saturating_sub(start),
+                        to: to - start,
+                    },
+                )
+            },
+        )?;
+
+        if outcome.is_terminated() {
+            return Ok(outcome);
+        }
+    }
+
+    Ok(ScanOutcome::Completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{BinaryArray, LargeStringArray, StringArray};
+
+    use crate::{patterns, prelude::*};
+
+    use super::*;
+
+    fn collect<T: ByteArrayType>(array: &GenericByteArray<T>, patterns: &Patterns) -> Vec<(usize, Match)> {
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        scan_array(array, &db, &mut s, |row, m| {
+            matches.push((row, m));
+            Matching::Continue
+        })
+        .unwrap();
+
+        matches
+    }
+
+    fn collect_vectored<T: ByteArrayType>(array: &GenericByteArray<T>, patterns: &Patterns) -> Vec<(usize, Match)> {
+        let db: VectoredDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        scan_array_vectored(array, &db, &mut s, |row, m| {
+            matches.push((row, m));
+            Matching::Continue
+        })
+        .unwrap();
+
+        matches
+    }
+
+    #[test]
+    fn test_scan_string_array() {
+        let patterns = patterns!("日本", "b+"; SOM_LEFTMOST | UTF8);
+        let array = StringArray::from(vec![Some("abb"), None, Some(""), Some("こんにちは日本"), Some("b")]);
+        let expected = vec![
+            (0, Match { id: 1, from: 1, to: 2 }),
+            (0, Match { id: 1, from: 1, to: 3 }),
+            (
+                3,
+                Match {
+                    id: 0,
+                    from: 15,
+                    to: 21,
+                },
+            ),
+            (4, Match { id: 1, from: 0, to: 1 }),
+        ];
+
+        assert_eq!(collect(&array, &patterns), expected);
+        assert_eq!(collect_vectored(&array, &patterns), expected);
+
+        let array = LargeStringArray::from(vec![Some("abb"), None, Some(""), Some("こんにちは日本"), Some("b")]);
+
+        assert_eq!(collect(&array, &patterns), expected);
+        assert_eq!(collect_vectored(&array, &patterns), expected);
+    }
+
+    #[test]
+    fn test_scan_binary_array() {
+        let patterns = patterns!("\\x00\\xff"; SOM_LEFTMOST);
+        let array = BinaryArray::from(vec![
+            Some(&b"\x00\xff"[..]),
+            None,
+            Some(b""),
+            Some(b"a\x00\xff\x00\xff"),
+        ]);
+        let expected = vec![
+            (0, Match { id: 0, from: 0, to: 2 }),
+            (3, Match { id: 0, from: 1, to: 3 }),
+            (3, Match { id: 0, from: 3, to: 5 }),
+        ];
+
+        assert_eq!(collect(&array, &patterns), expected);
+        assert_eq!(collect_vectored(&array, &patterns), expected);
+    }
+
+    #[test]
+    fn test_vectored_batches() {
+        let values = (0..BATCH_ROWS * 2 + 1)
+            .map(|i| format!("row {}", i))
+            .collect::<Vec<_>>();
+        let array = StringArray::from(values.iter().map(|s| s.as_str()).collect::<Vec<_>>());
+        let patterns = patterns!("row"; SOM_LEFTMOST);
+        let matches = collect_vectored(&array, &patterns);
+
+        assert_eq!(matches.len(), values.len());
+        assert_eq!(matches, collect(&array, &patterns));
+    }
+}
@@ -0,0 +1,304 @@
+//! Scan on the blocking thread pool of Tokio.
+//!
+//! Scanning a large buffer is CPU bound, and would stall the reactor if it runs on a worker thread.
+//! These helpers check out a scratch space from the pool, move the database, the data and the scratch
+//! into `tokio::task::spawn_blocking`, and return the collected matches, or the error of the scratch space
+//! which couldn't be checked out from the pool.
+//!
+//! # Cancellation
+//!
+//! A blocking task can't be interrupted. Dropping the returned future only detaches it,
+//! the scan runs to completion on the blocking thread pool, and the scratch space is still
+//! returned to the pool afterward. The matches of a detached scan are discarded.
+//!
+//! # Streaming from a reader
+//!
+//! [`scan_async_read`] scans the data of an `AsyncRead` as it arrives. Each chunk is small, so its scan runs
+//! inline on the task, without `spawn_blocking` or `block_in_place`, and the task yields between the chunks.
+use std::panic;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::task::{self, spawn_blocking, JoinError};
+
+use crate::{
+    common::{BlockDatabase, StreamingDatabase},
+    runtime::{Match, Matching, ScratchPool, ScratchRef},
+    Error, Result, ScanOutcome,
+};
+
+const READ_BUF_SIZE: usize = 4096;
+
+/// Scan the data with a block database on the blocking thread pool.
+///
+/// A scratch space which can't be checked out from the pool is returned as an error, before spawning the task.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use hyperscan::{blocking::scan_blocking, prelude::*, Match};
+/// let db: Arc<BlockDatabase> = Arc::new(pattern! {"test"; SOM_LEFTMOST}.build().unwrap());
+/// let pool = Arc::new(ScratchPool::new(&db).unwrap());
+///
+/// let rt = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+/// let matches = rt.block_on(scan_blocking(db, "foo test bar".into(), pool)).unwrap();
+///
+/// assert_eq!(matches, vec![Match { id: 0, from: 4, to: 8 }]);
+/// ```
+pub async fn scan_blocking(db: Arc<BlockDatabase>, data: Bytes, pool: Arc<ScratchPool>) -> Result<Vec<Match>> {
+    let mut scratch = pool.take()?;
+
+    spawn_blocking(move || {
+        let mut matches = vec![];
+        let res = db
+            .realloc_scratch(&mut scratch)
+            .and_then(|s| db.scan(&data, s, Match::collect(&mut matches)));
+
+        pool.put(scratch);
+
+        res.map(|_| matches)
+    })
+    .await
+    .unwrap_or_else(join_error)
+}
+
+/// Scan the data as a whole stream with a streaming database on the blocking thread pool.
+///
+/// The stream is opened, scanned and closed on the blocking thread, so the matches reported at the end of data
+/// (for example, `$` anchored expressions) are included.
+///
+/// A scratch space which can't be checked out from the pool is returned as an error, before spawning the task.
+pub async fn scan_stream_blocking(
+    db: Arc<StreamingDatabase>,
+    data: Bytes,
+    pool: Arc<ScratchPool>,
+) -> Result<Vec<Match>> {
+    let mut scratch = pool.take()?;
+
+    spawn_blocking(move || {
+        let mut matches = vec![];
+        let res = db.realloc_scratch(&mut scratch).and_then(|s| {
+            let stream = db.open_stream()?;
+
+            stream.scan(&data, s, Match::collect(&mut matches))?;
+            stream.close(s, Match::collect(&mut matches))
+        });
+
+        pool.put(scratch);
+
+        res.map(|_| matches)
+    })
+    .await
+    .unwrap_or_else(join_error)
+}
+
+/// Scan the data read from an `AsyncRead` as a stream, until the end of data or the termination of the scan.
+///
+/// The data is read in chunks of up to 4 KiB, each chunk is scanned inline on the current task with
+/// `hs_scan_stream`, then the task yields to the runtime before reading the next chunk.
+/// The stream is closed at the end of data, to report the matches at the end of data.
+///
+/// When the match handler returns `Matching::Terminate`, no more data is read from the reader,
+/// and `ScanOutcome::Terminated` is returned. A read error is returned
+/// as `Error::Io`, and dropping the future drops the stream without reporting the matches at the end of data.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{blocking::scan_async_read, prelude::*};
+/// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let mut matches = vec![];
+///
+/// let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// rt.block_on(scan_async_read(&db, &b"foo test bar"[..], &mut s, |_, from, to, _| {
+///     matches.push((from, to));
+///     Matching::Continue
+/// }))
+/// .unwrap();
+///
+/// assert_eq!(matches, vec![(4, 8)]);
+/// ```
+pub async fn scan_async_read<R, F>(
+    db: &StreamingDatabase,
+    mut reader: R,
+    scratch: &mut ScratchRef,
+    mut on_match_event: F,
+) -> Result<ScanOutcome>
+where
+    R: AsyncRead + Unpin,
+    F: FnMut(u32, u64, u64, u32) -> Matching,
+{
+    let stream = db.open_stream()?;
+    let mut buf = [0; READ_BUF_SIZE];
+
+    loop {
+        let len = reader.read(&mut buf).await?;
+
+        if len == 0 {
+            break;
+        }
+
+        if stream.scan(&buf[..len], scratch, &mut on_match_event)?.is_terminated() {
+            return Ok(ScanOutcome::Terminated);
+        }
+
+        task::yield_now().await;
+    }
+
+    stream.close(scratch, &mut on_match_event)?;
+
+    Ok(ScanOutcome::Completed)
+}
+
+/// Resume the panic of the blocking task, or report the task was cancelled by the runtime shutdown.
+pub(crate) fn join_error<T>(err: JoinError) -> Result<T> {
+    if err.is_panic() {
+        panic::resume_unwind(err.into_panic())
+    } else {
+        Err(Error::Cancelled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use tokio::io::AsyncWriteExt;
+    use tokio::runtime::{Builder as RuntimeBuilder, Runtime};
+
+    use super::*;
+    use crate::prelude::*;
+
+    const DATA_SIZE: usize = 32 * 1024 * 1024;
+
+    #[test]
+    fn test_scan_blocking() {
+        let db: Arc<BlockDatabase> = Arc::new(pattern! {"test"; SOM_LEFTMOST}.build().unwrap());
+        let pool = Arc::new(ScratchPool::new(&db).unwrap());
+        let rt = Runtime::new().unwrap();
+
+        let matches = rt
+            .block_on(scan_blocking(db, Bytes::from_static(b"test foo test"), pool.clone()))
+            .unwrap();
+
+        assert_eq!(
+            matches,
+            vec![Match { id: 0, from: 0, to: 4 }, Match { id: 0, from: 9, to: 13 }]
+        );
+        assert_eq!(pool.idle(), 1);
+    }
+
+    #[test]
+    fn test_scan_stream_blocking() {
+        let db: Arc<StreamingDatabase> = Arc::new(pattern! {"test$"; SOM_LEFTMOST}.build().unwrap());
+        let pool = Arc::new(ScratchPool::new(&db).unwrap());
+        let rt = Runtime::new().unwrap();
+
+        let matches = rt
+            .block_on(scan_stream_blocking(db, Bytes::from_static(b"foo test"), pool.clone()))
+            .unwrap();
+
+        assert_eq!(matches, vec![Match { id: 0, from: 4, to: 8 }]);
+        assert_eq!(pool.idle(), 1);
+    }
+
+    #[test]
+    fn test_reactor_responsive() {
+        let db: Arc<BlockDatabase> = Arc::new(pattern! {"a{15}b"}.build().unwrap());
+        let pool = Arc::new(ScratchPool::new(&db).unwrap());
+        let data = Bytes::from(b"aaaaaaaaaaaaaaab".repeat(DATA_SIZE / 16));
+        let rt = RuntimeBuilder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .unwrap();
+        let ticks = Arc::new(AtomicUsize::new(0));
+
+        let (ticks_while_scanning, matches) = rt.block_on(async {
+            let ticker = tokio::spawn({
+                let ticks = ticks.clone();
+
+                async move {
+                    loop {
+                        tokio::time::sleep(Duration::from_millis(1)).await;
+                        ticks.fetch_add(1, Ordering::SeqCst);
+                    }
+                }
+            });
+
+            let matches = tokio::spawn(scan_blocking(db, data, pool)).await.unwrap().unwrap();
+            let ticks_while_scanning = ticks.load(Ordering::SeqCst);
+
+            ticker.abort();
+
+            (ticks_while_scanning, matches)
+        });
+
+        assert_eq!(matches.len(), DATA_SIZE / 16);
+        assert!(ticks_while_scanning > 0, "the reactor stalled while scanning");
+    }
+
+    #[test]
+    fn test_scan_async_read() {
+        let db: Arc<StreamingDatabase> = Arc::new(pattern! {"foo[0-9]+bar"; SOM_LEFTMOST}.build().unwrap());
+        let mut s = db.alloc_scratch().unwrap();
+        let rt = Runtime::new().unwrap();
+
+        let matches = rt.block_on(async {
+            let (mut tx, rx) = tokio::io::duplex(8);
+
+            let writer = tokio::spawn(async move {
+                for chunk in b"xx foo12".chunks(3).chain(b"34bar foo5bar".chunks(2)) {
+                    tx.write_all(chunk).await.unwrap();
+                }
+            });
+
+            let scanner = tokio::spawn(async move {
+                let mut matches = vec![];
+
+                scan_async_read(&db, rx, &mut s, |_, from, to, _| {
+                    matches.push((from, to));
+                    Matching::Continue
+                })
+                .await
+                .map(|_| matches)
+            });
+
+            writer.await.unwrap();
+            scanner.await.unwrap().unwrap()
+        });
+
+        assert_eq!(matches, vec![(3, 13), (14, 21)]);
+    }
+
+    #[test]
+    fn test_scan_async_read_terminated() {
+        let db: StreamingDatabase = pattern! {"foo"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let rt = Runtime::new().unwrap();
+
+        let terminated = rt.block_on(async {
+            // the writer is kept open, the scan only returns when it stops reading
+            let (mut tx, rx) = tokio::io::duplex(64);
+
+            tx.write_all(b"bar foo").await.unwrap();
+
+            let res = tokio::time::timeout(
+                Duration::from_secs(10),
+                scan_async_read(&db, rx, &mut s, |_, _, _, _| Matching::Terminate),
+            )
+            .await
+            .expect("the reader should not be polled after the termination");
+
+            drop(tx);
+
+            res.unwrap().is_terminated()
+        });
+
+        assert!(terminated);
+    }
+}
@@ -0,0 +1,63 @@
+//! Scan on the blocking thread pool of Tokio.
+//!
+//! See `hyperscan::blocking` for the cancellation semantics, which are the same for Chimera:
+//! dropping the returned future only detaches it, the scan runs to completion on the blocking thread pool.
+use std::sync::Arc;
+
+use bytes::Bytes;
+use tokio::task::spawn_blocking;
+
+use crate::{
+    blocking::join_error,
+    chimera::{Database, Match, Matching, ScratchPool},
+    Result,
+};
+
+/// Scan the data with a Chimera database on the blocking thread pool.
+///
+/// The patterns which hit the PCRE match or recursion limit are skipped.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::sync::Arc;
+/// # use hyperscan::chimera::{blocking::scan_blocking, prelude::*, Match};
+/// let db: Arc<Database> = Arc::new("/test/i".parse().unwrap());
+/// let pool = Arc::new(ScratchPool::new(&db).unwrap());
+///
+/// let rt = tokio::runtime::Builder::new_multi_thread().build().unwrap();
+/// let matches = rt.block_on(scan_blocking(db, "some test data".into(), pool)).unwrap();
+///
+/// assert_eq!(matches.len(), 1);
+/// assert_eq!((matches[0].from, matches[0].to), (5, 9));
+/// ```
+pub async fn scan_blocking(db: Arc<Database>, data: Bytes, pool: Arc<ScratchPool>) -> Result<Vec<Match>> {
+    let mut scratch = pool.take();
+
+    spawn_blocking(move || {
+        let mut matches = vec![];
+        let res = db.realloc_scratch(&mut scratch).and_then(|s| {
+            db.scan(
+                &data,
+                s,
+                |id, from, to, _, captured: Option<&[_]>| {
+                    matches.push(Match {
+                        id,
+                        from,
+                        to,
+                        captured: captured.map(|captured| captured.to_vec()),
+                    });
+
+                    Matching::Continue
+                },
+                |_, _| Matching::Skip,
+            )
+        });
+
+        pool.put(scratch);
+
+        res.map(|_| matches)
+    })
+    .await
+    .unwrap_or_else(join_error)
+}
@@ -128,6 +128,7 @@ impl Default for Mode {
 }
 
 /// Define match limits for PCRE runtime.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct MatchLimit {
     /// A limit from pcre_extra on the amount of match function called in PCRE to limit backtracking that can take place.
     pub max_matches: u64,
@@ -135,6 +136,56 @@ pub struct MatchLimit {
     pub recursion_depth: u64,
 }
 
+/// The PCRE default limit of the match function calls.
+const DEFAULT_MATCH_LIMIT: u64 = 10_000_000;
+
+/// The PCRE default limit of the match function recursion, the same as the match limit.
+const DEFAULT_MATCH_LIMIT_RECURSION: u64 = DEFAULT_MATCH_LIMIT;
+
+/// The options of the Chimera compiler, used by `Builder::with_options`.
+///
+/// The limits bound the backtracking of PCRE when a candidate match is confirmed, a pattern which hits a limit
+/// reports an error event, see `ErrorEvents`. An unset limit uses the PCRE default, 10,000,000.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::chimera::{prelude::*, CompileOptions, Mode};
+/// let options = CompileOptions {
+///     match_limit: Some(100_000),
+///     mode: Mode::Groups,
+///     ..Default::default()
+/// };
+/// let db = r"/(\w+) \1/".with_options(&options).unwrap();
+/// let scratch = db.alloc_scratch().unwrap();
+///
+/// let events = db.scan_error_events("foo foo", &scratch, Matching::Continue).unwrap();
+///
+/// assert!(events.is_empty());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CompileOptions {
+    /// The limit of the match function calls in PCRE, which limits the backtracking.
+    pub match_limit: Option<u64>,
+    /// The limit of the recursion depth of the match function in PCRE.
+    pub match_limit_recursion: Option<u64>,
+    /// Report the capturing groups, or only the match offsets.
+    pub mode: Mode,
+}
+
+impl CompileOptions {
+    fn limits(&self) -> Option<MatchLimit> {
+        if self.match_limit.is_none() && self.match_limit_recursion.is_none() {
+            None
+        } else {
+            Some(MatchLimit {
+                max_matches: self.match_limit.unwrap_or(DEFAULT_MATCH_LIMIT),
+                recursion_depth: self.match_limit_recursion.unwrap_or(DEFAULT_MATCH_LIMIT_RECURSION),
+            })
+        }
+    }
+}
+
 /// Compile an expression into a Chimera database.
 ///
 /// # Examples
@@ -266,6 +317,13 @@ pub trait Builder {
         self.for_platform(Mode::Groups, None, None)
     }
 
+    /// Build an expression is compiled into a Chimera database with the compile options.
+    ///
+    /// The limits are passed to `ch_compile_ext_multi` when at least one of them is set.
+    fn with_options(&self, options: &CompileOptions) -> Result<Database, Self::Err> {
+        self.for_platform(options.mode, options.limits(), None)
+    }
+
     /// Build an expression is compiled into a Chimera database for a target platform.
     fn for_platform(
         &self,
@@ -400,6 +458,21 @@ impl Builder for Patterns {
             }
             .ok_or_else(|| err.assume_init())
             .map(|_| Database::from_ptr(db.assume_init()))
+            .map_err(|err| match err {
+                // map the failed expression back to its pattern
+                Error::Chimera(ChError::CompileError(err)) => {
+                    match err.expression().filter(|&index| index < self.len()) {
+                        Some(index) => Error::InvalidChimeraPattern {
+                            index,
+                            id: ids[index] as usize,
+                            expression: self[index].expression.clone(),
+                            error: err,
+                        },
+                        None => ChError::CompileError(err).into(),
+                    }
+                }
+                err => err,
+            })
         }
     }
 }
@@ -411,3 +484,124 @@ impl FromStr for Database {
         s.parse::<Pattern>()?.build()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use crate::chimera::prelude::*;
+    use crate::chimera::{CompileOptions, Flags, MatchError, Mode};
+    use crate::Error;
+
+    #[test]
+    fn test_back_reference() {
+        // the back-references are rejected by Hyperscan, unless compiled in prefilter mode
+        assert!("/(\\w+) \\1/".parse::<crate::BlockDatabase>().is_err());
+
+        let patterns = Patterns::try_from("1:/(\\w+) \\1/\n2:/ba+r/i".parse::<crate::Patterns>().unwrap()).unwrap();
+        let db = patterns.build().unwrap();
+        let scratch = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan(
+            "foo bar bar BAAR",
+            &scratch,
+            |id, from, to, _, _| {
+                matches.push((id, from, to));
+                Matching::Continue
+            },
+            |_, _| Matching::Skip,
+        )
+        .unwrap();
+
+        matches.sort_unstable_by_key(|&(id, from, to)| (to, id, from));
+
+        assert_eq!(matches, vec![(2, 4, 7), (1, 4, 11), (2, 8, 11), (2, 12, 16)]);
+    }
+
+    #[test]
+    fn test_match_limit() {
+        let expression = r"/(\w+)+ \1/";
+        let data = format!("{} {}", "a".repeat(20), "a".repeat(20));
+        let scan = |options: &CompileOptions| {
+            let db = expression.with_options(options).unwrap();
+            let s = db.alloc_scratch().unwrap();
+            let mut matches = 0;
+
+            let events = db
+                .scan_error_events(&data, &s, |_, _, _, _, _| {
+                    matches += 1;
+                    Matching::Continue
+                })
+                .unwrap();
+
+            (matches, events)
+        };
+
+        // a tiny match limit is hit by the backtracking
+        let (matches, events) = scan(&CompileOptions {
+            match_limit: Some(10),
+            ..Default::default()
+        });
+
+        assert_eq!(matches, 0);
+        assert!(events.count_of(0, MatchError::MatchLimit) > 0);
+
+        // a generous limit, or the default one, isn't
+        for options in &[
+            CompileOptions {
+                match_limit: Some(1_000_000),
+                match_limit_recursion: Some(100_000),
+                mode: Mode::Groups,
+            },
+            CompileOptions::default(),
+        ] {
+            let (matches, events) = scan(options);
+
+            assert_eq!(matches, 1);
+            assert!(events.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let patterns: Patterns = "1:/foo/\n7:/(bar/".parse().unwrap();
+
+        match patterns.build().err().unwrap() {
+            Error::InvalidChimeraPattern {
+                index, id, expression, ..
+            } => assert_eq!((index, id, expression.as_str()), (1, 7, "(bar")),
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_flags() {
+        assert_eq!(
+            Flags::try_from(crate::PatternFlags::CASELESS | crate::PatternFlags::UTF8).unwrap(),
+            Flags::CASELESS | Flags::UTF8
+        );
+        assert_eq!(
+            Flags::try_from(crate::PatternFlags::SOM_LEFTMOST).unwrap_err(),
+            Error::UnsupportedChimeraFlags("SOM_LEFTMOST".to_owned())
+        );
+        assert!(Flags::try_from(crate::PatternFlags::CASELESS | crate::PatternFlags::PREFILTER).is_err());
+
+        // the Hyperscan only flags aren't parsed
+        assert_eq!("i8".parse::<Flags>().unwrap(), Flags::CASELESS | Flags::UTF8);
+        assert_eq!("iL".parse::<Flags>().unwrap_err(), Error::InvalidFlag('L'));
+    }
+
+    #[test]
+    fn test_unsupported_pattern() {
+        assert!(matches!(
+            Pattern::try_from(pattern! {"foo"; CASELESS | SOM_LEFTMOST}),
+            Err(Error::UnsupportedChimeraFlags(_))
+        ));
+        assert!(Pattern::try_from(pattern! {"foo"}.min_offset(3)).is_err());
+        assert_eq!(
+            Pattern::try_from(pattern! {7 => "foo"; CASELESS | UTF8}).unwrap(),
+            "7:/foo/i8".parse().unwrap()
+        );
+    }
+}
@@ -81,7 +81,7 @@ pub enum Error {
     UnknownError,
 
     /// Returned when pcre_exec (called for some expressions internally from `ch_scan`) failed due to a fatal error.
-    #[cfg(feature = "v5_4")]
+    #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
     #[error("Failed due to a fatal error")]
     FailInternal,
 
@@ -112,7 +112,7 @@ impl From<ffi::ch_error_t> for Error {
             ffi::CH_BAD_ALIGN => BadAlign,
             ffi::CH_BAD_ALLOC => BadAlloc,
             ffi::CH_SCRATCH_IN_USE => ScratchInUse,
-            #[cfg(feature = "v5_4")]
+            #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
             ffi::CH_FAIL_INTERNAL => FailInternal,
             ffi::CH_UNKNOWN_HS_ERROR => UnknownHSError,
             _ => Code(err),
@@ -25,26 +25,32 @@
 //! assert_eq!(matches, vec![(5, 9)]);
 //! assert_eq!(errors, vec![]);
 //! ```
+#[cfg(feature = "tokio")]
+pub mod blocking;
 mod common;
 mod compile;
 mod error;
 mod pattern;
+mod pool;
 mod runtime;
 
 #[doc(hidden)]
 pub use crate::ffi::chimera as ffi;
 
 pub use self::common::{version, Database, DatabaseRef};
-pub use self::compile::{compile, Builder, CompileError, Mode};
+pub use self::compile::{compile, Builder, CompileError, CompileOptions, MatchLimit, Mode};
 pub use self::error::Error;
 pub use self::pattern::{Flags, Pattern, Patterns};
+pub use self::pool::{PooledScratch, ScratchPool};
 pub use self::runtime::{
-    Capture, Error as MatchError, ErrorEventHandler, MatchEventHandler, Matching, Scratch, ScratchRef,
+    Capture, Captures, Error as MatchError, ErrorEventHandler, ErrorEvents, Match, MatchEventHandler, Matching,
+    Scratch, ScratchRef,
 };
 
 pub mod prelude {
     //! The `chimera` Prelude
     pub use crate::chimera::{
-        compile, Builder, Capture, Database, DatabaseRef, Error, Matching, Pattern, Patterns, Scratch, ScratchRef,
+        compile, Builder, Capture, Captures, Database, DatabaseRef, Error, Matching, Pattern, Patterns, Scratch,
+        ScratchPool, ScratchRef,
     };
 }
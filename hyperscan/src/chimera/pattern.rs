@@ -1,3 +1,4 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::iter::FromIterator;
 use std::str::FromStr;
@@ -26,6 +27,25 @@ bitflags! {
     }
 }
 
+#[cfg(feature = "compile")]
+impl TryFrom<crate::compile::Flags> for Flags {
+    type Error = Error;
+
+    /// Convert the Hyperscan flags, which share their values with the Chimera flags.
+    ///
+    /// The flags only supported by Hyperscan, such as `SOM_LEFTMOST` which Chimera always reports,
+    /// or `PREFILTER` and `COMBINATION`, are rejected as `Error::UnsupportedChimeraFlags`.
+    fn try_from(flags: crate::compile::Flags) -> Result<Self, Self::Error> {
+        let unsupported = flags - crate::compile::Flags::from_bits_truncate(Flags::all().bits());
+
+        if unsupported.is_empty() {
+            Ok(Flags::from_bits_truncate(flags.bits()))
+        } else {
+            Err(Error::UnsupportedChimeraFlags(format!("{:?}", unsupported)))
+        }
+    }
+}
+
 impl FromStr for Flags {
     type Err = Error;
 
@@ -181,6 +201,30 @@ impl FromStr for Pattern {
     }
 }
 
+#[cfg(feature = "compile")]
+impl TryFrom<crate::compile::Pattern> for Pattern {
+    type Error = Error;
+
+    /// Convert a Hyperscan pattern, with the flags supported by Chimera.
+    ///
+    /// The flags without Chimera equivalent are rejected as `Error::UnsupportedChimeraFlags`,
+    /// and the extended parameters as `Error::UnsupportedChimeraPattern`.
+    fn try_from(pattern: crate::compile::Pattern) -> Result<Self, Self::Error> {
+        if !pattern.ext.is_empty() {
+            return Err(Error::UnsupportedChimeraPattern(format!(
+                "extended parameters, `{}`",
+                pattern.expression
+            )));
+        }
+
+        Ok(Pattern {
+            flags: Flags::try_from(pattern.flags)?,
+            expression: pattern.expression,
+            id: pattern.id,
+        })
+    }
+}
+
 /// Vec of `Pattern`
 #[repr(transparent)]
 #[derive(Clone, Debug, Deref, DerefMut, From, Index, IndexMut, Into, IntoIterator)]
@@ -194,6 +238,16 @@ impl FromIterator<Pattern> for Patterns {
     }
 }
 
+#[cfg(feature = "compile")]
+impl TryFrom<crate::compile::Patterns> for Patterns {
+    type Error = Error;
+
+    /// Convert a set of Hyperscan patterns, keeping their IDs.
+    fn try_from(patterns: crate::compile::Patterns) -> Result<Self, Self::Error> {
+        patterns.0.into_iter().map(Pattern::try_from).collect()
+    }
+}
+
 impl FromStr for Patterns {
     type Err = Error;
 
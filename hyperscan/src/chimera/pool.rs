@@ -0,0 +1,112 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use crate::{
+    chimera::{DatabaseRef, Scratch},
+    Result,
+};
+
+/// A pool of scratch spaces shared by the concurrent callers of a database.
+///
+/// The scratch spaces are cloned from a prototype allocated for the database,
+/// and returned to the pool once the caller has finished scanning with them.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::chimera::prelude::*;
+/// let db: Database = "/test/i".parse().unwrap();
+/// let pool = ScratchPool::new(&db).unwrap();
+/// let mut matches = vec![];
+///
+/// db.scan("some test data", &pool.get(), |_, from, to, _, _| {
+///     matches.push(from..to);
+///     Matching::Continue
+/// }, ())
+/// .unwrap();
+///
+/// assert_eq!(matches, vec![5..9]);
+/// assert_eq!(pool.idle(), 1);
+/// ```
+pub struct ScratchPool {
+    prototype: Mutex<Scratch>,
+    free: Mutex<Vec<Scratch>>,
+}
+
+impl fmt::Debug for ScratchPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScratchPool").field("idle", &self.idle()).finish()
+    }
+}
+
+impl ScratchPool {
+    /// Create a new pool of scratch spaces for the database.
+    pub fn new(db: &DatabaseRef) -> Result<ScratchPool> {
+        Ok(ScratchPool {
+            prototype: Mutex::new(db.alloc_scratch()?),
+            free: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Returns the number of scratch spaces waiting in the pool.
+    pub fn idle(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+
+    /// Check out a scratch space, which is returned to the pool when the guard is dropped.
+    pub fn get(&self) -> PooledScratch<'_> {
+        PooledScratch {
+            pool: self,
+            scratch: Some(self.take()),
+        }
+    }
+
+    /// Check out an owned scratch space, which should be given back with `put`.
+    ///
+    /// A new scratch space is cloned from the prototype if the pool is empty.
+    pub fn take(&self) -> Scratch {
+        let scratch = self.free.lock().unwrap().pop();
+
+        scratch.unwrap_or_else(|| self.prototype.lock().unwrap().clone())
+    }
+
+    /// Give back a scratch space to the pool.
+    pub fn put(&self, scratch: Scratch) {
+        self.free.lock().unwrap().push(scratch)
+    }
+}
+
+/// A scratch space checked out from a `ScratchPool`.
+pub struct PooledScratch<'a> {
+    pool: &'a ScratchPool,
+    scratch: Option<Scratch>,
+}
+
+impl fmt::Debug for PooledScratch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledScratch").finish()
+    }
+}
+
+impl Deref for PooledScratch<'_> {
+    type Target = Scratch;
+
+    fn deref(&self) -> &Scratch {
+        self.scratch.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledScratch<'_> {
+    fn deref_mut(&mut self) -> &mut Scratch {
+        self.scratch.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledScratch<'_> {
+    fn drop(&mut self) {
+        if let Some(scratch) = self.scratch.take() {
+            self.pool.put(scratch)
+        }
+    }
+}
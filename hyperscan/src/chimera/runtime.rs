@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::mem::{self, MaybeUninit};
 use std::ops::Range;
@@ -8,8 +9,9 @@ use derive_more::{Deref, From, Into};
 use foreign_types::{foreign_type, ForeignType, ForeignTypeRef};
 
 use crate::{
-    chimera::{error::AsResult, ffi, DatabaseRef},
-    Result,
+    chimera::{error::AsResult, ffi, DatabaseRef, Error as ChError},
+    unwind::{catch, guarded},
+    Result, ScanOutcome,
 };
 
 foreign_type! {
@@ -41,6 +43,44 @@ impl ScratchRef {
 
         unsafe { ffi::ch_scratch_size(self.as_ptr(), size.as_mut_ptr()).map(|_| size.assume_init()) }
     }
+
+    /// Clone the scratch space, e.g. a prototype scratch space for each worker thread.
+    ///
+    /// Unlike `Clone`, a failed allocation is returned as `Error::NoMem` instead of panicking.
+    pub fn try_clone(&self) -> Result<Scratch> {
+        let mut p = MaybeUninit::uninit();
+
+        unsafe { ffi::ch_clone_scratch(self.as_ptr(), p.as_mut_ptr()).map(|_| Scratch::from_ptr(p.assume_init())) }
+    }
+}
+
+impl Scratch {
+    /// Allocate a scratch space large enough for all the databases.
+    ///
+    /// The scratch space is allocated for the first database, and grown for the others.
+    /// `Error::Invalid` is returned without any database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::chimera::prelude::*;
+    /// let foo: Database = "/foo/".parse().unwrap();
+    /// let bar: Database = r"/(\w+) \1/".parse().unwrap();
+    /// let s = Scratch::for_databases(&[&foo, &bar]).unwrap();
+    ///
+    /// foo.scan("foo", &s, Matching::Continue, ()).unwrap();
+    /// bar.scan("bar bar", &s, Matching::Continue, ()).unwrap();
+    /// ```
+    pub fn for_databases(dbs: &[&DatabaseRef]) -> Result<Scratch> {
+        let (first, rest) = dbs.split_first().ok_or(ChError::Invalid)?;
+        let mut s = first.alloc_scratch()?;
+
+        for db in rest {
+            db.realloc_scratch(&mut s)?;
+        }
+
+        Ok(s)
+    }
 }
 
 impl DatabaseRef {
@@ -55,16 +95,21 @@ impl DatabaseRef {
     }
 
     /// Reallocate a `scratch` space for use by Chimera.
-    pub fn realloc_scratch(&self, s: &mut Scratch) -> Result<&ScratchRef> {
-        let mut p = s.as_ptr();
-
-        unsafe {
-            ffi::ch_alloc_scratch(self.as_ptr(), &mut p).map(|_| {
-                s.0 = ptr::NonNull::new_unchecked(p);
-
-                ScratchRef::from_ptr(p)
-            })
+    ///
+    /// The scratch space is grown when it's too small for the database, so it may be used with several databases.
+    ///
+    /// Chimera frees the old scratch space when a larger one can't be allocated, and nulls the pointer,
+    /// so a clone is grown instead, and replaces the scratch space on success, which is left valid on failure.
+    pub fn realloc_scratch<'a>(&self, s: &'a mut Scratch) -> Result<&'a ScratchRef> {
+        let mut p = s.try_clone()?.into_ptr();
+        let res = unsafe { ffi::ch_alloc_scratch(self.as_ptr(), &mut p).ok() };
+        let grown = ptr::NonNull::new(p).map(|p| unsafe { Scratch::from_ptr(p.as_ptr()) });
+
+        if let Some(grown) = grown.filter(|_| res.is_ok()) {
+            *s = grown;
         }
+
+        res.map(move |_| &**s)
     }
 }
 
@@ -88,7 +133,7 @@ impl Default for Matching {
 
 /// The type of error event that occurred.
 #[repr(u32)]
-#[derive(Clone, Copy, Debug, From, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, From, PartialEq, Eq, Hash)]
 pub enum Error {
     /// PCRE hits its match limit.
     MatchLimit = ffi::CH_ERROR_MATCHLIMIT,
@@ -96,6 +141,53 @@ pub enum Error {
     RecursionLimit = ffi::CH_ERROR_RECURSIONLIMIT,
 }
 
+/// The error events of a scan, counted by pattern.
+///
+/// Without an error handler, a pattern which hits a PCRE limit silently misses some matches.
+/// `ErrorEvents::handler` records each event, and skips the pattern as `Matching::Skip` does.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ErrorEvents(HashMap<(u32, Error), usize>);
+
+impl ErrorEvents {
+    /// Returns `true` if no error event occurred.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The number of error events of all the patterns.
+    pub fn total(&self) -> usize {
+        self.0.values().sum()
+    }
+
+    /// The number of error events of the pattern.
+    pub fn count(&self, id: u32) -> usize {
+        self.0
+            .iter()
+            .filter(|&(&(event_id, _), _)| event_id == id)
+            .map(|(_, &n)| n)
+            .sum()
+    }
+
+    /// The number of error events of the type for the pattern.
+    pub fn count_of(&self, id: u32, error: Error) -> usize {
+        self.0.get(&(id, error)).copied().unwrap_or_default()
+    }
+
+    /// An iterator over the pattern IDs and error types, with their number of events.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, Error, usize)> + '_ {
+        self.0.iter().map(|(&(id, error), &n)| (id, error, n))
+    }
+
+    /// Record the error events, and skip the patterns which hit a limit.
+    pub fn handler(&mut self) -> impl FnMut(Error, u32) -> Matching + '_ {
+        move |error, id| {
+            *self.0.entry((id, error)).or_default() += 1;
+
+            Matching::Skip
+        }
+    }
+}
+
 /// Structure representing a captured subexpression within a match.
 #[repr(transparent)]
 #[derive(Clone, Copy, From, Into, Deref, PartialEq, Eq)]
@@ -129,6 +221,85 @@ impl Capture {
     }
 }
 
+/// A capture group is serialized as `{"from", "to"}`, or `null` if it isn't active.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Capture {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        if self.is_active() {
+            let mut capture = serializer.serialize_struct("Capture", 2)?;
+
+            capture.serialize_field("from", &self.from)?;
+            capture.serialize_field("to", &self.to)?;
+            capture.end()
+        } else {
+            serializer.serialize_none()
+        }
+    }
+}
+
+/// The capture groups of a match, only valid during the match callback.
+///
+/// The group `0` is the entire pattern match, followed by each captured subexpression in the order of their
+/// opening parenthesis. The captures are only reported for a database compiled with `Mode::Groups`,
+/// otherwise they are empty.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Captures<'a>(&'a [Capture]);
+
+impl<'a> Captures<'a> {
+    /// The number of capture groups, including the entire pattern match.
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Returns `true` if no capture group is reported.
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the offsets of the capture group, or `None` if the group didn't participate in the match.
+    pub fn get(&self, group: usize) -> Option<(u64, u64)> {
+        self.0
+            .get(group)
+            .filter(|capture| capture.is_active())
+            .map(|capture| (capture.from, capture.to))
+    }
+
+    /// Returns the text of the capture group in the scanned data.
+    pub fn slice<'d>(&self, data: &'d [u8], group: usize) -> Option<&'d [u8]> {
+        self.get(group)
+            .and_then(|(from, to)| data.get(from as usize..to as usize))
+    }
+
+    /// An iterator over the offsets of the capture groups.
+    pub fn iter(&self) -> impl Iterator<Item = Option<(u64, u64)>> + 'a {
+        let captures = *self;
+
+        (0..captures.len()).map(move |group| captures.get(group))
+    }
+
+    /// Copy the offsets of the capture groups, to keep them after the match callback.
+    pub fn to_owned(&self) -> Vec<Option<(u64, u64)>> {
+        self.iter().collect()
+    }
+}
+
+/// A match reported by Chimera, collected for scanning away from the caller.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Match {
+    /// The ID number of the expression that matched.
+    pub id: u32,
+    /// The offset of the first byte that matches the expression.
+    pub from: u64,
+    /// The offset after the last byte that matches the expression.
+    pub to: u64,
+    /// The entire pattern match and each captured subexpression, if the captures were requested.
+    #[cfg_attr(feature = "serde", serde(rename = "groups"))]
+    pub captured: Option<Vec<Capture>>,
+}
+
 /// Definition of the match event callback function type.
 ///
 /// A callback function matching the defined type must be provided by the
@@ -195,18 +366,13 @@ where
     F: FnMut(u32, u64, u64, u32, Option<&'a [Capture]>) -> Matching,
 {
     let &mut (ref mut callback, _) = &mut *(ctx as *mut (&mut F, *mut ()));
+    let captured = if captured.is_null() || size == 0 {
+        None
+    } else {
+        Some(slice::from_raw_parts(captured as *const _, size as usize))
+    };
 
-    callback(
-        id,
-        from,
-        to,
-        flags,
-        if captured.is_null() || size == 0 {
-            None
-        } else {
-            Some(slice::from_raw_parts(captured as *const _, size as usize))
-        },
-    ) as i32
+    catch(|| callback(id, from, to, flags, captured)).unwrap_or(Matching::Terminate) as i32
 }
 
 /// Definition of the Chimera error event callback function type.
@@ -263,8 +429,9 @@ where
     F: FnMut(Error, u32) -> Matching,
 {
     let &mut (_, ref mut callback) = &mut *(ctx as *mut (*mut (), &mut F));
+    let error_type = mem::transmute::<ffi::ch_error_event_t, Error>(error_type);
 
-    callback(mem::transmute(error_type), id) as i32
+    catch(|| callback(error_type, id)).unwrap_or(Matching::Terminate) as i32
 }
 
 impl DatabaseRef {
@@ -293,7 +460,7 @@ impl DatabaseRef {
     ///
     /// ### Return
     ///
-    /// The callback can return `Matching::Terminate` to stop matching.
+    /// The callback can return `Matching::Terminate` to stop matching, the scan returns `ScanOutcome::Terminated`.
     /// Otherwise, a return value of `Matching::Continue` will continue,
     /// with the current pattern if configured to produce multiple matches per pattern,
     /// while a return value of `Matching::Skip` will cease matching this pattern but continue matching the next pattern.
@@ -325,7 +492,7 @@ impl DatabaseRef {
         scratch: &'a ScratchRef,
         mut on_match_event: F,
         mut on_error_event: E,
-    ) -> Result<()>
+    ) -> Result<ScanOutcome>
     where
         T: AsRef<[u8]>,
         F: MatchEventHandler<'a>,
@@ -338,19 +505,98 @@ impl DatabaseRef {
 
             let mut userdata = (on_match_data, on_error_data);
 
-            ffi::ch_scan(
-                self.as_ptr(),
-                data.as_ptr() as *const _,
-                data.len() as _,
-                0,
-                scratch.as_ptr(),
-                on_match_callback,
-                on_error_callback,
-                &mut userdata as *mut _ as *mut _,
-            )
-            .ok()
+            ScanOutcome::of(guarded(|| {
+                ffi::ch_scan(
+                    self.as_ptr(),
+                    data.as_ptr() as *const _,
+                    data.len() as _,
+                    0,
+                    scratch.as_ptr(),
+                    on_match_callback,
+                    on_error_callback,
+                    &mut userdata as *mut _ as *mut _,
+                )
+                .ok()
+            }))
         }
     }
+
+    /// Scan the data, and returns the error events of the patterns which hit a PCRE limit.
+    ///
+    /// The patterns are skipped after their first error event, the other patterns are still matched.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::chimera::prelude::*;
+    /// let db: Database = "/test/i".parse().unwrap();
+    /// let scratch = db.alloc_scratch().unwrap();
+    ///
+    /// let events = db.scan_error_events("some test data", &scratch, Matching::Continue).unwrap();
+    ///
+    /// assert!(events.is_empty());
+    /// ```
+    pub fn scan_error_events<'a, T, F>(
+        &self,
+        data: T,
+        scratch: &'a ScratchRef,
+        on_match_event: F,
+    ) -> Result<ErrorEvents>
+    where
+        T: AsRef<[u8]>,
+        F: MatchEventHandler<'a>,
+    {
+        let mut events = ErrorEvents::default();
+
+        self.scan(data, scratch, on_match_event, events.handler())?;
+
+        Ok(events)
+    }
+
+    /// Scan the data, and report the capture groups of each match.
+    ///
+    /// The captures borrow the scratch space of the scan, so they can't escape the match handler,
+    /// use `Captures::to_owned` to keep them.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::chimera::prelude::*;
+    /// let pattern: Pattern = r"(\w+)@(\w+)\.com".parse().unwrap();
+    /// let db = pattern.with_groups().unwrap();
+    /// let scratch = db.alloc_scratch().unwrap();
+    /// let data = "mail alice@example.com";
+    /// let mut domains = vec![];
+    ///
+    /// db.scan_captures(data, &scratch, |_, _, _, captures| {
+    ///     domains.push(captures.slice(data.as_bytes(), 2).map(<[u8]>::to_vec));
+    ///     Matching::Continue
+    /// }, Matching::Skip)
+    /// .unwrap();
+    ///
+    /// assert_eq!(domains, vec![Some(b"example".to_vec())]);
+    /// ```
+    pub fn scan_captures<T, F, E>(
+        &self,
+        data: T,
+        scratch: &ScratchRef,
+        mut on_match: F,
+        on_error_event: E,
+    ) -> Result<ScanOutcome>
+    where
+        T: AsRef<[u8]>,
+        F: FnMut(u32, u64, u64, Captures<'_>) -> Matching,
+        E: ErrorEventHandler,
+    {
+        self.scan(
+            data,
+            scratch,
+            |id, from, to, _, captured: Option<&[Capture]>| {
+                on_match(id, from, to, Captures(captured.unwrap_or_default()))
+            },
+            on_error_event,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -360,6 +606,8 @@ pub mod tests {
     use foreign_types::ForeignType;
 
     use crate::chimera::prelude::*;
+    use crate::chimera::{MatchError, MatchLimit, Mode};
+    use crate::ScanOutcome;
 
     const SCRATCH_SIZE: usize = 2000;
 
@@ -384,4 +632,253 @@ pub mod tests {
         assert!(!ptr::eq(s.as_ptr(), s2.as_ptr()));
         assert!(s2.size().unwrap() >= s.size().unwrap());
     }
+
+    #[test]
+    fn test_scratch_threads() {
+        let db: Database = r"/(\w+) \1/".parse().unwrap();
+        let s = db.alloc_scratch().unwrap();
+        let s2 = s.try_clone().unwrap();
+
+        assert_eq!(s2.size().unwrap(), s.size().unwrap());
+
+        let scan = |s: Scratch, data: &str| {
+            let mut matches = vec![];
+
+            for _ in 0..100 {
+                matches.clear();
+
+                db.scan(
+                    data,
+                    &s,
+                    |_, from, to, _, _| {
+                        matches.push(from..to);
+                        Matching::Continue
+                    },
+                    (),
+                )
+                .unwrap();
+            }
+
+            matches
+        };
+
+        let (matches, matches2) = std::thread::scope(|t| {
+            // the scratch space isn't `Sync`, each thread owns its clone
+            let h = t.spawn(|| scan(s, "foo foo"));
+            let h2 = t.spawn(|| scan(s2, "a bar bar"));
+
+            (h.join().unwrap(), h2.join().unwrap())
+        });
+
+        assert_eq!(matches, vec![0..7]);
+        assert_eq!(matches2, vec![2..9]);
+    }
+
+    #[test]
+    fn test_scratch_for_databases() {
+        let db: Database = "test".parse().unwrap();
+        let db2: Database = r"/(\w+)@(\w+)\.com|(\d+)-(\d+)/".parse().unwrap();
+        let s = Scratch::for_databases(&[&db, &db2]).unwrap();
+
+        assert!(s.size().unwrap() >= db.alloc_scratch().unwrap().size().unwrap());
+        assert!(s.size().unwrap() >= db2.alloc_scratch().unwrap().size().unwrap());
+        assert!(db.scan("test", &s, Matching::Continue, ()).is_ok());
+        assert!(db2.scan("a@b.com", &s, Matching::Continue, ()).is_ok());
+        assert!(Scratch::for_databases(&[]).is_err());
+    }
+
+    #[test]
+    fn test_terminate_and_skip() {
+        let patterns: Patterns = "1:/foo/\n2:/ba+r/".parse().unwrap();
+        let db = patterns.build().unwrap();
+        let s = db.alloc_scratch().unwrap();
+        let mut ids = vec![];
+
+        let outcome = db.scan(
+            "foo bar foo baar",
+            &s,
+            |id, _, _, _, _| {
+                ids.push(id);
+                Matching::Terminate
+            },
+            (),
+        );
+
+        // the termination requested by the handler isn't an error
+        assert_eq!(outcome.unwrap(), ScanOutcome::Terminated);
+        assert_eq!(ids, vec![1]);
+
+        ids.clear();
+
+        let outcome = db.scan(
+            "foo bar foo baar",
+            &s,
+            |id, _, _, _, _| {
+                ids.push(id);
+                if id == 1 {
+                    Matching::Skip
+                } else {
+                    Matching::Continue
+                }
+            },
+            (),
+        );
+
+        assert_eq!(outcome.unwrap(), ScanOutcome::Completed);
+        assert_eq!(ids, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn test_error_events() {
+        let patterns: Patterns = r"1:/(\w+) \1/
+2:/ba+r/"
+            .parse()
+            .unwrap();
+        let limit = MatchLimit {
+            max_matches: 1,
+            recursion_depth: 10_000,
+        };
+        let db = patterns.for_platform(Mode::NoGroups, Some(limit), None).unwrap();
+        let s = db.alloc_scratch().unwrap();
+        let mut ids = vec![];
+
+        let events = db
+            .scan_error_events("foo foo bar", &s, |id, _, _, _, _| {
+                ids.push(id);
+                Matching::Continue
+            })
+            .unwrap();
+
+        // the back-reference hits the match limit, the other pattern still matches
+        assert_eq!(ids, vec![2]);
+        assert!(events.count(1) > 0);
+        assert_eq!(events.count(1), events.count_of(1, MatchError::MatchLimit));
+        assert_eq!(events.count(2), 0);
+        assert_eq!(events.total(), events.iter().map(|(_, _, n)| n).sum::<usize>());
+
+        // without limit, the back-reference matches
+        let db = patterns.build().unwrap();
+        let s = db.alloc_scratch().unwrap();
+        let mut ids = vec![];
+
+        let events = db
+            .scan_error_events("foo foo bar", &s, |id, _, _, _, _| {
+                ids.push(id);
+                Matching::Continue
+            })
+            .unwrap();
+
+        ids.sort_unstable();
+
+        assert_eq!(ids, vec![1, 2]);
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_scan_captures() {
+        let pattern: Pattern = r"(\w+)@(\w+)\.com".parse().unwrap();
+        let db = pattern.with_groups().unwrap();
+        let s = db.alloc_scratch().unwrap();
+        let data = "mail alice@example.com or bob@test.com";
+        let mut captures = vec![];
+        let mut users = vec![];
+
+        db.scan_captures(
+            data,
+            &s,
+            |_, _, _, groups| {
+                captures.push(groups.to_owned());
+                users.push(String::from_utf8(groups.slice(data.as_bytes(), 1).unwrap().to_vec()).unwrap());
+
+                assert_eq!(groups.len(), 3);
+                assert_eq!(groups.get(3), None);
+
+                Matching::Continue
+            },
+            (),
+        )
+        .unwrap();
+
+        assert_eq!(
+            captures,
+            vec![
+                vec![Some((5, 22)), Some((5, 10)), Some((11, 18))],
+                vec![Some((26, 38)), Some((26, 29)), Some((30, 34))],
+            ]
+        );
+        assert_eq!(users, vec!["alice", "bob"]);
+
+        // the group which didn't participate in the match is inactive
+        let db = "/(a)|(b)/".parse::<Pattern>().unwrap().with_groups().unwrap();
+        let mut captures = vec![];
+
+        db.scan_captures(
+            "b",
+            &db.alloc_scratch().unwrap(),
+            |_, _, _, groups| {
+                captures.push(groups.iter().collect::<Vec<_>>());
+                Matching::Continue
+            },
+            (),
+        )
+        .unwrap();
+
+        assert_eq!(captures, vec![vec![Some((0, 1)), None, Some((0, 1))]]);
+
+        // the captures aren't reported without `Mode::Groups`
+        let db = pattern.build().unwrap();
+        let mut empty = vec![];
+
+        db.scan_captures(
+            data,
+            &db.alloc_scratch().unwrap(),
+            |_, _, _, groups| {
+                empty.push(groups.is_empty());
+                Matching::Continue
+            },
+            (),
+        )
+        .unwrap();
+
+        assert_eq!(empty, vec![true, true]);
+    }
+
+    #[test]
+    fn test_handler_panic() {
+        let db: Database = "foo".parse().unwrap();
+        let s = db.alloc_scratch().unwrap();
+
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            db.scan("foo foo", &s, |_, _, _, _, _| -> Matching { panic!("boom") }, ())
+        }))
+        .expect_err("the handler should panic");
+
+        assert_eq!(*payload.downcast::<&str>().unwrap(), "boom");
+        assert!(db.scan("foo", &s, Matching::Continue, ()).is_ok());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_match() {
+        use serde_json::json;
+
+        use crate::chimera::{ffi, Capture, Match};
+
+        let capture = |flags, from, to| Capture::from(ffi::ch_capture { flags, from, to });
+        let m = Match {
+            id: 1,
+            from: 4,
+            to: 10,
+            captured: Some(vec![capture(ffi::CH_CAPTURE_FLAG_ACTIVE, 4, 10), capture(0, 0, 0)]),
+        };
+
+        assert_eq!(
+            serde_json::to_value(&m).unwrap(),
+            json!({"id": 1, "from": 4, "to": 10, "groups": [{"from": 4, "to": 10}, null]})
+        );
+        assert_eq!(
+            serde_json::to_value(Match { captured: None, ..m }).unwrap(),
+            json!({"id": 1, "from": 4, "to": 10, "groups": null})
+        );
+    }
 }
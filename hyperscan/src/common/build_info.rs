@@ -0,0 +1,143 @@
+use alloc::format;
+use core::fmt;
+
+use crate::{common::version_parsed, ffi, Error, Result};
+
+/// How the Hyperscan library was linked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LinkKind {
+    /// Linked with the static library.
+    Static,
+    /// Linked with the dynamic library.
+    Dynamic,
+}
+
+impl fmt::Display for LinkKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LinkKind::Static => "static",
+            LinkKind::Dynamic => "dylib",
+        })
+    }
+}
+
+/// The information about how the Hyperscan library was built and linked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The `(major, minor, patch)` version of the Hyperscan headers used at build time.
+    pub version: (u32, u32, u32),
+    /// How the Hyperscan library was linked.
+    pub link_kind: LinkKind,
+    /// Whether the Chimera library was linked.
+    pub chimera: bool,
+    /// The directory of the Hyperscan headers used at build time.
+    pub include_dir: &'static str,
+}
+
+impl BuildInfo {
+    /// Verify the version of the library linked at runtime matches the headers used at build time.
+    ///
+    /// Only the major and minor versions are compared, the patch releases don't change the API.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// if let Err(err) = hyperscan::build_info().verify() {
+    ///     eprintln!("{}", err);
+    /// }
+    /// ```
+    pub fn verify(&self) -> Result<()> {
+        self.verify_with(version_parsed())
+    }
+
+    fn verify_with(&self, linked: (u32, u32, u32)) -> Result<()> {
+        if (self.version.0, self.version.1) == (linked.0, linked.1) {
+            Ok(())
+        } else {
+            Err(Error::VersionMismatch {
+                built: format!("{}.{}.{}", self.version.0, self.version.1, self.version.2),
+                linked: format!("{}.{}.{}", linked.0, linked.1, linked.2),
+            })
+        }
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (major, minor, patch) = self.version;
+
+        write!(
+            f,
+            "hyperscan {}.{}.{} ({}{}), headers @ {}",
+            major,
+            minor,
+            patch,
+            self.link_kind,
+            if self.chimera { ", chimera" } else { "" },
+            self.include_dir
+        )
+    }
+}
+
+/// Returns how the Hyperscan library was built and linked.
+///
+/// # Examples
+///
+/// ```rust
+/// println!("{}", hyperscan::build_info());
+/// ```
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        version: ffi::HS_DETECTED_VERSION,
+        link_kind: if ffi::HS_LINK_KIND == "static" {
+            LinkKind::Static
+        } else {
+            LinkKind::Dynamic
+        },
+        chimera: ffi::HS_CHIMERA,
+        include_dir: ffi::HS_INCLUDE_DIR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+
+        assert!(info.version >= (4, 0, 0));
+        assert_eq!(info.link_kind == LinkKind::Static, cfg!(feature = "static"));
+        assert_eq!(info.chimera, cfg!(feature = "chimera"));
+        assert!(!info.include_dir.is_empty());
+        assert!(info.to_string().starts_with("hyperscan "));
+        assert!(info.verify().is_ok());
+    }
+
+    #[test]
+    fn test_verify_mismatch() {
+        let info = BuildInfo {
+            version: (5, 4, 0),
+            link_kind: LinkKind::Dynamic,
+            chimera: false,
+            include_dir: "/usr/include/hs",
+        };
+
+        assert!(info.verify_with((5, 4, 0)).is_ok());
+        assert!(info.verify_with((5, 4, 2)).is_ok());
+        assert_eq!(
+            info.verify_with((5, 2, 1)).unwrap_err(),
+            Error::VersionMismatch {
+                built: "5.4.0".into(),
+                linked: "5.2.1".into()
+            }
+        );
+        assert!(info.verify_with((4, 4, 0)).is_err());
+        assert_eq!(
+            info.verify_with((5, 2, 1)).unwrap_err().to_string(),
+            "built with Hyperscan 5.4.0, but linked with 5.2.1"
+        );
+        assert_eq!(info.to_string(), "hyperscan 5.4.0 (dylib), headers @ /usr/include/hs");
+    }
+}
@@ -1,17 +1,23 @@
-use std::ffi::CStr;
-use std::marker::PhantomData;
-use std::mem::MaybeUninit;
+use alloc::{borrow::ToOwned, string::String};
+use core::ffi::CStr;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::str::FromStr;
 
 use foreign_types::{foreign_type, ForeignTypeRef};
 
 use crate::{
-    common::{Block, Mode, Streaming, Vectored},
+    allocator,
+    common::{Block, DbMode, Error as HsError, Mode, Serialized, Streaming, Vectored},
     error::AsResult,
-    ffi, Result,
+    ffi, Error, Result,
 };
 
 foreign_type! {
     /// A compiled pattern database that can then be used to scan data.
+    ///
+    /// The database is `Send` and `Sync`: Hyperscan never modifies a compiled database,
+    /// so it may be shared by any number of threads, as long as each thread scans with its own `Scratch`.
     pub unsafe type Database<T>: Send + Sync {
         type CType = ffi::hs_database_t;
         type PhantomData = PhantomData<T>;
@@ -55,18 +61,200 @@ impl<T> DatabaseRef<T> {
     }
 
     /// Utility function providing information about a database.
+    ///
+    /// The information is formatted as `Version: 5.4.0 Features: AVX2 Mode: BLOCK`.
     pub fn info(&self) -> Result<String> {
         let mut p = MaybeUninit::uninit();
 
         unsafe {
             ffi::hs_database_info(self.as_ptr(), p.as_mut_ptr()).and_then(|_| {
                 let p = p.assume_init();
-                let info = CStr::from_ptr(p).to_str()?.to_owned();
-                libc::free(p as *mut _);
-                Ok(info)
+                let info = CStr::from_ptr(p).to_str().map(ToOwned::to_owned);
+                allocator::free_misc(p as *mut _);
+                Ok(info?)
             })
         }
     }
+
+    /// Utility function providing the parsed information about a database.
+    pub fn parsed_info(&self) -> Result<DbInfo> {
+        self.info()?.parse()
+    }
+
+    /// The identity of the database, the length and the CRC of its bytecode recorded by Hyperscan in its header.
+    ///
+    /// The identity is kept by the serialized and deserialized copies of the database.
+    pub(crate) fn tag(&self) -> u64 {
+        // the head of `struct hs_database` in Hyperscan's `database.h`
+        #[allow(dead_code)]
+        #[repr(C)]
+        struct Header {
+            magic: u32,
+            version: u32,
+            length: u32,
+            platform: u64,
+            crc32: u32,
+        }
+
+        let header = unsafe { &*(self.as_ptr() as *const Header) };
+
+        (u64::from(header.length) << 32) | u64::from(header.crc32)
+    }
+}
+
+/// The information about a database, parsed from `DatabaseRef::info` or `Serialized::info`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{DbInfo, DbMode};
+/// let info: DbInfo = "Version: 5.4.0 Features: AVX2 Mode: STREAM".parse().unwrap();
+///
+/// assert_eq!(info.version, (5, 4, 0));
+/// assert_eq!(info.raw_features, "AVX2");
+/// assert_eq!(info.mode, DbMode::Streaming);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DbInfo {
+    /// The version of Hyperscan which compiled the database, as `(major, minor, patch)`.
+    pub version: (u32, u32, u32),
+    /// The CPU features the database was compiled for, empty for the generic platform.
+    ///
+    /// The unknown features, e.g. of a newer version of Hyperscan, are skipped here and kept in `raw_features`.
+    #[cfg(feature = "compile")]
+    pub features: crate::compile::CpuFeatures,
+    /// The CPU features as formatted by Hyperscan, e.g. `AVX2 AVX512`.
+    pub raw_features: String,
+    /// The mode of the database.
+    pub mode: DbMode,
+}
+
+impl FromStr for DbInfo {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidDbInfo(s.to_owned());
+        let (version, rest) = s
+            .strip_prefix("Version: ")
+            .and_then(|rest| rest.split_once(" Features:"))
+            .ok_or_else(invalid)?;
+        let (features, mode) = rest.rsplit_once(" Mode: ").ok_or_else(invalid)?;
+        let mut parts = version.trim().splitn(3, '.').map(|part| part.parse::<u32>().ok());
+        let version = match (parts.next(), parts.next(), parts.next()) {
+            (Some(Some(major)), Some(Some(minor)), Some(Some(patch))) => (major, minor, patch),
+            _ => return Err(invalid()),
+        };
+        let features = features.trim();
+
+        Ok(DbInfo {
+            version,
+            #[cfg(feature = "compile")]
+            features: features
+                .split_whitespace()
+                .flat_map(|name| name.parse())
+                .fold(crate::compile::CpuFeatures::empty(), |features, feature| {
+                    features | feature
+                }),
+            raw_features: features.to_owned(),
+            mode: match mode.trim() {
+                "BLOCK" => DbMode::Block,
+                "STREAM" => DbMode::Streaming,
+                "VECTORED" => DbMode::Vectored,
+                _ => return Err(invalid()),
+            },
+        })
+    }
+}
+
+/// A database of the mode recorded in its serialized bytes, e.g. a database loaded at runtime.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, AnyDatabase, HsError, StreamingMode};
+/// let db: StreamingDatabase = pattern! {"foo"}.build().unwrap();
+/// let db = AnyDatabase::deserialize(db.serialize().unwrap()).unwrap();
+///
+/// assert!(matches!(db, AnyDatabase::Streaming(_)));
+/// assert_eq!(db.mode(), StreamingMode::ID);
+///
+/// let db = db.try_into_streaming().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let st = db.open_stream().unwrap();
+///
+/// st.scan("foo", &mut s, |_, _, _, _| Matching::Continue).unwrap();
+/// ```
+pub enum AnyDatabase {
+    /// Block scan (non-streaming) database.
+    Block(BlockDatabase),
+    /// Streaming database.
+    Streaming(StreamingDatabase),
+    /// Vectored scanning database.
+    Vectored(VectoredDatabase),
+}
+
+impl AnyDatabase {
+    /// Reconstruct a pattern database of the mode recorded in the bytes previously generated by `Database::serialize()`.
+    pub fn deserialize<B: AsRef<[u8]>>(bytes: B) -> Result<AnyDatabase> {
+        let bytes = bytes.as_ref();
+
+        match bytes.parsed_info()?.mode {
+            DbMode::Block => bytes.deserialize().map(AnyDatabase::Block),
+            DbMode::Streaming => bytes.deserialize().map(AnyDatabase::Streaming),
+            DbMode::Vectored => bytes.deserialize().map(AnyDatabase::Vectored),
+        }
+    }
+
+    /// Provides the id of compiled mode of the database.
+    pub fn mode(&self) -> u32 {
+        match self {
+            AnyDatabase::Block(_) => Block::ID,
+            AnyDatabase::Streaming(_) => Streaming::ID,
+            AnyDatabase::Vectored(_) => Vectored::ID,
+        }
+    }
+
+    /// Returns the block database, otherwise `HsError::DbModeError`.
+    pub fn try_into_block(self) -> Result<BlockDatabase> {
+        match self {
+            AnyDatabase::Block(db) => Ok(db),
+            _ => Err(HsError::DbModeError.into()),
+        }
+    }
+
+    /// Returns the streaming database, otherwise `HsError::DbModeError`.
+    pub fn try_into_streaming(self) -> Result<StreamingDatabase> {
+        match self {
+            AnyDatabase::Streaming(db) => Ok(db),
+            _ => Err(HsError::DbModeError.into()),
+        }
+    }
+
+    /// Returns the vectored database, otherwise `HsError::DbModeError`.
+    pub fn try_into_vectored(self) -> Result<VectoredDatabase> {
+        match self {
+            AnyDatabase::Vectored(db) => Ok(db),
+            _ => Err(HsError::DbModeError.into()),
+        }
+    }
+}
+
+impl From<BlockDatabase> for AnyDatabase {
+    fn from(db: BlockDatabase) -> Self {
+        AnyDatabase::Block(db)
+    }
+}
+
+impl From<StreamingDatabase> for AnyDatabase {
+    fn from(db: StreamingDatabase) -> Self {
+        AnyDatabase::Streaming(db)
+    }
+}
+
+impl From<VectoredDatabase> for AnyDatabase {
+    fn from(db: VectoredDatabase) -> Self {
+        AnyDatabase::Vectored(db)
+    }
 }
 
 #[cfg(test)]
@@ -74,6 +262,7 @@ pub mod tests {
     use regex::Regex;
 
     use crate::prelude::*;
+    use crate::CpuFeatures;
 
     use super::*;
 
@@ -112,6 +301,23 @@ pub mod tests {
         validate_database_with_size(db, DATABASE_SIZE);
     }
 
+    #[test]
+    fn test_database_send_sync() {
+        fn assert_send<T: Send>() {}
+        fn assert_sync<T: Sync>() {}
+
+        assert_send::<BlockDatabase>();
+        assert_sync::<BlockDatabase>();
+        assert_send::<StreamingDatabase>();
+        assert_sync::<StreamingDatabase>();
+        assert_send::<VectoredDatabase>();
+        assert_sync::<VectoredDatabase>();
+        assert_sync::<DatabaseRef<Block>>();
+        assert_send::<AnyDatabase>();
+        assert_sync::<AnyDatabase>();
+        assert_send::<Scratch>();
+    }
+
     #[test]
     fn test_database() {
         let db: BlockDatabase = "test".parse().unwrap();
@@ -123,4 +329,123 @@ pub mod tests {
 
         validate_database_info(&db_info);
     }
+
+    #[test]
+    fn test_database_size_and_info() {
+        fn check<T: Mode>(db: &DatabaseRef<T>, mode: &str) -> usize {
+            let info = db.info().unwrap();
+
+            assert!(
+                info.starts_with(&format!("Version: {}.", crate::version().major)),
+                "{}",
+                info
+            );
+            assert!(info.ends_with(&format!("Mode: {}", mode)), "{}", info);
+
+            db.size().unwrap()
+        }
+
+        let patterns: Patterns = (0..50).map(|i| pattern! {format!("foo{}bar", i)}).collect();
+        let one = Patterns(patterns[..1].to_vec());
+
+        let small: BlockDatabase = one.build().unwrap();
+        let large: BlockDatabase = patterns.build().unwrap();
+
+        assert!(check(&small, "BLOCK") > 0);
+        assert!(check(&large, "BLOCK") > check(&small, "BLOCK"));
+
+        let small: StreamingDatabase = one.build().unwrap();
+        let large: StreamingDatabase = patterns.build().unwrap();
+
+        assert!(check(&small, "STREAM") > 0);
+        assert!(check(&large, "STREAM") > check(&small, "STREAM"));
+
+        let small: VectoredDatabase = one.build().unwrap();
+        let large: VectoredDatabase = patterns.build().unwrap();
+
+        assert!(check(&small, "VECTORED") > 0);
+        assert!(check(&large, "VECTORED") > check(&small, "VECTORED"));
+
+        let deserialized = VectoredDatabase::deserialize(large.serialize().unwrap()).unwrap();
+
+        assert_eq!(check(&deserialized, "VECTORED"), large.size().unwrap());
+        assert_eq!(deserialized.info().unwrap(), large.info().unwrap());
+    }
+
+    #[test]
+    fn test_db_info() {
+        let db: StreamingDatabase = "test".parse().unwrap();
+        let info = db.parsed_info().unwrap();
+        let version = crate::version();
+
+        assert_eq!(
+            info.version,
+            (version.major as u32, version.minor as u32, version.patch as u32)
+        );
+        assert_eq!(info.mode, DbMode::Streaming);
+
+        let info: DbInfo = "Version: 5.2.1 Features:  Mode: BLOCK".parse().unwrap();
+
+        assert_eq!(
+            info,
+            DbInfo {
+                version: (5, 2, 1),
+                features: CpuFeatures::empty(),
+                raw_features: String::new(),
+                mode: DbMode::Block,
+            }
+        );
+
+        assert_eq!(
+            "Version: 5.2.1 Mode: BLOCK".parse::<DbInfo>().unwrap_err(),
+            Error::InvalidDbInfo("Version: 5.2.1 Mode: BLOCK".to_owned())
+        );
+        assert!("Version: 5.2.1 Features: AVX2 Mode: PARALLEL"
+            .parse::<DbInfo>()
+            .is_err());
+        assert!("Version: five Features: AVX2 Mode: BLOCK".parse::<DbInfo>().is_err());
+        assert!("Version: 5.2 Features: AVX2 Mode: BLOCK".parse::<DbInfo>().is_err());
+    }
+
+    #[test]
+    fn test_db_info_features() {
+        let block: BlockDatabase = "test".parse().unwrap();
+        let streaming: StreamingDatabase = "test".parse().unwrap();
+        let block_info = block.parsed_info().unwrap();
+        let streaming_info: DbInfo = streaming.info().unwrap().parse().unwrap();
+
+        assert_eq!(block_info.mode, DbMode::Block);
+        assert_eq!(streaming_info.mode, DbMode::Streaming);
+        assert_ne!(block_info.mode, streaming_info.mode);
+        assert_eq!(block_info.version, streaming_info.version);
+        assert_eq!(block_info.features, streaming_info.features);
+        assert_eq!(block_info.raw_features, streaming_info.raw_features);
+        assert_eq!(block.serialize().unwrap().parsed_info().unwrap(), block_info);
+
+        let info: DbInfo = "Version: 9.0.0 Features: AVX2 AVX1024 Mode: STREAM".parse().unwrap();
+
+        assert_eq!(info.features, CpuFeatures::AVX2);
+        assert_eq!(info.raw_features, "AVX2 AVX1024");
+    }
+
+    #[test]
+    fn test_any_database() {
+        let db: VectoredDatabase = "test".parse().unwrap();
+        let data = db.serialize().unwrap();
+        let db = AnyDatabase::deserialize(&data).unwrap();
+
+        assert_eq!(db.mode(), DbMode::Vectored.id());
+        assert!(AnyDatabase::deserialize(&data).unwrap().try_into_vectored().is_ok());
+        assert_eq!(db.try_into_block().err(), Some(Error::Hyperscan(HsError::DbModeError)));
+
+        let db: BlockDatabase = "test".parse().unwrap();
+        let db = AnyDatabase::from(db);
+
+        assert_eq!(db.mode(), DbMode::Block.id());
+        assert_eq!(
+            db.try_into_streaming().err(),
+            Some(Error::Hyperscan(HsError::DbModeError))
+        );
+        assert!(AnyDatabase::deserialize(b"garbage").is_err());
+    }
 }
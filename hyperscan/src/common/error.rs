@@ -55,7 +55,7 @@ pub enum Error {
     InsufficientSpace,
 
     /// Unexpected internal error.
-    #[cfg(feature = "v5")]
+    #[cfg(all(feature = "v5", hs_version_gte_5_0))]
     #[error("Unexpected internal error.")]
     UnknownError,
 
@@ -81,7 +81,7 @@ impl From<ffi::hs_error_t> for Error {
             ffi::HS_SCRATCH_IN_USE => ScratchInUse,
             ffi::HS_ARCH_ERROR => ArchError,
             ffi::HS_INSUFFICIENT_SPACE => InsufficientSpace,
-            #[cfg(feature = "v5")]
+            #[cfg(all(feature = "v5", hs_version_gte_5_0))]
             ffi::HS_UNKNOWN_ERROR => UnknownError,
             _ => Code(err),
         }
@@ -1,36 +1,22 @@
+mod build_info;
 mod database;
 mod error;
 mod mode;
 mod serialized;
+mod version;
 
-pub use self::database::{BlockDatabase, Database, DatabaseRef, StreamingDatabase, VectoredDatabase};
+pub use self::build_info::{build_info, BuildInfo, LinkKind};
+pub use self::database::{
+    AnyDatabase, BlockDatabase, Database, DatabaseRef, DbInfo, StreamingDatabase, VectoredDatabase,
+};
 pub use self::error::Error;
-pub use self::mode::{Block, Mode, Streaming, Vectored};
+pub use self::mode::{Block, DbMode, Mode, Streaming, Vectored};
 pub use self::serialized::Serialized;
+pub use self::version::{
+    supports_combinations, supports_literal_api, version, version_parsed, version_str, version_string,
+};
 
 #[cfg(test)]
 pub mod tests {
     pub use super::database::tests::*;
 }
-
-use std::ffi::CStr;
-
-use crate::ffi;
-
-/// The current Hyperscan version information.
-///
-/// # Examples
-///
-/// ```rust
-/// assert!(hyperscan::version_str().to_string_lossy().starts_with(&hyperscan::version().to_string()));
-/// ```
-pub fn version() -> semver::Version {
-    semver::Version::parse(version_str().to_string_lossy().split(' ').next().unwrap()).unwrap()
-}
-
-/// Utility function for identifying this release version.
-///
-/// Returns a string containing the version number of this release build  and the date of the build.
-pub fn version_str() -> &'static CStr {
-    unsafe { CStr::from_ptr(ffi::hs_version()) }
-}
@@ -49,3 +49,43 @@ impl Mode for Vectored {
     const ID: u32 = ffi::HS_MODE_VECTORED;
     const NAME: &'static str = "Vectored";
 }
+
+/// The mode of a database, read at runtime from its information.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DbMode {
+    /// Block scan (non-streaming) database.
+    Block,
+    /// Streaming database.
+    Streaming,
+    /// Vectored scanning database.
+    Vectored,
+}
+
+impl DbMode {
+    /// The mode of the type `T`.
+    pub fn of<T: Mode>() -> DbMode {
+        match T::ID {
+            Streaming::ID => DbMode::Streaming,
+            Vectored::ID => DbMode::Vectored,
+            _ => DbMode::Block,
+        }
+    }
+
+    /// Provides the id of the mode.
+    pub fn id(self) -> u32 {
+        match self {
+            DbMode::Block => Block::ID,
+            DbMode::Streaming => Streaming::ID,
+            DbMode::Vectored => Vectored::ID,
+        }
+    }
+
+    /// Provides the name of the mode.
+    pub fn name(self) -> &'static str {
+        match self {
+            DbMode::Block => Block::NAME,
+            DbMode::Streaming => Streaming::NAME,
+            DbMode::Vectored => Vectored::NAME,
+        }
+    }
+}
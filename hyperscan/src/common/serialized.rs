@@ -1,13 +1,14 @@
-use std::ffi::CStr;
-use std::fmt;
-use std::mem::MaybeUninit;
-use std::result::Result as StdResult;
+use alloc::{borrow::ToOwned, string::String};
+use core::ffi::CStr;
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::result::Result as StdResult;
 
 use foreign_types::{ForeignType, ForeignTypeRef};
 use libc::c_char;
-use malloc_buf::Malloc;
 
-use crate::common::{Database, DatabaseRef};
+use crate::allocator::{self, MiscBuf};
+use crate::common::{Database, DatabaseRef, DbInfo, DbMode, Error as HsError, Mode};
 use crate::error::{AsResult, Error, Result};
 use crate::ffi;
 
@@ -22,8 +23,13 @@ pub trait Serialized {
     /// Providing information about a serialized database.
     fn info(&self) -> StdResult<String, Self::Error>;
 
+    /// Providing the parsed information about a serialized database, without deserializing it.
+    fn parsed_info(&self) -> StdResult<DbInfo, Self::Error>;
+
     /// Reconstruct a pattern database from a stream of bytes previously generated by `Database::serialize()`.
-    fn deserialize<M>(&self) -> StdResult<Database<M>, Self::Error>;
+    ///
+    /// The database must be compiled in the mode `M`, otherwise `HsError::DbModeError` is returned.
+    fn deserialize<M: Mode>(&self) -> StdResult<Database<M>, Self::Error>;
 }
 
 impl<T: AsRef<[u8]>> Serialized for T {
@@ -43,20 +49,30 @@ impl<T: AsRef<[u8]>> Serialized for T {
         let buf = self.as_ref();
         let mut p = MaybeUninit::uninit();
 
+        allocator::mark_in_use();
+
         unsafe {
             ffi::hs_serialized_database_info(buf.as_ptr() as *const _, buf.len(), p.as_mut_ptr()).and_then(|_| {
                 let p = p.assume_init();
-                let info = CStr::from_ptr(p).to_str()?.to_owned();
-                libc::free(p as *mut _);
-                Ok(info)
+                let info = CStr::from_ptr(p).to_str().map(ToOwned::to_owned);
+                allocator::free_misc(p as *mut _);
+                Ok(info?)
             })
         }
     }
 
-    fn deserialize<M>(&self) -> Result<Database<M>> {
+    fn parsed_info(&self) -> Result<DbInfo> {
+        self.info()?.parse()
+    }
+
+    fn deserialize<M: Mode>(&self) -> Result<Database<M>> {
+        check_mode::<M>(self.as_ref())?;
+
         let buf = self.as_ref();
         let mut db = MaybeUninit::uninit();
 
+        allocator::mark_in_use();
+
         unsafe {
             ffi::hs_deserialize_database(buf.as_ptr() as *const c_char, buf.len(), db.as_mut_ptr())
                 .map(|_| Database::from_ptr(db.assume_init()))
@@ -64,6 +80,104 @@ impl<T: AsRef<[u8]>> Serialized for T {
     }
 }
 
+/// The alignment of a database deserialized in the caller-provided memory.
+const DATABASE_ALIGN: usize = 8;
+
+/// Check the serialized database was compiled in the mode `M`, if its mode can be read.
+fn check_mode<M: Mode>(bytes: &[u8]) -> Result<()> {
+    if bytes.parsed_info().is_ok_and(|info| info.mode != DbMode::of::<M>()) {
+        Err(HsError::DbModeError.into())
+    } else {
+        Ok(())
+    }
+}
+
+impl<T: Mode> Database<T> {
+    /// Reconstruct a pattern database from a stream of bytes previously generated by `Database::serialize()`.
+    ///
+    /// The database must be compiled in the mode of `T`, otherwise `HsError::DbModeError` is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, HsError};
+    /// let db: StreamingDatabase = pattern! {"foo"}.build().unwrap();
+    /// let buf = db.serialize().unwrap();
+    ///
+    /// assert!(StreamingDatabase::deserialize(&buf).is_ok());
+    /// assert_eq!(
+    ///     BlockDatabase::deserialize(&buf).err(),
+    ///     Some(HsError::DbModeError.into())
+    /// );
+    /// ```
+    pub fn deserialize<B: AsRef<[u8]>>(bytes: B) -> Result<Database<T>> {
+        bytes.as_ref().deserialize()
+    }
+
+    /// Reconstruct a pattern database in the memory provided by the caller, e.g. a shared memory or huge pages.
+    ///
+    /// The target must be 8-byte aligned, otherwise `HsError::BadAlign` is returned,
+    /// and hold at least `Serialized::size` bytes, otherwise `HsError::InsufficientSpace` is returned.
+    ///
+    /// The returned database borrows the target, it is never freed with `hs_free_database`.
+    /// The serialized bytes may be borrowed from an `include_bytes!` data or a read-only mapped file,
+    /// and the target from a writable mapping, e.g. `memmap2::MmapMut`, which can't be unmapped
+    /// while the database is used.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, SerializedDatabase};
+    /// let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+    /// let buf = db.serialize().unwrap();
+    ///
+    /// let mut storage = vec![0u64; (buf.size().unwrap() + 7) / 8];
+    /// let target = unsafe { std::slice::from_raw_parts_mut(storage.as_mut_ptr().cast::<u8>(), storage.len() * 8) };
+    /// let db = BlockDatabase::deserialize_into(&buf, target).unwrap();
+    ///
+    /// assert!(db.size().unwrap() > 0);
+    /// ```
+    ///
+    /// The database can't outlive its target.
+    ///
+    /// ```compile_fail
+    /// # use hyperscan::{prelude::*, SerializedDatabase};
+    /// let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+    /// let buf = db.serialize().unwrap();
+    ///
+    /// let mut storage = vec![0u64; (buf.size().unwrap() + 7) / 8];
+    /// let target = unsafe { std::slice::from_raw_parts_mut(storage.as_mut_ptr().cast::<u8>(), storage.len() * 8) };
+    /// let db = BlockDatabase::deserialize_into(&buf, target).unwrap();
+    ///
+    /// drop(storage);
+    ///
+    /// db.alloc_scratch().unwrap();
+    /// ```
+    pub fn deserialize_into<B: AsRef<[u8]>>(bytes: B, target: &mut [u8]) -> Result<&DatabaseRef<T>> {
+        let bytes = bytes.as_ref();
+
+        if target.as_ptr().align_offset(DATABASE_ALIGN) != 0 {
+            return Err(HsError::BadAlign.into());
+        }
+        if target.len() < bytes.size()? {
+            return Err(HsError::InsufficientSpace.into());
+        }
+
+        check_mode::<T>(bytes)?;
+
+        let db = unsafe { DatabaseRef::from_ptr_mut(target.as_mut_ptr().cast::<ffi::hs_database_t>()) };
+
+        unsafe { deserialize_at(bytes, db.as_ptr())? };
+
+        Ok(db)
+    }
+}
+
+/// Reconstruct the database at the location, which must be aligned and large enough for it.
+unsafe fn deserialize_at(bytes: &[u8], db: *mut ffi::hs_database_t) -> Result<()> {
+    ffi::hs_deserialize_database_at(bytes.as_ptr() as *const c_char, bytes.len(), db).ok()
+}
+
 impl<T> DatabaseRef<T> {
     /// Serialize a pattern database to a stream of bytes.
     ///
@@ -89,28 +203,157 @@ impl<T> DatabaseRef<T> {
     ///
     /// assert_eq!(matches, vec![6..12]);
     /// ```
-    pub fn serialize(&self) -> Result<Malloc<[u8]>> {
+    pub fn serialize(&self) -> Result<MiscBuf> {
         let mut ptr = MaybeUninit::uninit();
         let mut size = MaybeUninit::uninit();
 
         unsafe {
             ffi::hs_serialize_database(self.as_ptr(), ptr.as_mut_ptr(), size.as_mut_ptr())
-                .map(|_| Malloc::from_array(ptr.assume_init() as *mut u8, size.assume_init()))
+                .map(|_| MiscBuf::from_raw_parts(ptr.assume_init() as *mut u8, size.assume_init()))
         }
     }
+}
 
+impl<T: Mode> DatabaseRef<T> {
     /// Reconstruct a pattern database from a stream of bytes
     /// previously generated by `DatabaseRef::serialize()` at a given memory location.
+    ///
+    /// The database is overwritten in place, so the serialized database must fit in its size,
+    /// otherwise `HsError::InsufficientSpace` is returned, and must be compiled in the mode of `T`,
+    /// otherwise `HsError::DbModeError` is returned.
     pub fn deserialize_at<B: AsRef<[u8]>>(&mut self, bytes: B) -> Result<()> {
         let bytes = bytes.as_ref();
 
-        unsafe { ffi::hs_deserialize_database_at(bytes.as_ptr() as *const c_char, bytes.len(), self.as_ptr()).ok() }
+        if self.size()? < bytes.size()? {
+            return Err(HsError::InsufficientSpace.into());
+        }
+
+        check_mode::<T>(bytes)?;
+
+        unsafe { deserialize_at(bytes, self.as_ptr()) }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use alloc::vec::Vec;
+    use core::fmt;
+    use core::marker::PhantomData;
+    use core::result::Result as StdResult;
+
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::Serialized;
+    use crate::common::{Database, DatabaseRef, Mode};
+
+    /// The database is serialized as the bytes of `DatabaseRef::serialize`.
+    impl<T> Serialize for DatabaseRef<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            let bytes = DatabaseRef::serialize(self).map_err(ser::Error::custom)?;
+
+            serializer.serialize_bytes(&bytes)
+        }
+    }
+
+    impl<T> Serialize for Database<T> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            Serialize::serialize(&**self, serializer)
+        }
+    }
+
+    /// The database is deserialized from the bytes, or a sequence of bytes for the formats like JSON,
+    /// a database of another mode, version or platform is rejected with the Hyperscan error.
+    impl<'de, T: Mode> Deserialize<'de> for Database<T> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            struct BytesVisitor<T>(PhantomData<T>);
+
+            impl<T: Mode> BytesVisitor<T> {
+                fn database<E: de::Error>(bytes: &[u8]) -> StdResult<Database<T>, E> {
+                    bytes.deserialize().map_err(E::custom)
+                }
+            }
+
+            impl<'de, T: Mode> de::Visitor<'de> for BytesVisitor<T> {
+                type Value = Database<T>;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a serialized database")
+                }
+
+                fn visit_bytes<E: de::Error>(self, v: &[u8]) -> StdResult<Self::Value, E> {
+                    Self::database(v)
+                }
+
+                fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> StdResult<Self::Value, A::Error> {
+                    let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or_default());
+
+                    while let Some(b) = seq.next_element()? {
+                        bytes.push(b);
+                    }
+
+                    Self::database(&bytes)
+                }
+            }
+
+            deserializer.deserialize_bytes(BytesVisitor(PhantomData))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use crate::common::Error as HsError;
+        use crate::prelude::*;
+
+        fn scan(db: &BlockDatabase) -> Vec<(u64, u64)> {
+            let mut s = db.alloc_scratch().unwrap();
+            let mut matches = vec![];
+
+            db.scan("a foo", &mut s, |_, from, to, _| {
+                matches.push((from, to));
+                Matching::Continue
+            })
+            .unwrap();
+
+            matches
+        }
+
+        // CBOR stands for the binary formats, the same as bincode the database is read with `deserialize_bytes`,
+        // never `deserialize_any`, which the formats without the types in their data don't support
+        #[test]
+        fn test_serde_cbor() {
+            let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+            let mut buf = vec![];
+
+            ciborium::ser::into_writer(&db, &mut buf).unwrap();
+
+            let decoded: BlockDatabase = ciborium::de::from_reader(&buf[..]).unwrap();
+
+            assert_eq!(scan(&decoded), vec![(2, 4), (2, 5)]);
+        }
+
+        #[test]
+        fn test_serde_json() {
+            let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+            let json = serde_json::to_string(&db).unwrap();
+
+            assert!(json.starts_with('['));
+
+            let decoded: BlockDatabase = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(scan(&decoded), vec![(2, 4), (2, 5)]);
+
+            let err = serde_json::from_str::<StreamingDatabase>(&json).err().unwrap();
+
+            assert!(err.to_string().contains(&HsError::DbModeError.to_string()), "{}", err);
+            assert!(serde_json::from_str::<BlockDatabase>("[1, 2, 3]").is_err());
+        }
     }
 }
 
 #[cfg(test)]
 pub mod tests {
     use crate::common::database::tests::*;
+    use crate::common::{Block, Streaming};
     use crate::prelude::*;
 
     use super::*;
@@ -145,6 +388,143 @@ pub mod tests {
         validate_database(&db);
     }
 
+    #[test]
+    fn test_database_round_trip() {
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let data = db.serialize().unwrap().to_vec();
+
+        drop(db);
+
+        let db = BlockDatabase::deserialize(&data).unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan("a foo", &mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![2..4, 2..5]);
+    }
+
+    #[test]
+    fn test_database_deserialize_mode() {
+        let db: StreamingDatabase = "test".parse().unwrap();
+        let data = db.serialize().unwrap();
+
+        assert_eq!(data.parsed_info().unwrap().mode, DbMode::Streaming);
+        assert!(data.deserialize::<Streaming>().is_ok());
+        assert_eq!(
+            data.deserialize::<Block>().err(),
+            Some(Error::Hyperscan(HsError::DbModeError))
+        );
+        assert_eq!(
+            VectoredDatabase::deserialize(&data).err(),
+            Some(Error::Hyperscan(HsError::DbModeError))
+        );
+    }
+
+    #[test]
+    fn test_serialized_db_info() {
+        let db: BlockDatabase = "test".parse().unwrap();
+        let data = db.serialize().unwrap();
+        let info = data.parsed_info().unwrap();
+
+        assert_eq!(info, db.parsed_info().unwrap());
+        let version = crate::version();
+
+        assert_eq!(
+            info.version,
+            (version.major as u32, version.minor as u32, version.patch as u32)
+        );
+        assert_eq!(info.mode, DbMode::Block);
+    }
+
+    #[test]
+    fn test_database_deserialize_into() {
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let data = db.serialize().unwrap();
+        let size = data.size().unwrap();
+        let mut storage = vec![0u64; size / 8 + 1];
+        let target = unsafe { std::slice::from_raw_parts_mut(storage.as_mut_ptr().cast::<u8>(), storage.len() * 8) };
+
+        assert_eq!(
+            BlockDatabase::deserialize_into(&data, &mut target[1..]).err(),
+            Some(Error::Hyperscan(HsError::BadAlign))
+        );
+        assert_eq!(
+            BlockDatabase::deserialize_into(&data, &mut target[..size - 8]).err(),
+            Some(Error::Hyperscan(HsError::InsufficientSpace))
+        );
+        assert_eq!(
+            StreamingDatabase::deserialize_into(&data, target).err(),
+            Some(Error::Hyperscan(HsError::DbModeError))
+        );
+
+        drop(db);
+
+        let db = BlockDatabase::deserialize_into(&data, target).unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan("a foo", &mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![2..4, 2..5]);
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_database_deserialize_mmap() {
+        use std::fs::{self, File};
+
+        use memmap2::{Mmap, MmapMut};
+
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let path = std::env::temp_dir().join(format!("hyperscan-db-{}.bin", std::process::id()));
+
+        fs::write(&path, db.serialize().unwrap().as_ref()).unwrap();
+
+        drop(db);
+
+        let data = unsafe { Mmap::map(&File::open(&path).unwrap()).unwrap() };
+        let mut target = MmapMut::map_anon(data.size().unwrap()).unwrap();
+        let db = BlockDatabase::deserialize_into(&data[..], &mut target[..]).unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan("a foo", &mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![2..4, 2..5]);
+
+        drop(data);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_garbage_bytes() {
+        let garbage = b"not a serialized database at all, just some garbage bytes".to_vec();
+
+        assert!(garbage.size().is_err());
+        assert!(garbage.info().is_err());
+        assert!(garbage.parsed_info().is_err());
+        assert!(garbage.deserialize::<Block>().is_err());
+        assert!(BlockDatabase::deserialize(&[][..]).is_err());
+
+        let db: BlockDatabase = "test".parse().unwrap();
+        let data = db.serialize().unwrap();
+
+        assert!((&data[..data.len() / 2]).deserialize::<Block>().is_err());
+    }
+
     #[test]
     fn test_database_deserialize_at() {
         let mut db: BlockDatabase = "test".parse().unwrap();
@@ -156,5 +536,26 @@ pub mod tests {
         db.deserialize_at(&data).unwrap();
 
         validate_database(&db);
+
+        // a larger database doesn't fit in place
+        let large: BlockDatabase = "/foo/\n/bar/\n/ba+z/\n/qu+x/"
+            .parse::<Patterns>()
+            .unwrap()
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            db.deserialize_at(large.serialize().unwrap()).err(),
+            Some(Error::Hyperscan(HsError::InsufficientSpace))
+        );
+
+        let streaming: StreamingDatabase = "test".parse().unwrap();
+
+        assert_eq!(
+            db.deserialize_at(streaming.serialize().unwrap()).err(),
+            Some(Error::Hyperscan(HsError::DbModeError))
+        );
+
+        validate_database(&db);
     }
 }
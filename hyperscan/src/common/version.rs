@@ -0,0 +1,107 @@
+use core::ffi::CStr;
+
+use crate::ffi;
+
+/// The current Hyperscan version information.
+///
+/// # Examples
+///
+/// ```rust
+/// assert!(hyperscan::version_str().to_string_lossy().starts_with(&hyperscan::version().to_string()));
+/// ```
+pub fn version() -> semver::Version {
+    let (major, minor, patch) = version_parsed();
+
+    semver::Version::new(major.into(), minor.into(), patch.into())
+}
+
+/// Utility function for identifying this release version.
+///
+/// Returns a string containing the version number of this release build  and the date of the build.
+pub fn version_str() -> &'static CStr {
+    unsafe { CStr::from_ptr(ffi::hs_version()) }
+}
+
+/// The version string of the linked Hyperscan library, e.g. `5.4.0 2020-05-01`.
+///
+/// # Examples
+///
+/// ```rust
+/// println!("hyperscan {}", hyperscan::version_string());
+/// ```
+pub fn version_string() -> &'static str {
+    version_str().to_str().unwrap_or_default()
+}
+
+/// The `(major, minor, patch)` version of the linked Hyperscan library.
+///
+/// Any build date or other suffix after the version number is ignored,
+/// and the missing or malformed components are treated as `0`.
+///
+/// # Examples
+///
+/// ```rust
+/// let (major, _, _) = hyperscan::version_parsed();
+///
+/// assert!(major >= 4);
+/// ```
+pub fn version_parsed() -> (u32, u32, u32) {
+    parse_version(version_string())
+}
+
+/// Returns `true` if the linked library supports compiling pure literals (added in Hyperscan 5.2.0).
+pub fn supports_literal_api() -> bool {
+    version_parsed() >= (5, 2, 0)
+}
+
+/// Returns `true` if the linked library supports logical combinations of patterns (added in Hyperscan 5.1.0).
+pub fn supports_combinations() -> bool {
+    version_parsed() >= (5, 1, 0)
+}
+
+fn parse_version(s: &str) -> (u32, u32, u32) {
+    let mut parts = s.split_whitespace().next().unwrap_or_default().split('.').map(|part| {
+        part.bytes()
+            .take_while(u8::is_ascii_digit)
+            .try_fold(0u32, |n, b| n.checked_mul(10)?.checked_add(u32::from(b - b'0')))
+            .unwrap_or_default()
+    });
+
+    let major = parts.next().unwrap_or_default();
+    let minor = parts.next().unwrap_or_default();
+    let patch = parts.next().unwrap_or_default();
+
+    (major, minor, patch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("4.7.0 2018-01-24"), (4, 7, 0));
+        assert_eq!(parse_version("5.0.0 2018-07-09"), (5, 0, 0));
+        assert_eq!(parse_version("5.1.1 2019-02-25"), (5, 1, 1));
+        assert_eq!(parse_version("5.4.0 2020-05-01"), (5, 4, 0));
+        assert_eq!(parse_version("5.4.11 2023-11-20"), (5, 4, 11));
+        assert_eq!(parse_version("5.4.2"), (5, 4, 2));
+        assert_eq!(parse_version("5.4.2-dev 2021-01-01"), (5, 4, 2));
+        assert_eq!(parse_version("5.4"), (5, 4, 0));
+        assert_eq!(parse_version(""), (0, 0, 0));
+        assert_eq!(parse_version("unknown"), (0, 0, 0));
+    }
+
+    #[test]
+    fn test_version() {
+        let (major, minor, patch) = version_parsed();
+
+        assert!(version_string().starts_with(&format!("{}.{}.{}", major, minor, patch)));
+        assert_eq!(
+            version(),
+            semver::Version::new(major.into(), minor.into(), patch.into())
+        );
+        assert_eq!(supports_literal_api(), (major, minor) >= (5, 2));
+        assert_eq!(supports_combinations(), (major, minor) >= (5, 1));
+    }
+}
@@ -1,18 +1,19 @@
 use std::ffi::CString;
 use std::mem::MaybeUninit;
-use std::ptr::null_mut;
+use std::ptr::{null, null_mut};
 use std::str::FromStr;
 
 use foreign_types::{ForeignType, ForeignTypeRef};
 use libc::c_char;
 
 use crate::{
-    common::{Database, Mode},
-    compile::{AsCompileResult, Flags, Pattern, Patterns, PlatformRef},
+    allocator,
+    common::{Database, Error as HsError, Mode},
+    compile::{AsCompileResult, ExprExt, Flags, Pattern, Patterns, PlatformRef},
     ffi, Error,
 };
 
-#[cfg(feature = "literal")]
+#[cfg(all(feature = "literal", hs_version_gte_5_2))]
 use crate::compile::{Literal, LiteralFlags, Literals};
 
 /// The regular expression pattern database builder.
@@ -27,6 +28,50 @@ pub trait Builder {
 
     /// Build an expression is compiled into a Hyperscan database for a target platform.
     fn for_platform<T: Mode>(&self, platform: Option<&PlatformRef>) -> Result<Database<T>, Self::Err>;
+
+    /// Build an expression is compiled into a Hyperscan database for an owned or borrowed target platform.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// # use hyperscan::{CpuFeatures, Platform, Tune};
+    /// let pattern = pattern! {"test"};
+    ///
+    /// let _: BlockDatabase = pattern.build_for(Platform::new(Tune::Generic, CpuFeatures::empty())).unwrap();
+    /// let _: BlockDatabase = pattern.build_for(Platform::host_cached().unwrap()).unwrap();
+    /// ```
+    fn build_for<T: Mode, P: AsRef<PlatformRef>>(&self, platform: P) -> Result<Database<T>, Self::Err> {
+        self.for_platform(Some(platform.as_ref()))
+    }
+}
+
+/// Compile the database in a `compile` span, and report the size of the compiled database.
+#[cfg(feature = "tracing")]
+fn traced<T: Mode>(
+    patterns: usize,
+    compile: impl FnOnce() -> Result<Database<T>, Error>,
+) -> Result<Database<T>, Error> {
+    let span = tracing::debug_span!(target: "hyperscan", "compile", patterns, mode = T::NAME);
+    let _enter = span.enter();
+
+    let res = compile();
+
+    match res {
+        Ok(ref db) => tracing::debug!(target: "hyperscan", size = db.size().unwrap_or_default(), "compiled"),
+        Err(ref err) => tracing::debug!(target: "hyperscan", error = %err, "compile failed"),
+    }
+
+    res
+}
+
+#[cfg(not(feature = "tracing"))]
+#[inline(always)]
+fn traced<T: Mode>(
+    _patterns: usize,
+    compile: impl FnOnce() -> Result<Database<T>, Error>,
+) -> Result<Database<T>, Error> {
+    compile()
 }
 
 /// Compile an expression into a Hyperscan database.
@@ -88,24 +133,42 @@ impl Builder for Pattern {
     /// into a Hyperscan database which can be passed to the runtime functions
     ///
     fn for_platform<T: Mode>(&self, platform: Option<&PlatformRef>) -> Result<Database<T>, Self::Err> {
+        self.check()?;
+
         let expr = CString::new(self.expression.as_bytes())?;
         let mode = T::ID | if T::is_streaming() { self.som() } else { None }.map_or(0, |som| som as _);
         let mut db = MaybeUninit::uninit();
         let mut err = MaybeUninit::uninit();
 
-        unsafe {
-            ffi::hs_compile(
-                expr.as_bytes_with_nul().as_ptr() as *const c_char,
-                self.flags.bits(),
-                mode,
-                platform.map_or_else(null_mut, ForeignTypeRef::as_ptr),
-                db.as_mut_ptr(),
-                err.as_mut_ptr(),
-            )
+        allocator::mark_in_use();
+
+        traced::<T>(1, || unsafe {
+            if self.ext.is_empty() {
+                ffi::hs_compile(
+                    expr.as_bytes_with_nul().as_ptr() as *const c_char,
+                    self.flags.bits(),
+                    mode,
+                    platform.map_or_else(null_mut, ForeignTypeRef::as_ptr),
+                    db.as_mut_ptr(),
+                    err.as_mut_ptr(),
+                )
+            } else {
+                ffi::hs_compile_ext_multi(
+                    &(expr.as_ptr()),
+                    &self.flags.bits(),
+                    &0,
+                    &(&self.ext as *const ExprExt as *const ffi::hs_expr_ext_t),
+                    1,
+                    mode,
+                    platform.map_or_else(null_mut, ForeignTypeRef::as_ptr),
+                    db.as_mut_ptr(),
+                    err.as_mut_ptr(),
+                )
+            }
             .ok_or_else(|| err.assume_init())
             .map(|_| Database::from_ptr(db.assume_init()))
             .map_err(|err| err.into())
-        }
+        })
     }
 }
 
@@ -121,6 +184,10 @@ impl Builder for Patterns {
     // which is passed into the match callback to identify the pattern that has matched.
     ///
     fn for_platform<T: Mode>(&self, platform: Option<&PlatformRef>) -> Result<Database<T>, Self::Err> {
+        for pattern in self.iter() {
+            pattern.check()?;
+        }
+
         let expressions = self
             .iter()
             .map(|Pattern { expression, .. }| CString::new(expression.as_str()))
@@ -133,34 +200,152 @@ impl Builder for Patterns {
             .iter()
             .map(|Pattern { flags, .. }| flags.bits() as _)
             .collect::<Vec<_>>();
-        let ids = self
+        let ids = self.ids().map(|id| id as _).collect::<Vec<_>>();
+        // the patterns without the extended parameters are given a NULL `hs_expr_ext_t`
+        let exts = self
             .iter()
-            .enumerate()
-            .map(|(i, Pattern { id, .. })| id.unwrap_or(i) as _)
+            .map(|Pattern { ext, .. }| {
+                if ext.is_empty() {
+                    null()
+                } else {
+                    ext as *const ExprExt as *const ffi::hs_expr_ext_t
+                }
+            })
             .collect::<Vec<_>>();
         let mode = T::ID | if T::is_streaming() { self.som() } else { None }.map_or(0, |som| som as _);
         let mut db = MaybeUninit::uninit();
         let mut err = MaybeUninit::uninit();
 
-        unsafe {
-            ffi::hs_compile_multi(
-                ptrs.as_ptr(),
-                flags.as_ptr(),
-                ids.as_ptr(),
-                self.len() as u32,
-                mode,
-                platform.map_or_else(null_mut, ForeignTypeRef::as_ptr),
-                db.as_mut_ptr(),
-                err.as_mut_ptr(),
-            )
+        allocator::mark_in_use();
+
+        traced::<T>(self.len(), || unsafe {
+            if exts.iter().all(|ext| ext.is_null()) {
+                ffi::hs_compile_multi(
+                    ptrs.as_ptr(),
+                    flags.as_ptr(),
+                    ids.as_ptr(),
+                    self.len() as u32,
+                    mode,
+                    platform.map_or_else(null_mut, ForeignTypeRef::as_ptr),
+                    db.as_mut_ptr(),
+                    err.as_mut_ptr(),
+                )
+            } else {
+                ffi::hs_compile_ext_multi(
+                    ptrs.as_ptr(),
+                    flags.as_ptr(),
+                    ids.as_ptr(),
+                    exts.as_ptr(),
+                    self.len() as u32,
+                    mode,
+                    platform.map_or_else(null_mut, ForeignTypeRef::as_ptr),
+                    db.as_mut_ptr(),
+                    err.as_mut_ptr(),
+                )
+            }
             .ok_or_else(|| err.assume_init())
             .map(|_| Database::from_ptr(db.assume_init()))
-            .map_err(|err| err.into())
+            .map_err(|err| match err {
+                // map the failed expression back to its pattern
+                HsError::CompileError(err) => match err.expression().filter(|&index| index < self.len()) {
+                    Some(index) => Error::InvalidPattern {
+                        index,
+                        id: ids[index] as usize,
+                        expression: self[index].expression.clone(),
+                        error: err,
+                    },
+                    None => HsError::CompileError(err).into(),
+                },
+                err => err.into(),
+            })
+        })
+    }
+}
+
+impl Patterns {
+    /// Compile each pattern of the set individually, and return the index and the error of every failing pattern.
+    ///
+    /// A multi-pattern compile stops at the first invalid pattern, this reports all of them in one pass,
+    /// for example to validate a rule set before deploying it. It is much slower than compiling the set,
+    /// the patterns are split across the available threads and the databases are dropped.
+    ///
+    /// The logical combinations refer to the other patterns, so they are compiled with all the valid patterns
+    /// which aren't combinations.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// # use hyperscan::Block;
+    /// let patterns: Patterns = "/foo/\n/ba(r/\n/baz/\n/qu[x/".parse().unwrap();
+    /// let errors = patterns.check_all::<Block>(None);
+    ///
+    /// assert_eq!(errors.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![1, 3]);
+    /// ```
+    pub fn check_all<T: Mode>(&self, platform: Option<&PlatformRef>) -> Vec<(usize, Error)> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let chunk_size = (self.len() / threads).max(1);
+        let patterns = self
+            .iter()
+            .enumerate()
+            .filter(|(_, pattern)| !pattern.is_combination())
+            .collect::<Vec<_>>();
+        let mut errors = std::thread::scope(|s| {
+            patterns
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    s.spawn(move || {
+                        chunk
+                            .iter()
+                            .flat_map(|&(index, pattern)| {
+                                pattern.for_platform::<T>(platform).err().map(|err| (index, err))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        // keep the IDs of the valid patterns, which the combinations refer to
+        let operands = self
+            .iter()
+            .enumerate()
+            .filter(|&(index, pattern)| !pattern.is_combination() && errors.iter().all(|&(failed, _)| failed != index))
+            .map(|(index, pattern)| Pattern {
+                id: Some(pattern.id.unwrap_or(index)),
+                ..pattern.clone()
+            })
+            .collect::<Vec<_>>();
+
+        for (index, pattern) in self.iter().enumerate().filter(|(_, pattern)| pattern.is_combination()) {
+            let mut patterns = operands.clone();
+
+            patterns.push(Pattern {
+                id: Some(pattern.id.unwrap_or(index)),
+                ..pattern.clone()
+            });
+
+            if let Err(err) = Patterns(patterns).for_platform::<T>(platform) {
+                errors.push((
+                    index,
+                    match err {
+                        Error::InvalidPattern { error, .. } => HsError::CompileError(error).into(),
+                        err => err,
+                    },
+                ));
+            }
         }
+
+        errors.sort_by_key(|&(index, _)| index);
+
+        errors
     }
 }
 
-#[cfg(feature = "literal")]
+#[cfg(all(feature = "literal", hs_version_gte_5_2))]
 impl Builder for Literal {
     type Err = Error;
 
@@ -171,11 +356,15 @@ impl Builder for Literal {
     /// into a Hyperscan database which can be passed to the runtime functions
     ///
     fn for_platform<T: Mode>(&self, platform: Option<&PlatformRef>) -> Result<Database<T>, Self::Err> {
+        self.validate()?;
+
         let mode = T::ID | if T::is_streaming() { self.som() } else { None }.map_or(0, |som| som as _);
         let mut db = MaybeUninit::uninit();
         let mut err = MaybeUninit::uninit();
 
-        unsafe {
+        allocator::mark_in_use();
+
+        traced::<T>(1, || unsafe {
             ffi::hs_compile_lit(
                 self.expression.as_ptr() as *const _,
                 self.flags.bits(),
@@ -188,11 +377,11 @@ impl Builder for Literal {
             .ok_or_else(|| err.assume_init())
             .map(|_| Database::from_ptr(db.assume_init()))
             .map_err(|err| err.into())
-        }
+        })
     }
 }
 
-#[cfg(feature = "literal")]
+#[cfg(all(feature = "literal", hs_version_gte_5_2))]
 impl Builder for Literals {
     type Err = Error;
 
@@ -205,6 +394,10 @@ impl Builder for Literals {
     // which is passed into the match callback to identify the pattern that has matched.
     ///
     fn for_platform<T: Mode>(&self, platform: Option<&PlatformRef>) -> Result<Database<T>, Self::Err> {
+        for literal in self.iter() {
+            literal.validate()?;
+        }
+
         let ptrs = self
             .iter()
             .map(|Literal { expression, .. }| expression.as_ptr() as *const _)
@@ -226,7 +419,9 @@ impl Builder for Literals {
         let mut db = MaybeUninit::uninit();
         let mut err = MaybeUninit::uninit();
 
-        unsafe {
+        allocator::mark_in_use();
+
+        traced::<T>(self.len(), || unsafe {
             ffi::hs_compile_lit_multi(
                 ptrs.as_ptr(),
                 flags.as_ptr(),
@@ -241,7 +436,7 @@ impl Builder for Literals {
             .ok_or_else(|| err.assume_init())
             .map(|_| Database::from_ptr(db.assume_init()))
             .map_err(|err| err.into())
-        }
+        })
     }
 }
 
@@ -262,8 +457,8 @@ impl<T: Mode> Database<T> {
     ///
     /// This is the function call with which an pure literal expression is compiled
     /// into a Hyperscan database which can be passed to the runtime functions.
-    #[cfg(feature = "literal")]
-    pub fn compile_literal<S: Into<String>>(
+    #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+    pub fn compile_literal<S: Into<Vec<u8>>>(
         expression: S,
         flags: LiteralFlags,
         platform: Option<&PlatformRef>,
@@ -0,0 +1,372 @@
+use std::convert::TryInto;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::{
+    common::{BlockDatabase, Serialized},
+    compile::{Builder, CpuFeatures, Patterns, Platform, PlatformRef, Tune},
+    Error, Result,
+};
+
+const MAGIC: &[u8; 8] = b"HSBUNDLE";
+const FORMAT_VERSION: u32 = 1;
+
+const SECTION_PATTERNS: u32 = 1;
+const SECTION_VARIANT: u32 = 2;
+
+/// The size of a section header, the tag, the payload length and the payload checksum.
+const SECTION_HEADER_SIZE: usize = 4 + 8 + 4;
+/// The size of a variant header, the tuning family and the CPU features.
+const VARIANT_HEADER_SIZE: usize = 4 + 8;
+
+/// A single-file bundle of the patterns and the block databases compiled for several platforms.
+///
+/// The on-disk format is a simple length-prefixed container.
+/// After the `HSBUNDLE` magic and the format version, each section is stored as a tag,
+/// the length and the CRC-32 checksum of the payload, followed by the payload.
+/// The patterns section contains one pattern per line, and each variant section contains
+/// the target platform followed by the serialized database.
+///
+/// Note that the start of match horizon of the patterns is not stored.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, Bundle, CpuFeatures, Platform, Tune};
+/// let patterns: Patterns = "1:/foo/i\n2:/bar/".parse().unwrap();
+/// let bundle = Bundle::build(patterns, &[Platform::generic(), Platform::avx2_generic()]).unwrap();
+///
+/// let bundle = Bundle::from_bytes(&bundle.to_bytes()).unwrap();
+/// let (patterns, db) = bundle.open_for(&Platform::new(Tune::Haswell, CpuFeatures::AVX2)).unwrap();
+///
+/// assert_eq!(patterns.len(), 2);
+///
+/// let mut s = db.alloc_scratch().unwrap();
+/// let mut matches = vec![];
+///
+/// db.scan("FOO bar", &mut s, |id, _, to, _| {
+///     matches.push((id, to));
+///     Matching::Continue
+/// })
+/// .unwrap();
+///
+/// assert_eq!(matches, vec![(1, 3), (2, 7)]);
+/// ```
+pub struct Bundle {
+    patterns: Patterns,
+    variants: Vec<Variant>,
+}
+
+struct Variant {
+    platform: Platform,
+    db: Vec<u8>,
+}
+
+impl fmt::Debug for Bundle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bundle")
+            .field("patterns", &self.patterns)
+            .field("platforms", &self.platforms().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Bundle {
+    /// Compile the patterns into a block database for each of the target platforms.
+    pub fn build<P: AsRef<PlatformRef>>(patterns: Patterns, platforms: &[P]) -> Result<Bundle> {
+        let variants = platforms
+            .iter()
+            .map(|platform| {
+                let platform = platform.as_ref();
+                let db: BlockDatabase = patterns.build_for(platform)?;
+
+                Ok(Variant {
+                    platform: platform.to_owned(),
+                    db: db.serialize()?.to_vec(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Bundle { patterns, variants })
+    }
+
+    /// Returns the patterns of this bundle.
+    pub fn patterns(&self) -> &Patterns {
+        &self.patterns
+    }
+
+    /// Returns the target platforms of the database variants, in the order they were built.
+    pub fn platforms(&self) -> impl Iterator<Item = (Option<Tune>, CpuFeatures)> + '_ {
+        self.variants
+            .iter()
+            .map(|variant| (variant.platform.tune(), variant.platform.cpu_features()))
+    }
+
+    /// Open the most specific database variant compatible with the current host.
+    pub fn open_best(&self) -> Result<(Patterns, BlockDatabase)> {
+        self.open_for(Platform::host_cached()?)
+    }
+
+    /// Open the most specific database variant compatible with the `host` platform.
+    ///
+    /// The variant requiring the most CPU features among those compatible with the host is picked,
+    /// and `Error::NoCompatibleVariant` is returned if none of them can run on the host.
+    pub fn open_for(&self, host: &PlatformRef) -> Result<(Patterns, BlockDatabase)> {
+        let variant = self
+            .variants
+            .iter()
+            .filter(|variant| variant.platform.is_compatible_with(host))
+            .max_by_key(|variant| {
+                let features = variant.platform.cpu_features().bits();
+
+                (features.count_ones(), features)
+            })
+            .ok_or(Error::NoCompatibleVariant)?;
+
+        Ok((self.patterns.clone(), variant.db.deserialize()?))
+    }
+
+    /// Save the bundle to a file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        fs::write(path, self.to_bytes()).map_err(Error::from)
+    }
+
+    /// Load a bundle from a file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bundle> {
+        Bundle::from_bytes(&fs::read(path)?)
+    }
+
+    /// Write the bundle to a writer.
+    pub fn write_to<W: Write>(&self, mut w: W) -> Result<()> {
+        w.write_all(&self.to_bytes()).map_err(Error::from)
+    }
+
+    /// Read a bundle from a reader.
+    pub fn read_from<R: Read>(mut r: R) -> Result<Bundle> {
+        let mut buf = vec![];
+
+        r.read_to_end(&mut buf)?;
+
+        Bundle::from_bytes(&buf)
+    }
+
+    /// Encode the bundle in the on-disk format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+
+        buf.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+
+        let patterns = self
+            .patterns
+            .iter()
+            .map(|pattern| pattern.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        write_section(&mut buf, SECTION_PATTERNS, patterns.as_bytes());
+
+        for variant in &self.variants {
+            let mut payload = Vec::with_capacity(VARIANT_HEADER_SIZE + variant.db.len());
+
+            payload.extend_from_slice(&(variant.platform.tune().unwrap_or_default() as u32).to_le_bytes());
+            payload.extend_from_slice(&variant.platform.cpu_features().bits().to_le_bytes());
+            payload.extend_from_slice(&variant.db);
+
+            write_section(&mut buf, SECTION_VARIANT, &payload);
+        }
+
+        buf
+    }
+
+    /// Decode a bundle from the on-disk format, verifying the checksum of each section.
+    pub fn from_bytes(buf: &[u8]) -> Result<Bundle> {
+        let mut buf = buf.strip_prefix(&MAGIC[..]).ok_or_else(|| invalid("missing magic"))?;
+
+        let version = u32::from_le_bytes(take(&mut buf, 4)?.try_into().unwrap());
+
+        if version != FORMAT_VERSION {
+            return Err(invalid(format!("unsupported format version {}", version)));
+        }
+
+        let mut patterns = None;
+        let mut variants = vec![];
+
+        while !buf.is_empty() {
+            let (tag, payload) = read_section(&mut buf)?;
+
+            match tag {
+                SECTION_PATTERNS => {
+                    patterns = Some(std::str::from_utf8(payload)?.parse()?);
+                }
+                SECTION_VARIANT => {
+                    let mut payload = payload;
+                    let tune = u32::from_le_bytes(take(&mut payload, 4)?.try_into().unwrap());
+                    let cpu_features = u64::from_le_bytes(take(&mut payload, 8)?.try_into().unwrap());
+                    let tune = Tune::from_raw(tune).ok_or_else(|| invalid(format!("unknown tune {}", tune)))?;
+
+                    variants.push(Variant {
+                        platform: Platform::new(tune, CpuFeatures::from_bits_truncate(cpu_features)),
+                        db: payload.to_vec(),
+                    });
+                }
+                _ => return Err(invalid(format!("unknown section {}", tag))),
+            }
+        }
+
+        Ok(Bundle {
+            patterns: patterns.ok_or_else(|| invalid("missing patterns section"))?,
+            variants,
+        })
+    }
+}
+
+fn invalid<S: Into<String>>(reason: S) -> Error {
+    Error::InvalidBundle(reason.into())
+}
+
+fn take<'a>(buf: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if buf.len() < len {
+        return Err(invalid("truncated"));
+    }
+
+    let (head, tail) = buf.split_at(len);
+
+    *buf = tail;
+
+    Ok(head)
+}
+
+fn write_section(buf: &mut Vec<u8>, tag: u32, payload: &[u8]) {
+    buf.reserve(SECTION_HEADER_SIZE + payload.len());
+    buf.extend_from_slice(&tag.to_le_bytes());
+    buf.extend_from_slice(&(payload.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&crc32(payload).to_le_bytes());
+    buf.extend_from_slice(payload);
+}
+
+fn read_section<'a>(buf: &mut &'a [u8]) -> Result<(u32, &'a [u8])> {
+    let header = take(buf, SECTION_HEADER_SIZE)?;
+    let tag = u32::from_le_bytes(header[..4].try_into().unwrap());
+    let len = u64::from_le_bytes(header[4..12].try_into().unwrap());
+    let checksum = u32::from_le_bytes(header[12..].try_into().unwrap());
+    let payload = take(buf, len.try_into().map_err(|_| invalid("section too large"))?)?;
+
+    if crc32(payload) != checksum {
+        return Err(invalid(format!("checksum mismatch in section {}", tag)));
+    }
+
+    Ok((tag, payload))
+}
+
+/// The CRC-32 (IEEE) checksum of the data.
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        (0..8).fold(crc ^ b as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bundle() -> Bundle {
+        let patterns: Patterns = "1:/foo/i\n2:/bar/s".parse().unwrap();
+
+        Bundle::build(
+            patterns,
+            &[
+                Platform::generic(),
+                Platform::avx2_generic(),
+                Platform::new(Tune::Skylake, CpuFeatures::AVX2 | CpuFeatures::AVX512),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_crc32() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let bundle = bundle();
+        let buf = bundle.to_bytes();
+        let loaded = Bundle::from_bytes(&buf).unwrap();
+
+        assert_eq!(
+            loaded.patterns().iter().map(|p| p.to_string()).collect::<Vec<_>>(),
+            vec!["1:/foo/i", "2:/bar/s"]
+        );
+        assert_eq!(
+            loaded.platforms().collect::<Vec<_>>(),
+            bundle.platforms().collect::<Vec<_>>()
+        );
+        assert_eq!(loaded.to_bytes(), buf);
+    }
+
+    #[test]
+    fn test_open_for_host() {
+        let bundle = bundle();
+
+        let avx512 = Platform::new(Tune::Icelake, CpuFeatures::AVX2 | CpuFeatures::AVX512);
+        let (_, db) = bundle.open_for(&avx512).unwrap();
+        assert_eq!(db.serialize().unwrap().to_vec(), bundle.variants[2].db);
+
+        let avx2 = Platform::new(Tune::Haswell, CpuFeatures::AVX2);
+        let (_, db) = bundle.open_for(&avx2).unwrap();
+        assert_eq!(db.serialize().unwrap().to_vec(), bundle.variants[1].db);
+
+        let (patterns, db) = bundle.open_for(&Platform::generic()).unwrap();
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(db.serialize().unwrap().to_vec(), bundle.variants[0].db);
+    }
+
+    #[test]
+    fn test_no_compatible_variant() {
+        let patterns: Patterns = "foo".parse().unwrap();
+        let bundle = Bundle::build(patterns, &[Platform::avx2_generic()]).unwrap();
+
+        assert!(matches!(
+            bundle.open_for(&Platform::generic()),
+            Err(Error::NoCompatibleVariant)
+        ));
+    }
+
+    #[test]
+    fn test_corrupted() {
+        let mut buf = bundle().to_bytes();
+
+        assert!(matches!(Bundle::from_bytes(&buf[1..]), Err(Error::InvalidBundle(_))));
+        assert!(matches!(
+            Bundle::from_bytes(&buf[..buf.len() - 1]),
+            Err(Error::InvalidBundle(_))
+        ));
+
+        let last = buf.len() - 1;
+        buf[last] ^= 0xFF;
+
+        assert!(matches!(Bundle::from_bytes(&buf), Err(Error::InvalidBundle(_))));
+    }
+
+    #[test]
+    fn test_save_load() {
+        let path = std::env::temp_dir().join(format!("hyperscan-bundle-{}.bin", std::process::id()));
+        let bundle = bundle();
+
+        bundle.save(&path).unwrap();
+        let loaded = Bundle::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.to_bytes(), bundle.to_bytes());
+    }
+}
@@ -0,0 +1,285 @@
+use std::marker::PhantomData;
+
+use crate::{
+    common::{Database, Mode},
+    compile::{Builder, Flags, Pattern, Patterns, PlatformRef, SomHorizon},
+    Error, Result,
+};
+
+#[cfg(all(feature = "literal", hs_version_gte_5_2))]
+use crate::compile::{Literal, LiteralFlags, Literals};
+
+/// A fluent builder of the compile options, created by [`Database::builder`].
+///
+/// The mode is the type of the database, the options are validated before calling the compiler,
+/// and an invalid combination is returned as `Error::InvalidOptions`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::prelude::*;
+/// # use hyperscan::{patterns, Platform, SomHorizon};
+/// let platform = Platform::host().unwrap();
+/// let db: StreamingDatabase = Database::builder()
+///     .platform(&platform)
+///     .som_horizon(SomHorizon::Large)
+///     .patterns(patterns!("foo", "bar"; SOM_LEFTMOST))
+///     .build()
+///     .unwrap();
+/// ```
+pub struct Compiler<'a, T> {
+    patterns: Patterns,
+    platform: Option<&'a PlatformRef>,
+    som_horizon: Option<SomHorizon>,
+    #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+    literal: bool,
+    _mode: PhantomData<T>,
+}
+
+impl<T: Mode> Database<T> {
+    /// Create a fluent builder of the compile options for the mode of the database.
+    pub fn builder<'a>() -> Compiler<'a, T> {
+        Compiler {
+            patterns: Patterns(vec![]),
+            platform: None,
+            som_horizon: None,
+            #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+            literal: false,
+            _mode: PhantomData,
+        }
+    }
+}
+
+impl<'a, T: Mode> Compiler<'a, T> {
+    /// Compile the database for the target platform, instead of the host.
+    pub fn platform(mut self, platform: &'a PlatformRef) -> Self {
+        self.platform = Some(platform);
+        self
+    }
+
+    /// The precision to track the start of match offsets in the stream state, only for the streaming mode.
+    pub fn som_horizon(mut self, som_horizon: SomHorizon) -> Self {
+        self.som_horizon = Some(som_horizon);
+        self
+    }
+
+    /// Compile the expressions as pure literals with `hs_compile_lit_multi`, instead of regular expressions.
+    ///
+    /// Only the `CASELESS`, `SINGLEMATCH` and `SOM_LEFTMOST` flags are supported, without extended parameters.
+    #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+    pub fn literal(mut self, literal: bool) -> Self {
+        self.literal = literal;
+        self
+    }
+
+    /// Add a pattern.
+    pub fn pattern(mut self, pattern: Pattern) -> Self {
+        self.patterns.0.push(pattern);
+        self
+    }
+
+    /// Add the patterns.
+    pub fn patterns<I: IntoIterator<Item = Pattern>>(mut self, patterns: I) -> Self {
+        self.patterns.0.extend(patterns);
+        self
+    }
+
+    /// Validate the options and compile the database.
+    pub fn build(&self) -> Result<Database<T>> {
+        let invalid = |reason: &str| Err(Error::InvalidOptions(reason.to_owned()));
+
+        if self.patterns.is_empty() {
+            return invalid("no pattern to compile");
+        }
+
+        if self.som_horizon.is_some() {
+            if !T::is_streaming() {
+                return invalid("the SOM horizon is only used in streaming mode");
+            }
+
+            if !self
+                .patterns
+                .iter()
+                .any(|pattern| pattern.flags.contains(Flags::SOM_LEFTMOST))
+            {
+                return invalid("the SOM horizon requires a pattern with the SOM_LEFTMOST flag");
+            }
+        }
+
+        #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+        if self.literal {
+            return self.build_literals();
+        }
+
+        let mut patterns = self.patterns.clone();
+
+        if let Some(som_horizon) = self.som_horizon {
+            for pattern in patterns.iter_mut() {
+                pattern.som = Some(som_horizon);
+            }
+        }
+
+        patterns.for_platform(self.platform)
+    }
+
+    #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+    fn build_literals(&self) -> Result<Database<T>> {
+        let literals = self
+            .patterns
+            .iter()
+            .map(|pattern| {
+                if !pattern.ext.is_empty() {
+                    return Err(Error::InvalidOptions(format!(
+                        "literal `{}` has extended parameters",
+                        pattern.expression
+                    )));
+                }
+
+                Ok(Literal {
+                    expression: pattern.expression.clone().into_bytes(),
+                    flags: LiteralFlags::from_bits(pattern.flags.bits())
+                        .ok_or_else(|| Error::UnsupportedLiteralFlags(format!("{:?}", pattern.flags)))?,
+                    id: pattern.id,
+                    som: self.som_horizon,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Literals::from(literals).for_platform(self.platform)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+    use crate::{Block, Error, Platform, SomHorizon, Streaming};
+
+    fn ends(db: &BlockDatabase, data: &str) -> Vec<(u32, u64, u64)> {
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan(data, &mut s, |id, from, to, _| {
+            matches.push((id, from, to));
+            Matching::Continue
+        })
+        .unwrap();
+
+        matches
+    }
+
+    #[test]
+    fn test_build() {
+        let platform = Platform::host().unwrap();
+        let db: BlockDatabase = Database::builder()
+            .platform(&platform)
+            .pattern(pattern! {1 => "foo"; SOM_LEFTMOST})
+            .patterns(vec![pattern! {2 => "ba+r"; SOM_LEFTMOST}])
+            .build()
+            .unwrap();
+
+        assert_eq!(ends(&db, "foo baar"), vec![(1, 0, 3), (2, 4, 8)]);
+
+        let db: StreamingDatabase = Database::builder()
+            .som_horizon(SomHorizon::Large)
+            .pattern(pattern! {"foo"; SOM_LEFTMOST})
+            .build()
+            .unwrap();
+
+        assert!(db.stream_size().unwrap() > 0);
+    }
+
+    #[test]
+    fn test_som_horizon() {
+        let mut sizes = vec![];
+
+        for som_horizon in [
+            None,
+            Some(SomHorizon::Large),
+            Some(SomHorizon::Medium),
+            Some(SomHorizon::Small),
+        ] {
+            let builder = Database::builder().pattern(pattern! {"fo+bar"; SOM_LEFTMOST});
+            let builder = match som_horizon {
+                Some(som_horizon) => builder.som_horizon(som_horizon),
+                None => builder,
+            };
+            let db: StreamingDatabase = builder.build().unwrap();
+            let mut s = db.alloc_scratch().unwrap();
+            let st = db.open_stream().unwrap();
+            let mut matches = vec![];
+
+            for chunk in &["xx fo", "ooo", "obar fob", "ar"] {
+                st.scan(chunk, &mut s, |_, from, to, _| {
+                    matches.push((from, to));
+                    Matching::Continue
+                })
+                .unwrap();
+            }
+
+            st.close(&mut s, Matching::Continue).unwrap();
+
+            assert_eq!(matches, vec![(3, 12), (13, 18)], "{:?}", som_horizon);
+
+            sizes.push(db.stream_size().unwrap());
+        }
+
+        // the default is the most precise horizon
+        assert_eq!(sizes[0], sizes[1]);
+        assert!(sizes[1] >= sizes[3]);
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let err = Database::<Block>::builder()
+            .som_horizon(SomHorizon::Small)
+            .pattern(pattern! {"foo"; SOM_LEFTMOST})
+            .build()
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err,
+            Error::InvalidOptions("the SOM horizon is only used in streaming mode".to_owned())
+        );
+
+        let err = Database::<Streaming>::builder()
+            .som_horizon(SomHorizon::Small)
+            .pattern(pattern! {"foo"})
+            .build()
+            .err()
+            .unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "invalid compile options, the SOM horizon requires a pattern with the SOM_LEFTMOST flag"
+        );
+        assert!(Database::<Block>::builder().build().is_err());
+    }
+
+    #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+    #[test]
+    fn test_build_literal() {
+        let db: BlockDatabase = Database::builder()
+            .literal(true)
+            .pattern(pattern! {"a.b*"; CASELESS})
+            .build()
+            .unwrap();
+
+        assert_eq!(ends(&db, "aab A.B* a.b*"), vec![(0, 0, 8), (0, 0, 13)]);
+
+        assert!(matches!(
+            Database::<Block>::builder()
+                .literal(true)
+                .pattern(pattern! {"foo"; DOTALL})
+                .build(),
+            Err(Error::UnsupportedLiteralFlags(_))
+        ));
+        assert!(matches!(
+            Database::<Block>::builder()
+                .literal(true)
+                .pattern(pattern! {"foo"}.min_offset(3))
+                .build(),
+            Err(Error::InvalidOptions(_))
+        ));
+    }
+}
@@ -11,7 +11,8 @@ use libc::c_char;
 use thiserror::Error;
 
 use crate::{
-    compile::{AsCompileResult, Pattern},
+    allocator,
+    compile::{AsCompileResult, Pattern, Patterns},
     ffi, Result,
 };
 
@@ -26,6 +27,9 @@ pub enum Error {
 
     #[error("unexpected parameter {0}")]
     UnexpectedParameter(String),
+
+    #[error("edit distance and Hamming distance can't be set on the same expression")]
+    ConflictingDistances,
 }
 
 bitflags! {
@@ -149,6 +153,15 @@ impl ExprExt {
         self.flags().is_empty()
     }
 
+    /// Check the additional parameters can be combined, before passing them to the compiler.
+    pub(crate) fn validate(&self) -> Result<()> {
+        if self.flags().contains(Flags::EDIT_DISTANCE | Flags::HAMMING_DISTANCE) {
+            Err(Error::ConflictingDistances.into())
+        } else {
+            Ok(())
+        }
+    }
+
     /// The minimum end offset in the data stream at which this expression should match successfully.
     pub fn min_offset(&self) -> Option<u64> {
         if self.flags().contains(Flags::MIN_OFFSET) {
@@ -241,7 +254,7 @@ foreign_type! {
     /// let info = pattern.info().unwrap();
     ///
     /// assert_eq!(info.min_width(), 10);
-    /// assert_eq!(info.max_width(), 12);
+    /// assert_eq!(info.max_width(), Some(12));
     /// assert!(!info.unordered_matches());
     /// assert!(!info.matches_at_eod());
     /// assert!(!info.matches_only_at_eod());
@@ -254,7 +267,7 @@ foreign_type! {
 }
 
 unsafe fn drop_expr_info(info: *mut ffi::hs_expr_info) {
-    libc::free(info as *mut _);
+    allocator::free_misc(info as *mut _);
 }
 
 impl Deref for ExprInfoRef {
@@ -269,7 +282,7 @@ impl fmt::Debug for ExprInfo {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("ExprInfo")
             .field("min_width", &self.min_width)
-            .field("max_width", &self.max_width)
+            .field("max_width", &self.max_width())
             .field("unordered_matches", &self.unordered_matches())
             .field("matches_at_eod", &self.matches_at_eod())
             .field("matches_only_at_eod", &self.matches_only_at_eod())
@@ -283,9 +296,14 @@ impl ExprInfoRef {
         self.min_width as usize
     }
 
-    /// The maximum length in bytes of a match for the pattern.
-    pub fn max_width(&self) -> usize {
-        self.max_width as usize
+    /// The maximum length in bytes of a match for the pattern, or `None` if the width is unbounded.
+    pub fn max_width(&self) -> Option<usize> {
+        // Hyperscan reports an unbounded width as `UINT_MAX`
+        if self.max_width == u32::MAX {
+            None
+        } else {
+            Some(self.max_width as usize)
+        }
     }
 
     /// Whether this expression can produce matches that are not returned in order,
@@ -312,11 +330,51 @@ impl Pattern {
     /// The information provided in ExpressionInfo
     /// includes the minimum and maximum width of a pattern match.
     ///
+    /// The pattern with the extended parameters is delegated to `ext_info`.
+    ///
     pub fn info(&self) -> Result<ExprInfo> {
+        if !self.ext.is_empty() {
+            return self.ext_info();
+        }
+
+        self.check()?;
+
+        let expr = CString::new(self.expression.as_str())?;
+        let mut info = MaybeUninit::uninit();
+        let mut err = MaybeUninit::uninit();
+
+        allocator::mark_in_use();
+
+        let info = unsafe {
+            ffi::hs_expression_info(
+                expr.as_ptr() as *const c_char,
+                self.flags.bits(),
+                info.as_mut_ptr(),
+                err.as_mut_ptr(),
+            )
+            .ok_or_else(|| err.assume_init())?;
+
+            ExprInfo::from_ptr(info.assume_init())
+        };
+
+        Ok(info)
+    }
+
+    ///
+    /// Utility function providing information about a regular expression, with extended parameter support.
+    ///
+    /// The widths take the extended parameters into account,
+    /// for example an edit distance widens the range of the match widths.
+    ///
+    pub fn ext_info(&self) -> Result<ExprInfo> {
+        self.check()?;
+
         let expr = CString::new(self.expression.as_str())?;
         let mut info = MaybeUninit::uninit();
         let mut err = MaybeUninit::uninit();
 
+        allocator::mark_in_use();
+
         let info = unsafe {
             ffi::hs_expression_ext_info(
                 expr.as_ptr() as *const c_char,
@@ -332,4 +390,36 @@ impl Pattern {
 
         Ok(info)
     }
+
+    /// Check the pattern compiles, without building a database.
+    ///
+    /// The expression is parsed with its flags and extended parameters by `info` or `ext_info`,
+    /// which is much cheaper than compiling the bytecode. The unsupported constructs, such as the back-references
+    /// without the `PREFILTER` flag, are reported as the compile error.
+    ///
+    /// The logical combinations refer to the other patterns of a set, they are only checked when compiled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// assert!(pattern! {r"\x{100}"; UTF8}.validate().is_ok());
+    /// assert!(pattern! {r"\x{100}"}.validate().is_err());
+    /// ```
+    pub fn validate(&self) -> Result<()> {
+        self.info().map(|_| ())
+    }
+}
+
+impl Patterns {
+    /// Check each pattern of the set compiles, without building a database, and reports all the failing patterns.
+    ///
+    /// The failing patterns are returned with their index in the set, the logical combinations are skipped.
+    pub fn validate_all(&self) -> Vec<(usize, crate::Error)> {
+        self.iter()
+            .enumerate()
+            .filter(|(_, pattern)| !pattern.is_combination())
+            .flat_map(|(index, pattern)| pattern.validate().err().map(|err| (index, err)))
+            .collect()
+    }
 }
@@ -57,21 +57,28 @@ impl fmt::Display for Flags {
 }
 
 /// The pattern with pure literal expression.
+///
+/// The expression is matched as is, its bytes may contain the regex metacharacters and NUL bytes.
+/// Only the `CASELESS`, `SINGLEMATCH` and `SOM_LEFTMOST` flags are supported, the others are rejected
+/// when the literal is compiled.
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct Literal {
-    /// The expression to parse.
-    pub expression: String,
+    /// The bytes to match.
+    pub expression: Vec<u8>,
     /// Flags which modify the behaviour of the expression.
     pub flags: Flags,
     /// ID number to be associated with the corresponding literal in the expressions array.
     pub id: Option<usize>,
     /// The precision to track start of match offsets in stream state.
+    ///
+    /// A streaming database with the `SOM_LEFTMOST` flag defaults to `SomHorizon::Large`,
+    /// and a set uses the most precise horizon of its patterns.
     pub som: Option<SomHorizon>,
 }
 
 impl Literal {
     /// Construct a literal with expression.
-    pub fn new<S: Into<String>>(expr: S) -> Result<Literal> {
+    pub fn new<S: Into<Vec<u8>>>(expr: S) -> Result<Literal> {
         Ok(Literal {
             expression: expr.into(),
             flags: Flags::empty(),
@@ -81,7 +88,7 @@ impl Literal {
     }
 
     /// Construct a literal with expression and flags.
-    pub fn with_flags<S: Into<String>>(expr: S, flags: Flags) -> Result<Literal> {
+    pub fn with_flags<S: Into<Vec<u8>>>(expr: S, flags: Flags) -> Result<Literal> {
         Ok(Literal {
             expression: expr.into(),
             flags,
@@ -114,9 +121,20 @@ impl Literal {
         self
     }
 
+    /// Check the flags are supported by the literal compiler.
+    pub(crate) fn validate(&self) -> Result<()> {
+        let unsupported = self.flags - (Flags::CASELESS | Flags::SINGLEMATCH | Flags::SOM_LEFTMOST);
+
+        if unsupported.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::UnsupportedLiteralFlags(format!("{:?}", unsupported)))
+        }
+    }
+
     pub(crate) fn som(&self) -> Option<SomHorizon> {
         if self.flags.contains(Flags::SOM_LEFTMOST) {
-            self.som.or(Some(SomHorizon::Medium))
+            self.som.or(Some(SomHorizon::Large))
         } else {
             None
         }
@@ -129,10 +147,12 @@ impl fmt::Display for Literal {
             write!(f, "{}:", id)?;
         }
 
+        let expression = String::from_utf8_lossy(&self.expression);
+
         if self.id.is_some() || !self.flags.is_empty() {
-            write!(f, "/{}/", self.expression)?;
+            write!(f, "/{}/", expression)?;
         } else {
-            write!(f, "{}", self.expression)?;
+            write!(f, "{}", expression)?;
         }
 
         if !self.flags.is_empty() {
@@ -210,10 +230,11 @@ impl Literals {
             .iter()
             .any(|Literal { flags, .. }| flags.contains(Flags::SOM_LEFTMOST))
         {
+            // the most precise horizon, which has the lowest mode bit
             self.iter()
                 .flat_map(|&Literal { som, .. }| som)
-                .max()
-                .or(Some(SomHorizon::Medium))
+                .min()
+                .or(Some(SomHorizon::Large))
         } else {
             None
         }
@@ -288,42 +309,42 @@ mod tests {
         let p: Literal = "test".parse().unwrap();
 
         assert_eq!(p, literal! { "test" });
-        assert_eq!(p.expression, "test");
+        assert_eq!(p.expression, b"test");
         assert!(p.flags.is_empty());
         assert_eq!(p.id, None);
 
         let p: Literal = "/test/".parse().unwrap();
 
         assert_eq!(p, literal! { "test" });
-        assert_eq!(p.expression, "test");
+        assert_eq!(p.expression, b"test");
         assert!(p.flags.is_empty());
         assert_eq!(p.id, None);
 
         let p: Literal = "/test/i".parse().unwrap();
 
         assert_eq!(p, literal! { "test"; CASELESS });
-        assert_eq!(p.expression, "test");
+        assert_eq!(p.expression, b"test");
         assert_eq!(p.flags, Flags::CASELESS);
         assert_eq!(p.id, None);
 
         let p: Literal = "3:/test/i".parse().unwrap();
 
         assert_eq!(p, literal! { 3 => "test"; CASELESS });
-        assert_eq!(p.expression, "test");
+        assert_eq!(p.expression, b"test");
         assert_eq!(p.flags, Flags::CASELESS);
         assert_eq!(p.id, Some(3));
 
         let p: Literal = "test/i".parse().unwrap();
 
         assert_eq!(p, literal! { "test/i" });
-        assert_eq!(p.expression, "test/i");
+        assert_eq!(p.expression, b"test/i");
         assert!(p.flags.is_empty());
         assert_eq!(p.id, None);
 
         let p: Literal = "/t/e/s/t/i".parse().unwrap();
 
         assert_eq!(p, literal! { "t/e/s/t"; CASELESS });
-        assert_eq!(p.expression, "t/e/s/t");
+        assert_eq!(p.expression, b"t/e/s/t");
         assert_eq!(p.flags, Flags::CASELESS);
         assert_eq!(p.id, None);
     }
@@ -332,7 +353,7 @@ mod tests {
     fn test_pattern_build() {
         let p = &literal! {"test"};
 
-        assert_eq!(p.expression, "test");
+        assert_eq!(p.expression, b"test");
         assert!(p.flags.is_empty());
         assert_eq!(p.id, None);
 
@@ -345,7 +366,7 @@ mod tests {
     fn test_pattern_build_with_flags() {
         let p = &literal! {"test"; CASELESS};
 
-        assert_eq!(p.expression, "test");
+        assert_eq!(p.expression, b"test");
         assert_eq!(p.flags, Flags::CASELESS);
         assert_eq!(p.id, None);
 
@@ -367,4 +388,31 @@ mod tests {
 
         validate_database_with_size(&db, DATABASE_SIZE);
     }
+
+    #[test]
+    fn test_literal_with_nul() {
+        let p = Literal::new(&b"foo\0.*"[..]).unwrap();
+        let db: BlockDatabase = p.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan(&b"foo\0.* foo\0bar foo"[..], &mut s, |_, _, to, _| {
+            matches.push(to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![6]);
+    }
+
+    #[test]
+    fn test_unsupported_flags() {
+        let p = literal! {"test"; CASELESS | MULTILINE};
+
+        assert_eq!(
+            p.build::<crate::Block>().err().unwrap().to_string(),
+            "unsupported literal flags: MULTILINE"
+        );
+        assert!(literals!("test", "foo"; MULTILINE).build::<crate::Block>().is_err());
+    }
 }
@@ -2,13 +2,17 @@ mod error;
 #[macro_use]
 mod pattern;
 mod builder;
+mod bundle;
+mod compiler;
 mod expr;
 #[macro_use]
-#[cfg(feature = "literal")]
+#[cfg(all(feature = "literal", hs_version_gte_5_2))]
 mod literal;
 mod platform;
 
 pub use self::builder::{compile, Builder};
+pub use self::bundle::Bundle;
+pub use self::compiler::Compiler;
 pub use self::error::{AsCompileResult, Error};
 #[doc(hidden)]
 #[deprecated = "use `ExprExt` instead"]
@@ -17,7 +21,9 @@ pub use self::expr::ExprExt as ExpressionExt;
 #[deprecated = "use `ExprInfo` instead"]
 pub use self::expr::ExprInfo as ExpressionInfo;
 pub use self::expr::{Error as ExprError, ExprExt, ExprInfo};
-#[cfg(feature = "literal")]
+#[cfg(all(feature = "literal", hs_version_gte_5_2))]
 pub use self::literal::{Flags as LiteralFlags, Literal, Literals};
-pub use self::pattern::{Flags, Pattern, Patterns, SomHorizon};
-pub use self::platform::{CpuFeatures, Platform, PlatformRef, Tune};
+pub use self::pattern::{Flags, Pattern, PatternFileOptions, Patterns, SomHorizon};
+pub use self::platform::{
+    best_database_variant, check_platform, CpuFeatures, Platform, PlatformRef, PlatformReport, Tune,
+};
@@ -1,5 +1,9 @@
+use std::collections::HashMap;
 use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::iter::FromIterator;
+use std::path::Path;
 use std::str::FromStr;
 
 use bitflags::bitflags;
@@ -34,10 +38,10 @@ bitflags! {
         /// Enable leftmost start of match reporting.
         const SOM_LEFTMOST = ffi::HS_FLAG_SOM_LEFTMOST;
         /// Logical combination.
-        #[cfg(feature = "v5")]
+        #[cfg(all(feature = "v5", hs_version_gte_5_1))]
         const COMBINATION = ffi::HS_FLAG_COMBINATION;
         /// Don't do any match reporting.
-        #[cfg(feature = "v5")]
+        #[cfg(all(feature = "v5", hs_version_gte_5_1))]
         const QUIET = ffi::HS_FLAG_QUIET;
     }
 }
@@ -59,9 +63,9 @@ impl FromStr for Flags {
                 'W' => flags |= Flags::UCP,
                 'P' => flags |= Flags::PREFILTER,
                 'L' => flags |= Flags::SOM_LEFTMOST,
-                #[cfg(feature = "v5")]
+                #[cfg(all(feature = "v5", hs_version_gte_5_1))]
                 'C' => flags |= Flags::COMBINATION,
-                #[cfg(feature = "v5")]
+                #[cfg(all(feature = "v5", hs_version_gte_5_1))]
                 'Q' => flags |= Flags::QUIET,
                 _ => return Err(Error::InvalidFlag(c)),
             }
@@ -100,11 +104,11 @@ impl fmt::Display for Flags {
         if self.contains(Flags::SOM_LEFTMOST) {
             write!(f, "L")?
         }
-        #[cfg(feature = "v5")]
+        #[cfg(all(feature = "v5", hs_version_gte_5_1))]
         if self.contains(Flags::COMBINATION) {
             write!(f, "C")?
         }
-        #[cfg(feature = "v5")]
+        #[cfg(all(feature = "v5", hs_version_gte_5_1))]
         if self.contains(Flags::QUIET) {
             write!(f, "Q")?
         }
@@ -113,6 +117,8 @@ impl fmt::Display for Flags {
 }
 
 /// Defines the precision to track start of match offsets in stream state.
+///
+/// It is only used by the streaming databases with the `SOM_LEFTMOST` flag, which default to `SomHorizon::Large`.
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum SomHorizon {
@@ -148,6 +154,9 @@ pub struct Pattern {
     /// Extended behaviour for this pattern
     pub ext: ExprExt,
     /// The precision to track start of match offsets in stream state.
+    ///
+    /// A streaming database with the `SOM_LEFTMOST` flag defaults to `SomHorizon::Large`,
+    /// and a set uses the most precise horizon of its patterns.
     pub som: Option<SomHorizon>,
 }
 
@@ -228,23 +237,77 @@ impl Pattern {
         self
     }
 
+    /// The precision to track start of match offsets in stream state, with `SOM_LEFTMOST` in streaming mode.
+    pub fn som_horizon(mut self, som: SomHorizon) -> Self {
+        self.som = Some(som);
+        self
+    }
+
     /// Logical combination.
-    #[cfg(feature = "v5")]
+    #[cfg(all(feature = "v5", hs_version_gte_5_1))]
     pub fn combination(mut self) -> Self {
         self.flags |= Flags::COMBINATION;
         self
     }
 
     /// Don't do any match reporting.
-    #[cfg(feature = "v5")]
+    #[cfg(all(feature = "v5", hs_version_gte_5_1))]
     pub fn quiet(mut self) -> Self {
         self.flags |= Flags::QUIET;
         self
     }
 
+    /// Only report the matches ending at or after the offset in the data.
+    pub fn min_offset(mut self, min_offset: u64) -> Self {
+        self.ext.set_min_offset(min_offset);
+        self
+    }
+
+    /// Only report the matches ending at or before the offset in the data.
+    pub fn max_offset(mut self, max_offset: u64) -> Self {
+        self.ext.set_max_offset(max_offset);
+        self
+    }
+
+    /// Only report the matches at least as long as the length, from start to end.
+    pub fn min_length(mut self, min_length: u64) -> Self {
+        self.ext.set_min_length(min_length);
+        self
+    }
+
+    /// Allow the expression to approximately match within the edit distance.
+    pub fn edit_distance(mut self, edit_distance: u32) -> Self {
+        self.ext.set_edit_distance(edit_distance);
+        self
+    }
+
+    /// Allow the expression to approximately match within the Hamming distance.
+    pub fn hamming_distance(mut self, hamming_distance: u32) -> Self {
+        self.ext.set_hamming_distance(hamming_distance);
+        self
+    }
+
+    /// Returns `true` if the pattern is a logical combination of the other patterns.
+    pub(crate) fn is_combination(&self) -> bool {
+        #[cfg(all(feature = "v5", hs_version_gte_5_1))]
+        return self.flags.contains(Flags::COMBINATION);
+        #[cfg(not(all(feature = "v5", hs_version_gte_5_1)))]
+        return false;
+    }
+
+    /// Check the flags and the extended parameters can be combined, before passing them to the compiler.
+    pub(crate) fn check(&self) -> Result<()> {
+        #[cfg(all(feature = "v5", hs_version_gte_5_1))]
+        if self.flags.contains(Flags::QUIET | Flags::SOM_LEFTMOST) {
+            return Err(Error::ConflictingFlags("QUIET", "SOM_LEFTMOST"));
+        }
+
+        self.ext.validate()
+    }
+
     pub(crate) fn som(&self) -> Option<SomHorizon> {
         if self.flags.contains(Flags::SOM_LEFTMOST) {
-            self.som.or(Some(SomHorizon::Medium))
+            self.som.or(Some(SomHorizon::Large))
         } else {
             None
         }
@@ -257,7 +320,13 @@ impl fmt::Display for Pattern {
             write!(f, "{}:", id)?;
         }
 
-        if self.id.is_some() || !self.flags.is_empty() || !self.ext.is_empty() {
+        // an expression which would be parsed as delimited or with an ID is always delimited
+        if self.id.is_some()
+            || !self.flags.is_empty()
+            || !self.ext.is_empty()
+            || self.expression.starts_with('/')
+            || self.expression.contains(":/")
+        {
             write!(f, "/{}/", self.expression)?;
         } else {
             write!(f, "{}", self.expression)?;
@@ -277,14 +346,26 @@ impl fmt::Display for Pattern {
 impl FromStr for Pattern {
     type Err = Error;
 
+    /// Parse a pattern in the `id:/expression/flags{ext}` format of the Hyperscan tools,
+    /// where the ID, the flags and the extended parameters are optional.
+    ///
+    /// The expression ends at the last unescaped `/`, so it may contain the slashes, escaped or not.
+    /// A string which isn't delimited by the slashes is the expression itself, without flags.
     fn from_str(s: &str) -> Result<Self> {
         let (id, expr) = match s.find(":/") {
-            Some(off) => (Some(s[..off].parse()?), &s[off + 1..]),
-            None => (None, s),
+            Some(off) if off > 0 && s[..off].bytes().all(|b| b.is_ascii_digit()) => {
+                (Some(s[..off].parse()?), &s[off + 1..])
+            }
+            _ => (None, s),
         };
+        let end = expr.rfind('/').filter(|&end| {
+            let escapes = expr[..end].bytes().rev().take_while(|&b| b == b'\\').count();
+
+            end > 0 && escapes % 2 == 0
+        });
 
-        match (expr.starts_with('/'), expr.rfind('/')) {
-            (true, Some(end)) if end > 0 => {
+        match (expr.starts_with('/'), end) {
+            (true, Some(end)) => {
                 let (expr, remaining) = (&expr[1..end], &expr[end + 1..]);
                 let (flags, ext) = match (remaining.ends_with('}'), remaining.rfind('{')) {
                     (true, Some(start)) => {
@@ -347,16 +428,204 @@ impl FromStr for Patterns {
     }
 }
 
+/// The options to read a pattern file with `Patterns::from_reader_with` or `Patterns::from_file_with`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PatternFileOptions {
+    /// Accept the patterns with the same ID, instead of reporting the second one as an error.
+    pub allow_duplicate_ids: bool,
+}
+
 impl Patterns {
+    /// Read the patterns in the `id:/expression/flags` format of `hsbench` and `hscollider`, one per line.
+    ///
+    /// The empty lines and the lines starting with `#` are skipped. The first line which can't be parsed,
+    /// or which defines an ID already used by another pattern, is returned as `Error::InvalidLine`.
+    /// A pattern without ID is given its index in the set, the same as `Patterns::build`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let patterns = Patterns::from_reader("# web\n1:/foo/i\n\n2:/bar/s".as_bytes()).unwrap();
+    ///
+    /// assert_eq!(patterns.len(), 2);
+    ///
+    /// let err = Patterns::from_reader("1:/foo/i\n1:/bar/Z".as_bytes()).unwrap_err();
+    ///
+    /// assert_eq!(err.to_string(), "line 2: invalid pattern flag: Z, `1:/bar/Z`");
+    /// ```
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Patterns> {
+        Self::from_reader_with(reader, PatternFileOptions::default())
+    }
+
+    /// Read the patterns like `Patterns::from_reader`, with the options.
+    pub fn from_reader_with<R: BufRead>(reader: R, options: PatternFileOptions) -> Result<Patterns> {
+        let mut patterns = vec![];
+        let mut lines = HashMap::<usize, usize>::new();
+
+        for (n, line) in reader.lines().enumerate() {
+            let line = line?;
+            let text = line.trim();
+
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |reason: String| Error::InvalidLine {
+                line: n + 1,
+                text: text.to_owned(),
+                reason,
+            };
+            let pattern = text.parse::<Pattern>().map_err(|err| invalid(err.to_string()))?;
+            let id = pattern.id.unwrap_or(patterns.len());
+
+            if let Some(first) = lines.insert(id, n + 1) {
+                if !options.allow_duplicate_ids {
+                    return Err(invalid(format!(
+                        "duplicate pattern id {}, first defined at line {}",
+                        id, first
+                    )));
+                }
+            }
+
+            patterns.push(pattern);
+        }
+
+        Ok(Patterns(patterns))
+    }
+
+    /// Read the patterns of a file like `Patterns::from_reader`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Patterns> {
+        Self::from_file_with(path, PatternFileOptions::default())
+    }
+
+    /// Read the patterns of a file like `Patterns::from_reader`, with the options.
+    pub fn from_file_with<P: AsRef<Path>>(path: P, options: PatternFileOptions) -> Result<Patterns> {
+        Self::from_reader_with(BufReader::new(File::open(path)?), options)
+    }
+
+    /// Returns the IDs of the patterns, a pattern without ID is given its index in the set.
+    pub fn ids(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().enumerate().map(|(i, pattern)| pattern.id.unwrap_or(i))
+    }
+
+    /// Returns the largest ID of the patterns, or `None` if the set is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let patterns: Patterns = "/foo/\n100:/bar/\n/baz/".parse().unwrap();
+    ///
+    /// assert_eq!(patterns.max_id(), Some(100));
+    /// assert_eq!(Patterns(vec![]).max_id(), None);
+    /// ```
+    pub fn max_id(&self) -> Option<usize> {
+        self.ids().max()
+    }
+
+    /// Append a logical combination of the patterns of the set, for example `101 & (102 | 103)`.
+    ///
+    /// The operands are the pattern IDs, or the indexes of the patterns without ID,
+    /// combined with `&`, `|`, `!` and the parentheses. The operands must be in the set,
+    /// and the ID of the combination must not be, otherwise `Error::InvalidCombination` is returned.
+    ///
+    /// With `quiet`, the operands are marked `QUIET`, so only the combination reports its matches.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let mut patterns: Patterns = "101:/foo/\n102:/bar/\n103:/baz/".parse().unwrap();
+    ///
+    /// patterns.push_combination(1000, "101 & (102 | 103)", true).unwrap();
+    ///
+    /// let db: BlockDatabase = patterns.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let mut matches = vec![];
+    ///
+    /// db.scan("foo and baz", &mut s, |id, _, _, _| {
+    ///     matches.push(id);
+    ///     Matching::Continue
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(matches, vec![1000]);
+    /// ```
+    #[cfg(all(feature = "v5", hs_version_gte_5_1))]
+    pub fn push_combination<S: Into<String>>(&mut self, id: usize, expression: S, quiet: bool) -> Result<()> {
+        let expression = expression.into();
+        let ids = self.ids().collect::<Vec<_>>();
+        let invalid = |reason: String| Err(Error::InvalidCombination(reason));
+
+        if ids.contains(&id) {
+            return invalid(format!("pattern id {} is already in the set", id));
+        }
+
+        let mut operands = vec![];
+        let mut chars = expression.char_indices().peekable();
+
+        while let Some((start, c)) = chars.next() {
+            match c {
+                '&' | '|' | '!' | '(' | ')' => {}
+                c if c.is_whitespace() => {}
+                c if c.is_ascii_digit() => {
+                    let mut end = start + 1;
+
+                    while let Some(&(off, c)) = chars.peek() {
+                        if !c.is_ascii_digit() {
+                            break;
+                        }
+
+                        end = off + 1;
+                        chars.next();
+                    }
+
+                    let operand = expression[start..end].parse()?;
+
+                    if !ids.contains(&operand) {
+                        return invalid(format!("unknown pattern id {}", operand));
+                    }
+
+                    operands.push(operand);
+                }
+                c => return invalid(format!("unexpected character `{}` at {}", c, start)),
+            }
+        }
+
+        if operands.is_empty() {
+            return invalid("no operand".to_owned());
+        }
+
+        if quiet {
+            for (pattern, id) in self.iter_mut().zip(ids) {
+                if operands.contains(&id) {
+                    pattern.flags |= Flags::QUIET;
+                }
+            }
+        }
+
+        self.0.push(Pattern {
+            expression,
+            flags: Flags::COMBINATION,
+            id: Some(id),
+            ext: ExprExt::default(),
+            som: None,
+        });
+
+        Ok(())
+    }
+
     pub(crate) fn som(&self) -> Option<SomHorizon> {
         if self
             .iter()
             .any(|Pattern { flags, .. }| flags.contains(Flags::SOM_LEFTMOST))
         {
+            // the most precise horizon, which has the lowest mode bit
             self.iter()
                 .flat_map(|&Pattern { som, .. }| som)
-                .max()
-                .or(Some(SomHorizon::Medium))
+                .min()
+                .or(Some(SomHorizon::Large))
         } else {
             None
         }
@@ -411,6 +680,8 @@ macro_rules! patterns {
 
 #[cfg(test)]
 mod tests {
+    use proptest::prelude::*;
+
     use crate::common::tests::*;
     use crate::prelude::*;
 
@@ -487,6 +758,85 @@ mod tests {
         assert_eq!(p.expression, "t/e/s/t");
         assert_eq!(p.flags, Flags::CASELESS);
         assert_eq!(p.id, None);
+
+        let p: Pattern = r"2:/a\/b/8WPLV".parse().unwrap();
+
+        assert_eq!(
+            p,
+            pattern! { 2 => r"a\/b"; UTF8 | UCP | PREFILTER | SOM_LEFTMOST | ALLOWEMPTY }
+        );
+        assert_eq!(p.to_string(), r"2:/a\/b/V8WPL");
+
+        // the last slash is escaped, so the string isn't delimited
+        let p: Pattern = r"/a\/".parse().unwrap();
+
+        assert_eq!(p.expression, r"/a\/");
+        assert!(p.flags.is_empty());
+
+        // an expression with `:/` but without ID
+        let p: Pattern = r"/https?:\/\//i".parse().unwrap();
+
+        assert_eq!(p, pattern! { r"https?:\/\/"; CASELESS });
+
+        let p = pattern! { "/usr/bin" };
+
+        assert_eq!(p.to_string(), "//usr/bin/");
+        assert_eq!(p.to_string().parse::<Pattern>().unwrap(), p);
+
+        assert_eq!(
+            "/test/iZ".parse::<Pattern>().unwrap_err().to_string(),
+            "invalid pattern flag: Z"
+        );
+        assert_eq!(
+            "/test/i{min_width=3}".parse::<Pattern>().unwrap_err().to_string(),
+            "unexpected parameter min_width"
+        );
+    }
+
+    fn flags() -> impl Strategy<Value = Flags> {
+        any::<u32>().prop_map(Flags::from_bits_truncate)
+    }
+
+    fn ext() -> impl Strategy<Value = ExprExt> {
+        (any::<Option<u64>>(), any::<Option<u64>>(), any::<Option<u32>>()).prop_map(
+            |(min_offset, min_length, edit_distance)| {
+                let mut ext = ExprExt::default();
+
+                if let Some(min_offset) = min_offset {
+                    ext.set_min_offset(min_offset);
+                }
+                if let Some(min_length) = min_length {
+                    ext.set_min_length(min_length);
+                }
+                if let Some(edit_distance) = edit_distance {
+                    ext.set_edit_distance(edit_distance);
+                }
+
+                ext
+            },
+        )
+    }
+
+    proptest! {
+        #[test]
+        fn test_pattern_round_trip(
+            expression in r"[a-z0-9/:{}\.*]{0,12}".prop_filter("a trailing backslash escapes the delimiter", |expr| {
+                expr.bytes().rev().take_while(|&b| b == b'\\').count() % 2 == 0
+            }),
+            flags in flags(),
+            id in any::<Option<u32>>(),
+            ext in ext(),
+        ) {
+            let p = Pattern {
+                expression,
+                flags,
+                id: id.map(|id| id as usize),
+                ext,
+                som: None,
+            };
+
+            prop_assert_eq!(p.to_string().parse::<Pattern>()?, p);
+        }
     }
 
     #[test]
@@ -510,6 +860,79 @@ mod tests {
         validate_database(&db);
     }
 
+    #[test]
+    fn test_pattern_info() {
+        let info = pattern! {r"fo{2,5}"}.info().unwrap();
+
+        assert_eq!(info.min_width(), 3);
+        assert_eq!(info.max_width(), Some(6));
+
+        let info = pattern! {"foo.*bar"}.info().unwrap();
+
+        assert_eq!(info.min_width(), 6);
+        assert_eq!(info.max_width(), None);
+        assert!(!info.matches_at_eod());
+
+        let info = pattern! {"foo$"}.info().unwrap();
+
+        assert!(info.matches_at_eod());
+        assert!(!info.matches_only_at_eod());
+
+        let info = pattern! {r"foo\z"}.info().unwrap();
+
+        assert!(info.matches_only_at_eod());
+
+        let exact = pattern! {"foobar"}.info().unwrap();
+        let approximate = pattern! {"foobar"}.edit_distance(1).info().unwrap();
+
+        assert_eq!((exact.min_width(), exact.max_width()), (6, Some(6)));
+        assert_eq!((approximate.min_width(), approximate.max_width()), (5, Some(7)));
+        assert_eq!(
+            pattern! {"foobar"}.min_length(6).ext_info().unwrap().min_width(),
+            exact.min_width()
+        );
+
+        let err = pattern! {"foo(bar"}.info().unwrap_err();
+
+        assert!(matches!(err, Error::Hyperscan(crate::common::Error::CompileError(_))));
+        assert_eq!(
+            err.to_string(),
+            "Missing close parenthesis for group started at index 3."
+        );
+    }
+
+    #[test]
+    fn test_pattern_validate() {
+        let is_compile_error = |pattern: Pattern| {
+            matches!(
+                pattern.validate(),
+                Err(Error::Hyperscan(crate::common::Error::CompileError(_)))
+            )
+        };
+
+        assert_eq!(pattern! {"fo+bar"; CASELESS}.validate(), Ok(()));
+        assert_eq!(pattern! {"foobar"}.hamming_distance(1).validate(), Ok(()));
+        assert!(is_compile_error(pattern! {"foo(bar"}));
+        assert!(is_compile_error(pattern! {"foo(bar"}.edit_distance(1)));
+
+        // the back-references are only supported in prefilter mode
+        assert!(is_compile_error(pattern! {r"(\w+) \1"}));
+        assert_eq!(pattern! {r"(\w+) \1"; PREFILTER}.validate(), Ok(()));
+
+        // the code points above 0xFF are only valid in UTF-8 mode
+        assert!(is_compile_error(pattern! {r"\x{100}"}));
+        assert_eq!(pattern! {r"\x{100}"; UTF8}.validate(), Ok(()));
+
+        let patterns: Patterns = "1:/foo/\n2:/foo(bar/\n3:/(a)\\1/\n4:/bar/".parse().unwrap();
+        let errors = patterns.validate_all();
+
+        assert_eq!(errors.iter().map(|&(index, _)| index).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            errors[0].1.to_string(),
+            "Missing close parenthesis for group started at index 3."
+        );
+    }
+
     #[test]
     fn test_pattern_build_with_flags() {
         let p = &pattern! {"test"; CASELESS};
@@ -536,4 +959,240 @@ mod tests {
 
         validate_database_with_size(&db, DATABASE_SIZE);
     }
+
+    fn scan(db: &BlockDatabase, data: &str) -> Vec<(u32, u64)> {
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan(data, &mut s, |id, _, to, _| {
+            matches.push((id, to));
+            Matching::Continue
+        })
+        .unwrap();
+
+        matches
+    }
+
+    #[test]
+    fn test_pattern_build_with_ext() {
+        let p = Pattern::new("foo").unwrap().min_offset(10);
+
+        assert_eq!(p.ext.min_offset(), Some(10));
+        assert_eq!(p.ext.max_offset(), None);
+
+        let db: BlockDatabase = p.build().unwrap();
+
+        assert_eq!(scan(&db, "..foo.......foo"), vec![(0, 15)]);
+
+        let db: BlockDatabase = Pattern::new("fo+").unwrap().left_most().min_length(3).build().unwrap();
+
+        assert_eq!(scan(&db, "fo foo"), vec![(0, 6)]);
+    }
+
+    #[test]
+    fn test_patterns_build_with_ext() {
+        // only some of the patterns have the extended parameters
+        let patterns = Patterns(vec![
+            pattern! {1 => "foo"; SOM_LEFTMOST},
+            pattern! {2 => "foo"; SOM_LEFTMOST}.max_offset(5),
+            pattern! {3 => "bar"; SOM_LEFTMOST},
+            pattern! {4 => "foo"; SOM_LEFTMOST}.min_offset(10),
+        ]);
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut matches = scan(&db, "..foo.bar...foo");
+
+        matches.sort_by_key(|&(id, to)| (to, id));
+
+        assert_eq!(matches, vec![(1, 5), (2, 5), (3, 9), (1, 15), (4, 15)]);
+    }
+
+    #[test]
+    fn test_pattern_build_approximate() {
+        let db: BlockDatabase = Pattern::new("foobar").unwrap().edit_distance(1).build().unwrap();
+
+        assert_eq!(scan(&db, "fooxar"), vec![(0, 6)]);
+
+        let db: BlockDatabase = "/foobar/{hamming_distance=1}".build().unwrap();
+
+        assert_eq!(scan(&db, "fooxar"), vec![(0, 6)]);
+        assert!(scan(&db, "fooar").is_empty());
+
+        // both distances are rejected before compiling
+        let p = Pattern::new("foobar").unwrap().edit_distance(1).hamming_distance(1);
+
+        assert_eq!(
+            p.build::<crate::Block>().err().unwrap().to_string(),
+            "edit distance and Hamming distance can't be set on the same expression"
+        );
+        assert!(Patterns(vec![pattern! {"foo"}, p.clone()])
+            .build::<crate::Block>()
+            .is_err());
+        assert!(p.info().is_err());
+
+        // the library rejects the expressions which would match anything within the distance
+        let err = Pattern::new("ab")
+            .unwrap()
+            .edit_distance(2)
+            .build::<crate::Block>()
+            .err()
+            .unwrap();
+
+        assert!(matches!(err, Error::Hyperscan(crate::common::Error::CompileError(_))));
+        assert!(
+            err.to_string().to_lowercase().contains("approximate matching"),
+            "{}",
+            err
+        );
+    }
+
+    #[cfg(all(feature = "v5", hs_version_gte_5_1))]
+    #[test]
+    fn test_push_combination() {
+        let mut patterns = patterns!("foo", "bar", "baz");
+
+        assert_eq!(
+            patterns.push_combination(3, "0 & 4", true).unwrap_err().to_string(),
+            "invalid logical combination, unknown pattern id 4"
+        );
+        assert_eq!(
+            patterns.push_combination(2, "0 & 1", true).unwrap_err().to_string(),
+            "invalid logical combination, pattern id 2 is already in the set"
+        );
+        assert_eq!(
+            patterns.push_combination(3, "0 + 1", true).unwrap_err().to_string(),
+            "invalid logical combination, unexpected character `+` at 2"
+        );
+        assert!(patterns.push_combination(3, "()", true).is_err());
+        assert_eq!(patterns.len(), 3);
+
+        patterns.push_combination(10, "0 & (1 | 2)", true).unwrap();
+
+        assert!(patterns[..3].iter().all(|pattern| pattern.flags.contains(Flags::QUIET)));
+        assert_eq!(patterns[3].flags, Flags::COMBINATION);
+        assert_eq!(patterns[3].id, Some(10));
+
+        let db: BlockDatabase = patterns.build().unwrap();
+
+        assert_eq!(scan(&db, "foo baz"), vec![(10, 7)]);
+        assert!(scan(&db, "bar baz").is_empty());
+
+        // the operands without `quiet` still report their matches
+        let mut patterns = patterns!("foo", "bar");
+
+        patterns.push_combination(10, "0 | 1", false).unwrap();
+
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut matches = scan(&db, "foo");
+
+        matches.sort_unstable();
+
+        assert_eq!(matches, vec![(0, 3), (10, 3)]);
+    }
+
+    #[cfg(all(feature = "v5", hs_version_gte_5_1))]
+    #[test]
+    fn test_quiet() {
+        let patterns: Patterns = "1:/foo/Q\n2:/bar/".parse().unwrap();
+
+        assert_eq!(patterns[0].flags, Flags::QUIET);
+        assert_eq!(patterns[0].to_string(), "1:/foo/Q");
+
+        let db: BlockDatabase = patterns.build().unwrap();
+
+        assert_eq!(scan(&db, "foo bar foo"), vec![(2, 7)]);
+
+        let p = pattern! {"foo"}.quiet().left_most();
+
+        assert_eq!(
+            p.build::<crate::Block>().err().unwrap(),
+            Error::ConflictingFlags("QUIET", "SOM_LEFTMOST")
+        );
+        assert_eq!(
+            p.info().unwrap_err().to_string(),
+            "pattern flags QUIET and SOM_LEFTMOST can't be used together"
+        );
+    }
+
+    #[test]
+    fn test_patterns_build_error() {
+        let patterns: Patterns = "1:/foo/\n/ba(r/i\n3:/baz/".parse().unwrap();
+        let err = patterns.build::<crate::Block>().err().unwrap();
+
+        match err {
+            Error::InvalidPattern {
+                index,
+                id,
+                ref expression,
+                ref error,
+            } => {
+                assert_eq!(index, 1);
+                assert_eq!(id, 1);
+                assert_eq!(expression, "ba(r");
+                assert_eq!(error.expression(), Some(1));
+            }
+            _ => panic!("unexpected error: {}", err),
+        }
+
+        assert!(err
+            .to_string()
+            .starts_with("invalid pattern #1 with id 1, `ba(r`: Missing close parenthesis"));
+    }
+
+    #[test]
+    fn test_check_all() {
+        let mut patterns = (0..50).map(|i| pattern! {format!("foo{}", i)}).collect::<Patterns>();
+
+        patterns[7].expression = "ba(r".into();
+        patterns[23].expression = "ba[r".into();
+        patterns[41].flags = Flags::SOM_LEFTMOST;
+        patterns[41].ext.set_edit_distance(1);
+        patterns[41].ext.set_hamming_distance(1);
+
+        let errors = patterns.check_all::<crate::Block>(None);
+
+        assert_eq!(
+            errors.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            vec![7, 23, 41]
+        );
+        assert!(matches!(
+            errors[0].1,
+            Error::Hyperscan(crate::common::Error::CompileError(_))
+        ));
+        assert!(matches!(errors[2].1, Error::Expr(_)));
+        assert!(Patterns(patterns[..5].to_vec())
+            .check_all::<crate::Block>(None)
+            .is_empty());
+    }
+
+    #[cfg(all(feature = "v5", hs_version_gte_5_1))]
+    #[test]
+    fn test_check_all_combinations() {
+        let patterns: Patterns = "1:/foo/Q\n2:/ba(r/Q\n3:/1 & 4/C\n4:/baz/Q\n5:/1 | 4/C\n6:/1 & 2/C"
+            .parse()
+            .unwrap();
+        let errors = patterns.check_all::<crate::Block>(None);
+
+        // the combination of an invalid pattern fails too
+        assert_eq!(errors.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![1, 5]);
+    }
+
+    #[test]
+    fn test_patterns_som() {
+        let mut patterns = patterns!("foo", "bar");
+
+        assert_eq!(patterns.som(), None);
+
+        patterns[0].flags = Flags::SOM_LEFTMOST;
+
+        assert_eq!(patterns.som(), Some(SomHorizon::Large));
+
+        patterns[0].som = Some(SomHorizon::Small);
+        patterns[1].som = Some(SomHorizon::Medium);
+
+        assert_eq!(patterns.som(), Some(SomHorizon::Medium));
+        assert_eq!(
+            pattern! {"foo"; SOM_LEFTMOST}.som_horizon(SomHorizon::Small).som(),
+            Some(SomHorizon::Small)
+        );
+    }
 }
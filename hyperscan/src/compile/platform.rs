@@ -1,11 +1,18 @@
+use std::fmt;
 use std::mem::{self, MaybeUninit};
+use std::result::Result as StdResult;
+use std::str::FromStr;
+use std::sync::OnceLock;
 
 use bitflags::bitflags;
-use foreign_types::{foreign_type, ForeignType};
+use foreign_types::{foreign_type, ForeignType, ForeignTypeRef};
 
-use crate::{error::AsResult, ffi, Result};
+use crate::{common::Error as HsError, error::AsResult, ffi, Error, Result};
 
 /// Tuning Parameter
+///
+/// The tuning families are only meaningful on x86 CPUs,
+/// Vectorscan reports `Tune::Generic` on the other architectures.
 #[repr(u32)]
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Tune {
@@ -37,11 +44,11 @@ pub enum Tune {
     Goldmont = ffi::HS_TUNE_FAMILY_GLM,
 
     /// Intel(R) microarchitecture code name Icelake
-    #[cfg(feature = "v5_4")]
+    #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
     Icelake = ffi::HS_TUNE_FAMILY_ICL,
 
     /// Intel(R) microarchitecture code name Icelake Server
-    #[cfg(feature = "v5_4")]
+    #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
     IcelakeServer = ffi::HS_TUNE_FAMILY_ICX,
 }
 
@@ -51,8 +58,83 @@ impl Default for Tune {
     }
 }
 
+impl Tune {
+    pub(crate) fn from_raw(tune: u32) -> Option<Tune> {
+        use Tune::*;
+
+        match tune {
+            ffi::HS_TUNE_FAMILY_GENERIC => Some(Generic),
+            ffi::HS_TUNE_FAMILY_SNB => Some(SandyBridge),
+            ffi::HS_TUNE_FAMILY_IVB => Some(IvyBridge),
+            ffi::HS_TUNE_FAMILY_HSW => Some(Haswell),
+            ffi::HS_TUNE_FAMILY_SLM => Some(Silvermont),
+            ffi::HS_TUNE_FAMILY_BDW => Some(Broadwell),
+            ffi::HS_TUNE_FAMILY_SKL => Some(Skylake),
+            ffi::HS_TUNE_FAMILY_SKX => Some(SkylakeServer),
+            ffi::HS_TUNE_FAMILY_GLM => Some(Goldmont),
+            #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+            ffi::HS_TUNE_FAMILY_ICL => Some(Icelake),
+            #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+            ffi::HS_TUNE_FAMILY_ICX => Some(IcelakeServer),
+            _ => None,
+        }
+    }
+
+    const NAMES: &'static [(Tune, &'static str)] = &[
+        (Tune::Generic, "generic"),
+        (Tune::SandyBridge, "sandy_bridge"),
+        (Tune::IvyBridge, "ivy_bridge"),
+        (Tune::Haswell, "haswell"),
+        (Tune::Silvermont, "silvermont"),
+        (Tune::Broadwell, "broadwell"),
+        (Tune::Skylake, "skylake"),
+        (Tune::SkylakeServer, "skylake_server"),
+        (Tune::Goldmont, "goldmont"),
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        (Tune::Icelake, "icelake"),
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        (Tune::IcelakeServer, "icelake_server"),
+    ];
+
+    /// The tuning family of the current host, guessed from the CPU family and model without calling into Hyperscan.
+    ///
+    /// The known models are the ones Hyperscan recognizes, `Tune::Generic` is returned for the others
+    /// and on the non-x86 architectures.
+    pub fn detect() -> Tune {
+        arch::tune()
+    }
+}
+
+impl fmt::Display for Tune {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = Tune::NAMES
+            .iter()
+            .find(|&&(tune, _)| tune == *self)
+            .map(|&(_, name)| name)
+            .unwrap_or_default();
+
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Tune {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim();
+
+        Tune::NAMES
+            .iter()
+            .find(|&&(_, s)| s.eq_ignore_ascii_case(name))
+            .map(|&(tune, _)| tune)
+            .ok_or_else(|| Error::InvalidTune(name.into()))
+    }
+}
+
 bitflags! {
     /// CPU feature support flags
+    ///
+    /// The flags are x86 specific, no feature is reported on the other architectures supported by Vectorscan.
     #[derive(Default)]
     pub struct CpuFeatures: u64 {
         /// Intel(R) Advanced Vector Extensions 2 (Intel(R) AVX2)
@@ -60,11 +142,92 @@ bitflags! {
         /// Intel(R) Advanced Vector Extensions 512 (Intel(R) AVX512)
         const AVX512 = ffi::HS_CPU_FEATURES_AVX512 as u64;
         /// Intel(R) Advanced Vector Extensions 512 Vector Byte Manipulation Instructions (Intel(R) AVX512VBMI)
-        #[cfg(feature = "v5_4")]
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
         const AVX512VBMI = ffi::HS_CPU_FEATURES_AVX512VBMI as u64;
     }
 }
 
+impl CpuFeatures {
+    const NAMES: &'static [(CpuFeatures, &'static str)] = &[
+        (CpuFeatures::AVX2, "AVX2"),
+        (CpuFeatures::AVX512, "AVX512"),
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        (CpuFeatures::AVX512VBMI, "AVX512VBMI"),
+    ];
+}
+
+impl CpuFeatures {
+    /// The CPU features of the current host, detected with `is_x86_feature_detected!` without calling into Hyperscan.
+    ///
+    /// It may be used to pick a database variant before Hyperscan is loaded,
+    /// no feature is returned on the non-x86 architectures.
+    pub fn detect() -> CpuFeatures {
+        arch::cpu_features()
+    }
+}
+
+/// Pick the most specific database variant which can run on the current host.
+///
+/// Each variant is described by the CPU features it was compiled for,
+/// the index of the variant requiring the most features supported by the host is returned.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{best_database_variant, CpuFeatures};
+/// let variants = [CpuFeatures::empty(), CpuFeatures::AVX2, CpuFeatures::AVX2 | CpuFeatures::AVX512];
+/// let best = best_database_variant(&variants).unwrap();
+///
+/// assert!(CpuFeatures::detect().contains(variants[best]));
+/// ```
+pub fn best_database_variant(variants: &[CpuFeatures]) -> Option<usize> {
+    best_variant_for(CpuFeatures::detect(), variants)
+}
+
+fn best_variant_for(host: CpuFeatures, variants: &[CpuFeatures]) -> Option<usize> {
+    variants
+        .iter()
+        .enumerate()
+        .filter(|(_, features)| host.contains(**features))
+        .max_by_key(|(_, features)| (features.bits().count_ones(), features.bits()))
+        .map(|(idx, _)| idx)
+}
+
+impl fmt::Display for CpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+
+        for &(feature, name) in CpuFeatures::NAMES {
+            if self.contains(feature) {
+                if !first {
+                    f.write_str("|")?;
+                }
+                f.write_str(name)?;
+                first = false;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl FromStr for CpuFeatures {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        s.split('|')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .try_fold(CpuFeatures::empty(), |features, name| {
+                CpuFeatures::NAMES
+                    .iter()
+                    .find(|&&(_, s)| s.eq_ignore_ascii_case(name))
+                    .map(|&(feature, _)| features | feature)
+                    .ok_or_else(|| Error::InvalidCpuFeature(name.into()))
+            })
+    }
+}
+
 foreign_type! {
     /// A type containing information on the target platform
     /// which may optionally be provided to the compile calls
@@ -72,13 +235,45 @@ foreign_type! {
         type CType = ffi::hs_platform_info_t;
 
         fn drop = free_platform_info;
+        fn clone = clone_platform_info;
     }
 }
 
+/// Free a platform previously allocated by `Platform::from_info`.
 unsafe fn free_platform_info(p: *mut ffi::hs_platform_info_t) {
     mem::drop(Box::from_raw(p));
 }
 
+/// Copy the plain platform information into a new allocation.
+unsafe fn clone_platform_info(p: *mut ffi::hs_platform_info_t) -> *mut ffi::hs_platform_info_t {
+    Box::into_raw(Box::new(*p))
+}
+
+impl Platform {
+    /// All the `Platform` must be allocated here, since it will be freed as a `Box` on drop.
+    fn from_info(info: ffi::hs_platform_info_t) -> Platform {
+        unsafe { Platform::from_ptr(Box::into_raw(Box::new(info))) }
+    }
+}
+
+/// The platform information of the current host, populated once.
+fn host_platform_info() -> Result<ffi::hs_platform_info_t> {
+    static HOST: OnceLock<StdResult<ffi::hs_platform_info_t, ffi::hs_error_t>> = OnceLock::new();
+
+    let info = *HOST.get_or_init(|| {
+        let mut platform = MaybeUninit::zeroed();
+
+        unsafe {
+            match ffi::hs_populate_platform(platform.as_mut_ptr()) {
+                err if err == ffi::HS_SUCCESS as ffi::hs_error_t => Ok(platform.assume_init()),
+                err => Err(err),
+            }
+        }
+    });
+
+    info.map_err(|err| HsError::from(err).into())
+}
+
 impl Platform {
     /// Utility function to test the current system architecture.
     ///
@@ -89,29 +284,661 @@ impl Platform {
     /// This function does not test for more advanced features
     /// if Hyperscan has been built for a more specific architecture,
     /// for example the AVX2 instruction set.
+    ///
+    /// When the architecture is not supported, `Error::ArchError` describes the missing capability.
     pub fn is_valid() -> Result<()> {
-        unsafe { ffi::hs_valid_platform().ok() }
+        match unsafe { ffi::hs_valid_platform() } {
+            ffi::HS_ARCH_ERROR => Err(Error::ArchError(describe_arch_error())),
+            err => err.ok(),
+        }
     }
 
     /// Populates the platform information based on the current host.
     pub fn host() -> Result<Platform> {
         let mut platform = MaybeUninit::zeroed();
 
-        unsafe {
-            ffi::hs_populate_platform(platform.as_mut_ptr())
-                .map(|_| Platform::from_ptr(Box::into_raw(Box::new(platform.assume_init()))))
+        unsafe { ffi::hs_populate_platform(platform.as_mut_ptr()).map(|_| Platform::from_info(platform.assume_init())) }
+    }
+
+    /// Returns the platform information of the current host.
+    ///
+    /// The host is only probed once, later calls share the same cached platform.
+    pub fn host_cached() -> Result<&'static Platform> {
+        static HOST: OnceLock<Platform> = OnceLock::new();
+
+        if let Some(platform) = HOST.get() {
+            return Ok(platform);
         }
+
+        let info = host_platform_info()?;
+
+        Ok(HOST.get_or_init(|| Platform::from_info(info)))
     }
 
     /// Constructs a target platform which may be used to guide the optimisation process of the compile.
+    ///
+    /// The CPU feature bits are passed through to Hyperscan unchanged.
     pub fn new(tune: Tune, cpu_features: CpuFeatures) -> Platform {
-        unsafe {
-            Platform::from_ptr(Box::into_raw(Box::new(ffi::hs_platform_info_t {
-                tune: tune as u32,
+        Platform::from_info(ffi::hs_platform_info_t {
+            tune: tune as u32,
+            cpu_features: cpu_features.bits(),
+            reserved1: 0,
+            reserved2: 0,
+        })
+    }
+
+    /// The generic platform, which runs on any host supported by Hyperscan.
+    pub fn generic() -> Platform {
+        Platform::new(Tune::Generic, CpuFeatures::empty())
+    }
+
+    /// The generic platform with Intel(R) AVX2 instructions enabled.
+    pub fn avx2_generic() -> Platform {
+        Platform::new(Tune::Generic, CpuFeatures::AVX2)
+    }
+
+    /// The lowest common platform of the given platforms.
+    ///
+    /// Only the CPU features supported by all the platforms are kept,
+    /// and the tuning family falls back to `Tune::Generic` unless all the platforms share the same one.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{CpuFeatures, Platform, Tune};
+    /// let fleet = [
+    ///     Platform::new(Tune::Skylake, CpuFeatures::AVX2 | CpuFeatures::AVX512),
+    ///     Platform::new(Tune::Haswell, CpuFeatures::AVX2),
+    /// ];
+    /// let common = Platform::min_common(&fleet).unwrap();
+    ///
+    /// assert_eq!(common.tune(), Some(Tune::Generic));
+    /// assert_eq!(common.cpu_features(), CpuFeatures::AVX2);
+    /// ```
+    pub fn min_common<P: AsRef<PlatformRef>>(platforms: &[P]) -> Result<Platform> {
+        let (first, rest) = platforms.split_first().ok_or(Error::EmptyPlatforms)?;
+        let first = unsafe { *first.as_ref().as_ptr() };
+
+        let (tune, cpu_features) = rest
+            .iter()
+            .fold((first.tune, first.cpu_features), |(tune, features), p| {
+                let info = unsafe { *p.as_ref().as_ptr() };
+
+                (
+                    if tune == info.tune { tune } else { Tune::Generic as u32 },
+                    features & info.cpu_features,
+                )
+            });
+
+        Ok(Platform::from_info(ffi::hs_platform_info_t {
+            tune,
+            cpu_features,
+            reserved1: 0,
+            reserved2: 0,
+        }))
+    }
+}
+
+/// Describe why the current host is not supported by Hyperscan.
+fn describe_arch_error() -> String {
+    let mut desc = arch::missing_features().map_or_else(
+        || {
+            format!(
+                "the {} CPU lacks the SIMD instructions required by the library",
+                std::env::consts::ARCH
+            )
+        },
+        |missing| {
+            if missing.contains(&"SSSE3") {
+                format!(
+                    "the CPU lacks the required SSSE3 instruction set (missing: {})",
+                    missing.join(", ")
+                )
+            } else if missing.is_empty() {
+                "Hyperscan was built for a more specific architecture than the CPU".to_owned()
+            } else {
+                format!(
+                    "Hyperscan was built for a more specific architecture than the CPU (missing: {})",
+                    missing.join(", ")
+                )
+            }
+        },
+    );
+
+    if let Ok(info) = host_platform_info() {
+        desc += &format!(
+            ", detected tune: {}, CPU features: {}",
+            Tune::from_raw(info.tune).map_or_else(|| info.tune.to_string(), |tune| tune.to_string()),
+            CpuFeatures::from_bits_truncate(info.cpu_features)
+        );
+    }
+
+    desc
+}
+
+#[cfg(target_arch = "x86_64")]
+mod arch {
+    use std::arch::x86_64::{__cpuid, __cpuid_count};
+
+    use super::{CpuFeatures, Tune};
+
+    /// The models of the family 6 known by Hyperscan, as `(model, tuning family)`.
+    const MODELS: &[(u32, Tune)] = &[
+        (0x37, Tune::Silvermont),
+        (0x4A, Tune::Silvermont),
+        (0x4C, Tune::Silvermont),
+        (0x4D, Tune::Silvermont),
+        (0x5A, Tune::Silvermont),
+        (0x5D, Tune::Silvermont),
+        (0x5C, Tune::Goldmont),
+        (0x5F, Tune::Goldmont),
+        (0x3C, Tune::Haswell),
+        (0x45, Tune::Haswell),
+        (0x46, Tune::Haswell),
+        (0x3F, Tune::Haswell),
+        (0x3E, Tune::IvyBridge),
+        (0x3A, Tune::IvyBridge),
+        (0x2A, Tune::SandyBridge),
+        (0x2D, Tune::SandyBridge),
+        (0x3D, Tune::Broadwell),
+        (0x47, Tune::Broadwell),
+        (0x4F, Tune::Broadwell),
+        (0x56, Tune::Broadwell),
+        (0x4E, Tune::Skylake),
+        (0x5E, Tune::Skylake),
+        (0x55, Tune::SkylakeServer),
+        (0x8E, Tune::Skylake),
+        (0x9E, Tune::Skylake),
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        (0x7D, Tune::Icelake),
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        (0x7E, Tune::Icelake),
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        (0x6A, Tune::IcelakeServer),
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        (0x6C, Tune::IcelakeServer),
+    ];
+
+    /// The tuning family of the CPU, from the family and the extended model reported by `cpuid`.
+    #[allow(unused_unsafe)]
+    pub fn tune() -> Tune {
+        let eax = unsafe { __cpuid(1) }.eax;
+        let family = (eax >> 8) & 0xF;
+        let model = ((eax >> 4) & 0xF) | ((eax >> 12) & 0xF0);
+
+        if family != 6 {
+            return Tune::Generic;
+        }
+
+        MODELS
+            .iter()
+            .find(|&&(known, _)| known == model)
+            .map_or(Tune::Generic, |&(_, tune)| tune)
+    }
+
+    /// The CPU features supported by the CPU and enabled by the OS.
+    pub fn cpu_features() -> CpuFeatures {
+        features_of(
+            is_x86_feature_detected!("avx2"),
+            is_x86_feature_detected!("avx512bw"),
+            is_x86_feature_detected!("avx512vbmi"),
+        )
+    }
+
+    /// The CPU features matching the detected instruction sets, as Hyperscan reports them.
+    ///
+    /// `AVX512VBMI` is only reported with `AVX512`, which requires `AVX512BW`.
+    #[cfg_attr(not(all(feature = "v5_4", hs_version_gte_5_4)), allow(unused_variables))]
+    pub fn features_of(avx2: bool, avx512bw: bool, avx512vbmi: bool) -> CpuFeatures {
+        let mut features = CpuFeatures::empty();
+
+        if avx2 {
+            features |= CpuFeatures::AVX2;
+        }
+
+        if avx512bw {
+            features |= CpuFeatures::AVX512;
+
+            #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+            if avx512vbmi {
+                features |= CpuFeatures::AVX512VBMI;
+            }
+        }
+
+        features
+    }
+
+    /// The instruction sets used by Hyperscan as `(name, leaf, bit of ECX or EBX)`.
+    const FEATURES: &[(&str, u32, u32)] = &[
+        ("SSSE3", 1, 9),
+        ("SSE4.1", 1, 19),
+        ("SSE4.2", 1, 20),
+        ("POPCNT", 1, 23),
+        ("AVX2", 7, 5),
+        ("BMI2", 7, 8),
+    ];
+
+    /// The instruction sets used by Hyperscan which are not supported by the CPU, queried with raw `cpuid`.
+    #[allow(unused_unsafe)]
+    pub fn missing_features() -> Option<Vec<&'static str>> {
+        let max_leaf = unsafe { __cpuid(0) }.eax;
+
+        Some(
+            FEATURES
+                .iter()
+                .filter(|&&(_, leaf, bit)| {
+                    let reg = if leaf > max_leaf {
+                        0
+                    } else if leaf == 1 {
+                        unsafe { __cpuid(1) }.ecx
+                    } else {
+                        unsafe { __cpuid_count(leaf, 0) }.ebx
+                    };
+
+                    reg & (1 << bit) == 0
+                })
+                .map(|&(name, _, _)| name)
+                .collect(),
+        )
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+mod arch {
+    use super::{CpuFeatures, Tune};
+
+    pub fn tune() -> Tune {
+        Tune::Generic
+    }
+
+    pub fn cpu_features() -> CpuFeatures {
+        CpuFeatures::empty()
+    }
+
+    pub fn missing_features() -> Option<Vec<&'static str>> {
+        None
+    }
+}
+
+/// The platform report of the current host.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlatformReport {
+    /// The version of the linked Hyperscan library.
+    pub version: &'static str,
+    /// The tuning family of the host, `None` if it is unknown to this crate.
+    pub tune: Option<Tune>,
+    /// The CPU features of the host.
+    pub cpu_features: CpuFeatures,
+}
+
+impl fmt::Display for PlatformReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "hyperscan {}, tune: ", self.version)?;
+
+        match self.tune {
+            Some(tune) => write!(f, "{}", tune)?,
+            None => f.write_str("unknown")?,
+        }
+
+        if self.cpu_features.is_empty() {
+            f.write_str(", CPU features: none")
+        } else {
+            write!(f, ", CPU features: {}", self.cpu_features)
+        }
+    }
+}
+
+/// Check whether the current host is supported by Hyperscan, and report the detected platform.
+///
+/// Applications may call it at startup to fail early with a meaningful message.
+///
+/// # Examples
+///
+/// ```rust
+/// match hyperscan::check_platform() {
+///     Ok(report) => println!("{}", report),
+///     Err(err) => eprintln!("unsupported platform, {}", err),
+/// }
+/// ```
+pub fn check_platform() -> Result<PlatformReport> {
+    Platform::is_valid()?;
+
+    let host = Platform::host_cached()?;
+
+    Ok(PlatformReport {
+        version: crate::version_string(),
+        tune: host.tune(),
+        cpu_features: host.cpu_features(),
+    })
+}
+
+impl AsRef<PlatformRef> for PlatformRef {
+    fn as_ref(&self) -> &PlatformRef {
+        self
+    }
+}
+
+impl PlatformRef {
+    /// The tuning family of the target platform, `None` if it is unknown to this crate.
+    pub fn tune(&self) -> Option<Tune> {
+        Tune::from_raw(unsafe { (*self.as_ptr()).tune })
+    }
+
+    /// Relevant CPU features available on the target platform
+    pub fn cpu_features(&self) -> CpuFeatures {
+        CpuFeatures::from_bits_truncate(unsafe { (*self.as_ptr()).cpu_features })
+    }
+
+    /// Returns `true` if a database built for this platform can be used on the `host` platform.
+    ///
+    /// The host must support at least the CPU features required by this platform,
+    /// the tuning family may differ since it only affects the optimisation.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{CpuFeatures, Platform, Tune};
+    /// let target = Platform::new(Tune::Haswell, CpuFeatures::AVX2);
+    ///
+    /// assert!(target.is_compatible_with(&Platform::new(Tune::Skylake, CpuFeatures::AVX2 | CpuFeatures::AVX512)));
+    /// assert!(!target.is_compatible_with(&Platform::new(Tune::Haswell, CpuFeatures::empty())));
+    /// ```
+    pub fn is_compatible_with(&self, host: &PlatformRef) -> bool {
+        self.missing_features(host).is_empty()
+    }
+
+    /// The CPU features required by this platform but not supported by the `host` platform.
+    pub fn missing_features(&self, host: &PlatformRef) -> CpuFeatures {
+        self.cpu_features() - host.cpu_features()
+    }
+}
+
+impl fmt::Debug for PlatformRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let info = unsafe { *self.as_ptr() };
+        let mut s = f.debug_struct("Platform");
+
+        match Tune::from_raw(info.tune) {
+            Some(tune) => s.field("tune", &tune),
+            None => s.field("tune", &info.tune),
+        };
+
+        s.field("cpu_features", &self.cpu_features()).finish()
+    }
+}
+
+impl fmt::Debug for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+/// The platforms are equal if they have the same tuning family and CPU features,
+/// a database built for one of them is built for the other.
+impl PartialEq for PlatformRef {
+    fn eq(&self, other: &Self) -> bool {
+        let (lhs, rhs) = unsafe { (*self.as_ptr(), *other.as_ptr()) };
+
+        lhs.tune == rhs.tune && lhs.cpu_features == rhs.cpu_features
+    }
+}
+
+impl Eq for PlatformRef {}
+
+impl PartialEq for Platform {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for Platform {}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::convert::TryFrom;
+    use std::fmt;
+    use std::result::Result as StdResult;
+
+    use foreign_types::ForeignTypeRef;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{CpuFeatures, Platform, PlatformRef, Tune};
+    use crate::{ffi, Error};
+
+    impl Serialize for Tune {
+        fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Tune {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            let RawTune(tune) = RawTune::deserialize(deserializer)?;
+
+            Tune::from_raw(tune).ok_or_else(|| de::Error::custom(Error::InvalidTune(tune.to_string())))
+        }
+    }
+
+    impl Serialize for CpuFeatures {
+        fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            serializer.collect_str(self)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for CpuFeatures {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+        }
+    }
+
+    /// The tuning family of a platform.
+    ///
+    /// The known families are represented by their names, e.g. `skylake`,
+    /// and the families unknown to this crate by the raw `u32` value.
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct RawTune(u32);
+
+    impl Default for RawTune {
+        fn default() -> Self {
+            RawTune(Tune::Generic as u32)
+        }
+    }
+
+    impl Serialize for RawTune {
+        fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            match Tune::from_raw(self.0) {
+                Some(tune) => tune.serialize(serializer),
+                None => serializer.serialize_u32(self.0),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for RawTune {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            struct RawTuneVisitor;
+
+            impl<'de> de::Visitor<'de> for RawTuneVisitor {
+                type Value = RawTune;
+
+                fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str("a tune name or a tuning family number")
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> StdResult<Self::Value, E> {
+                    v.parse::<Tune>().map(|tune| RawTune(tune as u32)).map_err(E::custom)
+                }
+
+                fn visit_u64<E: de::Error>(self, v: u64) -> StdResult<Self::Value, E> {
+                    u32::try_from(v)
+                        .map(RawTune)
+                        .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+                }
+
+                fn visit_i64<E: de::Error>(self, v: i64) -> StdResult<Self::Value, E> {
+                    u32::try_from(v)
+                        .map(RawTune)
+                        .map_err(|_| E::invalid_value(de::Unexpected::Signed(v), &self))
+                }
+            }
+
+            deserializer.deserialize_any(RawTuneVisitor)
+        }
+    }
+
+    /// The human-readable representation of a platform, e.g. `{"tune": "skylake", "cpu_features": "AVX2"}`.
+    ///
+    /// The missing fields fallback to the generic platform.
+    #[derive(Serialize, Deserialize)]
+    struct PlatformRepr {
+        #[serde(default)]
+        tune: RawTune,
+        #[serde(default)]
+        cpu_features: CpuFeatures,
+    }
+
+    impl Serialize for PlatformRef {
+        fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            let info = unsafe { *self.as_ptr() };
+
+            PlatformRepr {
+                tune: RawTune(info.tune),
+                cpu_features: self.cpu_features(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl Serialize for Platform {
+        fn serialize<S: Serializer>(&self, serializer: S) -> StdResult<S::Ok, S::Error> {
+            (**self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Platform {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> StdResult<Self, D::Error> {
+            let PlatformRepr {
+                tune: RawTune(tune),
+                cpu_features,
+            } = PlatformRepr::deserialize(deserializer)?;
+
+            Ok(Platform::from_info(ffi::hs_platform_info_t {
+                tune,
                 cpu_features: cpu_features.bits(),
                 reserved1: 0,
                 reserved2: 0,
-            })))
+            }))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_json::json;
+
+        use super::*;
+
+        #[test]
+        fn test_serde_tune() {
+            assert_eq!(serde_json::to_value(Tune::Skylake).unwrap(), json!("skylake"));
+            assert_eq!(
+                serde_json::to_value(Tune::SkylakeServer).unwrap(),
+                json!("skylake_server")
+            );
+
+            assert_eq!(serde_json::from_value::<Tune>(json!("Haswell")).unwrap(), Tune::Haswell);
+            assert_eq!(
+                serde_json::from_value::<Tune>(json!(ffi::HS_TUNE_FAMILY_GLM)).unwrap(),
+                Tune::Goldmont
+            );
+
+            let err = serde_json::from_value::<Tune>(json!("pentium")).unwrap_err();
+            assert!(err.to_string().contains("invalid tune: pentium"), "{}", err);
+
+            let err = serde_json::from_value::<Tune>(json!(1234)).unwrap_err();
+            assert!(err.to_string().contains("invalid tune: 1234"), "{}", err);
+
+            for &(tune, _) in Tune::NAMES {
+                assert_eq!(
+                    serde_json::from_value::<Tune>(serde_json::to_value(tune).unwrap()).unwrap(),
+                    tune
+                );
+            }
+        }
+
+        #[test]
+        fn test_serde_cpu_features() {
+            assert_eq!(serde_json::to_value(CpuFeatures::empty()).unwrap(), json!(""));
+            assert_eq!(
+                serde_json::to_value(CpuFeatures::AVX2 | CpuFeatures::AVX512).unwrap(),
+                json!("AVX2|AVX512")
+            );
+
+            assert_eq!(
+                serde_json::from_value::<CpuFeatures>(json!("avx2")).unwrap(),
+                CpuFeatures::AVX2
+            );
+
+            let err = serde_json::from_value::<CpuFeatures>(json!("AVX2|SSE9")).unwrap_err();
+            assert!(err.to_string().contains("invalid CPU feature: SSE9"), "{}", err);
+
+            assert!(serde_json::from_value::<CpuFeatures>(json!(4)).is_err());
+        }
+
+        #[test]
+        fn test_serde_platform() {
+            let platform = Platform::new(Tune::Skylake, CpuFeatures::AVX2);
+            let value = serde_json::to_value(&platform).unwrap();
+
+            assert_eq!(value, json!({"tune": "skylake", "cpu_features": "AVX2"}));
+
+            let decoded: Platform = serde_json::from_value(value).unwrap();
+
+            assert_eq!(unsafe { *decoded.as_ptr() }, unsafe { *platform.as_ptr() });
+        }
+
+        #[test]
+        #[cfg_attr(miri, ignore)]
+        fn test_serde_host() {
+            let host = Platform::host().unwrap();
+            let decoded: Platform = serde_json::from_str(&serde_json::to_string(&host).unwrap()).unwrap();
+
+            assert_eq!(decoded.tune(), host.tune());
+            assert_eq!(decoded.cpu_features(), host.cpu_features());
+            assert_eq!(decoded, host);
+        }
+
+        #[test]
+        fn test_serde_platform_unknown_tune() {
+            let platform = Platform::from_info(ffi::hs_platform_info_t {
+                tune: 1234,
+                cpu_features: 0,
+                reserved1: 0,
+                reserved2: 0,
+            });
+            let value = serde_json::to_value(&platform).unwrap();
+
+            assert_eq!(value, json!({"tune": 1234, "cpu_features": ""}));
+
+            let decoded: Platform = serde_json::from_value(value).unwrap();
+
+            assert_eq!(unsafe { (*decoded.as_ptr()).tune }, 1234);
+        }
+
+        #[test]
+        fn test_serde_platform_missing_fields() {
+            let platform: Platform = serde_json::from_str(r#"{"tune": "haswell"}"#).unwrap();
+
+            assert_eq!(unsafe { (*platform.as_ptr()).tune }, Tune::Haswell as u32);
+            assert_eq!(platform.cpu_features(), CpuFeatures::empty());
+
+            let platform: Platform = serde_json::from_str(r#"{"cpu_features": "AVX2"}"#).unwrap();
+
+            assert_eq!(unsafe { (*platform.as_ptr()).tune }, Tune::Generic as u32);
+            assert_eq!(platform.cpu_features(), CpuFeatures::AVX2);
+
+            let err = serde_json::from_str::<Platform>(r#"{"tune": "skylake", "cpu_features": "MMX"}"#)
+                .err()
+                .unwrap();
+            assert!(err.to_string().contains("invalid CPU feature: MMX"), "{}", err);
         }
     }
 }
@@ -121,7 +948,307 @@ pub mod tests {
     use super::*;
 
     #[test]
+    #[cfg_attr(miri, ignore)]
     pub fn test_platform() {
         assert!(Platform::is_valid().is_ok())
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    pub fn test_check_platform() {
+        let report = check_platform().unwrap();
+
+        assert_eq!(report.version, crate::version_string());
+        assert_eq!(report.tune, Some(Tune::detect()));
+        assert!(CpuFeatures::detect().contains(report.cpu_features));
+        assert!(report.to_string().starts_with("hyperscan "));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    pub fn test_describe_arch_error() {
+        let desc = describe_arch_error();
+
+        assert!(!desc.is_empty());
+
+        #[cfg(target_arch = "x86_64")]
+        assert_eq!(
+            arch::missing_features().unwrap().contains(&"SSSE3"),
+            !is_x86_feature_detected!("ssse3")
+        );
+    }
+
+    #[test]
+    pub fn test_platform_report() {
+        let report = PlatformReport {
+            version: "5.4.0 2020-05-01",
+            tune: Some(Tune::Skylake),
+            cpu_features: CpuFeatures::AVX2,
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "hyperscan 5.4.0 2020-05-01, tune: skylake, CPU features: AVX2"
+        );
+
+        let report = PlatformReport {
+            tune: None,
+            cpu_features: CpuFeatures::empty(),
+            ..report
+        };
+
+        assert_eq!(
+            report.to_string(),
+            "hyperscan 5.4.0 2020-05-01, tune: unknown, CPU features: none"
+        );
+    }
+
+    #[test]
+    pub fn test_cpu_features() {
+        assert_eq!(CpuFeatures::empty().to_string(), "");
+        assert_eq!(CpuFeatures::AVX2.to_string(), "AVX2");
+        assert_eq!((CpuFeatures::AVX2 | CpuFeatures::AVX512).to_string(), "AVX2|AVX512");
+
+        assert_eq!("".parse::<CpuFeatures>().unwrap(), CpuFeatures::empty());
+        assert_eq!(
+            "avx2 | AVX512".parse::<CpuFeatures>().unwrap(),
+            CpuFeatures::AVX2 | CpuFeatures::AVX512
+        );
+        assert_eq!(
+            "AVX2|SSE9".parse::<CpuFeatures>().unwrap_err(),
+            Error::InvalidCpuFeature("SSE9".into())
+        );
+
+        for features in &[
+            CpuFeatures::empty(),
+            CpuFeatures::AVX2,
+            CpuFeatures::AVX2 | CpuFeatures::AVX512,
+            CpuFeatures::all(),
+        ] {
+            assert_eq!(features.to_string().parse::<CpuFeatures>().unwrap(), *features);
+        }
+    }
+
+    #[test]
+    pub fn test_tune() {
+        assert_eq!(Tune::Skylake.to_string(), "skylake");
+        assert_eq!(Tune::SkylakeServer.to_string(), "skylake_server");
+        assert_eq!("Haswell".parse::<Tune>().unwrap(), Tune::Haswell);
+        assert_eq!(
+            "pentium".parse::<Tune>().unwrap_err(),
+            Error::InvalidTune("pentium".into())
+        );
+
+        for &(tune, _) in Tune::NAMES {
+            assert_eq!(tune.to_string().parse::<Tune>().unwrap(), tune);
+        }
+    }
+
+    #[test]
+    pub fn test_platform_clone() {
+        let platform = Platform::new(Tune::Skylake, CpuFeatures::AVX2);
+        let cloned = platform.clone();
+
+        assert!(!std::ptr::eq(platform.as_ptr(), cloned.as_ptr()));
+        assert_eq!(unsafe { *platform.as_ptr() }, unsafe { *cloned.as_ptr() });
+
+        drop(platform);
+
+        assert_eq!(cloned.cpu_features(), CpuFeatures::AVX2);
+    }
+
+    #[test]
+    pub fn test_platform_eq() {
+        let platform = Platform::new(Tune::Skylake, CpuFeatures::AVX2);
+
+        assert_eq!(platform, platform.clone());
+        assert_eq!(platform, Platform::new(Tune::Skylake, CpuFeatures::AVX2));
+        assert_ne!(platform, Platform::new(Tune::Haswell, CpuFeatures::AVX2));
+        assert_ne!(platform, Platform::new(Tune::Skylake, CpuFeatures::empty()));
+        assert_eq!(Platform::generic(), Platform::new(Tune::Generic, CpuFeatures::empty()));
+        assert_eq!(
+            format!("{:?}", platform),
+            "Platform { tune: Skylake, cpu_features: AVX2 }"
+        );
+
+        let unknown = Platform::from_info(ffi::hs_platform_info_t {
+            tune: 1234,
+            cpu_features: 0,
+            reserved1: 0,
+            reserved2: 0,
+        });
+
+        assert_eq!(
+            format!("{:?}", unknown),
+            "Platform { tune: 1234, cpu_features: (empty) }"
+        );
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    pub fn test_host_clone() {
+        let host = Platform::host().unwrap();
+        let cloned = host.clone();
+
+        drop(host);
+
+        assert_eq!(cloned.cpu_features(), Platform::host().unwrap().cpu_features());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    pub fn test_detect() {
+        let host = Platform::host().unwrap();
+
+        // Hyperscan reports at least the features detected by the standard library
+        #[cfg(target_arch = "x86_64")]
+        assert!(host.cpu_features().contains(CpuFeatures::detect()));
+        assert_eq!(Tune::detect() as u32, unsafe { (*host.as_ptr()).tune });
+
+        let cached = Platform::host_cached().unwrap();
+
+        assert!(std::ptr::eq(cached, Platform::host_cached().unwrap()));
+        assert_eq!(cached.cpu_features(), host.cpu_features());
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    pub fn test_detect_features_of() {
+        use super::arch::features_of;
+
+        assert_eq!(features_of(false, false, false), CpuFeatures::empty());
+        assert_eq!(features_of(true, false, false), CpuFeatures::AVX2);
+        assert_eq!(features_of(false, true, false), CpuFeatures::AVX512);
+        assert_eq!(features_of(true, true, false), CpuFeatures::AVX2 | CpuFeatures::AVX512);
+        assert_eq!(features_of(true, false, true), CpuFeatures::AVX2);
+
+        #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+        assert_eq!(
+            features_of(true, true, true),
+            CpuFeatures::AVX2 | CpuFeatures::AVX512 | CpuFeatures::AVX512VBMI
+        );
+    }
+
+    #[test]
+    pub fn test_compatible() {
+        let subsets = [
+            CpuFeatures::empty(),
+            CpuFeatures::AVX2,
+            CpuFeatures::AVX512,
+            CpuFeatures::AVX2 | CpuFeatures::AVX512,
+            CpuFeatures::all(),
+        ];
+
+        for &required in &subsets {
+            let target = Platform::new(Tune::Skylake, required);
+
+            for &supported in &subsets {
+                let host = Platform::new(Tune::Goldmont, supported);
+
+                assert_eq!(target.missing_features(&host), required - supported);
+                assert_eq!(
+                    target.is_compatible_with(&host),
+                    supported.contains(required),
+                    "required {:?}, supported {:?}",
+                    required,
+                    supported
+                );
+            }
+
+            assert!(target.is_compatible_with(&Platform::new(Tune::Generic, CpuFeatures::all())));
+        }
+
+        let target = Platform::new(Tune::Haswell, CpuFeatures::AVX2 | CpuFeatures::AVX512);
+        let host = Platform::new(Tune::Haswell, CpuFeatures::AVX2);
+
+        assert_eq!(target.missing_features(&host), CpuFeatures::AVX512);
+    }
+
+    #[test]
+    pub fn test_presets() {
+        assert_eq!(Platform::generic().tune(), Some(Tune::Generic));
+        assert_eq!(Platform::generic().cpu_features(), CpuFeatures::empty());
+        assert_eq!(Platform::avx2_generic().tune(), Some(Tune::Generic));
+        assert_eq!(Platform::avx2_generic().cpu_features(), CpuFeatures::AVX2);
+    }
+
+    #[test]
+    pub fn test_min_common() {
+        let skylake = Platform::new(Tune::Skylake, CpuFeatures::AVX2 | CpuFeatures::AVX512);
+        let silvermont = Platform::new(Tune::Silvermont, CpuFeatures::empty());
+
+        let common = Platform::min_common(&[&skylake, &silvermont]).unwrap();
+
+        assert_eq!(common.tune(), Some(Tune::Generic));
+        assert_eq!(common.cpu_features(), CpuFeatures::empty());
+
+        let common = Platform::min_common(&[skylake.clone(), Platform::new(Tune::Skylake, CpuFeatures::AVX2)]).unwrap();
+
+        assert_eq!(common.tune(), Some(Tune::Skylake));
+        assert_eq!(common.cpu_features(), CpuFeatures::AVX2);
+
+        let common = Platform::min_common(&[&skylake]).unwrap();
+
+        assert_eq!(unsafe { *common.as_ptr() }, unsafe { *skylake.as_ptr() });
+
+        assert!(matches!(
+            Platform::min_common::<Platform>(&[]),
+            Err(Error::EmptyPlatforms)
+        ));
+    }
+
+    #[test]
+    pub fn test_best_variant() {
+        let variants = [
+            CpuFeatures::empty(),
+            CpuFeatures::AVX2 | CpuFeatures::AVX512,
+            CpuFeatures::AVX2,
+        ];
+
+        assert_eq!(best_variant_for(CpuFeatures::empty(), &variants), Some(0));
+        assert_eq!(best_variant_for(CpuFeatures::AVX2, &variants), Some(2));
+        assert_eq!(
+            best_variant_for(CpuFeatures::AVX2 | CpuFeatures::AVX512, &variants),
+            Some(1)
+        );
+        assert_eq!(best_variant_for(CpuFeatures::AVX2, &variants[1..2]), None);
+        assert_eq!(best_variant_for(CpuFeatures::AVX2, &[]), None);
+    }
+
+    #[cfg(all(feature = "v5_4", hs_version_gte_5_4))]
+    #[test]
+    pub fn test_platform_new_keeps_all_features() {
+        let features = CpuFeatures::AVX2 | CpuFeatures::AVX512 | CpuFeatures::AVX512VBMI;
+        let platform = Platform::new(Tune::IcelakeServer, features);
+
+        assert_eq!(platform.cpu_features(), features);
+        assert_eq!(unsafe { (*platform.as_ptr()).cpu_features }, features.bits());
+        assert_eq!(CpuFeatures::AVX512VBMI.to_string(), "AVX512VBMI");
+    }
+
+    #[cfg(all(feature = "v5_4", hs_version_gte_5_4, target_arch = "x86_64"))]
+    #[test]
+    pub fn test_host_avx512vbmi() {
+        let bits = (ffi::HS_CPU_FEATURES_AVX2 | ffi::HS_CPU_FEATURES_AVX512 | ffi::HS_CPU_FEATURES_AVX512VBMI) as u64;
+        let platform = Platform::from_info(ffi::hs_platform_info_t {
+            tune: Tune::IcelakeServer as u32,
+            cpu_features: bits,
+            reserved1: 0,
+            reserved2: 0,
+        });
+
+        assert!(platform.cpu_features().contains(CpuFeatures::AVX512VBMI));
+        assert_eq!(platform.cpu_features().bits(), bits);
+        assert_eq!(
+            Platform::new(Tune::IcelakeServer, platform.cpu_features()).cpu_features(),
+            platform.cpu_features()
+        );
+
+        // the library populates the bit on the hosts supporting the instructions
+        if is_x86_feature_detected!("avx512vbmi") && is_x86_feature_detected!("avx512bw") {
+            let host = Platform::host().unwrap();
+
+            assert!(host.cpu_features().contains(CpuFeatures::AVX512VBMI));
+        }
+    }
 }
@@ -0,0 +1,252 @@
+//! Scan a directory tree of files against a rule set.
+//!
+//! [`scan_dir`] walks the files under a directory, maps each file into memory, and scans the files in parallel
+//! with the rayon thread pool, checking out the scratch spaces from a pool.
+//!
+//! The length of a block mode scan is limited to 4 GiB, so a file larger than [`ScanOptions::stream_threshold`]
+//! is scanned in chunks with the streaming database given by [`ScanOptions::streaming`],
+//! or reported with `Error::FileTooLarge` without one.
+//!
+//! An error, for example a file which can't be read, is reported for the file without aborting the walk.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! # use hyperscan::{corpus::{scan_dir, ScanOptions}, prelude::*};
+//! let db: BlockDatabase = pattern! {"password"}.build().unwrap();
+//!
+//! for report in scan_dir("/etc", &db, ScanOptions::default().max_size(1 << 20)).unwrap() {
+//!     match report.result {
+//!         Ok(matches) if !matches.is_empty() => println!("{}: {} matches", report.path.display(), matches.len()),
+//!         Ok(_) => {}
+//!         Err(err) => eprintln!("{}: {}", report.path.display(), err),
+//!     }
+//! }
+//! ```
+use std::fmt;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use memmap2::Mmap;
+use rayon::prelude::*;
+use walkdir::WalkDir;
+
+use crate::{
+    common::{BlockDatabase, StreamingDatabase},
+    runtime::{Match, ScratchPool},
+    Error, Result,
+};
+
+/// The largest file which could be scanned in block mode.
+const MAX_BLOCK_SIZE: u64 = u32::MAX as u64;
+
+/// The options to walk and scan the files.
+pub struct ScanOptions {
+    min_size: u64,
+    max_size: u64,
+    follow_symlinks: bool,
+    stream_threshold: u64,
+    chunk_size: usize,
+    streaming: Option<StreamingDatabase>,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions {
+            min_size: 0,
+            max_size: u64::MAX,
+            follow_symlinks: false,
+            stream_threshold: MAX_BLOCK_SIZE,
+            chunk_size: 1 << 20,
+            streaming: None,
+        }
+    }
+}
+
+impl fmt::Debug for ScanOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScanOptions")
+            .field("min_size", &self.min_size)
+            .field("max_size", &self.max_size)
+            .field("follow_symlinks", &self.follow_symlinks)
+            .field("stream_threshold", &self.stream_threshold)
+            .field("chunk_size", &self.chunk_size)
+            .field("streaming", &self.streaming.is_some())
+            .finish()
+    }
+}
+
+impl ScanOptions {
+    /// Skip the files smaller than `size` bytes.
+    pub fn min_size(mut self, size: u64) -> Self {
+        self.min_size = size;
+        self
+    }
+
+    /// Skip the files larger than `size` bytes.
+    pub fn max_size(mut self, size: u64) -> Self {
+        self.max_size = size;
+        self
+    }
+
+    /// Follow the symbolic links, the files are reported with the path of the link.
+    pub fn follow_symlinks(mut self, yes: bool) -> Self {
+        self.follow_symlinks = yes;
+        self
+    }
+
+    /// Scan the files larger than `size` bytes in streaming mode, the threshold is capped at 4 GiB.
+    pub fn stream_threshold(mut self, size: u64) -> Self {
+        self.stream_threshold = size.min(MAX_BLOCK_SIZE);
+        self
+    }
+
+    /// The size of the chunks written to the stream.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = size.clamp(1, MAX_BLOCK_SIZE as usize);
+        self
+    }
+
+    /// The streaming database, compiled from the same patterns, to scan the large files.
+    pub fn streaming(mut self, db: StreamingDatabase) -> Self {
+        self.streaming = Some(db);
+        self
+    }
+}
+
+/// The result of scanning a file.
+#[derive(Debug)]
+pub struct FileReport {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The size of the file, or `0` if its metadata can't be read.
+    pub size: u64,
+    /// The matches ordered by their end offset, or the error to read or scan the file.
+    pub result: Result<Vec<Match>>,
+}
+
+/// A report is serialized as `{"path", "size", "matches"}`, or `{"path", "size", "error"}` with the error message.
+#[cfg(feature = "serde")]
+impl serde::Serialize for FileReport {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut report = serializer.serialize_struct("FileReport", 3)?;
+
+        report.serialize_field("path", &self.path.to_string_lossy())?;
+        report.serialize_field("size", &self.size)?;
+
+        match self.result {
+            Ok(ref matches) => report.serialize_field("matches", matches)?,
+            Err(ref err) => report.serialize_field("error", &err.to_string())?,
+        }
+
+        report.end()
+    }
+}
+
+/// Scan the files under the directory, and report the matches of each file in the walking order, sorted by the file names.
+///
+/// An error is only returned if a scratch space can't be allocated for the databases.
+pub fn scan_dir<P: AsRef<Path>>(
+    path: P,
+    db: &BlockDatabase,
+    options: ScanOptions,
+) -> Result<impl Iterator<Item = FileReport>> {
+    let scanner = Scanner {
+        block: ScratchPool::new(db)?,
+        db,
+        streaming: match options.streaming {
+            Some(ref db) => Some((db, ScratchPool::new(db)?)),
+            None => None,
+        },
+        options: &options,
+    };
+
+    let files = WalkDir::new(path)
+        .follow_links(options.follow_symlinks)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|entry| match entry {
+            Ok(entry) if entry.file_type().is_file() => match entry.metadata() {
+                Ok(metadata) => {
+                    let size = metadata.len();
+
+                    if (options.min_size..=options.max_size).contains(&size) {
+                        Some((entry.into_path(), Ok(size)))
+                    } else {
+                        None
+                    }
+                }
+                Err(err) => Some((entry.into_path(), Err(Error::Io(err.to_string())))),
+            },
+            Ok(_) => None,
+            Err(err) => Some((
+                err.path().map(Path::to_path_buf).unwrap_or_default(),
+                Err(Error::Io(err.to_string())),
+            )),
+        })
+        .collect::<Vec<_>>();
+
+    let reports = files
+        .into_par_iter()
+        .map(|(path, size)| match size {
+            Ok(size) => FileReport {
+                result: scanner.scan_file(&path, size),
+                path,
+                size,
+            },
+            Err(err) => FileReport {
+                path,
+                size: 0,
+                result: Err(err),
+            },
+        })
+        .collect::<Vec<_>>();
+
+    Ok(reports.into_iter())
+}
+
+struct Scanner<'a> {
+    db: &'a BlockDatabase,
+    block: ScratchPool,
+    streaming: Option<(&'a StreamingDatabase, ScratchPool)>,
+    options: &'a ScanOptions,
+}
+
+impl Scanner<'_> {
+    fn scan_file(&self, path: &Path, size: u64) -> Result<Vec<Match>> {
+        let mut matches = vec![];
+
+        // an empty file can't be mapped on some platforms
+        if size == 0 {
+            let mut scratch = self.block.get()?;
+
+            self.db.scan(b"", &mut scratch, Match::collect(&mut matches))?;
+
+            return Ok(matches);
+        }
+
+        let file = File::open(path)?;
+        let data = unsafe { Mmap::map(&file)? };
+
+        if data.len() as u64 <= self.options.stream_threshold {
+            let mut scratch = self.block.get()?;
+
+            self.db.scan(&data, &mut scratch, Match::collect(&mut matches))?;
+        } else if let Some((db, ref pool)) = self.streaming {
+            let mut scratch = pool.get()?;
+            let stream = db.open_stream()?;
+
+            for chunk in data.chunks(self.options.chunk_size) {
+                stream.scan(chunk, &mut scratch, Match::collect(&mut matches))?;
+            }
+
+            stream.close(&mut scratch, Match::collect(&mut matches))?;
+        } else {
+            return Err(Error::FileTooLarge(data.len() as u64));
+        }
+
+        Ok(matches)
+    }
+}
@@ -0,0 +1,285 @@
+//! Route the matches of each pattern to its own handler.
+//!
+//! An IDS-style application pairs each rule with an action. A [`Dispatcher`] compiles the patterns of the rules
+//! into a single block database, assigning the ID of each pattern to the index of its rule,
+//! so that a match is routed to its handler with a `Vec` lookup, regardless of the IDs in the patterns.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use hyperscan::{dispatch::Dispatcher, prelude::*};
+//! let dispatcher = Dispatcher::builder()
+//!     .rule(pattern! {"GET"}, |hits: &mut Vec<&str>, _| {
+//!         hits.push("get");
+//!         Matching::Continue
+//!     })
+//!     .rule_limited(pattern! {"\\d+"}, 1, |hits, _| {
+//!         hits.push("digits");
+//!         Matching::Continue
+//!     })
+//!     .build()
+//!     .unwrap();
+//!
+//! let mut s = dispatcher.alloc_scratch().unwrap();
+//! let mut hits = vec![];
+//!
+//! dispatcher.scan("GET /index/123", &mut s, &mut hits).unwrap();
+//!
+//! assert_eq!(hits, vec!["get", "digits"]);
+//! ```
+use std::fmt;
+
+use crate::{
+    common::BlockDatabase,
+    compile::{Builder, Pattern, Patterns},
+    runtime::{Match, Matching, Scratch, ScratchRef},
+    Result, ScanOutcome,
+};
+
+/// The action of a rule, called with the scan context and the match.
+///
+/// The ID of the match is the index of the rule in the dispatcher.
+pub type Handler<C> = Box<dyn Fn(&mut C, Match) -> Matching + Send + Sync>;
+
+struct Rule<C> {
+    handler: Handler<C>,
+    limit: Option<usize>,
+}
+
+/// A dispatcher routes the matches of each pattern to its handler.
+pub struct Dispatcher<C> {
+    db: BlockDatabase,
+    rules: Vec<Rule<C>>,
+    default: Option<Handler<C>>,
+}
+
+impl<C> fmt::Debug for Dispatcher<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dispatcher").field("rules", &self.rules.len()).finish()
+    }
+}
+
+impl<C> Dispatcher<C> {
+    /// Compile the patterns of the rules, and dispatch their matches to the paired handlers.
+    pub fn new<I>(rules: I) -> Result<Dispatcher<C>>
+    where
+        I: IntoIterator<Item = (Pattern, Handler<C>)>,
+    {
+        rules
+            .into_iter()
+            .fold(Dispatcher::builder(), |builder, (pattern, handler)| {
+                builder.push(pattern, handler, None)
+            })
+            .build()
+    }
+
+    /// Create a builder to add the rules one by one.
+    pub fn builder() -> DispatcherBuilder<C> {
+        DispatcherBuilder::default()
+    }
+
+    /// The number of rules.
+    pub fn len(&self) -> usize {
+        self.rules.len()
+    }
+
+    /// Returns `true` if there is no rule.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The compiled block database.
+    pub fn database(&self) -> &BlockDatabase {
+        &self.db
+    }
+
+    /// Allocate a scratch space for the database.
+    pub fn alloc_scratch(&self) -> Result<Scratch> {
+        self.db.alloc_scratch()
+    }
+
+    /// Scan the data, and call the handler of each match with the context.
+    ///
+    /// A rule with a limit is fired at most `limit` times per scan, the extra matches are dropped.
+    /// If a handler returns `Matching::Terminate`, the scan stops and returns `ScanOutcome::Terminated`.
+    pub fn scan<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef, ctx: &mut C) -> Result<ScanOutcome> {
+        let mut fired = vec![0; self.rules.len()];
+
+        self.db.scan(data, scratch, |id, from, to, _| {
+            let m = Match { id, from, to };
+
+            match self.rules.get(id as usize) {
+                Some(rule) => {
+                    let fired = &mut fired[id as usize];
+
+                    if rule.limit.is_some_and(|limit| *fired >= limit) {
+                        return Matching::Continue;
+                    }
+
+                    *fired += 1;
+
+                    (rule.handler)(ctx, m)
+                }
+                None => self
+                    .default
+                    .as_ref()
+                    .map_or(Matching::Continue, |handler| handler(ctx, m)),
+            }
+        })
+    }
+}
+
+/// A builder to add the rules of a [`Dispatcher`].
+pub struct DispatcherBuilder<C> {
+    patterns: Vec<Pattern>,
+    rules: Vec<Rule<C>>,
+    default: Option<Handler<C>>,
+}
+
+impl<C> Default for DispatcherBuilder<C> {
+    fn default() -> Self {
+        DispatcherBuilder {
+            patterns: vec![],
+            rules: vec![],
+            default: None,
+        }
+    }
+}
+
+impl<C> fmt::Debug for DispatcherBuilder<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DispatcherBuilder")
+            .field("patterns", &self.patterns)
+            .finish()
+    }
+}
+
+impl<C> DispatcherBuilder<C> {
+    /// Add a rule which fires the handler on each match of the pattern.
+    pub fn rule<F>(self, pattern: Pattern, handler: F) -> Self
+    where
+        F: Fn(&mut C, Match) -> Matching + Send + Sync + 'static,
+    {
+        self.push(pattern, Box::new(handler), None)
+    }
+
+    /// Add a rule which fires the handler at most `limit` times per scan.
+    pub fn rule_limited<F>(self, pattern: Pattern, limit: usize, handler: F) -> Self
+    where
+        F: Fn(&mut C, Match) -> Matching + Send + Sync + 'static,
+    {
+        self.push(pattern, Box::new(handler), Some(limit))
+    }
+
+    /// Set the handler for the matches which don't belong to any rule.
+    ///
+    /// Since the IDs are assigned by the dispatcher, it should never be called.
+    pub fn default_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&mut C, Match) -> Matching + Send + Sync + 'static,
+    {
+        self.default = Some(Box::new(handler));
+        self
+    }
+
+    /// Compile the patterns of the rules into a dispatcher.
+    pub fn build(self) -> Result<Dispatcher<C>> {
+        let db = Patterns(self.patterns).build()?;
+
+        Ok(Dispatcher {
+            db,
+            rules: self.rules,
+            default: self.default,
+        })
+    }
+
+    fn push(mut self, pattern: Pattern, handler: Handler<C>, limit: Option<usize>) -> Self {
+        self.patterns.push(Pattern {
+            id: Some(self.rules.len()),
+            ..pattern
+        });
+        self.rules.push(Rule { handler, limit });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    use super::*;
+
+    #[test]
+    fn test_dispatch_by_rule_index() {
+        let dispatcher = Dispatcher::new(vec![
+            (
+                pattern! {7 => "foo"; SOM_LEFTMOST},
+                Box::new(|ctx: &mut Vec<(&str, Match)>, m| {
+                    ctx.push(("foo", m));
+                    Matching::Continue
+                }) as Handler<_>,
+            ),
+            (
+                pattern! {7 => "bar"; SOM_LEFTMOST},
+                Box::new(|ctx: &mut Vec<(&str, Match)>, m| {
+                    ctx.push(("bar", m));
+                    Matching::Continue
+                }),
+            ),
+        ])
+        .unwrap();
+        let mut s = dispatcher.alloc_scratch().unwrap();
+        let mut hits = vec![];
+
+        assert_eq!(dispatcher.len(), 2);
+
+        dispatcher.scan("bar foo bar", &mut s, &mut hits).unwrap();
+
+        assert_eq!(
+            hits,
+            vec![
+                ("bar", Match { id: 1, from: 0, to: 3 }),
+                ("foo", Match { id: 0, from: 4, to: 7 }),
+                ("bar", Match { id: 1, from: 8, to: 11 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rate_limit_per_scan() {
+        let dispatcher = Dispatcher::builder()
+            .rule_limited(pattern! {"a"}, 2, |n: &mut usize, _| {
+                *n += 1;
+                Matching::Continue
+            })
+            .build()
+            .unwrap();
+        let mut s = dispatcher.alloc_scratch().unwrap();
+        let mut n = 0;
+
+        dispatcher.scan("aaaaa", &mut s, &mut n).unwrap();
+        assert_eq!(n, 2);
+
+        dispatcher.scan("aaaaa", &mut s, &mut n).unwrap();
+        assert_eq!(n, 4);
+    }
+
+    #[test]
+    fn test_terminate() {
+        let dispatcher = Dispatcher::builder()
+            .rule(pattern! {"a"}, |n: &mut usize, _| {
+                *n += 1;
+                Matching::Terminate
+            })
+            .build()
+            .unwrap();
+        let mut s = dispatcher.alloc_scratch().unwrap();
+        let mut n = 0;
+
+        // the stop requested by the handler isn't a failure
+        assert_eq!(dispatcher.scan("aaa", &mut s, &mut n).unwrap(), ScanOutcome::Terminated);
+        assert_eq!(n, 1);
+        assert_eq!(dispatcher.scan("bbb", &mut s, &mut n).unwrap(), ScanOutcome::Completed);
+        assert_eq!(n, 1);
+    }
+}
@@ -0,0 +1,164 @@
+use regex::bytes::{Regex, RegexSet};
+
+use crate::{
+    compile::{Flags, Pattern},
+    engine::{Capabilities, EngineStream, PatternEngine},
+    Error, Match, Patterns, Result,
+};
+
+/// The pure Rust pattern engine backed by `regex::bytes`.
+///
+/// Only the leftmost non-overlapping matches of each pattern are reported, with their start offset.
+/// The logical combinations and the extended parameters are not supported.
+pub struct RegexEngine {
+    set: RegexSet,
+    regexes: Vec<Rule>,
+}
+
+struct Rule {
+    id: u32,
+    flags: Flags,
+    regex: Regex,
+}
+
+impl Rule {
+    fn is_reported(&self, from: usize, to: usize) -> bool {
+        !self.flags.contains(Flags::QUIET) && (from < to || self.flags.contains(Flags::ALLOWEMPTY))
+    }
+}
+
+/// Translate the flags of the pattern to the inline flags of the expression.
+fn with_inline_flags(expression: &str, flags: Flags) -> String {
+    let mut inline = String::new();
+
+    if flags.contains(Flags::CASELESS) {
+        inline.push('i');
+    }
+    if flags.contains(Flags::DOTALL) {
+        inline.push('s');
+    }
+    if flags.contains(Flags::MULTILINE) {
+        inline.push('m');
+    }
+    if !flags.contains(Flags::UTF8) {
+        inline.push_str("-u");
+    }
+
+    if inline.is_empty() {
+        expression.to_owned()
+    } else {
+        format!("(?{}:{})", inline, expression)
+    }
+}
+
+fn fallback_error<E: ToString>(err: E) -> Error {
+    Error::Fallback(err.to_string())
+}
+
+impl PatternEngine for RegexEngine {
+    fn compile(patterns: &Patterns) -> Result<Self> {
+        let regexes = patterns
+            .iter()
+            .enumerate()
+            .map(
+                |(
+                    i,
+                    Pattern {
+                        expression,
+                        flags,
+                        id,
+                        ext,
+                        ..
+                    },
+                )| {
+                    if flags.contains(Flags::COMBINATION) {
+                        return Err(Error::Fallback(format!("logical combination `{}`", expression)));
+                    }
+                    if !ext.is_empty() {
+                        return Err(Error::Fallback(format!("extended parameters of `{}`", expression)));
+                    }
+
+                    Regex::new(&with_inline_flags(expression, *flags))
+                        .map(|regex| Rule {
+                            id: id.unwrap_or(i) as u32,
+                            flags: *flags,
+                            regex,
+                        })
+                        .map_err(fallback_error)
+                },
+            )
+            .collect::<Result<Vec<_>>>()?;
+
+        let set = RegexSet::new(regexes.iter().map(|rule| rule.regex.as_str())).map_err(fallback_error)?;
+
+        Ok(RegexEngine { set, regexes })
+    }
+
+    fn name(&self) -> &'static str {
+        "regex"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::START_OFFSET
+    }
+
+    fn is_match(&self, data: &[u8]) -> Result<bool> {
+        Ok(self.set.matches(data).into_iter().any(|i| {
+            let rule = &self.regexes[i];
+
+            rule.regex.find_iter(data).any(|m| rule.is_reported(m.start(), m.end()))
+        }))
+    }
+
+    fn scan_matches(&self, data: &[u8]) -> Result<Vec<Match>> {
+        let mut matches = vec![];
+
+        for i in self.set.matches(data).into_iter() {
+            let rule = &self.regexes[i];
+            let found = rule
+                .regex
+                .find_iter(data)
+                .filter(|m| rule.is_reported(m.start(), m.end()))
+                .map(|m| Match {
+                    id: rule.id,
+                    from: m.start() as u64,
+                    to: m.end() as u64,
+                });
+
+            if rule.flags.contains(Flags::SINGLEMATCH) {
+                matches.extend(found.take(1));
+            } else {
+                matches.extend(found);
+            }
+        }
+
+        matches.sort_by_key(|m| (m.to, m.id, m.from));
+
+        Ok(matches)
+    }
+
+    fn open_stream(&self) -> Result<Box<dyn EngineStream + '_>> {
+        Ok(Box::new(BufferedStream {
+            engine: self,
+            buf: vec![],
+        }))
+    }
+}
+
+/// The stream of the fallback engine, buffered until it is finished.
+struct BufferedStream<'a> {
+    engine: &'a RegexEngine,
+    buf: Vec<u8>,
+}
+
+impl EngineStream for BufferedStream<'_> {
+    fn feed(&mut self, data: &[u8]) -> Result<Vec<Match>> {
+        self.buf.extend_from_slice(data);
+
+        Ok(vec![])
+    }
+
+    fn finish(self: Box<Self>) -> Result<Vec<Match>> {
+        self.engine.scan_matches(&self.buf)
+    }
+}
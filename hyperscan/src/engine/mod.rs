@@ -0,0 +1,201 @@
+//! Pluggable pattern engines.
+//!
+//! A [`PatternEngine`] compiles the `Patterns`, and scans the data in block or streaming mode.
+//! It is implemented by [`HyperscanEngine`], and by [`RegexEngine`] with the `fallback` feature,
+//! a slower pure Rust engine backed by `regex::bytes`, for the hosts which Hyperscan doesn't support.
+//!
+//! The engines don't report the same matches, [`Capabilities`] describes what each engine provides.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use hyperscan::{engine::PatternEngine, prelude::*};
+//! let patterns: Patterns = "1:/foo/\n2:/bar/".parse().unwrap();
+//! let engine = <dyn PatternEngine>::best_available(&patterns).unwrap();
+//!
+//! assert!(engine.is_match(b"some bar").unwrap());
+//!
+//! let matches = engine.scan_matches(b"foo bar").unwrap();
+//!
+//! assert_eq!(matches.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1, 2]);
+//! ```
+use bitflags::bitflags;
+
+use crate::{compile::Platform, Match, Patterns, Result};
+
+#[cfg(feature = "fallback")]
+mod fallback;
+mod native;
+
+#[cfg(feature = "fallback")]
+pub use self::fallback::RegexEngine;
+pub use self::native::HyperscanEngine;
+
+bitflags! {
+    /// The capabilities of a pattern engine.
+    #[derive(Default)]
+    pub struct Capabilities: u32 {
+        /// The start offset of the matches is reported, otherwise `Match::from` is always `0`.
+        const START_OFFSET = 1;
+        /// Every match end is reported, otherwise only the leftmost non-overlapping matches are reported.
+        const ALL_MATCHES = 2;
+        /// The matches are reported as the stream is fed, otherwise the stream is buffered until it is finished.
+        const STREAMING = 4;
+    }
+}
+
+/// A pattern engine which compiles the patterns, and scans the data.
+pub trait PatternEngine: Send + Sync {
+    /// Compile the patterns.
+    fn compile(patterns: &Patterns) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// The name of the engine.
+    fn name(&self) -> &'static str;
+
+    /// The capabilities of the compiled engine.
+    fn capabilities(&self) -> Capabilities;
+
+    /// Returns `true` if any of the patterns matches the data.
+    fn is_match(&self, data: &[u8]) -> Result<bool>;
+
+    /// Scan the data, and collect the matches ordered by their end offset.
+    fn scan_matches(&self, data: &[u8]) -> Result<Vec<Match>>;
+
+    /// Open a stream to scan the data written in chunks.
+    fn open_stream(&self) -> Result<Box<dyn EngineStream + '_>>;
+}
+
+/// A stream opened by a pattern engine.
+pub trait EngineStream {
+    /// Write the chunk to the stream, and return the matches found so far.
+    fn feed(&mut self, data: &[u8]) -> Result<Vec<Match>>;
+
+    /// Close the stream, and return the remaining matches, for example the matches at the end of data.
+    fn finish(self: Box<Self>) -> Result<Vec<Match>>;
+}
+
+impl dyn PatternEngine {
+    /// Compile the patterns with Hyperscan if the host supports it, or the fallback engine otherwise.
+    ///
+    /// Without the `fallback` feature, `Error::ArchError` is returned for an unsupported host.
+    pub fn best_available(patterns: &Patterns) -> Result<Box<dyn PatternEngine>> {
+        match Platform::is_valid() {
+            Ok(_) => Ok(Box::new(HyperscanEngine::compile(patterns)?)),
+            #[cfg(feature = "fallback")]
+            Err(crate::Error::ArchError(_)) => Ok(Box::new(RegexEngine::compile(patterns)?)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "fallback"))]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    const RULES: &str = r"
+1:/foo/
+2:/ba[rz]/i
+3:/\d{3}-\d{4}/
+4:/^GET /
+5:/hello world$/
+6:/colou?r/s
+7:/x.y/
+";
+
+    const DOCS: &[&str] = &[
+        "",
+        "foo",
+        "BAR and baz",
+        "call 555-1234 now",
+        "GET /index.html",
+        "POST /GET ",
+        "say hello world",
+        "hello world, again",
+        "color or colour",
+        "x\ny xzy",
+        "nothing to see",
+    ];
+
+    fn ids(matches: &[Match]) -> BTreeSet<u32> {
+        matches.iter().map(|m| m.id).collect()
+    }
+
+    fn engines() -> (HyperscanEngine, RegexEngine) {
+        let patterns: Patterns = RULES.parse().unwrap();
+
+        (
+            HyperscanEngine::compile(&patterns).unwrap(),
+            RegexEngine::compile(&patterns).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_differential_block() {
+        let (hs, re) = engines();
+
+        for doc in DOCS {
+            let doc = doc.as_bytes();
+
+            assert_eq!(hs.is_match(doc).unwrap(), re.is_match(doc).unwrap(), "{:?}", doc);
+            assert_eq!(
+                ids(&hs.scan_matches(doc).unwrap()),
+                ids(&re.scan_matches(doc).unwrap()),
+                "{:?}",
+                doc
+            );
+        }
+    }
+
+    #[test]
+    fn test_differential_stream() {
+        let (hs, re) = engines();
+
+        for doc in DOCS {
+            let mut found = vec![];
+
+            for engine in [&hs as &dyn PatternEngine, &re].iter() {
+                let mut stream = engine.open_stream().unwrap();
+                let mut matches = vec![];
+
+                for chunk in doc.as_bytes().chunks(3) {
+                    matches.extend(stream.feed(chunk).unwrap());
+                }
+
+                matches.extend(stream.finish().unwrap());
+                found.push(ids(&matches));
+            }
+
+            assert_eq!(found[0], found[1], "{:?}", doc);
+        }
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let (hs, re) = engines();
+
+        assert!(hs
+            .capabilities()
+            .contains(Capabilities::ALL_MATCHES | Capabilities::STREAMING));
+        assert!(!hs.capabilities().contains(Capabilities::START_OFFSET));
+        assert_eq!(re.capabilities(), Capabilities::START_OFFSET);
+
+        let patterns: Patterns = "/a+/L".parse().unwrap();
+        let hs = HyperscanEngine::compile(&patterns).unwrap();
+        let re = RegexEngine::compile(&patterns).unwrap();
+
+        assert!(hs.capabilities().contains(Capabilities::START_OFFSET));
+        assert_eq!(
+            hs.scan_matches(b"baaa").unwrap(),
+            vec![
+                Match { id: 0, from: 1, to: 2 },
+                Match { id: 0, from: 1, to: 3 },
+                Match { id: 0, from: 1, to: 4 }
+            ]
+        );
+        assert_eq!(re.scan_matches(b"baaa").unwrap(), vec![Match { id: 0, from: 1, to: 4 }]);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    common::{BlockDatabase, StreamingDatabase},
+    compile::{Builder, Flags, Pattern},
+    engine::{Capabilities, EngineStream, PatternEngine},
+    runtime::{Match, Matching, PooledScratch, ScratchPool, Stream},
+    Patterns, Result, ScanOutcome,
+};
+
+/// The pattern engine backed by Hyperscan.
+///
+/// Both a block and a streaming database are compiled from the patterns.
+pub struct HyperscanEngine {
+    block: BlockDatabase,
+    streaming: StreamingDatabase,
+    block_pool: ScratchPool,
+    stream_pool: ScratchPool,
+    capabilities: Capabilities,
+}
+
+impl HyperscanEngine {
+    /// The compiled block database.
+    pub fn block_database(&self) -> &BlockDatabase {
+        &self.block
+    }
+
+    /// The compiled streaming database.
+    pub fn streaming_database(&self) -> &StreamingDatabase {
+        &self.streaming
+    }
+}
+
+impl PatternEngine for HyperscanEngine {
+    fn compile(patterns: &Patterns) -> Result<Self> {
+        let block: BlockDatabase = patterns.build()?;
+        let streaming: StreamingDatabase = patterns.build()?;
+        let mut capabilities = Capabilities::ALL_MATCHES | Capabilities::STREAMING;
+
+        if patterns
+            .iter()
+            .all(|Pattern { flags, .. }| flags.contains(Flags::SOM_LEFTMOST))
+        {
+            capabilities |= Capabilities::START_OFFSET;
+        }
+
+        Ok(HyperscanEngine {
+            block_pool: ScratchPool::new(&block)?,
+            stream_pool: ScratchPool::new(&streaming)?,
+            block,
+            streaming,
+            capabilities,
+        })
+    }
+
+    fn name(&self) -> &'static str {
+        "hyperscan"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.capabilities
+    }
+
+    fn is_match(&self, data: &[u8]) -> Result<bool> {
+        let mut scratch = self.block_pool.get()?;
+
+        self.block
+            .scan(data, &mut scratch, |_, _, _, _| Matching::Terminate)
+            .map(ScanOutcome::is_terminated)
+    }
+
+    fn scan_matches(&self, data: &[u8]) -> Result<Vec<Match>> {
+        let mut scratch = self.block_pool.get()?;
+        let mut matches = vec![];
+
+        self.block
+            .scan(data, &mut scratch, Match::collect(&mut matches))
+            .map(|_| matches)
+    }
+
+    fn open_stream(&self) -> Result<Box<dyn EngineStream + '_>> {
+        Ok(Box::new(HyperscanStream {
+            stream: self.streaming.open_stream()?,
+            scratch: self.stream_pool.get()?,
+        }))
+    }
+}
+
+struct HyperscanStream<'a> {
+    stream: Stream<'a>,
+    scratch: PooledScratch<'a>,
+}
+
+impl EngineStream for HyperscanStream<'_> {
+    fn feed(&mut self, data: &[u8]) -> Result<Vec<Match>> {
+        let mut matches = vec![];
+
+        self.stream
+            .scan(data, &mut self.scratch, Match::collect(&mut matches))
+            .map(|_| matches)
+    }
+
+    fn finish(self: Box<Self>) -> Result<Vec<Match>> {
+        let HyperscanStream { stream, mut scratch } = *self;
+        let mut matches = vec![];
+
+        stream
+            .close(&mut scratch, Match::collect(&mut matches))
+            .map(|_| matches)
+    }
+}
@@ -1,5 +1,6 @@
-use std::fmt;
-use std::result::Result as StdResult;
+use alloc::string::String;
+use core::fmt;
+use core::result::Result as StdResult;
 
 use thiserror::Error;
 
@@ -21,24 +22,247 @@ pub enum Error {
     Chimera(#[from] crate::chimera::Error),
 
     /// Expression error
+    #[cfg(feature = "compile")]
     #[error(transparent)]
     Expr(#[from] crate::compile::ExprError),
 
     /// Invalid UTF-8 string
     #[error(transparent)]
-    Utf8(#[from] std::str::Utf8Error),
+    Utf8(#[from] core::str::Utf8Error),
 
     /// Parse integer error
     #[error(transparent)]
-    ParseInt(#[from] std::num::ParseIntError),
+    ParseInt(#[from] core::num::ParseIntError),
 
     /// Parse C string error
     #[error(transparent)]
-    NulByte(#[from] std::ffi::NulError),
+    NulByte(#[from] alloc::ffi::NulError),
 
     /// Invalid flag
     #[error("invalid pattern flag: {0}")]
     InvalidFlag(char),
+
+    /// The flags are not supported by the literal compiler
+    #[cfg(all(feature = "literal", hs_version_gte_5_2))]
+    #[error("unsupported literal flags: {0}")]
+    UnsupportedLiteralFlags(String),
+
+    /// The pattern flags can't be used together
+    #[cfg(feature = "compile")]
+    #[error("pattern flags {0} and {1} can't be used together")]
+    ConflictingFlags(&'static str, &'static str),
+
+    /// Invalid CPU feature
+    #[error("invalid CPU feature: {0}")]
+    InvalidCpuFeature(String),
+
+    /// Invalid tuning family
+    #[error("invalid tune: {0}")]
+    InvalidTune(String),
+
+    /// Unsupported CPU architecture, with the description of the missing capability
+    #[error("unsupported CPU architecture, {0}")]
+    ArchError(String),
+
+    /// The major or minor version of the runtime library doesn't match the headers used at build time
+    #[error("built with Hyperscan {built}, but linked with {linked}")]
+    VersionMismatch {
+        /// The version of the headers used at build time.
+        built: String,
+        /// The version of the library linked at runtime.
+        linked: String,
+    },
+
+    /// No platform was given
+    #[error("no platform was given")]
+    EmptyPlatforms,
+
+    /// The allocator was set after Hyperscan allocated memory
+    #[error("the allocator must be set before any database is compiled or deserialized")]
+    AllocatorInUse,
+
+    /// The self test failed at a stage
+    #[cfg(all(feature = "compile", feature = "runtime"))]
+    #[error("self test failed at the {stage} stage, {reason}")]
+    SelfTest {
+        /// The stage which failed.
+        stage: crate::SelfTestStage,
+        /// The description of the failure.
+        reason: String,
+    },
+
+    /// Invalid database information string
+    #[error("invalid database information: {0}")]
+    InvalidDbInfo(String),
+
+    /// I/O error, with the description of the underlying error
+    #[error("I/O error, {0}")]
+    Io(String),
+
+    /// Invalid or corrupted bundle
+    #[cfg(feature = "compile")]
+    #[error("invalid bundle, {0}")]
+    InvalidBundle(String),
+
+    /// The pattern of a set failed to compile
+    #[cfg(feature = "compile")]
+    #[error("invalid pattern #{index} with id {id}, `{expression}`: {error}")]
+    InvalidPattern {
+        /// The index of the pattern in the set.
+        index: usize,
+        /// The ID of the pattern, or its index if it has no ID.
+        id: usize,
+        /// The expression of the pattern.
+        expression: String,
+        /// The error returned by the compiler.
+        error: crate::compile::Error,
+    },
+
+    /// The pattern of a Chimera set failed to compile
+    #[cfg(feature = "chimera")]
+    #[error("invalid pattern #{index} with id {id}, `{expression}`: {error}")]
+    InvalidChimeraPattern {
+        /// The index of the pattern in the set.
+        index: usize,
+        /// The ID of the pattern, or its index if it has no ID.
+        id: usize,
+        /// The expression of the pattern.
+        expression: String,
+        /// The error returned by the Chimera compiler.
+        error: crate::chimera::CompileError,
+    },
+
+    /// The extended parameters of a pattern are not supported by Chimera
+    #[cfg(all(feature = "chimera", feature = "compile"))]
+    #[error("unsupported Chimera pattern, {0}")]
+    UnsupportedChimeraPattern(String),
+
+    /// The flags are not supported by Chimera
+    #[cfg(all(feature = "chimera", feature = "compile"))]
+    #[error("unsupported Chimera flags: {0}")]
+    UnsupportedChimeraFlags(String),
+
+    /// Invalid line of a pattern file
+    #[cfg(feature = "compile")]
+    #[error("line {line}: {reason}, `{text}`")]
+    InvalidLine {
+        /// The line number, 1-based.
+        line: usize,
+        /// The text of the line.
+        text: String,
+        /// The description of the problem.
+        reason: String,
+    },
+
+    /// Invalid combination of the compile options
+    #[cfg(feature = "compile")]
+    #[error("invalid compile options, {0}")]
+    InvalidOptions(String),
+
+    /// Invalid logical combination of patterns
+    #[cfg(feature = "compile")]
+    #[error("invalid logical combination, {0}")]
+    InvalidCombination(String),
+
+    /// None of the database variants is compatible with the host
+    #[cfg(feature = "compile")]
+    #[error("no database variant is compatible with the host")]
+    NoCompatibleVariant,
+
+    /// The pattern is not supported by the fallback engine
+    #[cfg(feature = "fallback")]
+    #[error("fallback engine, {0}")]
+    Fallback(String),
+
+    /// The global metrics sink was already installed
+    #[cfg(feature = "runtime")]
+    #[error("global metrics sink already installed")]
+    SinkAlreadyInstalled,
+
+    /// The data is too large to scan in a call, with its size
+    ///
+    /// The larger data can be scanned in chunks, written to a stream or split by `ParScan::par_scan`.
+    #[cfg(feature = "runtime")]
+    #[error("data of {0} bytes is too large to scan, the limit is 4 GiB")]
+    DataTooLarge(u64),
+
+    /// The file is too large to scan in block mode, with its size
+    #[cfg(feature = "mmap")]
+    #[error("file of {0} bytes is too large to scan in block mode")]
+    FileTooLarge(u64),
+
+    /// The blocking scan task was cancelled by the runtime shutdown
+    #[cfg(feature = "tokio")]
+    #[error("blocking scan task was cancelled")]
+    Cancelled,
+}
+
+impl Error {
+    /// The scan was terminated by its match event handler, which isn't a failure.
+    pub fn is_scan_terminated(&self) -> bool {
+        match self {
+            Error::Hyperscan(HsError::ScanTerminated) => true,
+            #[cfg(feature = "chimera")]
+            Error::Chimera(crate::chimera::Error::ScanTerminated) => true,
+            _ => false,
+        }
+    }
+}
+
+/// The outcome of a scan which didn't fail.
+///
+/// The termination requested by the match event handler, with `Matching::Terminate`, isn't a failure,
+/// the scan functions return `Ok(ScanOutcome::Terminated)`, and keep the genuine failures,
+/// such as `HsError::Invalid` or `HsError::NoMem`, as errors.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, ScanOutcome};
+/// let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let mut matches = 0;
+///
+/// let outcome = db
+///     .scan("foo foo", &mut s, |_, _, _, _| {
+///         matches += 1;
+///         Matching::Terminate
+///     })
+///     .unwrap();
+///
+/// assert_eq!(outcome, ScanOutcome::Terminated);
+/// assert_eq!(matches, 1);
+/// assert_eq!(db.scan("bar", &mut s, ()).unwrap(), ScanOutcome::Completed);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScanOutcome {
+    /// The whole data was scanned.
+    Completed,
+    /// The scan was terminated by the match event handler.
+    Terminated,
+}
+
+impl ScanOutcome {
+    /// Returns `true` when the scan was terminated by the match event handler.
+    pub fn is_terminated(self) -> bool {
+        self == ScanOutcome::Terminated
+    }
+
+    /// Map the result of a scan, the termination requested by the match event handler isn't an error.
+    pub(crate) fn of(res: Result<()>) -> Result<ScanOutcome> {
+        match res {
+            Ok(()) => Ok(ScanOutcome::Completed),
+            Err(err) if err.is_scan_terminated() => Ok(ScanOutcome::Terminated),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err.to_string())
+    }
 }
 
 pub trait AsResult
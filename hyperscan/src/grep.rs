@@ -0,0 +1,229 @@
+//! Integrate Hyperscan with the [`grep-matcher`](https://docs.rs/grep-matcher) interface.
+//!
+//! The `RegexMatcher` could be used with `grep-searcher` or other ripgrep-style tooling.
+//!
+//! # Matching semantics
+//!
+//! Hyperscan reports **all** the matches of the patterns, ordered by their end offsets,
+//! while `grep_matcher::Matcher` expects the leftmost match. The patterns are compiled with
+//! `SOM_LEFTMOST` to track the start of match, and the reported matches are reduced as following:
+//!
+//! - `find_at` returns the match with the leftmost start at or after `at`, the longest one if there are many.
+//! - `find_iter` scans the haystack once, and yields the leftmost-longest non-overlapping matches.
+//! - `is_match_at` and `shortest_match_at` stop at the first reported match.
+//!
+//! The haystack is always scanned from the beginning, so that the assertions (e.g. `\b` or `^`) before `at`
+//! are respected, and the matches starting before `at` are skipped. Since Hyperscan only reports the leftmost
+//! start of each match, a match overlapping `at` is skipped instead of being truncated to start at `at`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use grep_matcher::Matcher;
+//! use hyperscan::grep::RegexMatcher;
+//!
+//! let matcher = RegexMatcher::new(r"\w+@\w+\.com").unwrap();
+//! let m = matcher.find(b"contact: foo@example.com").unwrap().unwrap();
+//!
+//! assert_eq!((m.start(), m.end()), (9, 24));
+//! ```
+use std::sync::{Arc, Mutex};
+
+use grep_matcher::{Match, Matcher, NoCaptures};
+
+use crate::{
+    common::BlockDatabase,
+    compile::{Builder, Flags, Pattern, Patterns},
+    regex::som,
+    runtime::{Scratch, ScratchRef},
+    Error, Result,
+};
+
+/// A matcher implements the `grep_matcher::Matcher` trait with a block mode database.
+///
+/// The scratch spaces are pooled, so the matcher could be shared between threads.
+pub struct RegexMatcher {
+    db: Arc<BlockDatabase>,
+    scratches: Mutex<Vec<Scratch>>,
+}
+
+impl RegexMatcher {
+    /// Compile the expression into a matcher.
+    pub fn new<S: Into<String>>(expr: S) -> Result<RegexMatcher> {
+        Pattern::with_flags(expr, Flags::SOM_LEFTMOST)?
+            .build()
+            .and_then(RegexMatcher::from_database)
+    }
+
+    /// Compile the patterns into a matcher, `SOM_LEFTMOST` is enabled for each pattern.
+    pub fn with_patterns(patterns: Patterns) -> Result<RegexMatcher> {
+        patterns
+            .into_iter()
+            .map(Pattern::left_most)
+            .collect::<Patterns>()
+            .build()
+            .and_then(RegexMatcher::from_database)
+    }
+
+    /// Construct a matcher with a compiled database.
+    ///
+    /// The patterns of database should be compiled with `SOM_LEFTMOST`, otherwise all the matches start at 0.
+    pub fn from_database<D: Into<Arc<BlockDatabase>>>(db: D) -> Result<RegexMatcher> {
+        let db = db.into();
+        let scratch = db.alloc_scratch()?;
+
+        Ok(RegexMatcher {
+            db,
+            scratches: Mutex::new(vec![scratch]),
+        })
+    }
+
+    /// Returns the database of the matcher.
+    pub fn database(&self) -> &BlockDatabase {
+        &self.db
+    }
+
+    /// Call the closure with a scratch space from the pool.
+    fn with_scratch<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&BlockDatabase, &mut ScratchRef) -> Result<R>,
+    {
+        let scratch = self.scratches.lock().unwrap().pop();
+        let mut scratch = match scratch {
+            Some(scratch) => scratch,
+            None => self.db.alloc_scratch()?,
+        };
+
+        let res = f(&self.db, &mut scratch);
+
+        self.scratches.lock().unwrap().push(scratch);
+
+        res
+    }
+}
+
+impl Matcher for RegexMatcher {
+    type Captures = NoCaptures;
+    type Error = Error;
+
+    fn find_at(&self, haystack: &[u8], at: usize) -> Result<Option<Match>> {
+        self.with_scratch(|db, s| som::leftmost(db, haystack, at, s))
+            .map(|m| m.map(|m| Match::new(m.start, m.end)))
+    }
+
+    fn new_captures(&self) -> Result<NoCaptures> {
+        Ok(NoCaptures::new())
+    }
+
+    fn try_find_iter_at<F, E>(&self, haystack: &[u8], at: usize, mut matched: F) -> Result<std::result::Result<(), E>>
+    where
+        F: FnMut(Match) -> std::result::Result<bool, E>,
+    {
+        let matches = self.with_scratch(|db, s| som::non_overlapping(db, haystack, at, s))?;
+
+        for m in matches {
+            match matched(Match::new(m.start, m.end)) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => return Ok(Err(err)),
+            }
+        }
+
+        Ok(Ok(()))
+    }
+
+    fn is_match_at(&self, haystack: &[u8], at: usize) -> Result<bool> {
+        Ok(self.shortest_match_at(haystack, at)?.is_some())
+    }
+
+    fn shortest_match_at(&self, haystack: &[u8], at: usize) -> Result<Option<usize>> {
+        self.with_scratch(|db, s| som::shortest(db, haystack, at, s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use grep_matcher::{Match, Matcher};
+    use grep_searcher::{sinks::UTF8, Searcher};
+
+    use super::*;
+
+    fn sherlock() -> &'static Path {
+        Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/sherlock.txt"))
+    }
+
+    #[test]
+    fn test_find_at() {
+        let matcher = RegexMatcher::new(r"\bwo\w+").unwrap();
+        let text = b"a world of work";
+
+        assert_eq!(matcher.find(text).unwrap(), Some(Match::new(2, 7)));
+        assert_eq!(matcher.find_at(text, 2).unwrap(), Some(Match::new(2, 7)));
+        assert_eq!(matcher.find_at(text, 3).unwrap(), Some(Match::new(11, 15)));
+        assert_eq!(matcher.find_at(text, 12).unwrap(), None);
+
+        assert!(matcher.is_match(text).unwrap());
+        assert!(!matcher.is_match_at(text, 12).unwrap());
+        assert_eq!(matcher.shortest_match(text).unwrap(), Some(5));
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let matcher = RegexMatcher::new(r"a+").unwrap();
+        let mut matches = vec![];
+
+        matcher
+            .find_iter(b"aaa baa a", |m| {
+                matches.push(m);
+                true
+            })
+            .unwrap();
+
+        assert_eq!(matches, vec![Match::new(0, 3), Match::new(5, 7), Match::new(8, 9)]);
+
+        let mut matches = vec![];
+
+        matcher
+            .find_iter_at(b"aaa baa a", 1, |m| {
+                matches.push(m);
+                false
+            })
+            .unwrap();
+
+        assert_eq!(matches, vec![Match::new(5, 7)]);
+    }
+
+    #[test]
+    fn test_with_patterns() {
+        let matcher = RegexMatcher::with_patterns(patterns!("foo", "bar")).unwrap();
+
+        assert_eq!(matcher.find(b"xbarfoo").unwrap(), Some(Match::new(1, 4)));
+    }
+
+    #[test]
+    fn test_searcher() {
+        let matcher = RegexMatcher::new(r"Doctor \w+").unwrap();
+        let mut lines = vec![];
+
+        Searcher::new()
+            .search_path(
+                &matcher,
+                sherlock(),
+                UTF8(|lnum, line| {
+                    let m = matcher.find(line.as_bytes()).unwrap().unwrap();
+
+                    lines.push((lnum, line[m].to_string()));
+
+                    Ok(true)
+                }),
+            )
+            .unwrap();
+
+        assert_eq!(
+            lines,
+            vec![(1, "Doctor Watsons".to_string()), (5, "Doctor Watson".to_string())]
+        );
+    }
+}
@@ -20,9 +20,9 @@
 //! fn main() {
 //!     let pattern = pattern! {"test"; CASELESS | SOM_LEFTMOST};
 //!     let db: BlockDatabase = pattern.build().unwrap();
-//!     let scratch = db.alloc_scratch().unwrap();
+//!     let mut scratch = db.alloc_scratch().unwrap();
 //!
-//!     db.scan("some test data", &scratch, |id, from, to, _flags| {
+//!     db.scan("some test data", &mut scratch, |id, from, to, _flags| {
 //!         assert_eq!(id, 0);
 //!         assert_eq!(from, 5);
 //!         assert_eq!(to, 9);
@@ -33,15 +33,59 @@
 //!     }).unwrap();
 //! }
 //! ```
+//!
+//! # Tracing
+//!
+//! With the `tracing` feature, the following spans and events are reported at debug level
+//! with the `hyperscan` target. The instrumentation is compiled out when the feature is off.
+//!
+//! | Name            | Kind  | Fields                                 |
+//! |-----------------|-------|----------------------------------------|
+//! | `compile`       | span  | `patterns`, `mode`                     |
+//! | `compiled`      | event | `size`                                 |
+//! | `compile failed`| event | `error`                                |
+//! | `scan`          | event | `mode`, `bytes`, `matches`, `outcome`  |
+//! | `stream opened` | event | `ok`                                   |
+//! | `stream closed` | event | `matches`, `outcome`                   |
+//!
+//! - `patterns`: the number of patterns compiled.
+//! - `mode`: the database mode, `Block`, `Streaming` or `Vectored` for `compile`,
+//!   and `block`, `vectored` or `stream` for `scan`.
+//! - `size`: the size of the compiled database in bytes.
+//! - `bytes`: the number of bytes scanned.
+//! - `matches`: the number of matches reported to the callback.
+//! - `outcome`: `ok`, `terminated` by the callback, or `error`.
+//!
+//! # Metrics
+//!
+//! The scan throughput and match rates can be counted with a sink installed globally
+//! or for a single database, see the [`metrics`] module.
+//!
+//! # `no_std`
+//!
+//! Without the default `std` feature, the runtime-only configuration (`runtime`) builds with `#![no_std]` and `alloc`.
+//! The databases can be deserialized and scanned in block, vectored or streaming mode, but the helpers which need
+//! the standard library are unavailable: scanning an `std::io::Read`, the thread local and pooled scratch spaces,
+//! and the global metrics sink. The errors implement `core::error::Error`.
+#![cfg_attr(not(feature = "std"), no_std)]
 #![deny(missing_docs, rust_2018_compatibility, rust_2018_idioms)]
 #![cfg_attr(test, deny(warnings))]
 #![cfg_attr(feature = "pattern", feature(pattern))]
 
+extern crate alloc;
+
 mod ffi {
     pub use hyperscan_sys::*;
 }
 
+pub mod allocator;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(all(feature = "tokio", feature = "runtime"))]
+pub mod blocking;
 mod common;
+#[cfg(all(feature = "mmap", feature = "rayon"))]
+pub mod corpus;
 mod error;
 #[cfg(feature = "compile")]
 #[macro_use]
@@ -49,9 +93,31 @@ mod compile;
 #[cfg(feature = "chimera")]
 pub mod chimera;
 #[cfg(all(feature = "compile", feature = "runtime"))]
+pub mod dispatch;
+#[cfg(all(feature = "compile", feature = "runtime"))]
+pub mod engine;
+#[cfg(feature = "grep")]
+pub mod grep;
+#[cfg(feature = "bstr")]
+pub mod lines;
+#[cfg(feature = "compile")]
+pub mod loader;
+#[cfg(feature = "runtime")]
+pub mod metrics;
+#[cfg(feature = "tower")]
+pub mod middleware;
+#[cfg(feature = "prefilter")]
+pub mod prefilter;
+#[cfg(all(feature = "compile", feature = "runtime"))]
 pub mod regex;
 #[cfg(feature = "runtime")]
 mod runtime;
+#[cfg(all(feature = "compile", feature = "runtime"))]
+mod self_test;
+#[cfg(all(feature = "compile", feature = "runtime"))]
+mod tagged;
+#[cfg(any(feature = "runtime", feature = "chimera"))]
+mod unwind;
 
 #[doc(hidden)]
 #[deprecated = "use `BlockMode` instead"]
@@ -66,11 +132,12 @@ pub use crate::common::Streaming;
 #[deprecated = "use `VectoredMode` instead"]
 pub use crate::common::Vectored;
 pub use crate::common::{
-    version, version_str, Block as BlockMode, BlockDatabase, Database, DatabaseRef, Error as HsError, Mode,
-    Serialized as SerializedDatabase, Streaming as StreamingMode, StreamingDatabase, Vectored as VectoredMode,
-    VectoredDatabase,
+    build_info, supports_combinations, supports_literal_api, version, version_parsed, version_str, version_string,
+    AnyDatabase, Block as BlockMode, BlockDatabase, BuildInfo, Database, DatabaseRef, DbInfo, DbMode, Error as HsError,
+    LinkKind, Mode, Serialized as SerializedDatabase, Streaming as StreamingMode, StreamingDatabase,
+    Vectored as VectoredMode, VectoredDatabase,
 };
-pub use crate::error::{Error, Result};
+pub use crate::error::{Error, Result, ScanOutcome};
 
 cfg_if::cfg_if! {
     if #[cfg(feature = "compile")] {
@@ -84,16 +151,34 @@ cfg_if::cfg_if! {
         #[deprecated = "use `PatternFlags` instead"]
         pub use crate::compile::Flags as CompileFlags;
         pub use crate::compile::{
-            compile, Builder as DatabaseBuilder, Builder, CpuFeatures, Error as CompileError, ExprExt, ExprInfo,
-            Flags as PatternFlags, Pattern, Patterns, Platform, PlatformRef, SomHorizon, Tune,
+            best_database_variant, check_platform, compile, Builder as DatabaseBuilder, Builder, Bundle, Compiler,
+            CpuFeatures,
+            Error as CompileError, ExprExt, ExprInfo, Flags as PatternFlags, Pattern, PatternFileOptions, Patterns, Platform,
+            PlatformRef,
+            PlatformReport, SomHorizon, Tune,
         };
-        #[cfg(feature = "literal")]
+        #[cfg(all(feature = "literal", hs_version_gte_5_2))]
         pub use crate::compile::{Literal, LiteralFlags, Literals};
     }
 }
 
+#[cfg(all(feature = "compile", feature = "runtime"))]
+pub use crate::regex::{Regex, RegexSet};
+#[cfg(all(feature = "rayon", feature = "runtime"))]
+pub use crate::runtime::ParScan;
 #[cfg(feature = "runtime")]
-pub use crate::runtime::{MatchEventHandler, Matching, Scratch, ScratchRef, Stream, StreamRef};
+pub use crate::runtime::{
+    Filtered, FilteredDatabase, IdFilter, Match, MatchEventHandler, MatchedIds, Matching, ScanIter, Scratch,
+    ScratchRef, ScratchTarget, Stream, StreamRef,
+};
+#[cfg(all(feature = "runtime", feature = "std"))]
+pub use crate::runtime::{PooledScratch, ScratchPool, StreamPool, StreamWriter};
+#[cfg(all(feature = "compile", feature = "runtime"))]
+pub use crate::runtime::{ResolvedMatch, SomMatch};
+#[cfg(all(feature = "compile", feature = "runtime"))]
+pub use crate::self_test::{self_test, SelfTestReport, SelfTestStage, SelfTestStageReport};
+#[cfg(all(feature = "compile", feature = "runtime"))]
+pub use crate::tagged::TaggedDatabase;
 
 /// The `hyperscan` Prelude
 pub mod prelude {
@@ -101,7 +186,13 @@ pub mod prelude {
     pub use crate::{compile, pattern, Builder, CompileFlags, Pattern, Patterns};
 
     #[cfg(feature = "runtime")]
-    pub use crate::{Matching, Scratch, Stream};
+    pub use crate::{Matching, ScanOutcome, Scratch, Stream};
+
+    #[cfg(all(feature = "runtime", feature = "std"))]
+    pub use crate::ScratchPool;
+
+    #[cfg(all(feature = "rayon", feature = "runtime"))]
+    pub use crate::ParScan;
 
     pub use crate::{BlockDatabase, Database, Mode, StreamingDatabase, VectoredDatabase};
 }
@@ -0,0 +1,259 @@
+//! Locate the matches in the lines of a byte haystack, for grep-like output.
+//!
+//! A [`LineIndex`] records the start of each line with `memchr`, so the line, the column and the line range
+//! of an offset are found with a binary search. The haystack doesn't have to be UTF-8,
+//! the lines are rendered lossily with `bstr`.
+//!
+//! A line ends with `\n`, the `\r` before it is not a part of the line, and the last line may have no newline.
+//! A match is located at its start, `Match::from`, so the patterns should be compiled with `SOM_LEFTMOST`,
+//! and a match spanning several lines is reported on the line where it starts.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use hyperscan::{lines::LineIndex, prelude::*, Match};
+//! let data = b"first line\r\nsecond line\nthird";
+//! let index = LineIndex::new(data);
+//!
+//! assert_eq!(index.locate(19), (2, 8, 12..23));
+//!
+//! let m = index.line_match(Match { id: 0, from: 19, to: 23 });
+//!
+//! assert_eq!(m.display("input.txt").plain().to_string(), "input.txt:2:8: second line");
+//! ```
+use std::fmt;
+use std::ops::Range;
+
+use bstr::ByteSlice;
+
+use crate::runtime::Match;
+
+/// The index of the lines in a haystack.
+#[derive(Clone, Debug)]
+pub struct LineIndex<'a> {
+    data: &'a [u8],
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    /// Index the lines of the haystack.
+    pub fn new(data: &'a [u8]) -> LineIndex<'a> {
+        let starts = Some(0)
+            .into_iter()
+            .chain(memchr::memchr_iter(b'\n', data).map(|pos| pos + 1))
+            .collect();
+
+        LineIndex { data, starts }
+    }
+
+    /// The number of lines, an empty final line after a trailing newline is counted.
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// Returns the line number and the column of the offset, both 1-based and the column counted in bytes,
+    /// and the range of the line without its terminator.
+    ///
+    /// An offset past the end of the haystack is located at the end of the last line.
+    pub fn locate(&self, offset: usize) -> (usize, usize, Range<usize>) {
+        let offset = offset.min(self.data.len());
+        let line = self.starts.partition_point(|&start| start <= offset) - 1;
+
+        (line + 1, offset - self.starts[line] + 1, self.line_range(line))
+    }
+
+    /// Enrich the match with the line where it starts.
+    pub fn line_match(&self, m: Match) -> LineMatch<'a> {
+        let (line_no, column, line_range) = self.locate(m.from as usize);
+        let start = (m.from as usize).max(line_range.start) - line_range.start;
+        let end = (m.to as usize).clamp(line_range.start, line_range.end) - line_range.start;
+
+        LineMatch {
+            m,
+            line_no,
+            column,
+            line: &self.data[line_range.clone()],
+            line_range,
+            span: start..end.max(start),
+        }
+    }
+
+    fn line_range(&self, line: usize) -> Range<usize> {
+        let start = self.starts[line];
+        let mut end = self.starts.get(line + 1).map_or(self.data.len(), |&next| next - 1);
+
+        if end > start && self.data[end - 1] == b'\r' && end < self.data.len() {
+            end -= 1;
+        }
+
+        start..end
+    }
+}
+
+/// A match with the line where it starts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LineMatch<'a> {
+    /// The match.
+    pub m: Match,
+    /// The line number, 1-based.
+    pub line_no: usize,
+    /// The column of the match start in bytes, 1-based.
+    pub column: usize,
+    /// The range of the line in the haystack, without its terminator.
+    pub line_range: Range<usize>,
+    /// The line without its terminator.
+    pub line: &'a [u8],
+    /// The range of the match in the line, truncated at the end of line.
+    pub span: Range<usize>,
+}
+
+impl LineMatch<'_> {
+    /// Copy the match with its line, which is converted to UTF-8 lossily.
+    pub fn to_owned(&self) -> OwnedLineMatch {
+        OwnedLineMatch {
+            id: self.m.id,
+            from: self.m.from,
+            to: self.m.to,
+            line_no: self.line_no,
+            column: self.column,
+            line: self.line.to_str_lossy().into_owned(),
+        }
+    }
+
+    /// Render the match as `path:line:col: <line>`, with the match highlighted by the ANSI escape codes.
+    pub fn display<P: fmt::Display>(&self, path: P) -> LineDisplay<'_, P> {
+        LineDisplay {
+            m: self,
+            path,
+            highlight: true,
+        }
+    }
+}
+
+/// An owned [`LineMatch`], for example to serialize it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OwnedLineMatch {
+    /// The ID number of the expression that matched.
+    pub id: u32,
+    /// The offset of the first byte that matches the expression.
+    pub from: u64,
+    /// The offset after the last byte that matches the expression.
+    pub to: u64,
+    /// The line number, 1-based.
+    pub line_no: usize,
+    /// The column of the match start in bytes, 1-based.
+    pub column: usize,
+    /// The line without its terminator.
+    pub line: String,
+}
+
+/// Render a [`LineMatch`] in the grep-like format.
+#[derive(Clone, Debug)]
+pub struct LineDisplay<'a, P> {
+    m: &'a LineMatch<'a>,
+    path: P,
+    highlight: bool,
+}
+
+impl<P> LineDisplay<'_, P> {
+    /// Render the line without highlighting the match.
+    pub fn plain(mut self) -> Self {
+        self.highlight = false;
+        self
+    }
+}
+
+impl<P: fmt::Display> fmt::Display for LineDisplay<'_, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let LineMatch {
+            line_no,
+            column,
+            line,
+            span,
+            ..
+        } = self.m;
+
+        write!(f, "{}:{}:{}: ", self.path, line_no, column)?;
+
+        if self.highlight {
+            write!(
+                f,
+                "{}\x1b[1;31m{}\x1b[0m{}",
+                line[..span.start].as_bstr(),
+                line[span.clone()].as_bstr(),
+                line[span.end..].as_bstr()
+            )
+        } else {
+            write!(f, "{}", line.as_bstr())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate() {
+        let index = LineIndex::new(b"foo\r\nbar\n\nbaz");
+
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.locate(0), (1, 1, 0..3));
+        assert_eq!(index.locate(3), (1, 4, 0..3));
+        assert_eq!(index.locate(4), (1, 5, 0..3));
+        assert_eq!(index.locate(5), (2, 1, 5..8));
+        assert_eq!(index.locate(9), (3, 1, 9..9));
+        assert_eq!(index.locate(10), (4, 1, 10..13));
+        assert_eq!(index.locate(13), (4, 4, 10..13));
+        assert_eq!(index.locate(100), (4, 4, 10..13));
+    }
+
+    #[test]
+    fn test_trailing_newline_and_empty() {
+        let index = LineIndex::new(b"foo\n");
+
+        assert_eq!(index.line_count(), 2);
+        assert_eq!(index.locate(4), (2, 1, 4..4));
+
+        let index = LineIndex::new(b"");
+
+        assert_eq!(index.line_count(), 1);
+        assert_eq!(index.locate(0), (1, 1, 0..0));
+
+        // a final `\r` without newline is a part of the line
+        let index = LineIndex::new(b"foo\r");
+
+        assert_eq!(index.locate(0), (1, 1, 0..4));
+    }
+
+    #[test]
+    fn test_line_match() {
+        let data = b"one\r\ntwo three\nfour\xff";
+        let index = LineIndex::new(data);
+
+        let m = index.line_match(Match { id: 1, from: 9, to: 14 });
+
+        assert_eq!(m.line_no, 2);
+        assert_eq!(m.column, 5);
+        assert_eq!(m.line, b"two three");
+        assert_eq!(m.span, 4..9);
+        assert_eq!(m.display("a.txt").plain().to_string(), "a.txt:2:5: two three");
+        assert_eq!(m.display("a.txt").to_string(), "a.txt:2:5: two \x1b[1;31mthree\x1b[0m");
+
+        // a match spanning the newlines is reported on its first line
+        let m = index.line_match(Match { id: 1, from: 1, to: 18 });
+
+        assert_eq!((m.line_no, m.column, m.span.clone()), (1, 2, 1..3));
+        assert_eq!(m.display("a.txt").to_string(), "a.txt:1:2: o\x1b[1;31mne\x1b[0m");
+
+        // the invalid UTF-8 is rendered lossily
+        let m = index.line_match(Match {
+            id: 1,
+            from: 15,
+            to: 19,
+        });
+
+        assert_eq!(m.display("a.txt").plain().to_string(), "a.txt:3:1: four\u{fffd}");
+    }
+}
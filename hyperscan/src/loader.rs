@@ -0,0 +1,300 @@
+//! Load a rule set from the pattern files, with a report of all the problems found.
+//!
+//! A [`RuleSetLoader`] parses every line of the files in the `id:/expression/flags` format of `Pattern`,
+//! the empty lines and the lines starting with `#` are skipped. The problems are collected in a [`LoadReport`]
+//! instead of stopping at the first one:
+//!
+//! - the lines which can't be parsed, and the duplicate pattern IDs are errors,
+//! - the expressions rejected by Hyperscan, found with the expression info, are errors,
+//! - the expressions outside of the [`WidthPolicy`] are warnings,
+//! - the compile error is reported at the pattern which caused it, if Hyperscan can determine it.
+//!
+//! The rule set is only compiled if no error was found, and the report is returned with the database,
+//! so the warnings and the compile time can still be displayed.
+//!
+//! A pattern without ID is given its index in the rule set, the same as `Patterns::build`.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! # use hyperscan::{loader::{RuleSetLoader, WidthPolicy}, Block, PatternFlags};
+//! let rules = RuleSetLoader::new()
+//!     .file("web.rules")
+//!     .file("mail.rules")
+//!     .default_flags(PatternFlags::SOM_LEFTMOST)
+//!     .validate(WidthPolicy {
+//!         min_width: 3,
+//!         max_width: Some(1024),
+//!     })
+//!     .build::<Block>();
+//!
+//! match rules {
+//!     Ok(rules) => {
+//!         for warning in &rules.report.warnings {
+//!             eprintln!("warning: {}", warning);
+//!         }
+//!     }
+//!     Err(report) => {
+//!         for error in &report.errors {
+//!             eprintln!("error: {}", error);
+//!         }
+//!     }
+//! }
+//! ```
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::result::Result as StdResult;
+use std::time::{Duration, Instant};
+
+use crate::{
+    common::{Database, Mode},
+    compile::{Builder, Flags, Pattern, Patterns},
+    Error,
+};
+
+/// The range of the match widths expected from a pattern.
+///
+/// The patterns matching shorter or longer than the range, or unbounded with a `max_width`, are warned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct WidthPolicy {
+    /// The minimum width of a match, the shorter matches are often too noisy.
+    pub min_width: usize,
+    /// The maximum width of a match.
+    pub max_width: Option<usize>,
+}
+
+/// A problem found in a rule set, at the line of a file if it is known.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Diagnostic {
+    /// The file of the problem.
+    pub path: Option<PathBuf>,
+    /// The line of the problem in the file, 1-based.
+    pub line: Option<usize>,
+    /// The description of the problem.
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.path, self.line) {
+            (Some(path), Some(line)) => write!(f, "{}:{}: {}", path.display(), line, self.message),
+            (Some(path), None) => write!(f, "{}: {}", path.display(), self.message),
+            _ => f.write_str(&self.message),
+        }
+    }
+}
+
+/// The number of patterns loaded from a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FileCount {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The number of patterns parsed from the file.
+    pub patterns: usize,
+}
+
+/// The report of loading a rule set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LoadReport {
+    /// The problems which prevent the rule set from being compiled.
+    pub errors: Vec<Diagnostic>,
+    /// The problems which don't prevent the rule set from being compiled.
+    pub warnings: Vec<Diagnostic>,
+    /// The number of patterns of each file, in the loading order.
+    pub files: Vec<FileCount>,
+    /// The time to compile the database, if it was compiled.
+    pub compile_time: Option<Duration>,
+}
+
+impl LoadReport {
+    /// Returns `true` if no error was found.
+    pub fn is_ok(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+/// A rule set compiled by a [`RuleSetLoader`].
+pub struct RuleSet<T> {
+    /// The patterns in the loading order.
+    pub patterns: Patterns,
+    /// The database compiled from the patterns.
+    pub database: Database<T>,
+    /// The report of loading the rule set, without errors.
+    pub report: LoadReport,
+}
+
+/// A builder which loads a rule set from the pattern files.
+#[derive(Clone, Debug, Default)]
+pub struct RuleSetLoader {
+    files: Vec<PathBuf>,
+    default_flags: Flags,
+    policy: Option<WidthPolicy>,
+}
+
+impl RuleSetLoader {
+    /// Create a loader without files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the patterns of a file.
+    pub fn file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.files.push(path.as_ref().to_owned());
+        self
+    }
+
+    /// The flags of the patterns which have none.
+    pub fn default_flags(mut self, flags: Flags) -> Self {
+        self.default_flags = flags;
+        self
+    }
+
+    /// Warn the patterns with the match widths outside of the policy.
+    pub fn validate(mut self, policy: WidthPolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// Parse, validate and compile the rule set, or return the report with all the errors found.
+    pub fn build<T: Mode>(&self) -> StdResult<RuleSet<T>, LoadReport> {
+        let mut report = LoadReport::default();
+        let mut patterns = vec![];
+        let mut locations = vec![];
+
+        for path in &self.files {
+            let at = |line, message: String| Diagnostic {
+                path: Some(path.clone()),
+                line,
+                message,
+            };
+            let text = match fs::read_to_string(path) {
+                Ok(text) => text,
+                Err(err) => {
+                    report.errors.push(at(None, err.to_string()));
+                    continue;
+                }
+            };
+            let loaded = patterns.len();
+
+            for (n, line) in text.lines().enumerate() {
+                let line = line.trim();
+
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+
+                match line.parse::<Pattern>() {
+                    Ok(mut pattern) => {
+                        if pattern.flags.is_empty() {
+                            pattern.flags = self.default_flags;
+                        }
+
+                        patterns.push(pattern);
+                        locations.push((path, n + 1));
+                    }
+                    Err(err) => report.errors.push(at(Some(n + 1), err.to_string())),
+                }
+            }
+
+            report.files.push(FileCount {
+                path: path.clone(),
+                patterns: patterns.len() - loaded,
+            });
+        }
+
+        let at = |i: usize, message: String| {
+            let (path, line) = locations[i];
+
+            Diagnostic {
+                path: Some(path.clone()),
+                line: Some(line),
+                message,
+            }
+        };
+        let mut ids = HashMap::<usize, usize>::new();
+
+        for (i, pattern) in patterns.iter().enumerate() {
+            let id = pattern.id.unwrap_or(i);
+
+            if let Some(&first) = ids.get(&id) {
+                let (path, line) = locations[first];
+
+                report.errors.push(at(
+                    i,
+                    format!(
+                        "duplicate pattern id {}, first defined at {}:{}",
+                        id,
+                        path.display(),
+                        line
+                    ),
+                ));
+            } else {
+                ids.insert(id, i);
+            }
+
+            match pattern.info() {
+                Ok(info) => {
+                    if let Some(policy) = self.policy {
+                        report
+                            .warnings
+                            .extend(policy.check(info.min_width(), info.max_width()).map(|w| at(i, w)));
+                    }
+                }
+                Err(err) => report.errors.push(at(i, err.to_string())),
+            }
+        }
+
+        if !report.is_ok() {
+            return Err(report);
+        }
+
+        let patterns = Patterns(patterns);
+        let started = Instant::now();
+
+        match patterns.build::<T>() {
+            Ok(database) => {
+                report.compile_time = Some(started.elapsed());
+
+                Ok(RuleSet {
+                    patterns,
+                    database,
+                    report,
+                })
+            }
+            Err(err) => {
+                report.errors.push(match err {
+                    Error::InvalidPattern { index, ref error, .. } => at(index, error.to_string()),
+                    _ => Diagnostic {
+                        path: None,
+                        line: None,
+                        message: err.to_string(),
+                    },
+                });
+
+                Err(report)
+            }
+        }
+    }
+}
+
+impl WidthPolicy {
+    fn check(&self, min_width: usize, max_width: Option<usize>) -> Option<String> {
+        if min_width < self.min_width {
+            Some(format!("match width {} is shorter than {}", min_width, self.min_width))
+        } else {
+            match (self.max_width, max_width) {
+                (Some(_), None) => Some("match width is unbounded".to_owned()),
+                (Some(max), Some(width)) if width > max => {
+                    Some(format!("match width {} is longer than {}", width, max))
+                }
+                _ => None,
+            }
+        }
+    }
+}
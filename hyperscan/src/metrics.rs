@@ -0,0 +1,415 @@
+//! Counters of the scan throughput and match rates.
+//!
+//! A [`MetricsSink`] is notified after each scan with the number of bytes scanned, the number of matches
+//! reported to the callback, the outcome and the duration of the scan. It can be installed globally with
+//! [`set_global_sink`], or for a single database or stream with [`Metered`], which takes precedence.
+//!
+//! Without the `std` feature, there is no global sink and the duration is always zero.
+//!
+//! The matches are only counted when a sink is installed, otherwise scanning pays nothing but a check
+//! of the global sink. Closing a stream is reported as a scan of zero bytes, since it may report matches.
+//!
+//! [`ScanCounters`] is a sink that keeps the totals in atomic counters.
+//! With the `metrics` feature, [`MetricsBridge`] forwards them to the `metrics` crate.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use hyperscan::{prelude::*, metrics::ScanCounters};
+//! let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+//! let mut s = db.alloc_scratch().unwrap();
+//! let counters = ScanCounters::default();
+//!
+//! db.metered(&counters).scan("test foo test", &mut s, ()).unwrap();
+//!
+//! let stats = counters.snapshot();
+//!
+//! assert_eq!(stats.scans, 1);
+//! assert_eq!(stats.bytes, 13);
+//! assert_eq!(stats.matches, 2);
+//! ```
+use core::ops::Sub;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "std")]
+use crate::Error;
+use crate::{
+    common::{Block, DatabaseRef, Vectored},
+    runtime::{MatchEventHandler, ScratchRef, StreamRef},
+    Result, ScanOutcome,
+};
+
+/// The status of a scan, reported to the metrics sink.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ScanStatus {
+    /// The scan completed.
+    Ok,
+    /// The scan was terminated by the callback.
+    Terminated,
+    /// The scan failed.
+    Error,
+}
+
+impl ScanStatus {
+    pub(crate) fn of(res: &Result<ScanOutcome>) -> ScanStatus {
+        match res {
+            Ok(ScanOutcome::Completed) => ScanStatus::Ok,
+            Ok(ScanOutcome::Terminated) => ScanStatus::Terminated,
+            Err(_) => ScanStatus::Error,
+        }
+    }
+
+    /// The name of the status, `ok`, `terminated` or `error`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanStatus::Ok => "ok",
+            ScanStatus::Terminated => "terminated",
+            ScanStatus::Error => "error",
+        }
+    }
+}
+
+/// Receive the statistics of each scan.
+pub trait MetricsSink: Send + Sync {
+    /// Called after a scan of `bytes` bytes, which reported `matches` matches to the callback.
+    fn on_scan(&self, bytes: usize, matches: usize, status: ScanStatus, duration: Duration);
+}
+
+impl<T: MetricsSink + ?Sized> MetricsSink for &T {
+    fn on_scan(&self, bytes: usize, matches: usize, status: ScanStatus, duration: Duration) {
+        (**self).on_scan(bytes, matches, status, duration)
+    }
+}
+
+#[cfg(feature = "std")]
+static GLOBAL_SINK: OnceLock<&'static dyn MetricsSink> = OnceLock::new();
+
+/// Install the global metrics sink, which is notified of every scan without a [`Metered`] sink.
+///
+/// The global sink can only be installed once, `Error::SinkAlreadyInstalled` is returned afterward.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::metrics::{set_global_sink, ScanCounters};
+/// static COUNTERS: ScanCounters = ScanCounters::new();
+///
+/// set_global_sink(&COUNTERS).unwrap();
+///
+/// assert!(set_global_sink(&COUNTERS).is_err());
+/// ```
+#[cfg(feature = "std")]
+pub fn set_global_sink(sink: &'static dyn MetricsSink) -> Result<()> {
+    GLOBAL_SINK.set(sink).map_err(|_| Error::SinkAlreadyInstalled)
+}
+
+/// The installed global metrics sink.
+#[cfg(feature = "std")]
+pub fn global_sink() -> Option<&'static dyn MetricsSink> {
+    GLOBAL_SINK.get().copied()
+}
+
+/// The installed global metrics sink.
+#[cfg(not(feature = "std"))]
+pub fn global_sink() -> Option<&'static dyn MetricsSink> {
+    None
+}
+
+/// A metrics sink which keeps the totals in atomic counters.
+#[derive(Debug, Default)]
+pub struct ScanCounters {
+    bytes: AtomicU64,
+    scans: AtomicU64,
+    matches: AtomicU64,
+    terminated: AtomicU64,
+}
+
+/// A snapshot of the `ScanCounters`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ScanStats {
+    /// Total bytes scanned.
+    pub bytes: u64,
+    /// Scans performed.
+    pub scans: u64,
+    /// Matches reported to the callback.
+    pub matches: u64,
+    /// Scans terminated by the callback.
+    pub terminated: u64,
+}
+
+impl Sub for ScanStats {
+    type Output = ScanStats;
+
+    fn sub(self, rhs: ScanStats) -> ScanStats {
+        ScanStats {
+            bytes: self.bytes - rhs.bytes,
+            scans: self.scans - rhs.scans,
+            matches: self.matches - rhs.matches,
+            terminated: self.terminated - rhs.terminated,
+        }
+    }
+}
+
+impl ScanCounters {
+    /// Create the counters, which can be used as a static global sink.
+    pub const fn new() -> ScanCounters {
+        ScanCounters {
+            bytes: AtomicU64::new(0),
+            scans: AtomicU64::new(0),
+            matches: AtomicU64::new(0),
+            terminated: AtomicU64::new(0),
+        }
+    }
+
+    /// Take a snapshot of the counters.
+    pub fn snapshot(&self) -> ScanStats {
+        ScanStats {
+            bytes: self.bytes.load(Ordering::Relaxed),
+            scans: self.scans.load(Ordering::Relaxed),
+            matches: self.matches.load(Ordering::Relaxed),
+            terminated: self.terminated.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MetricsSink for ScanCounters {
+    fn on_scan(&self, bytes: usize, matches: usize, status: ScanStatus, _duration: Duration) {
+        self.scans.fetch_add(1, Ordering::Relaxed);
+        self.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+
+        if matches > 0 {
+            self.matches.fetch_add(matches as u64, Ordering::Relaxed);
+        }
+        if status == ScanStatus::Terminated {
+            self.terminated.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// A database or stream which reports its scans to the given metrics sink instead of the global one.
+pub struct Metered<'a, T: ?Sized> {
+    inner: &'a T,
+    sink: &'a dyn MetricsSink,
+}
+
+impl<T> DatabaseRef<T> {
+    /// Report the scans of the database to the metrics sink instead of the global one.
+    pub fn metered<'a>(&'a self, sink: &'a dyn MetricsSink) -> Metered<'a, Self> {
+        Metered { inner: self, sink }
+    }
+}
+
+impl StreamRef {
+    /// Report the scans of the stream to the metrics sink instead of the global one.
+    ///
+    /// Closing the stream is still reported to the global sink.
+    pub fn metered<'a>(&'a self, sink: &'a dyn MetricsSink) -> Metered<'a, Self> {
+        Metered { inner: self, sink }
+    }
+}
+
+impl Metered<'_, DatabaseRef<Block>> {
+    /// The block (non-streaming) regular expression scanner, see `DatabaseRef::<Block>::scan`.
+    pub fn scan<T, F>(&self, data: T, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
+    where
+        T: AsRef<[u8]>,
+        F: MatchEventHandler,
+    {
+        self.inner
+            .scan_metered(data.as_ref(), scratch, on_match_event, Some(self.sink))
+    }
+}
+
+impl Metered<'_, DatabaseRef<Vectored>> {
+    /// The vectored regular expression scanner, see `DatabaseRef::<Vectored>::scan`.
+    pub fn scan<I, T, F>(&self, data: I, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+        F: MatchEventHandler,
+    {
+        self.inner.scan_metered(data, scratch, on_match_event, Some(self.sink))
+    }
+}
+
+impl Metered<'_, StreamRef> {
+    /// Write data to be scanned to the opened stream, see `StreamRef::scan`.
+    pub fn scan<T, F>(&self, data: T, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
+    where
+        T: AsRef<[u8]>,
+        F: MatchEventHandler,
+    {
+        self.inner
+            .scan_metered(data.as_ref(), scratch, on_match_event, Some(self.sink))
+    }
+}
+
+/// A metrics sink which forwards the statistics to the `metrics` crate.
+///
+/// | Name                           | Kind      |
+/// |--------------------------------|-----------|
+/// | `hyperscan_bytes_scanned`      | counter   |
+/// | `hyperscan_scans`              | counter   |
+/// | `hyperscan_matches`            | counter   |
+/// | `hyperscan_scans_terminated`   | counter   |
+/// | `hyperscan_scan_duration_seconds` | histogram |
+#[cfg(feature = "metrics")]
+pub struct MetricsBridge {
+    bytes: ::metrics::Counter,
+    scans: ::metrics::Counter,
+    matches: ::metrics::Counter,
+    terminated: ::metrics::Counter,
+    duration: ::metrics::Histogram,
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsBridge {
+    /// Register the metrics with the installed `metrics` recorder.
+    pub fn new() -> MetricsBridge {
+        MetricsBridge::with_labels(Vec::new())
+    }
+
+    /// Register the metrics labeled with the database name, to report the databases separately.
+    pub fn with_database<S: Into<::metrics::SharedString>>(name: S) -> MetricsBridge {
+        MetricsBridge::with_labels(vec![::metrics::Label::new("database", name)])
+    }
+
+    fn with_labels(labels: Vec<::metrics::Label>) -> MetricsBridge {
+        MetricsBridge {
+            bytes: ::metrics::counter!("hyperscan_bytes_scanned", labels.iter()),
+            scans: ::metrics::counter!("hyperscan_scans", labels.iter()),
+            matches: ::metrics::counter!("hyperscan_matches", labels.iter()),
+            terminated: ::metrics::counter!("hyperscan_scans_terminated", labels.iter()),
+            duration: ::metrics::histogram!("hyperscan_scan_duration_seconds", labels.iter()),
+        }
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Default for MetricsBridge {
+    fn default() -> Self {
+        MetricsBridge::new()
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl MetricsSink for MetricsBridge {
+    fn on_scan(&self, bytes: usize, matches: usize, status: ScanStatus, duration: Duration) {
+        self.scans.increment(1);
+        self.bytes.increment(bytes as u64);
+        self.matches.increment(matches as u64);
+        if status == ScanStatus::Terminated {
+            self.terminated.increment(1);
+        }
+        self.duration.record(duration);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    static GLOBAL: ScanCounters = ScanCounters::new();
+
+    #[test]
+    fn test_block_counters() {
+        let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let counters = ScanCounters::default();
+        let metered = db.metered(&counters);
+
+        for _ in 0..10 {
+            metered.scan("test foo test bar", &mut s, ()).unwrap();
+        }
+
+        let before = counters.snapshot();
+
+        assert_eq!(
+            before,
+            ScanStats {
+                bytes: 170,
+                scans: 10,
+                matches: 20,
+                terminated: 0
+            }
+        );
+
+        assert!(metered
+            .scan("test test", &mut s, |_, _, _, _| Matching::Terminate)
+            .is_err());
+
+        assert_eq!(
+            counters.snapshot() - before,
+            ScanStats {
+                bytes: 9,
+                scans: 1,
+                matches: 1,
+                terminated: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_vectored_and_stream_counters() {
+        let counters = ScanCounters::default();
+
+        let db: VectoredDatabase = pattern! {"test"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        db.metered(&counters).scan(vec!["foo t", "est"], &mut s, ()).unwrap();
+
+        assert_eq!(
+            counters.snapshot(),
+            ScanStats {
+                bytes: 8,
+                scans: 1,
+                matches: 1,
+                terminated: 0
+            }
+        );
+
+        let db: StreamingDatabase = pattern! {"test"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        for data in &["te", "st", " test"] {
+            st.metered(&counters).scan(data, &mut s, ()).unwrap();
+        }
+
+        st.close(&mut s, ()).unwrap();
+
+        assert_eq!(
+            counters.snapshot(),
+            ScanStats {
+                bytes: 17,
+                scans: 4,
+                matches: 3,
+                terminated: 0
+            }
+        );
+    }
+
+    #[test]
+    fn test_global_sink() {
+        set_global_sink(&GLOBAL).unwrap();
+
+        let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let before = GLOBAL.snapshot();
+
+        db.scan("test test", &mut s, ()).unwrap();
+
+        let delta = GLOBAL.snapshot() - before;
+
+        // the other tests may scan concurrently
+        assert!(delta.scans >= 1);
+        assert!(delta.bytes >= 9);
+        assert!(delta.matches >= 2);
+
+        assert_eq!(set_global_sink(&GLOBAL), Err(Error::SinkAlreadyInstalled));
+    }
+}
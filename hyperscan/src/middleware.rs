@@ -0,0 +1,375 @@
+//! Scan the HTTP bodies flowing through a `tower` service.
+//!
+//! A [`ScanBody`] wraps an `http_body::Body`, and writes each data frame to a Hyperscan stream
+//! opened against a shared streaming database, so a match spanning several frames is found.
+//! A data frame is scanned before it is yielded, and the [`Policy`] decides on each match to:
+//!
+//! - [`Verdict::Continue`] forward the body,
+//! - [`Verdict::Strip`] end the body before the frame with the match, and discard the rest of it,
+//! - [`Verdict::Abort`] fail the body with [`ScanBodyError::Aborted`] and the status chosen by the policy,
+//!   for example the proxy could respond with this status if the response hasn't started.
+//!
+//! The stream is closed at the end of the body or before the trailers, where the matches at the end of data
+//! are reported, and a body dropped early also closes its stream.
+//!
+//! [`ScanLayer`] applies [`ScanBody`] to the requests and/or responses of a service.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use hyperscan::{middleware::{ScanLayer, Verdict}, prelude::*, Match};
+//! # use http::StatusCode;
+//! let db: StreamingDatabase = pattern! {"secret"}.build().unwrap();
+//! let layer = ScanLayer::new(db, |_: Match| Verdict::Abort(StatusCode::FORBIDDEN))
+//!     .unwrap()
+//!     .responses(false);
+//! ```
+use std::future::Future;
+use std::pin::Pin;
+use std::result::Result as StdResult;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+
+use bytes::{Buf, Bytes};
+use http::{Request, Response, StatusCode};
+use http_body::{Body, Frame, SizeHint};
+use pin_project_lite::pin_project;
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{
+    common::StreamingDatabase,
+    runtime::{Match, Matching, ScratchPool, Stream},
+    Error, Result,
+};
+
+/// The decision of the policy on a match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Forward the body.
+    Continue,
+    /// End the body before the frame with the match.
+    Strip,
+    /// Fail the body with the status.
+    Abort(StatusCode),
+}
+
+/// The policy called on each match in a body.
+pub trait Policy: Clone {
+    /// Decide what to do with the body on the match.
+    fn on_match(&self, m: Match) -> Verdict;
+}
+
+impl<F> Policy for F
+where
+    F: Fn(Match) -> Verdict + Clone,
+{
+    fn on_match(&self, m: Match) -> Verdict {
+        self(m)
+    }
+}
+
+/// The error of a scanned body.
+#[derive(Debug, thiserror::Error)]
+pub enum ScanBodyError<E> {
+    /// The error of the inner body.
+    #[error(transparent)]
+    Body(E),
+
+    /// The error of Hyperscan.
+    #[error(transparent)]
+    Hyperscan(#[from] Error),
+
+    /// The body was aborted by the policy, with the status to respond.
+    #[error("body aborted with status {0}")]
+    Aborted(StatusCode),
+}
+
+impl<E> ScanBodyError<E> {
+    /// The status to respond, if the body was aborted by the policy.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ScanBodyError::Aborted(status) => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Shared<P> {
+    db: Arc<StreamingDatabase>,
+    pool: Arc<ScratchPool>,
+    policy: P,
+}
+
+struct Scanner<P> {
+    stream: Option<Stream<'static>>,
+    shared: Shared<P>,
+}
+
+impl<P: Policy> Scanner<P> {
+    fn scan(&mut self, data: &[u8]) -> Result<Verdict> {
+        let Shared { db, pool, policy } = &self.shared;
+        let stream = match self.stream {
+            Some(ref stream) => stream,
+            None => self.stream.insert(db.open_shared_stream()?),
+        };
+        let mut scratch = pool.get()?;
+        let mut verdict = Verdict::Continue;
+
+        let res = stream.scan(data, &mut scratch, |id, from, to, _| {
+            verdict = policy.on_match(Match { id, from, to });
+
+            if verdict == Verdict::Continue {
+                Matching::Continue
+            } else {
+                Matching::Terminate
+            }
+        });
+
+        match verdict {
+            Verdict::Continue => res.map(|_| verdict),
+            _ => Ok(verdict),
+        }
+    }
+
+    fn close(&mut self) -> Result<Verdict> {
+        let Shared { pool, policy, .. } = &self.shared;
+        let mut verdict = Verdict::Continue;
+
+        if let Some(stream) = self.stream.take() {
+            let mut scratch = pool.get()?;
+            let res = stream.close(&mut scratch, |id, from, to, _| {
+                if verdict == Verdict::Continue {
+                    verdict = policy.on_match(Match { id, from, to });
+                }
+
+                Matching::Continue
+            });
+
+            res?;
+        }
+
+        Ok(verdict)
+    }
+}
+
+impl<P> Drop for Scanner<P> {
+    fn drop(&mut self) {
+        if let (Some(stream), Ok(mut scratch)) = (self.stream.take(), self.shared.pool.get()) {
+            let _ = stream.close(&mut scratch, ());
+        }
+    }
+}
+
+pin_project! {
+    /// A body which scans its data frames with a Hyperscan stream.
+    pub struct ScanBody<B, P> {
+        #[pin]
+        inner: B,
+        scanner: Option<Scanner<P>>,
+        done: bool,
+    }
+}
+
+impl<B, P> ScanBody<B, P> {
+    /// Scan the body with a stream opened against the database, and the scratch spaces from the pool.
+    pub fn new(inner: B, db: Arc<StreamingDatabase>, pool: Arc<ScratchPool>, policy: P) -> Self {
+        Self::with_shared(inner, Some(Shared { db, pool, policy }))
+    }
+
+    fn with_shared(inner: B, shared: Option<Shared<P>>) -> Self {
+        ScanBody {
+            inner,
+            scanner: shared.map(|shared| Scanner { stream: None, shared }),
+            done: false,
+        }
+    }
+}
+
+impl<B, P> Body for ScanBody<B, P>
+where
+    B: Body,
+    P: Policy,
+{
+    type Data = Bytes;
+    type Error = ScanBodyError<B::Error>;
+
+    fn poll_frame(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<StdResult<Frame<Bytes>, Self::Error>>> {
+        let this = self.project();
+
+        if *this.done {
+            return Poll::Ready(None);
+        }
+
+        let frame = ready!(this.inner.poll_frame(cx))
+            .map(|res| res.map(|frame| frame.map_data(|mut data| data.copy_to_bytes(data.remaining()))));
+
+        let scanner = match this.scanner {
+            Some(scanner) => scanner,
+            None => return Poll::Ready(frame.map(|res| res.map_err(ScanBodyError::Body))),
+        };
+
+        let (verdict, frame) = match frame {
+            Some(Ok(frame)) => match frame.data_ref() {
+                Some(data) => (scanner.scan(data), Some(frame)),
+                None => (scanner.close(), Some(frame)),
+            },
+            Some(Err(err)) => {
+                *this.done = true;
+
+                return Poll::Ready(Some(Err(ScanBodyError::Body(err))));
+            }
+            None => (scanner.close(), None),
+        };
+
+        match verdict {
+            Ok(Verdict::Continue) => {
+                *this.done = frame.is_none();
+
+                Poll::Ready(frame.map(Ok))
+            }
+            Ok(Verdict::Strip) => {
+                *this.done = true;
+
+                Poll::Ready(None)
+            }
+            Ok(Verdict::Abort(status)) => {
+                *this.done = true;
+
+                Poll::Ready(Some(Err(ScanBodyError::Aborted(status))))
+            }
+            Err(err) => {
+                *this.done = true;
+
+                Poll::Ready(Some(Err(err.into())))
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.done
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        if self.scanner.is_some() {
+            // the body may be stripped
+            let mut hint = SizeHint::new();
+
+            if let Some(upper) = self.inner.size_hint().upper() {
+                hint.set_upper(upper);
+            }
+
+            hint
+        } else {
+            self.inner.size_hint()
+        }
+    }
+}
+
+/// A layer which scans the bodies of the requests and/or responses, both by default.
+#[derive(Clone)]
+pub struct ScanLayer<P> {
+    shared: Shared<P>,
+    requests: bool,
+    responses: bool,
+}
+
+impl<P> ScanLayer<P> {
+    /// Scan the bodies with the database, a scratch pool is allocated for it.
+    pub fn new(db: StreamingDatabase, policy: P) -> Result<Self> {
+        let pool = ScratchPool::new(&db)?;
+
+        Ok(ScanLayer {
+            shared: Shared {
+                db: Arc::new(db),
+                pool: Arc::new(pool),
+                policy,
+            },
+            requests: true,
+            responses: true,
+        })
+    }
+
+    /// Scan the bodies of the requests.
+    pub fn requests(mut self, yes: bool) -> Self {
+        self.requests = yes;
+        self
+    }
+
+    /// Scan the bodies of the responses.
+    pub fn responses(mut self, yes: bool) -> Self {
+        self.responses = yes;
+        self
+    }
+}
+
+impl<S, P: Clone> Layer<S> for ScanLayer<P> {
+    type Service = ScanService<S, P>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ScanService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// A service which scans the bodies of the requests and/or responses.
+#[derive(Clone)]
+pub struct ScanService<S, P> {
+    inner: S,
+    layer: ScanLayer<P>,
+}
+
+impl<S, P, ReqBody, ResBody> Service<Request<ReqBody>> for ScanService<S, P>
+where
+    S: Service<Request<ScanBody<ReqBody, P>>, Response = Response<ResBody>>,
+    P: Policy,
+{
+    type Response = Response<ScanBody<ResBody, P>>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future, P>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<StdResult<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let ScanLayer {
+            shared,
+            requests,
+            responses,
+        } = &self.layer;
+        let req = req.map(|body| ScanBody::with_shared(body, requests.then(|| shared.clone())));
+
+        ResponseFuture {
+            inner: self.inner.call(req),
+            shared: responses.then(|| shared.clone()),
+        }
+    }
+}
+
+pin_project! {
+    /// The response future of [`ScanService`].
+    pub struct ResponseFuture<F, P> {
+        #[pin]
+        inner: F,
+        shared: Option<Shared<P>>,
+    }
+}
+
+impl<F, P, B, E> Future for ResponseFuture<F, P>
+where
+    F: Future<Output = StdResult<Response<B>, E>>,
+{
+    type Output = StdResult<Response<ScanBody<B, P>>, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let res = ready!(this.inner.poll(cx));
+        let shared = this.shared.take();
+
+        Poll::Ready(res.map(|res| res.map(|body| ScanBody::with_shared(body, shared))))
+    }
+}
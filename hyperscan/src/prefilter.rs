@@ -0,0 +1,255 @@
+//! Confirm the candidate matches of the patterns compiled in prefilter mode.
+//!
+//! With the `PREFILTER` flag, Hyperscan compiles the expressions it doesn't support, such as the back-references,
+//! as an over-approximation: every true match is reported, with some false positives. A [`PrefilterDatabase`]
+//! runs a [`Confirm`] of the pattern on the candidate window, and only reports the confirmed matches.
+//!
+//! The window ends at the candidate end offset, and starts at the start of match offset when it's reported.
+//! Hyperscan doesn't track the start of match in prefilter mode, so the window of a prefilter pattern starts
+//! at the start of data, or at the [`PrefilterDatabase::lookback`] before the end.
+//!
+//! # Examples
+//!
+//! ```rust
+//! # use std::collections::HashMap;
+//! # use hyperscan::{prefilter::PrefilterDatabase, prelude::*};
+//! let db: BlockDatabase = pattern! {1 => r"(\w+) \1"; PREFILTER}.build().unwrap();
+//! let mut confirmers = HashMap::new();
+//!
+//! // the `regex` crate doesn't support the back-references, confirm the window with a closure
+//! confirmers.insert(1, |window: &[u8]| {
+//!     let text = std::str::from_utf8(window).ok()?;
+//!
+//!     (0..text.len()).filter(|&start| text.is_char_boundary(start)).find(|&start| {
+//!         let mut words = text[start..].split(' ');
+//!
+//!         matches!((words.next(), words.next(), words.next()), (Some(a), Some(b), None) if !a.is_empty() && a == b)
+//!     })
+//! });
+//!
+//! let db = PrefilterDatabase::with_confirmers(db, confirmers);
+//! let mut s = db.alloc_scratch().unwrap();
+//! let mut matches = vec![];
+//!
+//! db.scan("foo bar bar", &mut s, |id, from, to| {
+//!     matches.push((id, from, to));
+//!     Matching::Continue
+//! })
+//! .unwrap();
+//!
+//! assert_eq!(matches, vec![(1, 4, 11)]);
+//! ```
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use crate::{common::BlockDatabase, runtime::Matching, Result, ScanOutcome, ScratchRef};
+
+/// Confirm a candidate match of a prefilter pattern.
+pub trait Confirm {
+    /// Returns the start of a true match ending at the end of the window, relative to the window.
+    fn confirm(&self, window: &[u8]) -> Option<usize>;
+}
+
+impl Confirm for regex::bytes::Regex {
+    /// Find a match ending at the end of the window.
+    ///
+    /// Only the leftmost-first non-overlapping matches are found, so the regex should be anchored with `$`.
+    fn confirm(&self, window: &[u8]) -> Option<usize> {
+        self.find_iter(window)
+            .find(|m| m.end() == window.len())
+            .map(|m| m.start())
+    }
+}
+
+impl Confirm for regex::Regex {
+    /// Find a match ending at the end of the window, after skipping an incomplete UTF-8 character at the start.
+    ///
+    /// Only the leftmost-first non-overlapping matches are found, so the regex should be anchored with `$`.
+    fn confirm(&self, window: &[u8]) -> Option<usize> {
+        let skip = (0..window.len().min(4)).find(|&n| std::str::from_utf8(&window[n..]).is_ok())?;
+        let text = std::str::from_utf8(&window[skip..]).ok()?;
+
+        self.find_iter(text)
+            .find(|m| m.end() == text.len())
+            .map(|m| skip + m.start())
+    }
+}
+
+/// A boxed confirmer, to confirm the patterns of a database with the confirmers of different types.
+impl Confirm for Box<dyn Confirm + Send + Sync> {
+    fn confirm(&self, window: &[u8]) -> Option<usize> {
+        (**self).confirm(window)
+    }
+}
+
+impl<F> Confirm for F
+where
+    F: Fn(&[u8]) -> Option<usize>,
+{
+    fn confirm(&self, window: &[u8]) -> Option<usize> {
+        self(window)
+    }
+}
+
+/// A block database of the prefilter patterns, which confirms their candidate matches.
+///
+/// The matches of the patterns without confirmer are reported as is.
+pub struct PrefilterDatabase {
+    db: BlockDatabase,
+    confirmers: HashMap<u32, Box<dyn Confirm + Send + Sync>>,
+    lookback: usize,
+}
+
+impl Deref for PrefilterDatabase {
+    type Target = BlockDatabase;
+
+    fn deref(&self) -> &Self::Target {
+        &self.db
+    }
+}
+
+impl PrefilterDatabase {
+    /// Confirm the candidate matches of the patterns with the confirmer of their ID.
+    ///
+    /// The confirmers of different types are given as `Box<dyn Confirm + Send + Sync>`.
+    pub fn with_confirmers<C>(db: BlockDatabase, confirmers: HashMap<u32, C>) -> Self
+    where
+        C: Confirm + Send + Sync + 'static,
+    {
+        PrefilterDatabase {
+            db,
+            confirmers: confirmers
+                .into_iter()
+                .map(|(id, confirmer)| (id, Box::new(confirmer) as Box<dyn Confirm + Send + Sync>))
+                .collect(),
+            lookback: usize::MAX,
+        }
+    }
+
+    /// Limit the window of a candidate match to the bytes before its end, when the start of match isn't available.
+    pub fn lookback(mut self, lookback: usize) -> Self {
+        self.lookback = lookback;
+        self
+    }
+
+    /// Scan the data and report the confirmed matches with their start and end offsets.
+    pub fn scan<T, F>(&self, data: T, scratch: &mut ScratchRef, mut on_match: F) -> Result<ScanOutcome>
+    where
+        T: AsRef<[u8]>,
+        F: FnMut(u32, u64, u64) -> Matching,
+    {
+        let data = data.as_ref();
+
+        self.db.scan(data, scratch, |id, from, to, _| {
+            let (from, to) = (from as usize, to as usize);
+
+            match self.confirmers.get(&id) {
+                Some(confirmer) => {
+                    let start = from.max(to.saturating_sub(self.lookback));
+
+                    match confirmer.confirm(&data[start..to]) {
+                        Some(off) => on_match(id, (start + off) as u64, to as u64),
+                        None => Matching::Continue,
+                    }
+                }
+                None => on_match(id, from as u64, to as u64),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::bytes::Regex;
+
+    use super::*;
+    use crate::prelude::*;
+
+    fn scan(db: &PrefilterDatabase, data: &str) -> Vec<(u32, u64, u64)> {
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan(data, &mut s, |id, from, to| {
+            matches.push((id, from, to));
+            Matching::Continue
+        })
+        .unwrap();
+
+        matches
+    }
+
+    #[test]
+    fn test_confirm_back_reference() {
+        let patterns: Patterns = r"1:/<(\w+)>[^<]*<\/\1>/P
+2:/<b>/"
+            .parse()
+            .unwrap();
+        let db: BlockDatabase = patterns.build().unwrap();
+        let candidates = {
+            let mut s = db.alloc_scratch().unwrap();
+            let mut ends = vec![];
+
+            db.scan("<a>x</b> <b>y</b>", &mut s, |id, _, to, _| {
+                ends.push((id, to));
+                Matching::Continue
+            })
+            .unwrap();
+
+            ends
+        };
+
+        // the back-reference is approximated, `<a>x</b>` is a false positive
+        assert!(candidates.contains(&(1, 8)));
+
+        let tag = |window: &[u8]| -> Option<usize> {
+            let end = Regex::new(r"</(\w+)>$").unwrap().captures(window)?.get(1)?;
+            let open = format!("<{}>", String::from_utf8_lossy(end.as_bytes()));
+
+            window.windows(open.len()).rposition(|w| w == open.as_bytes())
+        };
+        let mut confirmers = HashMap::new();
+
+        confirmers.insert(1, tag);
+
+        let db = PrefilterDatabase::with_confirmers(db, confirmers);
+
+        assert_eq!(scan(&db, "<a>x</b> <b>y</b>"), vec![(2, 0, 12), (1, 9, 17)]);
+
+        // the window is limited by the lookback
+        let db = db.lookback(6);
+
+        assert_eq!(scan(&db, "<a>x</b> <b>y</b>"), vec![(2, 0, 12)]);
+    }
+
+    #[test]
+    fn test_confirm_regex() {
+        let db: BlockDatabase = pattern! {1 => r"\d+-\d+"; PREFILTER}.build().unwrap();
+        let mut confirmers = HashMap::new();
+
+        confirmers.insert(1, regex::Regex::new(r"\d+-\d*2$").unwrap());
+
+        let db = PrefilterDatabase::with_confirmers(db, confirmers);
+
+        assert_eq!(scan(&db, "12-32 2-1"), vec![(1, 0, 5)]);
+
+        // the confirmers of different types are boxed
+        let patterns: Patterns = "1:/\\d+-\\d+/P\n2:/[a-z]+/P".parse().unwrap();
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut confirmers: HashMap<u32, Box<dyn Confirm + Send + Sync>> = HashMap::new();
+
+        confirmers.insert(1, Box::new(regex::Regex::new(r"\d+-\d*2$").unwrap()));
+        confirmers.insert(
+            2,
+            Box::new(|window: &[u8]| window.ends_with(b"ok").then(|| window.len() - 2)),
+        );
+
+        let db = PrefilterDatabase::with_confirmers(db, confirmers);
+
+        assert_eq!(scan(&db, "12-32 ok 2-1"), vec![(1, 0, 5), (2, 6, 8)]);
+        assert_eq!(Confirm::confirm(&Regex::new("b+$").unwrap(), b"abb"), Some(1));
+        assert_eq!(
+            Confirm::confirm(&regex::Regex::new("b+$").unwrap(), b"\xbfabb"),
+            Some(2)
+        );
+    }
+}
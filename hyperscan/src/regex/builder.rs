@@ -1,4 +1,8 @@
-use crate::{compile::Flags, regex::Regex, Result};
+use crate::{
+    compile::Flags,
+    regex::{Regex, RegexSet},
+    Result,
+};
 
 /// A configurable builder for a regular expression.
 ///
@@ -40,6 +44,27 @@ impl Builder<String> {
     }
 }
 
+impl Builder<Vec<String>> {
+    /// Create a new regular expression builder with the given patterns.
+    ///
+    /// If the patterns are invalid, then an error will be returned when build is called.
+    pub fn new<I, S>(patterns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Builder {
+            expr: patterns.into_iter().map(|s| s.as_ref().to_owned()).collect(),
+            flags: Flags::empty(),
+        }
+    }
+
+    /// Consume the builder and compile the regular expressions into a set.
+    pub fn build(&self) -> Result<RegexSet> {
+        RegexSet::with_flags(&self.expr, self.flags)
+    }
+}
+
 impl<T> Builder<T> {
     fn toggle(&mut self, flag: Flags, yes: bool) -> &mut Self {
         if yes {
@@ -0,0 +1,333 @@
+//! Match regular expressions on arbitrary bytes.
+//!
+//! This module provides a nearly identical API to the one found in the top-level of this module,
+//! but operates on `&[u8]` instead of `&str`, and the expression is not compiled in UTF-8 mode.
+use std::fmt;
+use std::ops::Range;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::vec;
+
+use crate::{
+    common::BlockDatabase,
+    compile::{Builder, Flags, Pattern},
+    regex::som,
+    runtime::IdFilter,
+    Error, Result,
+};
+
+/// Match represents a single match of a regex in a haystack.
+///
+/// The lifetime parameter `'t` refers to the lifetime of the matched text.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Match<'t> {
+    text: &'t [u8],
+    start: usize,
+    end: usize,
+}
+
+impl<'t> Match<'t> {
+    /// Returns the starting byte offset of the match in the haystack.
+    #[inline]
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the ending byte offset of the match in the haystack.
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the range over the starting and ending byte offsets of the
+    /// match in the haystack.
+    #[inline]
+    pub fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the matched text.
+    #[inline]
+    pub fn as_bytes(&self) -> &'t [u8] {
+        &self.text[self.start..self.end]
+    }
+
+    /// Creates a new match from the given haystack and byte offsets.
+    #[inline]
+    fn new(haystack: &'t [u8], start: usize, end: usize) -> Match<'t> {
+        Match {
+            text: haystack,
+            start,
+            end,
+        }
+    }
+}
+
+impl<'t> From<Match<'t>> for Range<usize> {
+    fn from(m: Match<'t>) -> Range<usize> {
+        m.range()
+    }
+}
+
+/// An iterator over all non-overlapping matches for a particular byte string.
+///
+/// The iterator yields a `Match` value. The iterator stops when no more
+/// matches can be found.
+///
+/// `'t` is the lifetime of the matched byte string.
+pub struct Matches<'t>(&'t [u8], vec::IntoIter<Range<usize>>);
+
+impl<'t> Matches<'t> {
+    /// Return the text being searched.
+    pub fn text(&self) -> &'t [u8] {
+        self.0
+    }
+}
+
+impl<'t> Iterator for Matches<'t> {
+    type Item = Match<'t>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.1.next().map(|range| Match::new(self.0, range.start, range.end))
+    }
+}
+
+impl<'t> DoubleEndedIterator for Matches<'t> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.1
+            .next_back()
+            .map(|range| Match::new(self.0, range.start, range.end))
+    }
+}
+
+/// A compiled regular expression for matching arbitrary bytes.
+///
+/// # Differences from the `regex` crate
+///
+/// The expression is compiled with `SOM_LEFTMOST` into a block mode database,
+/// and Hyperscan reports all the matches ordered by their end offsets,
+/// which are reduced to the leftmost matches.
+///
+/// - There are no capture groups.
+/// - Among the matches starting at the leftmost offset, the longest one is returned,
+///   so the lazy repetitions (e.g. `a+?`) behave as the greedy ones for `find` and `find_iter`.
+/// - The scratch space is cached by the current thread.
+#[derive(Clone)]
+pub struct Regex {
+    pub(crate) db: Arc<BlockDatabase>,
+    expr: Arc<str>,
+    filter: Option<Arc<IdFilter>>,
+}
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.as_str()).finish()
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Regex {
+    type Err = Error;
+
+    /// Attempts to parse a string into a regular expression
+    fn from_str(s: &str) -> Result<Regex> {
+        Regex::new(s)
+    }
+}
+
+/// Core regular expression methods.
+impl Regex {
+    /// Compiles a regular expression.
+    /// Once compiled, it can be used repeatedly to search in a byte string.
+    ///
+    /// If an invalid expression is given, then an error is returned.
+    pub fn new<S: Into<String>>(re: S) -> Result<Regex> {
+        Self::with_flags(re, Flags::empty())
+    }
+
+    pub(crate) fn with_flags<S: Into<String>>(re: S, flags: Flags) -> Result<Regex> {
+        let expr = re.into();
+        let db = Pattern::with_flags(expr.as_str(), flags | Flags::SOM_LEFTMOST)?.build()?;
+
+        Ok(Regex {
+            db: Arc::new(db),
+            expr: expr.into(),
+            filter: None,
+        })
+    }
+
+    /// Only report the matches of the regex while its pattern, the ID `0`, is enabled in the filter.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use hyperscan::{regex::bytes::Regex, IdFilter};
+    /// let filter = Arc::new(IdFilter::new(0));
+    /// let re = Regex::new(r"a+").unwrap().with_filter(filter.clone());
+    ///
+    /// assert!(re.is_match(b"baaab"));
+    ///
+    /// filter.disable(0);
+    ///
+    /// assert!(!re.is_match(b"baaab"));
+    /// ```
+    pub fn with_filter(self, filter: Arc<IdFilter>) -> Regex {
+        Regex {
+            filter: Some(filter),
+            ..self
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.filter.as_ref().is_none_or(|filter| filter.is_enabled(0))
+    }
+
+    /// Returns the original string of this regex.
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+
+    /// Returns true if and only if the regex matches the byte string given.
+    ///
+    /// # Examples
+    ///
+    /// Test if some text contains at least one word with exactly 13 ASCII word bytes:
+    ///
+    /// ```rust
+    /// # use hyperscan::regex::bytes::Regex;
+    /// let text = b"I categorically deny having triskaidekaphobia.";
+    /// assert!(Regex::new(r"\b\w{13}\b").unwrap().is_match(text));
+    /// ```
+    pub fn is_match(&self, text: &[u8]) -> bool {
+        self.shortest_match(text).is_some()
+    }
+
+    /// Returns the start and end byte range of the leftmost match in text. If no match exists, then None is returned.
+    ///
+    /// # Examples
+    ///
+    /// Find the start and end location of the first word with exactly 13 ASCII word bytes:
+    ///
+    /// ```rust
+    /// # use hyperscan::regex::bytes::Regex;
+    /// let text = b"I categorically deny having triskaidekaphobia.";
+    /// let mat = Regex::new(r"\b\w{13}\b").unwrap().find(text).unwrap();
+    /// assert_eq!((mat.start(), mat.end()), (2, 15));
+    /// ```
+    pub fn find<'t>(&self, text: &'t [u8]) -> Option<Match<'t>> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        self.db
+            .with_thread_scratch(|s| som::leftmost(&self.db, text, 0, s))
+            .and_then(|res| res)
+            .expect("scan")
+            .map(|range| Match::new(text, range.start, range.end))
+    }
+
+    /// Returns an iterator for each successive non-overlapping match in `text`,
+    /// returning the start and end byte indices with respect to `text`.
+    ///
+    /// # Examples
+    ///
+    /// Find the start and end location of every word with exactly 13 ASCII word bytes:
+    ///
+    /// ```rust
+    /// # use hyperscan::regex::bytes::Regex;
+    /// let text = b"Retroactively relinquishing remunerations is reprehensible.";
+    /// for mat in Regex::new(r"\b\w{13}\b").unwrap().find_iter(text) {
+    ///     println!("{:?}", mat);
+    /// }
+    /// ```
+    pub fn find_iter<'t>(&self, text: &'t [u8]) -> Matches<'t> {
+        if !self.is_enabled() {
+            return Matches(text, vec![].into_iter());
+        }
+
+        let matches = self
+            .db
+            .with_thread_scratch(|s| som::non_overlapping(&self.db, text, 0, s))
+            .and_then(|res| res)
+            .expect("scan");
+
+        Matches(text, matches.into_iter())
+    }
+
+    /// Returns the end location of a match in the text given.
+    ///
+    /// This method may have the same performance characteristics as `is_match`,
+    /// the match returned by this routine may be shorter than the one reported by `find`.
+    ///
+    /// # Examples
+    ///
+    /// Typically, `a+` would match the entire first sequence of `a` in some text,
+    /// but `shortest_match` can give up as soon as it sees the first `a`.
+    ///
+    /// ```rust
+    /// # use hyperscan::regex::bytes::Regex;
+    /// let text = b"aaaaa";
+    /// let pos = Regex::new(r"a+").unwrap().shortest_match(text);
+    /// assert_eq!(pos, Some(1));
+    /// ```
+    pub fn shortest_match(&self, text: &[u8]) -> Option<usize> {
+        if !self.is_enabled() {
+            return None;
+        }
+
+        self.db
+            .with_thread_scratch(|s| som::shortest(&self.db, text, 0, s))
+            .and_then(|res| res)
+            .expect("scan")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let re = Regex::new(r"a+").unwrap();
+
+        assert_eq!(re.as_str(), "a+");
+        assert!(re.is_match(b"baaab"));
+        assert!(!re.is_match(b"bbb"));
+        assert_eq!(re.find(b"baaab").map(|m| m.range()), Some(1..4));
+        assert_eq!(re.find(b"baaab").unwrap().as_bytes(), b"aaa");
+        assert_eq!(re.shortest_match(b"baaab"), Some(2));
+        assert_eq!(re.find(b"bbb"), None);
+    }
+
+    #[test]
+    fn test_find_non_utf8() {
+        let re = Regex::new(r"\xff+").unwrap();
+
+        assert_eq!(re.find(b"a\xff\xffb").map(|m| m.range()), Some(1..3));
+    }
+
+    #[test]
+    fn test_find_iter() {
+        let regex = r"\b\w{13}\b";
+        let text = b"Retroactively relinquishing remunerations is reprehensible.";
+
+        assert_eq!(
+            regex::bytes::Regex::new(regex)
+                .unwrap()
+                .find_iter(text)
+                .map(|m| m.range())
+                .collect::<Vec<_>>(),
+            Regex::new(regex)
+                .unwrap()
+                .find_iter(text)
+                .map(|m| m.range())
+                .collect::<Vec<_>>()
+        );
+    }
+}
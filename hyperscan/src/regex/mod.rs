@@ -1,6 +1,10 @@
 //! Regex compatible interface
 mod builder;
+pub mod bytes;
 mod re;
+mod set;
+pub(crate) mod som;
 
 pub use builder::{RegexBuilder, RegexSetBuilder};
-pub use re::{Regex, Match, Matches, Split, SplitN};
+pub use re::{Match, Matches, Regex, Split, SplitN};
+pub use set::{RegexSet, SetMatches, SetMatchesIntoIter, SetMatchesIter};
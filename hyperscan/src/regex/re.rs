@@ -1,14 +1,9 @@
+use std::fmt;
 use std::ops::Range;
 use std::str::FromStr;
-use std::sync::Arc;
 use std::vec;
 
-use crate::{
-    common::BlockDatabase,
-    compile::{Builder, Flags, Pattern},
-    runtime::Matching,
-    Error, Result,
-};
+use crate::{compile::Flags, regex::bytes, Error, Result};
 
 /// Match represents a single match of a regex in a haystack.
 ///
@@ -102,8 +97,22 @@ impl<'t> DoubleEndedIterator for Matches<'t> {
 }
 
 /// A compiled regular expression for matching Unicode strings.
+///
+/// The expression is compiled in UTF-8 mode, see `bytes::Regex` for the differences from the `regex` crate.
 #[derive(Clone)]
-pub struct Regex(pub(crate) Arc<BlockDatabase>);
+pub struct Regex(pub(crate) bytes::Regex);
+
+impl fmt::Debug for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Regex").field(&self.as_str()).finish()
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 impl FromStr for Regex {
     type Err = Error;
@@ -125,9 +134,17 @@ impl Regex {
     }
 
     pub(crate) fn with_flags<S: Into<String>>(re: S, flags: Flags) -> Result<Regex> {
-        Pattern::with_flags(re, flags | Flags::SOM_LEFTMOST | Flags::UTF8)?
-            .build()
-            .map(|db| Regex(Arc::new(db)))
+        bytes::Regex::with_flags(re, flags | Flags::UTF8).map(Regex)
+    }
+
+    /// Only report the matches of the regex while its pattern, the ID `0`, is enabled in the filter.
+    pub fn with_filter(self, filter: std::sync::Arc<crate::IdFilter>) -> Regex {
+        Regex(self.0.with_filter(filter))
+    }
+
+    /// Returns the original string of this regex.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
     }
 
     /// Returns true if and only if the regex matches the string given.
@@ -145,19 +162,10 @@ impl Regex {
     /// assert!(Regex::new(r"\b\w{13}\b").unwrap().is_match(text));
     /// ```
     pub fn is_match(&self, text: &str) -> bool {
-        let mut matched = false;
-
-        let s = self.0.alloc_scratch().unwrap();
-        let _ = self.0.scan(text, &s, |_, _, _, _| {
-            matched = true;
-
-            Matching::Terminate
-        });
-
-        matched
+        self.0.is_match(text.as_bytes())
     }
 
-    /// Returns the start and end byte range of the leftmost-first match in text. If no match exists, then None is returned.
+    /// Returns the start and end byte range of the leftmost match in text. If no match exists, then None is returned.
     ///
     /// Note that this should only be used if you want to discover the position of the match. Testing the existence of a match is faster if you use is_match.
     ///
@@ -173,18 +181,9 @@ impl Regex {
     /// assert_eq!(mat.end(), 15);
     /// ```
     pub fn find<'t>(&self, text: &'t str) -> Option<Match<'t>> {
-        let mut matched = vec![];
-
-        let s = self.0.alloc_scratch().unwrap();
-        let _ = self.0.scan(text, &s, |_, from, to, _| {
-            matched.push((from as usize, to as usize));
-
-            Matching::Terminate
-        });
-
-        matched
-            .first()
-            .map(|&(start, end)| Match::new(&text[start..end], start, end))
+        self.0
+            .find(text.as_bytes())
+            .map(|m| Match::new(text, m.start(), m.end()))
     }
 
     /// Returns an iterator for each successive non-overlapping match in
@@ -204,24 +203,29 @@ impl Regex {
     /// }
     /// ```
     pub fn find_iter<'t>(&self, text: &'t str) -> Matches<'t> {
-        let mut matched = Vec::<Range<usize>>::new();
+        let matches = self.0.find_iter(text.as_bytes()).map(Range::from).collect::<Vec<_>>();
 
-        let s = self.0.alloc_scratch().unwrap();
-        let _ = self.0.scan(text, &s, |_, from, to, _| {
-            let range = from as usize..to as usize;
-
-            match matched.last() {
-                Some(last) if last.start == range.start && last.end < range.end => {
-                    // only the non-overlapping match should be return
-                    *matched.last_mut().unwrap() = range;
-                }
-                _ => matched.push(range),
-            }
-
-            Matching::Continue
-        });
+        Matches(text, matches.into_iter())
+    }
 
-        Matches(text, matched.into_iter())
+    /// Returns the end location of a match in the text given.
+    ///
+    /// This method may have the same performance characteristics as `is_match`,
+    /// the match returned by this routine may be shorter than the one reported by `find`.
+    ///
+    /// # Examples
+    ///
+    /// Typically, `a+` would match the entire first sequence of `a` in some text,
+    /// but `shortest_match` can give up as soon as it sees the first `a`.
+    ///
+    /// ```rust
+    /// # use hyperscan::regex::Regex;
+    /// let text = "aaaaa";
+    /// let pos = Regex::new(r"a+").unwrap().shortest_match(text);
+    /// assert_eq!(pos, Some(1));
+    /// ```
+    pub fn shortest_match(&self, text: &str) -> Option<usize> {
+        self.0.shortest_match(text.as_bytes())
     }
 
     /// Returns an iterator of substrings of `text` delimited by a match of the
@@ -341,6 +345,15 @@ impl<'t> Iterator for SplitN<'t> {
 
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn test_find() {
+        let text = "I categorically deny having triskaidekaphobia.";
+        let mat = super::Regex::new(r"\b\w{13}\b").unwrap().find(text).unwrap();
+
+        assert_eq!(mat.range(), 2..15);
+        assert_eq!(mat.as_str(), "categorically");
+    }
+
     #[test]
     fn test_find_iter() {
         let regex = r"\b\w{13}\b";
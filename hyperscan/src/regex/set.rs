@@ -0,0 +1,438 @@
+use std::fmt;
+use std::iter::FusedIterator;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::{
+    common::BlockDatabase,
+    compile::{Builder, Flags, Pattern, Patterns},
+    runtime::{bitset::BitSet, IdFilter, Matching},
+    Result,
+};
+
+/// Match multiple (possibly overlapping) regular expressions in a single scan.
+///
+/// The expressions are compiled with `SINGLEMATCH`, so each pattern is reported at most once,
+/// and the scan terminates as soon as all the patterns have matched.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::regex::RegexSet;
+/// let set = RegexSet::new(&[r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar"]).unwrap();
+///
+/// let matches: Vec<_> = set.matches("foobar").into_iter().collect();
+/// assert_eq!(matches, vec![0, 2, 3, 4, 6]);
+///
+/// let matches = set.matches("foobar");
+/// assert!(!matches.matched(5));
+/// assert!(matches.matched(6));
+/// ```
+#[derive(Clone)]
+pub struct RegexSet {
+    db: Option<Arc<BlockDatabase>>,
+    exprs: Arc<[String]>,
+    filter: Option<Arc<IdFilter>>,
+}
+
+impl fmt::Debug for RegexSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("RegexSet").field(&self.patterns()).finish()
+    }
+}
+
+impl RegexSet {
+    /// Create a new regex set with the given regular expressions.
+    ///
+    /// If an invalid expression is given, then an error is returned.
+    pub fn new<I, S>(exprs: I) -> Result<RegexSet>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        Self::with_flags(exprs, Flags::empty())
+    }
+
+    /// Create a new empty regex set, which never matches.
+    pub fn empty() -> RegexSet {
+        RegexSet {
+            db: None,
+            exprs: Arc::new([]),
+            filter: None,
+        }
+    }
+
+    pub(crate) fn with_flags<I, S>(exprs: I, flags: Flags) -> Result<RegexSet>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let exprs = exprs.into_iter().map(|s| s.as_ref().to_owned()).collect::<Vec<_>>();
+
+        if exprs.is_empty() {
+            return Ok(Self::empty());
+        }
+
+        let db = exprs
+            .iter()
+            .enumerate()
+            .map(|(id, expr)| {
+                Pattern::with_flags(expr.as_str(), flags | Flags::UTF8 | Flags::SINGLEMATCH).map(|pattern| Pattern {
+                    id: Some(id),
+                    ..pattern
+                })
+            })
+            .collect::<Result<Patterns>>()?
+            .build()?;
+
+        Ok(RegexSet {
+            db: Some(Arc::new(db)),
+            exprs: exprs.into(),
+            filter: None,
+        })
+    }
+
+    /// Only report the regular expressions enabled in the filter, indexed by their position in the set.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use hyperscan::{regex::RegexSet, IdFilter};
+    /// let set = RegexSet::new(&[r"foo", r"bar"]).unwrap();
+    /// let filter = Arc::new(IdFilter::from(&set));
+    /// let set = set.with_filter(filter.clone());
+    ///
+    /// filter.disable(0);
+    ///
+    /// let matches: Vec<_> = set.matches("foobar").into_iter().collect();
+    /// assert_eq!(matches, vec![1]);
+    /// ```
+    pub fn with_filter(self, filter: Arc<IdFilter>) -> RegexSet {
+        RegexSet {
+            filter: Some(filter),
+            ..self
+        }
+    }
+
+    /// Returns the total number of regular expressions in this set.
+    pub fn len(&self) -> usize {
+        self.exprs.len()
+    }
+
+    /// Returns `true` if this set contains no regular expressions.
+    pub fn is_empty(&self) -> bool {
+        self.exprs.is_empty()
+    }
+
+    /// Returns the patterns that this set will match on.
+    pub fn patterns(&self) -> &[String] {
+        &self.exprs
+    }
+
+    /// Returns true if and only if one of the regexes in this set matches the text given.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::regex::RegexSet;
+    /// let set = RegexSet::new(&[r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar"]).unwrap();
+    ///
+    /// assert!(set.is_match("foo"));
+    /// assert!(!set.is_match("☃"));
+    /// ```
+    pub fn is_match(&self, text: &str) -> bool {
+        let mut matched = false;
+
+        self.scan(text.as_bytes(), |_| {
+            matched = true;
+
+            Matching::Terminate
+        });
+
+        matched
+    }
+
+    /// Returns the set of regular expressions that match in the given text.
+    ///
+    /// The set is stored in a fixed size bitset, no allocation is made per match.
+    pub fn matches(&self, text: &str) -> SetMatches {
+        self.matches_at(text, 0)
+    }
+
+    /// Returns the set of regular expressions that match in the text after the starting offset.
+    ///
+    /// The text before `start` is not scanned, so the assertions (e.g. `\b` or `^`)
+    /// are evaluated as if the text began at `start`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is greater than the length of text.
+    pub fn matches_at(&self, text: &str, start: usize) -> SetMatches {
+        let mut matches = SetMatches::new(self.len());
+        let mut remaining = self.len();
+
+        self.scan(&text.as_bytes()[start..], |id| {
+            if !matches.insert(id) {
+                return Matching::Continue;
+            }
+
+            remaining -= 1;
+
+            if remaining == 0 {
+                Matching::Terminate
+            } else {
+                Matching::Continue
+            }
+        });
+
+        matches
+    }
+
+    fn scan<F>(&self, data: &[u8], mut on_match: F)
+    where
+        F: FnMut(usize) -> Matching,
+    {
+        if let Some(ref db) = self.db {
+            if let Err(err) = db
+                .with_thread_scratch(|s| {
+                    db.scan(data, s, |id, _, _, _| {
+                        if self.filter.as_ref().is_none_or(|filter| filter.is_enabled(id)) {
+                            on_match(id as usize)
+                        } else {
+                            Matching::Continue
+                        }
+                    })
+                })
+                .and_then(|res| res)
+            {
+                panic!("scan, {}", err)
+            }
+        }
+    }
+}
+
+/// A set of matches returned by a regex set.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SetMatches {
+    matched_any: bool,
+    len: usize,
+    bits: BitSet,
+}
+
+impl SetMatches {
+    fn new(len: usize) -> SetMatches {
+        SetMatches {
+            matched_any: false,
+            len,
+            bits: BitSet::with_capacity(len),
+        }
+    }
+
+    /// Mark the regex as matched, returns `false` if it was already matched.
+    fn insert(&mut self, id: usize) -> bool {
+        let inserted = self.bits.insert(id);
+
+        self.matched_any |= inserted;
+
+        inserted
+    }
+
+    /// Whether this set contains any matches.
+    pub fn matched_any(&self) -> bool {
+        self.matched_any
+    }
+
+    /// Whether the regex at the given index matched.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `regex_index` is greater than or equal to `self.len()`.
+    pub fn matched(&self, regex_index: usize) -> bool {
+        assert!(regex_index < self.len, "regex index out of range");
+
+        self.bits.contains(regex_index)
+    }
+
+    /// The total number of regexes in the set that created these matches.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set that created these matches contains no regexes.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns an iterator over indexes in the regex that matched.
+    pub fn iter(&self) -> SetMatchesIter<'_> {
+        SetMatchesIter {
+            matches: self,
+            range: 0..self.len,
+        }
+    }
+}
+
+impl IntoIterator for SetMatches {
+    type Item = usize;
+    type IntoIter = SetMatchesIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SetMatchesIntoIter {
+            range: 0..self.len,
+            matches: self,
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a SetMatches {
+    type Item = usize;
+    type IntoIter = SetMatchesIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An owned iterator over the set of matches from a regex set.
+#[derive(Clone, Debug)]
+pub struct SetMatchesIntoIter {
+    matches: SetMatches,
+    range: Range<usize>,
+}
+
+impl Iterator for SetMatchesIntoIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let matches = &self.matches;
+
+        self.range.by_ref().find(|&i| matches.matched(i))
+    }
+}
+
+impl DoubleEndedIterator for SetMatchesIntoIter {
+    fn next_back(&mut self) -> Option<usize> {
+        let matches = &self.matches;
+
+        self.range.by_ref().rev().find(|&i| matches.matched(i))
+    }
+}
+
+impl FusedIterator for SetMatchesIntoIter {}
+
+/// A borrowed iterator over the set of matches from a regex set.
+///
+/// The lifetime `'a` refers to the lifetime of a `SetMatches` value.
+#[derive(Clone, Debug)]
+pub struct SetMatchesIter<'a> {
+    matches: &'a SetMatches,
+    range: Range<usize>,
+}
+
+impl<'a> Iterator for SetMatchesIter<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let matches = self.matches;
+
+        self.range.by_ref().find(|&i| matches.matched(i))
+    }
+}
+
+impl<'a> DoubleEndedIterator for SetMatchesIter<'a> {
+    fn next_back(&mut self) -> Option<usize> {
+        let matches = self.matches;
+
+        self.range.by_ref().rev().find(|&i| matches.matched(i))
+    }
+}
+
+impl<'a> FusedIterator for SetMatchesIter<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn test_set_matches() {
+        let mut matches = SetMatches::new(130);
+
+        assert!(!matches.matched_any());
+        assert_eq!(matches.len(), 130);
+
+        assert!(matches.insert(3));
+        assert!(!matches.insert(3));
+        assert!(matches.insert(64));
+        assert!(matches.insert(129));
+
+        assert!(matches.matched_any());
+        assert!(matches.matched(64));
+        assert!(!matches.matched(65));
+        assert_eq!(matches.iter().collect::<Vec<_>>(), vec![3, 64, 129]);
+        assert_eq!(matches.iter().rev().collect::<Vec<_>>(), vec![129, 64, 3]);
+        assert_eq!(matches.into_iter().collect::<Vec<_>>(), vec![3, 64, 129]);
+    }
+
+    #[test]
+    fn test_empty() {
+        let set = RegexSet::empty();
+
+        assert!(set.is_empty());
+        assert!(!set.is_match("foo"));
+        assert!(!set.matches("foo").matched_any());
+    }
+
+    #[test]
+    fn test_matches_at() {
+        let set = RegexSet::new(["^foo", "bar"]).unwrap();
+
+        assert_eq!(set.matches("foobar").into_iter().collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(set.matches_at("foobar", 3).into_iter().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(set.matches_at("barfoo", 3).into_iter().collect::<Vec<_>>(), vec![0]);
+    }
+
+    fn random_pattern<R: Rng>(rng: &mut R) -> String {
+        const ATOMS: &[&str] = &["a", "b", "c", "[ab]", "[^a]", "."];
+        const QUANTIFIERS: &[&str] = &["", "", "+", "?", "{2}"];
+
+        (0..rng.gen_range(1..4))
+            .map(|i| {
+                let atom = ATOMS.choose(rng).unwrap();
+                // the first piece must not be optional, Hyperscan rejects the patterns matching empty buffers
+                let quantifier = if i == 0 { "" } else { QUANTIFIERS.choose(rng).unwrap() };
+
+                format!("{}{}", atom, quantifier)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_differential() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+
+        for _ in 0..300 {
+            let patterns = (0..rng.gen_range(1..8))
+                .map(|_| random_pattern(&mut rng))
+                .collect::<Vec<_>>();
+            let text = (0..rng.gen_range(0..24))
+                .map(|_| *b"abcd".choose(&mut rng).unwrap() as char)
+                .collect::<String>();
+
+            let expected = regex::RegexSet::new(&patterns).unwrap().matches(&text);
+            let matches = RegexSet::new(&patterns).unwrap().matches(&text);
+
+            assert_eq!(
+                matches.iter().collect::<Vec<_>>(),
+                expected.iter().collect::<Vec<_>>(),
+                "patterns: {:?}, text: {:?}",
+                patterns,
+                text
+            );
+            assert_eq!(matches.matched_any(), expected.matched_any());
+            assert_eq!(matches.len(), expected.len());
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! Reduce all the matches reported by Hyperscan to the leftmost matches.
+//!
+//! Hyperscan reports every match of a `SOM_LEFTMOST` pattern ordered by its end offset,
+//! with the leftmost start of match (SOM) for each end offset.
+use std::ops::Range;
+
+use crate::{
+    common::BlockDatabase,
+    runtime::{Matching, ScratchRef},
+    Result,
+};
+
+/// Scan the data, the termination requested by the callback isn't an error.
+pub(crate) fn scan<F>(db: &BlockDatabase, data: &[u8], scratch: &mut ScratchRef, mut on_match: F) -> Result<()>
+where
+    F: FnMut(Range<usize>) -> Matching,
+{
+    db.scan(data, scratch, |_, from, to, _| on_match(from as usize..to as usize))
+        .map(|_| ())
+}
+
+/// Returns `true` if the match starts before the best match, or starts at the same offset but ends after it.
+pub(crate) fn is_leftmost_longest(m: &Range<usize>, best: Option<&Range<usize>>) -> bool {
+    best.is_none_or(|best| m.start < best.start || (m.start == best.start && m.end > best.end))
+}
+
+/// Find the leftmost-longest match which starts at or after `at`.
+pub(crate) fn leftmost(
+    db: &BlockDatabase,
+    data: &[u8],
+    at: usize,
+    scratch: &mut ScratchRef,
+) -> Result<Option<Range<usize>>> {
+    let mut best = None;
+
+    scan(db, data, scratch, |m| {
+        if m.start >= at && is_leftmost_longest(&m, best.as_ref()) {
+            best = Some(m);
+        }
+
+        Matching::Continue
+    })?;
+
+    Ok(best)
+}
+
+/// Find the end offset of the first reported match which starts at or after `at`.
+pub(crate) fn shortest(db: &BlockDatabase, data: &[u8], at: usize, scratch: &mut ScratchRef) -> Result<Option<usize>> {
+    let mut end = None;
+
+    scan(db, data, scratch, |m| {
+        if m.start >= at {
+            end = Some(m.end);
+
+            Matching::Terminate
+        } else {
+            Matching::Continue
+        }
+    })?;
+
+    Ok(end)
+}
+
+/// Find all the leftmost-longest non-overlapping matches which start at or after `at`.
+pub(crate) fn non_overlapping(
+    db: &BlockDatabase,
+    data: &[u8],
+    at: usize,
+    scratch: &mut ScratchRef,
+) -> Result<Vec<Range<usize>>> {
+    let mut matches = vec![];
+
+    scan(db, data, scratch, |m| {
+        if m.start >= at {
+            matches.push(m);
+        }
+
+        Matching::Continue
+    })?;
+
+    Ok(reduce(matches))
+}
+
+/// Keep the leftmost-longest non-overlapping matches, ordered by the start offsets.
+pub(crate) fn reduce(mut matches: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    matches.sort_by(|a, b| a.start.cmp(&b.start).then_with(|| b.end.cmp(&a.end)));
+
+    let mut last_end = None;
+
+    matches.retain(|m| {
+        if last_end.is_none_or(|end| m.start >= end) {
+            last_end = Some(m.end);
+            true
+        } else {
+            false
+        }
+    });
+
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_leftmost_longest() {
+        assert!(is_leftmost_longest(&(1..3), None));
+        assert!(is_leftmost_longest(&(1..3), Some(&(2..3))));
+        assert!(is_leftmost_longest(&(1..4), Some(&(1..3))));
+        assert!(!is_leftmost_longest(&(1..3), Some(&(1..3))));
+        assert!(!is_leftmost_longest(&(2..5), Some(&(1..3))));
+    }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(reduce(vec![0..1, 0..2, 0..3, 5..6, 5..7, 8..9]), vec![0..3, 5..7, 8..9]);
+        assert_eq!(reduce(vec![2..4, 0..3, 3..5]), vec![0..3, 3..5]);
+        assert_eq!(reduce(vec![]), vec![]);
+    }
+}
@@ -0,0 +1,125 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+
+pub(crate) const BITS: usize = usize::BITS as usize;
+
+/// Returns the word of the index, and the mask of its bit in the word.
+pub(crate) fn locate(index: usize) -> (usize, usize) {
+    (index / BITS, 1 << (index % BITS))
+}
+
+/// Returns the number of words holding the indexes below `capacity`.
+pub(crate) fn words(capacity: usize) -> usize {
+    capacity.div_ceil(BITS)
+}
+
+/// Returns the indexes of the bits set in the word at the given position.
+pub(crate) fn ones(word: usize, bits: usize) -> impl Iterator<Item = usize> {
+    (0..BITS)
+        .filter(move |bit| bits & (1 << bit) != 0)
+        .map(move |bit| word * BITS + bit)
+}
+
+/// A set of indexes, which grows when an index beyond its capacity is inserted.
+///
+/// The sets of the same indexes are equal and hash alike, whatever their capacities.
+#[derive(Clone, Default)]
+pub(crate) struct BitSet {
+    words: Vec<usize>,
+}
+
+impl BitSet {
+    /// Create an empty set holding the indexes below `capacity` without growing.
+    pub fn with_capacity(capacity: usize) -> BitSet {
+        BitSet {
+            words: alloc::vec![0; words(capacity)],
+        }
+    }
+
+    pub fn contains(&self, index: usize) -> bool {
+        let (word, bit) = locate(index);
+
+        self.words.get(word).is_some_and(|w| w & bit != 0)
+    }
+
+    /// Add the index, returns `false` if it was already in the set.
+    #[cfg(feature = "compile")]
+    pub fn insert(&mut self, index: usize) -> bool {
+        let (word, bit) = locate(index);
+
+        if word >= self.words.len() {
+            self.words.resize(word + 1, 0);
+        }
+
+        let inserted = self.words[word] & bit == 0;
+
+        self.words[word] |= bit;
+
+        inserted
+    }
+
+    /// Add the index without growing the set, returns `false` if it is beyond the capacity.
+    pub fn try_insert(&mut self, index: usize) -> bool {
+        let (word, bit) = locate(index);
+
+        self.words.get_mut(word).map(|w| *w |= bit).is_some()
+    }
+
+    /// Grow the set to hold the indexes below `capacity` without growing.
+    pub fn reserve(&mut self, capacity: usize) {
+        if words(capacity) > self.words.len() {
+            self.words.resize(words(capacity), 0);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    /// Returns the indexes in the set, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(word, &w)| ones(word, w))
+    }
+
+    /// Remove all the indexes, keeping the capacity of the set.
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|w| *w = 0);
+    }
+
+    #[cfg(all(test, feature = "compile"))]
+    pub fn capacity(&self) -> usize {
+        self.words.len() * BITS
+    }
+
+    /// The words up to the last non-zero one.
+    fn trimmed(&self) -> &[usize] {
+        let len = self.words.iter().rposition(|&w| w != 0).map_or(0, |last| last + 1);
+
+        &self.words[..len]
+    }
+}
+
+impl fmt::Debug for BitSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for BitSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.trimmed() == other.trimmed()
+    }
+}
+
+impl Eq for BitSet {}
+
+impl Hash for BitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.trimmed().hash(state)
+    }
+}
@@ -0,0 +1,586 @@
+use alloc::vec::Vec;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    common::{Block, DatabaseRef, Vectored},
+    ffi,
+    runtime::{
+        bitset::{self, locate, ones},
+        matched::DEFAULT_MAX_ID,
+        Match, MatchEventHandler, MatchedIds, Matching, ScanIter, ScratchRef,
+    },
+    Result, ScanOutcome,
+};
+
+/// A set of the enabled pattern IDs, to suppress the matches of some patterns without recompiling the database.
+///
+/// The filter is a bitset sized to the largest pattern ID, all the patterns are enabled when it is created,
+/// and an ID beyond its size is always enabled. The bits are atomics, so a filter shared between threads,
+/// for example in an `Arc`, can be changed while the scans are running, and the change takes effect on the next match.
+///
+/// The match event handler wrapped by [`IdFilter::filter`] only sees the matches of the enabled patterns,
+/// it can be passed to any scan function of the block, streaming and vectored databases which takes
+/// a match event handler. The helpers which install their own handler, such as `scan_matches`, `which_match`,
+/// `scan_iter` and `par_scan`, are filtered by the database returned by `DatabaseRef::with_filter`,
+/// and the `Regex` and `RegexSet` facades by their `with_filter`.
+/// The disabled patterns are still matched by Hyperscan, so they still consume the scanning time,
+/// only their matches are suppressed before the handler.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, IdFilter};
+/// let patterns: Patterns = "1:/foo/\n2:/bar/".parse().unwrap();
+/// let db: BlockDatabase = patterns.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let filter = IdFilter::from(&patterns);
+/// let mut matches = vec![];
+///
+/// filter.disable(1);
+///
+/// db.scan("foobar", &mut s, filter.filter(|id, _, _, _| {
+///     matches.push(id);
+///     Matching::Continue
+/// }))
+/// .unwrap();
+///
+/// assert_eq!(matches, vec![2]);
+/// ```
+pub struct IdFilter {
+    disabled: Vec<AtomicUsize>,
+}
+
+impl fmt::Debug for IdFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdFilter")
+            .field("disabled", &self.disabled().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl IdFilter {
+    /// Create a filter for the pattern IDs up to `max_id`, with all the patterns enabled.
+    pub fn new(max_id: u32) -> IdFilter {
+        IdFilter {
+            disabled: (0..bitset::words(max_id as usize + 1))
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+        }
+    }
+
+    /// Returns whether the matches of the pattern are reported.
+    pub fn is_enabled(&self, id: u32) -> bool {
+        let (word, bit) = locate(id as usize);
+
+        self.disabled
+            .get(word)
+            .is_none_or(|w| w.load(Ordering::Relaxed) & bit == 0)
+    }
+
+    /// Report the matches of the pattern.
+    pub fn enable(&self, id: u32) {
+        let (word, bit) = locate(id as usize);
+
+        if let Some(w) = self.disabled.get(word) {
+            w.fetch_and(!bit, Ordering::Relaxed);
+        }
+    }
+
+    /// Suppress the matches of the pattern.
+    ///
+    /// Returns `false` if the ID is beyond the size of the filter, the pattern is left enabled.
+    pub fn disable(&self, id: u32) -> bool {
+        let (word, bit) = locate(id as usize);
+
+        self.disabled
+            .get(word)
+            .map(|w| w.fetch_or(bit, Ordering::Relaxed))
+            .is_some()
+    }
+
+    /// Report the matches of all the patterns.
+    pub fn enable_all(&self) {
+        for w in &self.disabled {
+            w.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Suppress the matches of all the patterns, except the given ones.
+    ///
+    /// The patterns are disabled word by word, so a concurrent scan may see a part of them disabled.
+    pub fn disable_all_except<I: IntoIterator<Item = u32>>(&self, ids: I) {
+        let mut disabled = (0..self.disabled.len()).map(|_| !0).collect::<Vec<usize>>();
+
+        for id in ids {
+            let (word, bit) = locate(id as usize);
+
+            if let Some(w) = disabled.get_mut(word) {
+                *w &= !bit;
+            }
+        }
+
+        for (w, bits) in self.disabled.iter().zip(disabled) {
+            w.store(bits, Ordering::Relaxed);
+        }
+    }
+
+    /// Wrap the match event handler, which only receives the matches of the enabled patterns.
+    pub fn filter<F: MatchEventHandler>(&self, on_match_event: F) -> Filtered<'_, F> {
+        Filtered {
+            filter: self,
+            on_match_event,
+            callback: None,
+            userdata: core::ptr::null_mut(),
+        }
+    }
+
+    fn disabled(&self) -> impl Iterator<Item = u32> + '_ {
+        self.disabled
+            .iter()
+            .enumerate()
+            .flat_map(|(word, w)| ones(word, w.load(Ordering::Relaxed)))
+            .map(|id| id as u32)
+    }
+}
+
+/// A database which only reports the matches of the patterns enabled in an [`IdFilter`],
+/// to its match event handlers and to its helpers.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, IdFilter};
+/// let patterns: Patterns = "1:/foo/\n2:/bar/".parse().unwrap();
+/// let db: BlockDatabase = patterns.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let filter = IdFilter::from(&patterns);
+///
+/// filter.disable(2);
+///
+/// let matches = db.with_filter(&filter).scan_matches("foobar", &mut s).unwrap();
+///
+/// assert_eq!(matches.iter().map(|m| m.id).collect::<Vec<_>>(), vec![1]);
+/// ```
+pub struct FilteredDatabase<'a, M> {
+    pub(crate) inner: &'a DatabaseRef<M>,
+    pub(crate) filter: &'a IdFilter,
+}
+
+impl<M> fmt::Debug for FilteredDatabase<'_, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilteredDatabase")
+            .field("filter", &self.filter)
+            .finish()
+    }
+}
+
+impl<M> DatabaseRef<M> {
+    /// Only report the matches of the patterns enabled in the filter, see [`IdFilter`].
+    pub fn with_filter<'a>(&'a self, filter: &'a IdFilter) -> FilteredDatabase<'a, M> {
+        FilteredDatabase { inner: self, filter }
+    }
+}
+
+impl<M> FilteredDatabase<'_, M> {
+    /// Returns the filter of the database.
+    pub fn filter(&self) -> &IdFilter {
+        self.filter
+    }
+}
+
+impl FilteredDatabase<'_, Block> {
+    /// The block (non-streaming) regular expression scanner, see `DatabaseRef::<Block>::scan`.
+    pub fn scan<T, F>(&self, data: T, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
+    where
+        T: AsRef<[u8]>,
+        F: MatchEventHandler,
+    {
+        self.inner.scan(data, scratch, self.filter.filter(on_match_event))
+    }
+
+    /// Scan the data, and returns the matches of the enabled patterns, see `DatabaseRef::<Block>::scan_matches`.
+    pub fn scan_matches<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef) -> Result<Vec<Match>> {
+        let mut matches = Vec::new();
+
+        self.scan(data, scratch, Match::collect(&mut matches))?;
+
+        Ok(matches)
+    }
+
+    /// Scan the data, and returns the IDs of the enabled patterns which matched,
+    /// see `DatabaseRef::<Block>::which_match`.
+    pub fn which_match<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef) -> Result<MatchedIds> {
+        let mut ids = MatchedIds::new(DEFAULT_MAX_ID);
+
+        self.which_match_into(data, scratch, &mut ids)?;
+
+        Ok(ids)
+    }
+
+    /// Scan the data, and add the IDs of the enabled patterns which matched to `ids`,
+    /// see `DatabaseRef::<Block>::which_match_into`.
+    pub fn which_match_into<T: AsRef<[u8]>>(
+        &self,
+        data: T,
+        scratch: &mut ScratchRef,
+        ids: &mut MatchedIds,
+    ) -> Result<()> {
+        let data = data.as_ref();
+
+        ids.fill(|on_match| self.scan(data, scratch, |id, _, _, _| on_match(id)))
+    }
+
+    /// Returns an iterator over the matches of the enabled patterns, see `DatabaseRef::<Block>::scan_iter`.
+    ///
+    /// The filter should not be changed while iterating, the rescans of the data skip the matches
+    /// already returned by counting the matches of the enabled patterns.
+    pub fn scan_iter<'b, T: AsRef<[u8]>>(&'b self, data: T, scratch: &'b mut ScratchRef) -> ScanIter<'b, T> {
+        self.inner.scan_iter(data, scratch).with_filter(self.filter)
+    }
+}
+
+impl FilteredDatabase<'_, Vectored> {
+    /// The vectored regular expression scanner, see `DatabaseRef::<Vectored>::scan`.
+    pub fn scan<I, T, F>(&self, data: I, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+        F: MatchEventHandler,
+    {
+        self.inner.scan(data, scratch, self.filter.filter(on_match_event))
+    }
+
+    /// Scan the blocks of data, and returns the matches of the enabled patterns,
+    /// see `DatabaseRef::<Vectored>::scan_matches`.
+    pub fn scan_matches<I, T>(&self, data: I, scratch: &mut ScratchRef) -> Result<Vec<Match>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut matches = Vec::new();
+
+        self.scan(data, scratch, Match::collect(&mut matches))?;
+
+        Ok(matches)
+    }
+}
+
+#[cfg(feature = "compile")]
+impl From<&crate::compile::Patterns> for IdFilter {
+    /// Create a filter sized to the largest ID of the patterns.
+    fn from(patterns: &crate::compile::Patterns) -> IdFilter {
+        IdFilter::new(patterns.max_id().unwrap_or_default() as u32)
+    }
+}
+
+#[cfg(feature = "compile")]
+impl From<&crate::regex::RegexSet> for IdFilter {
+    /// Create a filter covering every regular expression of the set.
+    fn from(set: &crate::regex::RegexSet) -> IdFilter {
+        IdFilter::new(set.len().saturating_sub(1) as u32)
+    }
+}
+
+/// A match event handler which only receives the matches of the patterns enabled in an [`IdFilter`].
+pub struct Filtered<'a, F> {
+    filter: &'a IdFilter,
+    on_match_event: F,
+    callback: ffi::match_event_handler,
+    userdata: *mut libc::c_void,
+}
+
+impl<F> fmt::Debug for Filtered<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Filtered").field("filter", &self.filter).finish()
+    }
+}
+
+impl<F: MatchEventHandler> MatchEventHandler for Filtered<'_, F> {
+    unsafe fn split(&mut self) -> (ffi::match_event_handler, *mut libc::c_void) {
+        unsafe extern "C" fn trampoline<F>(
+            id: u32,
+            from: u64,
+            to: u64,
+            flags: u32,
+            ctx: *mut libc::c_void,
+        ) -> libc::c_int {
+            let filtered = &*ctx.cast::<Filtered<'_, F>>();
+
+            match filtered.callback {
+                Some(callback) if filtered.filter.is_enabled(id) => callback(id, from, to, flags, filtered.userdata),
+                _ => Matching::Continue as _,
+            }
+        }
+
+        let (callback, userdata) = self.on_match_event.split();
+
+        self.callback = callback;
+        self.userdata = userdata;
+
+        (Some(trampoline::<F>), self as *mut _ as *mut _)
+    }
+}
+
+#[cfg(all(test, feature = "compile"))]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    };
+    use std::thread;
+
+    use crate::prelude::*;
+
+    use super::*;
+
+    fn patterns() -> Patterns {
+        "1:/foo/\n2:/bar/\n100:/baz/".parse().unwrap()
+    }
+
+    #[test]
+    fn test_enable_disable() {
+        let filter = IdFilter::from(&patterns());
+
+        assert!(filter.is_enabled(1));
+
+        assert!(filter.disable(1));
+        assert!(filter.disable(100));
+        assert!(!filter.disable(1000));
+
+        assert!(!filter.is_enabled(1));
+        assert!(filter.is_enabled(2));
+        assert!(!filter.is_enabled(100));
+        assert!(filter.is_enabled(1000));
+
+        filter.enable(1);
+        filter.enable(1000);
+
+        assert!(filter.is_enabled(1));
+
+        filter.disable_all_except(vec![2, 1000]);
+
+        assert!(!filter.is_enabled(1));
+        assert!(filter.is_enabled(2));
+        assert!(!filter.is_enabled(100));
+        assert!(format!("{:?}", filter).contains("disabled: [0, 1, 3, 4,"));
+
+        filter.enable_all();
+
+        assert!(filter.is_enabled(100));
+    }
+
+    #[test]
+    fn test_toggle_between_scans() {
+        let patterns = patterns();
+        let filter = IdFilter::from(&patterns);
+        let mut matches = vec![];
+        let mut collect = |id, _, _, _| {
+            matches.push(id);
+            Matching::Continue
+        };
+
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        db.scan("foo bar baz", &mut s, filter.filter(&mut collect)).unwrap();
+        filter.disable(2);
+        db.scan("foo bar baz", &mut s, filter.filter(&mut collect)).unwrap();
+
+        let db: VectoredDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        db.scan(vec!["foo ba", "r baz"], &mut s, filter.filter(&mut collect))
+            .unwrap();
+
+        let db: StreamingDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        st.scan("foo ba", &mut s, filter.filter(&mut collect)).unwrap();
+        filter.enable(2);
+        filter.disable(1);
+        st.scan("r baz", &mut s, filter.filter(&mut collect)).unwrap();
+        st.close(&mut s, filter.filter(&mut collect)).unwrap();
+
+        assert_eq!(matches, vec![1, 2, 100, 1, 100, 1, 100, 1, 2, 100]);
+    }
+
+    #[test]
+    fn test_toggle_during_scan() {
+        let patterns = patterns();
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let filter = IdFilter::from(&patterns);
+        let mut matches = vec![];
+
+        // the first match of `foo` disables it for the rest of the scan
+        db.scan(
+            "foo foo bar foo",
+            &mut s,
+            filter.filter(|id, _, _, _| {
+                filter.disable(1);
+                matches.push(id);
+                Matching::Continue
+            }),
+        )
+        .unwrap();
+
+        assert_eq!(matches, vec![1, 2]);
+
+        // disable the pattern from another thread while scanning
+        let filter = Arc::new(IdFilter::from(&patterns));
+        let disabled = Arc::new(AtomicBool::new(false));
+        let after = Arc::new(AtomicUsize::new(0));
+
+        let scanner = thread::spawn({
+            let filter = filter.clone();
+            let disabled = disabled.clone();
+            let after = after.clone();
+            let data = "foo ".repeat(1000);
+
+            move || {
+                let mut s = db.alloc_scratch().unwrap();
+
+                while !disabled.load(Ordering::SeqCst) {
+                    db.scan(&data, &mut s, filter.filter(Matching::Continue)).unwrap();
+                }
+
+                for _ in 0..10 {
+                    db.scan(
+                        &data,
+                        &mut s,
+                        filter.filter(|_, _, _, _| {
+                            after.fetch_add(1, Ordering::SeqCst);
+                            Matching::Continue
+                        }),
+                    )
+                    .unwrap();
+                }
+            }
+        });
+
+        filter.disable(1);
+        disabled.store(true, Ordering::SeqCst);
+        scanner.join().unwrap();
+
+        assert_eq!(after.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_filtered_database() {
+        let patterns = patterns();
+        let filter = IdFilter::from(&patterns);
+        filter.disable(2);
+
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let db = db.with_filter(&filter);
+
+        let ids = |matches: Vec<Match>| matches.into_iter().map(|m| m.id).collect::<Vec<_>>();
+
+        assert_eq!(ids(db.scan_matches("foo bar baz", &mut s).unwrap()), vec![1, 100]);
+        assert_eq!(
+            db.which_match("foo bar baz", &mut s)
+                .unwrap()
+                .iter()
+                .collect::<Vec<_>>(),
+            vec![1, 100]
+        );
+        assert_eq!(
+            ids(db.scan_iter("foo bar baz", &mut s).collect::<Result<Vec<_>>>().unwrap()),
+            vec![1, 100]
+        );
+
+        let db: VectoredDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert_eq!(
+            ids(db
+                .with_filter(&filter)
+                .scan_matches(vec!["foo ba", "r baz"], &mut s)
+                .unwrap()),
+            vec![1, 100]
+        );
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_filtered_par_scan() {
+        use crate::ParScan;
+
+        let patterns = patterns();
+        let filter = IdFilter::from(&patterns);
+        filter.disable(100);
+
+        let db: BlockDatabase = patterns.build().unwrap();
+        let pool = ScratchPool::new(&db).unwrap();
+        let db = db.with_filter(&filter);
+        let docs = vec![&b"foo baz"[..], b"bar baz"];
+
+        let matches = db.par_scan_matches(docs, &pool).unwrap();
+
+        assert_eq!(
+            matches
+                .iter()
+                .map(|matches| matches.iter().map(|m| m.id).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            vec![vec![1], vec![2]]
+        );
+
+        let mut ids = vec![];
+
+        db.par_scan(b"foo bar baz", &pool, 3, |m| {
+            ids.push(m.id);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(ids, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_filtered_regex() {
+        use crate::regex::{bytes, Regex, RegexSet};
+
+        let filter = Arc::new(IdFilter::new(0));
+        let re = Regex::new(r"foo").unwrap().with_filter(filter.clone());
+        let bytes_re = bytes::Regex::new(r"foo").unwrap().with_filter(filter.clone());
+
+        assert!(re.is_match("foo"));
+        assert!(bytes_re.is_match(b"foo"));
+
+        filter.disable(0);
+
+        assert!(!re.is_match("foo"));
+        assert!(re.find("foo").is_none());
+        assert_eq!(re.find_iter("foo foo").count(), 0);
+        assert!(bytes_re.find(b"foo").is_none());
+        assert_eq!(bytes_re.find_iter(b"foo foo").count(), 0);
+
+        let set = RegexSet::new([r"foo", r"bar", r"baz"]).unwrap();
+        let filter = Arc::new(IdFilter::from(&set));
+        let set = set.with_filter(filter.clone());
+
+        filter.disable(1);
+
+        assert_eq!(set.matches("foo bar baz").into_iter().collect::<Vec<_>>(), vec![0, 2]);
+        assert!(!set.is_match("bar"));
+        assert!(!filter.disable(3));
+    }
+
+    #[test]
+    fn test_from_tagged_database() {
+        use crate::TaggedDatabase;
+
+        let db: TaggedDatabase<&str> = TaggedDatabase::build(vec![
+            (pattern! {10 => "foo"; CASELESS}, "low"),
+            (pattern! {20 => "bar"; CASELESS}, "high"),
+        ])
+        .unwrap();
+        let filter = IdFilter::from(&db);
+
+        assert!(filter.disable(20));
+        assert!(!filter.disable(21));
+    }
+}
@@ -0,0 +1,242 @@
+use core::fmt;
+
+use crate::{
+    common::{Block, DatabaseRef},
+    runtime::{bitset::BitSet, Matching, ScratchRef},
+    Result, ScanOutcome,
+};
+
+/// The largest pattern ID held by the set of `DatabaseRef::which_match` before scanning.
+pub(crate) const DEFAULT_MAX_ID: u32 = 1023;
+
+/// The set of the pattern IDs which matched at least once, returned by `DatabaseRef::which_match`.
+///
+/// The set is a bitset sized to the largest pattern ID, it never grows in the match handler:
+/// when a match has a larger ID, the set is grown after the scan and the data is scanned again,
+/// so a set created with `MatchedIds::new` or from the `Patterns` is filled in a single scan.
+/// The sets of the same IDs are equal and hash alike, whatever their sizes.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::prelude::*;
+/// let patterns: Patterns = "1:/foo/H\n2:/bar/H\n3:/baz/H".parse().unwrap();
+/// let db: BlockDatabase = patterns.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+///
+/// let ids = db.which_match("foo baz foo", &mut s).unwrap();
+///
+/// assert!(ids.contains(1) && !ids.contains(2));
+/// assert_eq!(ids.len(), 2);
+/// assert_eq!(ids.iter().collect::<Vec<_>>(), vec![1, 3]);
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+pub struct MatchedIds {
+    bits: BitSet,
+}
+
+impl fmt::Debug for MatchedIds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl MatchedIds {
+    /// Create an empty set for the pattern IDs up to `max_id`.
+    pub fn new(max_id: u32) -> MatchedIds {
+        MatchedIds {
+            bits: BitSet::with_capacity(max_id as usize + 1),
+        }
+    }
+
+    /// Returns `true` if the pattern matched.
+    pub fn contains(&self, id: u32) -> bool {
+        self.bits.contains(id as usize)
+    }
+
+    /// Returns the number of the patterns which matched.
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    /// Returns `true` if no pattern matched.
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    /// Returns an iterator over the IDs of the patterns which matched, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.bits.iter().map(|id| id as u32)
+    }
+
+    /// Remove all the IDs, keeping the size of the set.
+    pub fn clear(&mut self) {
+        self.bits.clear()
+    }
+
+    #[cfg(all(test, feature = "compile"))]
+    fn insert(&mut self, id: u32) {
+        self.bits.insert(id as usize);
+    }
+
+    /// Add the IDs reported by the scan, which is repeated after growing the set if an ID is beyond its size,
+    /// so the set is never reallocated in the match handler.
+    pub(crate) fn fill<F>(&mut self, mut scan: F) -> Result<()>
+    where
+        F: FnMut(&mut dyn FnMut(u32) -> Matching) -> Result<ScanOutcome>,
+    {
+        let mut overflow = None;
+
+        scan(&mut |id| {
+            if !self.bits.try_insert(id as usize) {
+                overflow = overflow.max(Some(id));
+            }
+
+            Matching::Continue
+        })?;
+
+        if let Some(max_id) = overflow {
+            self.bits.reserve(max_id as usize + 1);
+
+            scan(&mut |id| {
+                self.bits.try_insert(id as usize);
+
+                Matching::Continue
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compile")]
+impl From<&crate::compile::Patterns> for MatchedIds {
+    /// Create an empty set sized to the largest ID of the patterns.
+    fn from(patterns: &crate::compile::Patterns) -> MatchedIds {
+        MatchedIds::new(patterns.max_id().unwrap_or_default() as u32)
+    }
+}
+
+impl DatabaseRef<Block> {
+    /// Scan the data, and returns the IDs of the patterns which matched at least once.
+    ///
+    /// The flags of a compiled database can't be changed, so the patterns should be compiled with `SINGLEMATCH`,
+    /// as `RegexSet` does, for Hyperscan to report each pattern once, instead of each of its matches.
+    /// The scan isn't terminated early, since the number of the patterns isn't known from the database.
+    ///
+    /// The set holds the IDs up to 1023 before scanning, the data is scanned again if a larger ID matched,
+    /// use `which_match_into` with a set sized to the largest pattern ID to avoid it.
+    pub fn which_match<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef) -> Result<MatchedIds> {
+        let mut ids = MatchedIds::new(DEFAULT_MAX_ID);
+
+        self.which_match_into(data, scratch, &mut ids)?;
+
+        Ok(ids)
+    }
+
+    /// Scan the data, and add the IDs of the patterns which matched to `ids`.
+    ///
+    /// A set sized to the largest pattern ID, e.g. created from the `Patterns`, is reused without allocating.
+    /// A smaller set is grown after the scan, and the data is scanned again.
+    pub fn which_match_into<T: AsRef<[u8]>>(
+        &self,
+        data: T,
+        scratch: &mut ScratchRef,
+        ids: &mut MatchedIds,
+    ) -> Result<()> {
+        let data = data.as_ref();
+
+        ids.fill(|on_match| self.scan(data, scratch, |id, _, _, _| on_match(id)))
+    }
+}
+
+#[cfg(all(test, feature = "compile"))]
+mod tests {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_which_match() {
+        let patterns = (0..100)
+            .map(|id| format!("{}:/\\bword{}\\b/H", id * 3, id).parse().unwrap())
+            .collect::<Vec<Pattern>>();
+        let patterns = Patterns(patterns);
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let words = [4, 8, 15, 16, 23, 42, 99];
+        let data = words
+            .iter()
+            .map(|w| format!("word{} word{}", w, w))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let ids = db.which_match(&data, &mut s).unwrap();
+
+        assert_eq!(ids.len(), 7);
+        assert_eq!(
+            ids.iter().collect::<Vec<_>>(),
+            words.iter().map(|w| w * 3).collect::<Vec<_>>()
+        );
+        assert!(ids.contains(297) && !ids.contains(3) && !ids.contains(1000));
+
+        let mut ids = MatchedIds::from(&patterns);
+        let size = ids.bits.capacity();
+
+        db.which_match_into("word1 word2", &mut s, &mut ids).unwrap();
+
+        assert_eq!(ids.iter().collect::<Vec<_>>(), vec![3, 6]);
+
+        ids.clear();
+
+        assert!(ids.is_empty());
+        assert_eq!(ids.bits.capacity(), size);
+        assert!(db.which_match("nothing", &mut s).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_which_match_large_id() {
+        let patterns: Patterns = "1:/foo/H\n5000:/bar/H\n7000:/baz/H".parse().unwrap();
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        let ids = db.which_match("foo bar", &mut s).unwrap();
+
+        assert_eq!(ids.iter().collect::<Vec<_>>(), vec![1, 5000]);
+        assert!(ids.bits.capacity() > 5000);
+
+        let mut ids = MatchedIds::new(0);
+
+        db.which_match_into("baz bar foo", &mut s, &mut ids).unwrap();
+
+        assert_eq!(ids.iter().collect::<Vec<_>>(), vec![1, 5000, 7000]);
+        assert!(ids.bits.capacity() > 7000);
+    }
+
+    #[test]
+    fn test_eq_sizes() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        fn hash(ids: &MatchedIds) -> u64 {
+            let mut hasher = DefaultHasher::new();
+            ids.hash(&mut hasher);
+            hasher.finish()
+        }
+
+        let mut small = MatchedIds::default();
+        let mut large = MatchedIds::new(1000);
+
+        assert_eq!(small, large);
+        assert_eq!(hash(&small), hash(&large));
+
+        small.insert(3);
+        large.insert(3);
+
+        assert_eq!(small, large);
+        assert_eq!(hash(&small), hash(&large));
+
+        large.insert(999);
+
+        assert_ne!(small, large);
+    }
+}
@@ -1,11 +1,26 @@
-mod closure;
-#[cfg(feature = "pattern")]
+pub(crate) mod bitset;
+mod filter;
+mod matched;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(all(feature = "pattern", feature = "compile"))]
 mod pattern;
+#[cfg(feature = "std")]
+mod pool;
 mod scan;
 mod scratch;
 mod stream;
 
-pub use self::closure::split_closure;
-pub use self::scan::{MatchEventHandler, Matching};
-pub use self::scratch::{Scratch, ScratchRef};
+pub use self::filter::{Filtered, FilteredDatabase, IdFilter};
+pub use self::matched::MatchedIds;
+#[cfg(feature = "rayon")]
+pub use self::par::ParScan;
+#[cfg(feature = "std")]
+pub use self::pool::{PooledScratch, ScratchPool, StreamPool};
+pub use self::scan::{Match, MatchEventHandler, Matching, ScanIter};
+#[cfg(feature = "compile")]
+pub use self::scan::{ResolvedMatch, SomMatch};
+pub use self::scratch::{Scratch, ScratchRef, ScratchTarget};
+#[cfg(feature = "std")]
+pub use self::stream::StreamWriter;
 pub use self::stream::{Stream, StreamRef};
@@ -0,0 +1,462 @@
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::{
+    common::{Block, DatabaseRef},
+    runtime::{FilteredDatabase, Match, Matching, PooledScratch, ScratchPool},
+    Result, ScanOutcome,
+};
+
+/// The smallest chunk of a buffer scanned by a rayon job.
+const MIN_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Scan many documents in parallel with the rayon thread pool.
+///
+/// Each rayon job checks out a scratch space from the pool once, and reuses it for all the documents it scans.
+pub trait ParScan {
+    /// Scan the documents in parallel, and collect the matches of each document in the input order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, Match, ParScan};
+    /// # use rayon::prelude::*;
+    /// let db: BlockDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+    /// let pool = ScratchPool::new(&db).unwrap();
+    /// let docs = vec![&b"foo test"[..], b"bar", b"test"];
+    ///
+    /// let matches = db.par_scan_matches(docs.par_iter().copied(), &pool).unwrap();
+    ///
+    /// assert_eq!(
+    ///     matches,
+    ///     vec![
+    ///         vec![Match { id: 0, from: 4, to: 8 }],
+    ///         vec![],
+    ///         vec![Match { id: 0, from: 0, to: 4 }]
+    ///     ]
+    /// );
+    /// ```
+    fn par_scan_matches<'a, I>(&self, docs: I, pool: &ScratchPool) -> Result<Vec<Vec<Match>>>
+    where
+        I: IntoParallelIterator<Item = &'a [u8]>;
+
+    /// Scan the documents in parallel, and fold the matches without materializing them.
+    ///
+    /// The matches of each document are folded into a new value created by `identity`,
+    /// then the values of all the documents are combined with `reduce_op`, in no particular order.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, ParScan};
+    /// # use rayon::prelude::*;
+    /// let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+    /// let pool = ScratchPool::new(&db).unwrap();
+    /// let docs = vec![&b"test test"[..], b"bar", b"test"];
+    ///
+    /// let count = db
+    ///     .par_scan_fold(docs.par_iter().copied(), &pool, || 0, |n, _, _| n + 1, |a, b| a + b)
+    ///     .unwrap();
+    ///
+    /// assert_eq!(count, 3);
+    /// ```
+    fn par_scan_fold<'a, I, T, ID, F, R>(
+        &self,
+        docs: I,
+        pool: &ScratchPool,
+        identity: ID,
+        fold_op: F,
+        reduce_op: R,
+    ) -> Result<T>
+    where
+        I: IntoParallelIterator<Item = &'a [u8]>,
+        T: Send,
+        ID: Fn() -> T + Sync + Send,
+        F: Fn(T, &'a [u8], Match) -> T + Sync + Send,
+        R: Fn(T, T) -> T + Sync + Send;
+
+    /// Scan a large buffer in parallel, split into chunks scanned with the scratch spaces of the pool.
+    ///
+    /// Each chunk is scanned with the `overlap` bytes after it, so a match spanning the boundary of two chunks
+    /// is found when it's not longer than `overlap`, e.g. the `ExpressionInfo::max_width` of the patterns.
+    /// The match offsets are rebased to the buffer, and a match found by two chunks is reported once,
+    /// with its leftmost start. The matches are reported to `on_match` on the calling thread,
+    /// ordered by their end offset, ID and start offset, until `on_match` returns `Matching::Terminate`.
+    ///
+    /// The patterns are matched from the start of each chunk, so they shouldn't be anchored to the start of data,
+    /// nor assert the bytes before their start, such as `\b`. The start of match is only accurate with
+    /// `SOM_LEFTMOST`, and the matches of the `SINGLEMATCH` patterns are reported once per chunk.
+    ///
+    /// A chunk and its overlap are at most 4 GiB, so a buffer too large for a block scan
+    /// is split into several chunks even with a single thread.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, Match, ParScan};
+    /// let db: BlockDatabase = pattern! {"foobar"; SOM_LEFTMOST}.build().unwrap();
+    /// let pool = ScratchPool::new(&db).unwrap();
+    /// let data = "x".repeat(4 * 1024 * 1024) + "foobar";
+    /// let mut matches = vec![];
+    ///
+    /// db.par_scan(data.as_bytes(), &pool, 6, |m| {
+    ///     matches.push(m);
+    ///     Matching::Continue
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(matches, vec![Match { id: 0, from: 4 * 1024 * 1024, to: 4 * 1024 * 1024 + 6 }]);
+    /// ```
+    fn par_scan<F>(&self, data: &[u8], pool: &ScratchPool, overlap: usize, on_match: F) -> Result<ScanOutcome>
+    where
+        F: FnMut(Match) -> Matching;
+}
+
+impl ParScan for DatabaseRef<Block> {
+    fn par_scan_matches<'a, I>(&self, docs: I, pool: &ScratchPool) -> Result<Vec<Vec<Match>>>
+    where
+        I: IntoParallelIterator<Item = &'a [u8]>,
+    {
+        docs.into_par_iter()
+            .map_init(
+                || None,
+                |scratch, doc| {
+                    let scratch = checkout(scratch, pool)?;
+                    let mut matches = vec![];
+
+                    self.scan(doc, scratch, Match::collect(&mut matches)).map(|_| matches)
+                },
+            )
+            .collect()
+    }
+
+    fn par_scan_fold<'a, I, T, ID, F, R>(
+        &self,
+        docs: I,
+        pool: &ScratchPool,
+        identity: ID,
+        fold_op: F,
+        reduce_op: R,
+    ) -> Result<T>
+    where
+        I: IntoParallelIterator<Item = &'a [u8]>,
+        T: Send,
+        ID: Fn() -> T + Sync + Send,
+        F: Fn(T, &'a [u8], Match) -> T + Sync + Send,
+        R: Fn(T, T) -> T + Sync + Send,
+    {
+        docs.into_par_iter()
+            .map_init(
+                || None,
+                |scratch, doc| {
+                    let scratch = checkout(scratch, pool)?;
+                    let mut acc = Some(identity());
+
+                    self.scan(doc, scratch, |id, from, to, _| {
+                        acc = acc.take().map(|acc| fold_op(acc, doc, Match { id, from, to }));
+
+                        Matching::Continue
+                    })
+                    .map(|_| acc.unwrap())
+                },
+            )
+            .try_reduce(&identity, |a, b| Ok(reduce_op(a, b)))
+    }
+
+    fn par_scan<F>(&self, data: &[u8], pool: &ScratchPool, overlap: usize, on_match: F) -> Result<ScanOutcome>
+    where
+        F: FnMut(Match) -> Matching,
+    {
+        let chunk_size = chunk_size(data.len(), rayon::current_num_threads(), overlap);
+
+        par_scan_chunks(self, data, pool, chunk_size, overlap, on_match)
+    }
+}
+
+/// The matches of the disabled patterns are dropped before they are collected, folded or reported.
+impl ParScan for FilteredDatabase<'_, Block> {
+    fn par_scan_matches<'a, I>(&self, docs: I, pool: &ScratchPool) -> Result<Vec<Vec<Match>>>
+    where
+        I: IntoParallelIterator<Item = &'a [u8]>,
+    {
+        let mut matches = self.inner.par_scan_matches(docs, pool)?;
+
+        for doc in &mut matches {
+            doc.retain(|m| self.filter.is_enabled(m.id));
+        }
+
+        Ok(matches)
+    }
+
+    fn par_scan_fold<'a, I, T, ID, F, R>(
+        &self,
+        docs: I,
+        pool: &ScratchPool,
+        identity: ID,
+        fold_op: F,
+        reduce_op: R,
+    ) -> Result<T>
+    where
+        I: IntoParallelIterator<Item = &'a [u8]>,
+        T: Send,
+        ID: Fn() -> T + Sync + Send,
+        F: Fn(T, &'a [u8], Match) -> T + Sync + Send,
+        R: Fn(T, T) -> T + Sync + Send,
+    {
+        let filter = self.filter;
+
+        self.inner.par_scan_fold(
+            docs,
+            pool,
+            identity,
+            |acc, doc, m| {
+                if filter.is_enabled(m.id) {
+                    fold_op(acc, doc, m)
+                } else {
+                    acc
+                }
+            },
+            reduce_op,
+        )
+    }
+
+    fn par_scan<F>(&self, data: &[u8], pool: &ScratchPool, overlap: usize, mut on_match: F) -> Result<ScanOutcome>
+    where
+        F: FnMut(Match) -> Matching,
+    {
+        self.inner.par_scan(data, pool, overlap, |m| {
+            if self.filter.is_enabled(m.id) {
+                on_match(m)
+            } else {
+                Matching::Continue
+            }
+        })
+    }
+}
+
+/// Check out the scratch space of a rayon job on its first item, a failure is returned by the item and retried by the next one.
+fn checkout<'a, 'p>(
+    scratch: &'a mut Option<PooledScratch<'p>>,
+    pool: &'p ScratchPool,
+) -> Result<&'a mut PooledScratch<'p>> {
+    if scratch.is_none() {
+        *scratch = Some(pool.get()?);
+    }
+
+    Ok(scratch.as_mut().unwrap())
+}
+
+/// Split the buffer between the threads, a chunk and its overlap are scanned in a call, so at most 4 GiB.
+fn chunk_size(len: usize, threads: usize, overlap: usize) -> usize {
+    let limit = (u32::MAX as usize).saturating_sub(overlap).max(1);
+
+    len.div_ceil(threads.max(1)).max(MIN_CHUNK_SIZE).min(limit)
+}
+
+fn par_scan_chunks<F>(
+    db: &DatabaseRef<Block>,
+    data: &[u8],
+    pool: &ScratchPool,
+    chunk_size: usize,
+    overlap: usize,
+    mut on_match: F,
+) -> Result<ScanOutcome>
+where
+    F: FnMut(Match) -> Matching,
+{
+    let chunks = (0..data.len().max(1)).step_by(chunk_size.max(1)).collect::<Vec<_>>();
+    let mut matches = chunks
+        .into_par_iter()
+        .map_init(
+            || None,
+            |scratch, start| {
+                let scratch = checkout(scratch, pool)?;
+                let end = data.len().min(start.saturating_add(chunk_size).saturating_add(overlap));
+                let mut matches = vec![];
+
+                db.scan(&data[start..end], scratch, |id, from, to, _| {
+                    matches.push(Match {
+                        id,
+                        from: from + start as u64,
+                        to: to + start as u64,
+                    });
+
+                    Matching::Continue
+                })
+                .map(|_| matches)
+            },
+        )
+        .collect::<Result<Vec<_>>>()?
+        .concat();
+
+    // the matches ending in the overlap are also found by the next chunk, with a later start
+    matches.sort_unstable_by_key(|m| (m.to, m.id, m.from));
+    matches.dedup_by_key(|m| (m.to, m.id));
+
+    for m in matches {
+        if on_match(m) == Matching::Terminate {
+            return Ok(ScanOutcome::Terminated);
+        }
+    }
+
+    Ok(ScanOutcome::Completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{Rng, SeedableRng};
+    use rayon::prelude::*;
+
+    use super::*;
+    use crate::prelude::*;
+    use crate::{Match, ParScan};
+
+    #[test]
+    fn test_par_scan_matches_ordering() {
+        let db: BlockDatabase = pattern! {"\\d+"; SOM_LEFTMOST}.build().unwrap();
+        let pool = ScratchPool::new(&db).unwrap();
+        let docs = (0..10_000).map(|i| format!("doc {}", i)).collect::<Vec<_>>();
+
+        let expected = docs
+            .iter()
+            .map(|doc| {
+                let mut s = pool.get().unwrap();
+                let mut matches = vec![];
+
+                db.scan(doc, &mut s, Match::collect(&mut matches)).unwrap();
+
+                matches
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..4 {
+            let matches = db
+                .par_scan_matches(docs.par_iter().map(|doc| doc.as_bytes()), &pool)
+                .unwrap();
+
+            assert_eq!(matches, expected);
+        }
+
+        assert!(pool.idle() > 0);
+    }
+
+    #[test]
+    fn test_par_scan_fold() {
+        let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+        let pool = ScratchPool::new(&db).unwrap();
+        let docs = vec![b"test".to_vec(); 1_000];
+
+        let count = db
+            .par_scan_fold(
+                docs.par_iter().map(|doc| doc.as_slice()),
+                &pool,
+                || 0,
+                |n, doc, m| {
+                    assert_eq!(m.to as usize, doc.len());
+
+                    n + 1
+                },
+                |a, b| a + b,
+            )
+            .unwrap();
+
+        assert_eq!(count, docs.len());
+    }
+
+    #[test]
+    fn test_par_scan_chunk_boundaries() {
+        let patterns: Patterns = "1:/foobar/L\n2:/ab+a/L\n3:/b{2}/L".parse().unwrap();
+        let db: BlockDatabase = patterns.build().unwrap();
+        let pool = ScratchPool::new(&db).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(301);
+        let chunk_size = 1000;
+        let mut data = (0..100 * chunk_size)
+            .map(|_| rng.gen_range(b'a'..=b'c'))
+            .collect::<Vec<_>>();
+
+        // place the matches across, before and after each boundary
+        for (i, boundary) in (chunk_size..data.len()).step_by(chunk_size).enumerate() {
+            let at = boundary - 3 + i % 7;
+
+            data[at..at + 6].copy_from_slice(b"foobar");
+        }
+
+        let mut expected = db.scan_matches(&data, &mut pool.get().unwrap()).unwrap();
+
+        expected.sort_unstable_by_key(|m| (m.to, m.id, m.from));
+
+        assert!(expected.iter().filter(|m| m.id == 1).count() >= 99);
+
+        for overlap in [6, 64] {
+            let mut matches = vec![];
+
+            par_scan_chunks(&db, &data, &pool, chunk_size, overlap, |m| {
+                matches.push(m);
+                Matching::Continue
+            })
+            .unwrap();
+
+            assert_eq!(
+                matches.iter().filter(|m| m.id == 1).collect::<Vec<_>>(),
+                expected.iter().filter(|m| m.id == 1).collect::<Vec<_>>()
+            );
+        }
+
+        // the matches of `ab+a` may be longer than the overlap
+        let mut matches = vec![];
+
+        par_scan_chunks(&db, &data, &pool, chunk_size, data.len(), |m| {
+            matches.push(m);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, expected);
+
+        let mut matches = vec![];
+
+        db.par_scan(&data, &pool, 64, |m| {
+            matches.push(m);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, expected);
+    }
+
+    #[test]
+    fn test_par_scan_terminated() {
+        let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+        let pool = ScratchPool::new(&db).unwrap();
+        let mut ends = vec![];
+
+        let terminated = par_scan_chunks(&db, b"foo foo foo", &pool, 4, 3, |m| {
+            ends.push(m.to);
+            Matching::Terminate
+        })
+        .unwrap()
+        .is_terminated();
+
+        assert!(terminated);
+        assert_eq!(ends, vec![3]);
+        assert_eq!(
+            par_scan_chunks(&db, b"", &pool, 4, 3, |_| Matching::Terminate).unwrap(),
+            ScanOutcome::Completed
+        );
+    }
+
+    #[test]
+    fn test_par_scan_chunk_size() {
+        assert_eq!(chunk_size(100, 8, 16), MIN_CHUNK_SIZE);
+        assert_eq!(chunk_size(64 * MIN_CHUNK_SIZE, 8, 16), 8 * MIN_CHUNK_SIZE);
+
+        // a chunk and its overlap fit in a block scan
+        if usize::BITS > 32 {
+            const GIB: usize = 1 << 30;
+
+            assert_eq!(chunk_size(16 * GIB, 1, 1024), u32::MAX as usize - 1024);
+            assert_eq!(chunk_size(16 * GIB, 2, 0), u32::MAX as usize);
+            assert_eq!(chunk_size(16 * GIB, 8, 1024), 2 * GIB);
+            assert_eq!(chunk_size(16 * GIB, 1, usize::MAX), 1);
+        }
+    }
+}
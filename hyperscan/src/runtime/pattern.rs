@@ -10,10 +10,10 @@ impl<'a> pattern::Pattern<'a> for compile::Pattern {
     fn into_searcher(mut self, haystack: &'a str) -> Self::Searcher {
         self.flags |= Flags::SOM_LEFTMOST;
         let db: BlockDatabase = self.build().expect("build database");
-        let scratch = db.alloc_scratch().expect("alloc scratch");
+        let mut scratch = db.alloc_scratch().expect("alloc scratch");
         let mut matches = Vec::new();
 
-        db.scan(haystack, &scratch, |_, from, to, _| {
+        db.scan(haystack, &mut scratch, |_, from, to, _| {
             let from = from as usize;
             let to = to as usize;
 
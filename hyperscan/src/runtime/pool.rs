@@ -0,0 +1,366 @@
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+
+use crate::{
+    common::{DatabaseRef, Streaming},
+    runtime::{MatchEventHandler, Scratch, ScratchRef, ScratchTarget, Stream},
+    Result,
+};
+
+/// A pool of scratch spaces shared by the concurrent callers of a database.
+///
+/// The scratch spaces are cloned from a prototype allocated for the database,
+/// and returned to the pool once the caller has finished scanning with them.
+///
+/// The pool is `Sync`, it may be shared in an `Arc` by the threads of a worker pool,
+/// each concurrent caller scans with its own scratch space.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::prelude::*;
+/// let db: BlockDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+/// let pool = ScratchPool::new(&db).unwrap();
+/// let mut matches = vec![];
+///
+/// db.scan("foo test bar", &mut pool.get().unwrap(), |_, from, to, _| {
+///     matches.push(from..to);
+///     Matching::Continue
+/// })
+/// .unwrap();
+///
+/// assert_eq!(matches, vec![4..8]);
+/// assert_eq!(pool.idle(), 1);
+/// ```
+pub struct ScratchPool {
+    prototype: Mutex<Scratch>,
+    free: Mutex<Vec<Scratch>>,
+}
+
+impl fmt::Debug for ScratchPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScratchPool").field("idle", &self.idle()).finish()
+    }
+}
+
+impl ScratchPool {
+    /// Create a new pool of scratch spaces for the database.
+    pub fn new<T>(db: &DatabaseRef<T>) -> Result<ScratchPool> {
+        Ok(ScratchPool {
+            prototype: Mutex::new(db.alloc_scratch()?),
+            free: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Create a new pool of scratch spaces large enough for all the databases, whatever their modes.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let block: BlockDatabase = pattern! {"foo"}.build().unwrap();
+    /// let streaming: StreamingDatabase = pattern! {"bar"}.build().unwrap();
+    /// let pool = ScratchPool::for_databases(&[&block, &streaming]).unwrap();
+    ///
+    /// pool.with(|s| block.scan("foo", s, |_, _, _, _| Matching::Continue)).unwrap();
+    /// pool.with(|s| streaming.open_stream()?.scan("bar", s, |_, _, _, _| Matching::Continue))
+    ///     .unwrap();
+    /// ```
+    pub fn for_databases(dbs: &[&dyn ScratchTarget]) -> Result<ScratchPool> {
+        Ok(ScratchPool {
+            prototype: Mutex::new(Scratch::for_databases(dbs)?),
+            free: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Call the closure with a scratch space checked out from the pool.
+    ///
+    /// The scratch space is returned to the pool after the call, so it is reused by the next caller.
+    ///
+    /// Returns an error without calling the closure if a new scratch space can't be cloned from the prototype.
+    pub fn with<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut ScratchRef) -> Result<R>,
+    {
+        let mut scratch = self.get()?;
+
+        f(&mut scratch)
+    }
+
+    /// Returns the number of scratch spaces waiting in the pool.
+    pub fn idle(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+
+    /// Check out a scratch space, which is returned to the pool when the guard is dropped.
+    pub fn get(&self) -> Result<PooledScratch<'_>> {
+        Ok(PooledScratch {
+            pool: self,
+            scratch: Some(self.take()?),
+        })
+    }
+
+    /// Check out an owned scratch space, which should be given back with `put`.
+    ///
+    /// A new scratch space is cloned from the prototype if the pool is empty.
+    pub fn take(&self) -> Result<Scratch> {
+        let scratch = self.free.lock().unwrap().pop();
+
+        match scratch {
+            Some(scratch) => Ok(scratch),
+            None => self.prototype.lock().unwrap().try_clone(),
+        }
+    }
+
+    /// Give back a scratch space to the pool.
+    pub fn put(&self, scratch: Scratch) {
+        self.free.lock().unwrap().push(scratch)
+    }
+}
+
+/// A scratch space checked out from a `ScratchPool`.
+pub struct PooledScratch<'a> {
+    pool: &'a ScratchPool,
+    scratch: Option<Scratch>,
+}
+
+impl fmt::Debug for PooledScratch<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledScratch").finish()
+    }
+}
+
+impl Deref for PooledScratch<'_> {
+    type Target = Scratch;
+
+    fn deref(&self) -> &Scratch {
+        self.scratch.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledScratch<'_> {
+    fn deref_mut(&mut self) -> &mut Scratch {
+        self.scratch.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledScratch<'_> {
+    fn drop(&mut self) {
+        if let Some(scratch) = self.scratch.take() {
+            self.pool.put(scratch)
+        }
+    }
+}
+
+/// A pool of streams opened against a streaming database, reused for the new flows instead of reallocated.
+///
+/// The streams given back to the pool are reset, so no match leaks from a flow to the next one.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, StreamPool};
+/// let db: StreamingDatabase = pattern! {"foobar"; SOM_LEFTMOST}.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let pool = StreamPool::new(&db);
+/// let mut matches = vec![];
+///
+/// for flow in &[["foo", "bar"], ["bar", "foo"]] {
+///     let st = pool.take().unwrap();
+///
+///     for chunk in flow {
+///         st.scan(chunk, &mut s, |_, from, to, _| {
+///             matches.push(from..to);
+///             Matching::Continue
+///         })
+///         .unwrap();
+///     }
+///
+///     pool.put(st, &mut s, Matching::Continue).unwrap();
+/// }
+///
+/// assert_eq!(matches, vec![0..6]);
+/// assert_eq!(pool.idle(), 1);
+/// ```
+pub struct StreamPool<'db> {
+    db: &'db DatabaseRef<Streaming>,
+    free: Mutex<Vec<Stream<'db>>>,
+}
+
+impl fmt::Debug for StreamPool<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamPool").field("idle", &self.idle()).finish()
+    }
+}
+
+impl<'db> StreamPool<'db> {
+    /// Create a new pool of streams for the streaming database.
+    pub fn new(db: &'db DatabaseRef<Streaming>) -> StreamPool<'db> {
+        StreamPool {
+            db,
+            free: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Returns the number of streams waiting in the pool.
+    pub fn idle(&self) -> usize {
+        self.free.lock().unwrap().len()
+    }
+
+    /// Check out a stream, which should be given back with `put`.
+    ///
+    /// A new stream is opened if the pool is empty.
+    pub fn take(&self) -> Result<Stream<'db>> {
+        let stream = self.free.lock().unwrap().pop();
+
+        match stream {
+            Some(stream) => Ok(stream),
+            None => self.db.open_stream(),
+        }
+    }
+
+    /// Give back a stream to the pool, once reset.
+    ///
+    /// The matches at the end of data are reported to the match event handler, or discarded with `()`.
+    pub fn put<F>(&self, stream: Stream<'db>, scratch: &mut ScratchRef, on_match_event: F) -> Result<()>
+    where
+        F: MatchEventHandler,
+    {
+        stream.reset(scratch, on_match_event)?;
+
+        self.free.lock().unwrap().push(stream);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::thread;
+
+    use foreign_types::ForeignType;
+
+    use super::StreamPool;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_scratch_pool() {
+        let db: BlockDatabase = "test".parse().unwrap();
+        let pool = ScratchPool::new(&db).unwrap();
+
+        assert_eq!(pool.idle(), 0);
+
+        {
+            let s = pool.get().unwrap();
+            let s2 = pool.get().unwrap();
+
+            assert_ne!(s.as_ptr(), s2.as_ptr());
+            assert_eq!(pool.idle(), 0);
+        }
+
+        assert_eq!(pool.idle(), 2);
+
+        let s = pool.take().unwrap();
+
+        assert_eq!(pool.idle(), 1);
+
+        pool.put(s);
+
+        assert_eq!(pool.idle(), 2);
+    }
+
+    #[test]
+    fn test_scratch_pool_threads() {
+        const THREADS: usize = 16;
+
+        fn assert_sync<T: Send + Sync>() {}
+
+        assert_sync::<ScratchPool>();
+
+        let block: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let vectored: VectoredDatabase = pattern! {"ba+r"; SOM_LEFTMOST}.build().unwrap();
+        let pool = Arc::new(ScratchPool::for_databases(&[&block, &vectored]).unwrap());
+        let (block, vectored) = (Arc::new(block), Arc::new(vectored));
+
+        let handles = (0..THREADS)
+            .map(|_| {
+                let (pool, block, vectored) = (pool.clone(), block.clone(), vectored.clone());
+
+                thread::spawn(move || {
+                    let mut matches = 0;
+
+                    for _ in 0..200 {
+                        pool.with(|s| {
+                            block.scan("foo", s, |_, _, _, _| {
+                                matches += 1;
+                                Matching::Continue
+                            })
+                        })
+                        .unwrap();
+                        pool.with(|s| {
+                            vectored.scan(["b", "aar"], s, |_, _, _, _| {
+                                matches += 1;
+                                Matching::Continue
+                            })
+                        })
+                        .unwrap();
+                    }
+
+                    matches
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 200 * 3);
+        }
+
+        assert!(pool.idle() <= THREADS);
+    }
+
+    #[test]
+    fn test_stream_pool() {
+        let db: StreamingDatabase = pattern! {"foo$"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let pool = StreamPool::new(&db);
+        let mut matches = vec![];
+
+        let st = pool.take().unwrap();
+        let ptr = st.as_ptr();
+
+        st.scan("xfoo", &mut s, Matching::Continue).unwrap();
+
+        // the match at the end of data is reported when the stream is given back
+        pool.put(st, &mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![1..4]);
+        assert_eq!(pool.idle(), 1);
+
+        let st = pool.take().unwrap();
+
+        assert_eq!(st.as_ptr(), ptr);
+        assert_eq!(pool.idle(), 0);
+
+        // no stale match leaks from the previous flow
+        st.scan("fo", &mut s, Matching::Continue).unwrap();
+        pool.put(st, &mut s, ()).unwrap();
+
+        let st = pool.take().unwrap();
+
+        st.scan("o", &mut s, Matching::Continue).unwrap();
+        st.close(&mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![1..4]);
+        assert_eq!(pool.idle(), 0);
+    }
+}
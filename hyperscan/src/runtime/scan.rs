@@ -1,18 +1,30 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::iter::FusedIterator;
+use core::ptr;
+use core::time::Duration;
+#[cfg(feature = "std")]
 use std::io::Read;
-use std::mem;
-use std::ptr;
+#[cfg(feature = "std")]
+use std::time::Instant;
 
 use foreign_types::ForeignTypeRef;
 use libc::{c_char, c_uint};
 
 use crate::{
-    common::{Block, DatabaseRef, Streaming, Vectored},
+    common::{Block, DatabaseRef, Vectored},
     error::AsResult,
     ffi,
-    runtime::{split_closure, ScratchRef, StreamRef},
-    Result,
+    metrics::{self, MetricsSink, ScanStatus},
+    runtime::{IdFilter, ScratchRef, StreamRef},
+    unwind::{catch, guarded, resume},
+    Error, Result, ScanOutcome,
 };
 
+#[cfg(feature = "std")]
+use crate::common::Streaming;
+
 #[cfg(feature = "async")]
 use futures::io::{AsyncRead, AsyncReadExt};
 
@@ -32,6 +44,268 @@ impl Default for Matching {
     }
 }
 
+/// A match reported by Hyperscan, collected for scanning away from the caller.
+///
+/// The offsets are byte offsets, also for a `&str` scanned with the `UTF8` patterns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Match {
+    /// The ID number of the expression that matched.
+    pub id: u32,
+    /// The offset of the first byte that matches the expression, only accurate with `SOM_LEFTMOST`.
+    pub from: u64,
+    /// The offset after the last byte that matches the expression.
+    ///
+    /// The offsets of a vectored scan are in the concatenated blocks, and of a stream since it was opened.
+    pub to: u64,
+}
+
+impl Match {
+    /// Collect the match into `matches`, and continue matching.
+    pub(crate) fn collect(matches: &mut Vec<Match>) -> impl FnMut(u32, u64, u64, u32) -> Matching + '_ {
+        move |id, from, to, _| {
+            matches.push(Match { id, from, to });
+
+            Matching::Continue
+        }
+    }
+
+    /// Returns the matched text of the scanned string.
+    ///
+    /// Returns `None` when an offset isn't on a character boundary, e.g. the match of a pattern compiled
+    /// without `UTF8` may end in the middle of a character, or when the match is beyond the text.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, Match};
+    /// let db: BlockDatabase = pattern! {"é+"; UTF8 | SOM_LEFTMOST}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let text = String::from("café");
+    ///
+    /// let m = db.find(&text, &mut s).unwrap().unwrap();
+    ///
+    /// assert_eq!(m, Match { id: 0, from: 3, to: 5 });
+    /// assert_eq!(m.text(&text), Some("é"));
+    /// assert_eq!(Match { id: 0, from: 4, to: 5 }.text(&text), None);
+    /// ```
+    pub fn text<'t>(&self, text: &'t str) -> Option<&'t str> {
+        text.get(usize::try_from(self.from).ok()?..usize::try_from(self.to).ok()?)
+    }
+}
+
+#[cfg(feature = "compile")]
+impl Match {
+    /// Resolve the match with the expression of the pattern which has its ID,
+    /// the patterns must be the ones compiled into the database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, Match};
+    /// let patterns: Patterns = "1:/foo/\n2:/bar/".parse().unwrap();
+    /// let db: BlockDatabase = patterns.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let mut matches = vec![];
+    ///
+    /// db.scan("foobar", &mut s, |id, from, to, _| {
+    ///     matches.push(Match { id, from, to }.resolve(&patterns).unwrap());
+    ///     Matching::Continue
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(matches[1].pattern, "bar");
+    /// ```
+    pub fn resolve(&self, patterns: &crate::compile::Patterns) -> Option<ResolvedMatch> {
+        patterns
+            .ids()
+            .zip(patterns.iter())
+            .find(|&(id, _)| id as u32 == self.id)
+            .map(|(_, pattern)| ResolvedMatch {
+                id: self.id,
+                from: self.from,
+                to: self.to,
+                pattern: pattern.expression.clone(),
+            })
+    }
+}
+
+/// A match with the expression of the pattern which matched.
+#[cfg(feature = "compile")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ResolvedMatch {
+    /// The ID number of the expression that matched.
+    pub id: u32,
+    /// The offset of the first byte that matches the expression, only accurate with `SOM_LEFTMOST`.
+    pub from: u64,
+    /// The offset after the last byte that matches the expression.
+    pub to: u64,
+    /// The expression of the pattern.
+    pub pattern: alloc::string::String,
+}
+
+/// The number of matches buffered by the first batch of `ScanIter`, the next batches double it.
+const SCAN_ITER_BATCH: usize = 256;
+
+/// An iterator over the matches of a block scan, created by `DatabaseRef::scan_iter`.
+pub struct ScanIter<'a, T> {
+    db: &'a DatabaseRef<Block>,
+    filter: Option<&'a IdFilter>,
+    data: T,
+    scratch: &'a mut ScratchRef,
+    matches: VecDeque<Match>,
+    batch: usize,
+    returned: usize,
+    done: bool,
+}
+
+impl<'a, T> ScanIter<'a, T> {
+    /// Only return the matches of the patterns enabled in the filter.
+    pub(crate) fn with_filter(self, filter: &'a IdFilter) -> Self {
+        ScanIter {
+            filter: Some(filter),
+            ..self
+        }
+    }
+}
+
+impl<T: AsRef<[u8]>> ScanIter<'_, T> {
+    /// Rescan the data, and buffer the next batch of matches.
+    fn scan_batch(&mut self) -> Result<()> {
+        let matches = &mut self.matches;
+        let filter = self.filter;
+        let (skip, batch) = (self.returned, self.batch);
+        let mut reported = 0;
+
+        // only the first scan is reported to the metrics sink, the rescans report the same data and matches again
+        let sink = if skip == 0 { metrics::global_sink() } else { None };
+
+        let outcome = self.db.scan_metered(
+            self.data.as_ref(),
+            self.scratch,
+            |id, from, to, _| {
+                if filter.is_some_and(|filter| !filter.is_enabled(id)) {
+                    return Matching::Continue;
+                }
+
+                reported += 1;
+
+                if reported > skip {
+                    matches.push_back(Match { id, from, to });
+                }
+
+                if matches.len() < batch {
+                    Matching::Continue
+                } else {
+                    Matching::Terminate
+                }
+            },
+            sink,
+        )?;
+
+        // the scan is only terminated by a full batch, otherwise all the matches are buffered
+        self.done = !outcome.is_terminated();
+        self.returned += self.matches.len();
+        self.batch = batch.saturating_mul(2);
+
+        Ok(())
+    }
+}
+
+impl<T: AsRef<[u8]>> Iterator for ScanIter<'_, T> {
+    type Item = Result<Match>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.matches.is_empty() && !self.done {
+            if let Err(err) = self.scan_batch() {
+                self.done = true;
+
+                return Some(Err(err));
+            }
+        }
+
+        self.matches.pop_front().map(Ok)
+    }
+}
+
+impl<T: AsRef<[u8]>> FusedIterator for ScanIter<'_, T> {}
+
+/// A match with the start of match offset, only when the pattern which matched reports it.
+///
+/// Hyperscan reports the start of match offset of the patterns compiled with `SOM_LEFTMOST`,
+/// the `Match::from` of the other patterns is `0`. The patterns are looked up by their ID,
+/// and must be the ones compiled into the database.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, SomMatch};
+/// let patterns: Patterns = "1:/foo/L\n2:/bar/".parse().unwrap();
+/// let db: BlockDatabase = patterns.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+///
+/// assert_eq!(
+///     db.scan_som_matches("foobar", &mut s, &patterns).unwrap(),
+///     vec![
+///         SomMatch { id: 1, start: Some(0), end: 3 },
+///         SomMatch { id: 2, start: None, end: 6 }
+///     ]
+/// );
+/// ```
+#[cfg(feature = "compile")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct SomMatch {
+    /// The ID number of the expression that matched.
+    pub id: u32,
+    /// The offset of the first byte that matches the expression, with `SOM_LEFTMOST`.
+    pub start: Option<u64>,
+    /// The offset after the last byte that matches the expression.
+    pub end: u64,
+}
+
+#[cfg(feature = "compile")]
+impl Match {
+    /// Returns the match with its start offset when the pattern which has its ID reports it,
+    /// the patterns must be the ones compiled into the database.
+    pub fn with_som(&self, patterns: &crate::compile::Patterns) -> Option<SomMatch> {
+        patterns
+            .ids()
+            .zip(patterns.iter())
+            .find(|&(id, _)| id as u32 == self.id)
+            .map(|(_, pattern)| self.som(pattern.flags.contains(crate::compile::Flags::SOM_LEFTMOST)))
+    }
+
+    fn som(&self, som: bool) -> SomMatch {
+        SomMatch {
+            id: self.id,
+            start: if som { Some(self.from) } else { None },
+            end: self.to,
+        }
+    }
+}
+
+/// Collect the matches with the start offset of the patterns compiled with `SOM_LEFTMOST`.
+#[cfg(feature = "compile")]
+fn collect_som<'a>(
+    matches: &'a mut Vec<SomMatch>,
+    patterns: &crate::compile::Patterns,
+) -> impl FnMut(u32, u64, u64, u32) -> Matching + 'a {
+    let som = patterns
+        .ids()
+        .zip(patterns.iter())
+        .filter(|(_, pattern)| pattern.flags.contains(crate::compile::Flags::SOM_LEFTMOST))
+        .map(|(id, _)| id as u32)
+        .collect::<alloc::collections::BTreeSet<_>>();
+
+    move |id, from, to, _| {
+        matches.push(Match { id, from, to }.som(som.contains(&id)));
+
+        Matching::Continue
+    }
+}
+
 /// Definition of the match event callback function type.
 ///
 /// A callback function matching the defined type must be provided by the
@@ -77,9 +351,25 @@ impl MatchEventHandler for Matching {
     }
 }
 
-impl MatchEventHandler for (ffi::match_event_handler, *mut libc::c_void) {
+/// A match event handler split once, and passed to the successive scans of a stream.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy)]
+pub(crate) struct SplitHandler(ffi::match_event_handler, *mut libc::c_void);
+
+#[cfg(feature = "std")]
+impl SplitHandler {
+    /// Split the match event handler, which must outlive the scans.
+    pub(crate) unsafe fn of<F: MatchEventHandler>(on_match_event: &mut F) -> Self {
+        let (callback, userdata) = on_match_event.split();
+
+        SplitHandler(callback, userdata)
+    }
+}
+
+#[cfg(feature = "std")]
+impl MatchEventHandler for SplitHandler {
     unsafe fn split(&mut self) -> (ffi::match_event_handler, *mut libc::c_void) {
-        *self
+        (self.0, self.1)
     }
 }
 
@@ -87,13 +377,109 @@ impl<F> MatchEventHandler for F
 where
     F: FnMut(u32, u64, u64, u32) -> Matching,
 {
+    /// The closure is called with `catch_unwind`, a panic terminates the scan and is resumed after it returns.
     unsafe fn split(&mut self) -> (ffi::match_event_handler, *mut libc::c_void) {
-        let (callback, userdata) = split_closure(self);
+        unsafe extern "C" fn trampoline<F>(
+            id: u32,
+            from: u64,
+            to: u64,
+            flags: u32,
+            ctx: *mut libc::c_void,
+        ) -> libc::c_int
+        where
+            F: FnMut(u32, u64, u64, u32) -> Matching,
+        {
+            let callback = &mut *ctx.cast::<F>();
 
-        (Some(mem::transmute(callback)), userdata)
+            catch(|| callback(id, from, to, flags)).unwrap_or(Matching::Terminate) as _
+        }
+
+        (Some(trampoline::<F>), self as *mut _ as *mut _)
+    }
+}
+
+/// Count the matches reported to the wrapped match event handler.
+pub(crate) struct Counted {
+    callback: ffi::match_event_handler,
+    userdata: *mut libc::c_void,
+    pub matches: usize,
+}
+
+impl Counted {
+    pub unsafe fn new<F: MatchEventHandler>(on_match_event: &mut F) -> Counted {
+        let (callback, userdata) = on_match_event.split();
+
+        Counted {
+            callback,
+            userdata,
+            matches: 0,
+        }
+    }
+}
+
+impl MatchEventHandler for Counted {
+    unsafe fn split(&mut self) -> (ffi::match_event_handler, *mut libc::c_void) {
+        unsafe extern "C" fn trampoline(
+            id: u32,
+            from: u64,
+            to: u64,
+            flags: u32,
+            ctx: *mut libc::c_void,
+        ) -> libc::c_int {
+            let counted = &mut *ctx.cast::<Counted>();
+
+            counted.matches += 1;
+
+            match counted.callback {
+                Some(callback) => callback(id, from, to, flags, counted.userdata),
+                None => Matching::Continue as _,
+            }
+        }
+
+        (Some(trampoline), self as *mut _ as *mut _)
     }
 }
 
+/// Run the scan with the match event handler, and report it to the metrics sink.
+///
+/// The matches are only counted for the tracing or an installed sink, otherwise `0` is returned as the matches.
+/// A panic of the match event handler is resumed after the scan is reported.
+pub(crate) unsafe fn instrumented<F, S>(
+    on_match_event: &mut F,
+    sink: Option<&dyn MetricsSink>,
+    bytes: usize,
+    scan: S,
+) -> (Result<ScanOutcome>, usize)
+where
+    F: MatchEventHandler,
+    S: FnOnce(ffi::match_event_handler, *mut libc::c_void) -> Result<()>,
+{
+    if cfg!(not(feature = "tracing")) && sink.is_none() {
+        let (callback, userdata) = on_match_event.split();
+
+        return (ScanOutcome::of(guarded(|| scan(callback, userdata))), 0);
+    }
+
+    #[cfg(feature = "std")]
+    let started = sink.map(|_| Instant::now());
+    let mut counted = Counted::new(on_match_event);
+    let (callback, userdata) = counted.split();
+    let res = ScanOutcome::of(scan(callback, userdata));
+
+    if let Some(sink) = sink {
+        #[cfg(feature = "std")]
+        let duration = started.map_or_else(Duration::default, |started| started.elapsed());
+        #[cfg(not(feature = "std"))]
+        let duration = Duration::default();
+
+        sink.on_scan(bytes, counted.matches, ScanStatus::of(&res), duration);
+    }
+
+    resume();
+
+    (res, counted.matches)
+}
+
 impl DatabaseRef<Block> {
     /// The block (non-streaming) regular expression scanner.
     ///
@@ -104,36 +490,260 @@ impl DatabaseRef<Block> {
     /// ```rust
     /// # use hyperscan::prelude::*;
     /// let db: BlockDatabase = pattern! {"test"; CASELESS | SOM_LEFTMOST}.build().unwrap();
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let mut matches = vec![];
     ///
-    /// db.scan("foo test bar", &s, |_, from, to, _| {
+    /// db.scan("foo test bar", &mut s, |_, from, to, _| {
     ///     matches.push(from..to);
     ///     Matching::Continue
     /// }).unwrap();
     ///
     /// assert_eq!(matches, vec![4..8]);
     /// ```
-    pub fn scan<T, F>(&self, data: T, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub fn scan<T, F>(&self, data: T, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
     where
         T: AsRef<[u8]>,
         F: MatchEventHandler,
     {
-        let data = data.as_ref();
+        self.scan_metered(data.as_ref(), scratch, on_match_event, metrics::global_sink())
+    }
 
-        unsafe {
-            let (callback, userdata) = on_match_event.split();
+    /// Scan the data, and returns all the matches in the order reported by Hyperscan.
+    ///
+    /// The returned vector doesn't allocate when there is no match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, Match};
+    /// let db: BlockDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    ///
+    /// assert_eq!(db.scan_matches("foo test bar", &mut s).unwrap(), vec![Match { id: 0, from: 4, to: 8 }]);
+    /// assert!(db.scan_matches("foo bar", &mut s).unwrap().is_empty());
+    /// ```
+    pub fn scan_matches<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef) -> Result<Vec<Match>> {
+        let mut matches = Vec::new();
+
+        self.scan(data, scratch, Match::collect(&mut matches))?;
+
+        Ok(matches)
+    }
+
+    /// Scan the data, and returns all the matches with the start offset of the `SOM_LEFTMOST` patterns,
+    /// the patterns must be the ones compiled into the database.
+    #[cfg(feature = "compile")]
+    pub fn scan_som_matches<T: AsRef<[u8]>>(
+        &self,
+        data: T,
+        scratch: &mut ScratchRef,
+        patterns: &crate::compile::Patterns,
+    ) -> Result<Vec<SomMatch>> {
+        let mut matches = Vec::new();
+
+        self.scan(data, scratch, collect_som(&mut matches, patterns))?;
+
+        Ok(matches)
+    }
+
+    /// Returns `true` if any pattern matches the data, the scan is terminated on the first match.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: BlockDatabase = pattern! {"fo+"}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    ///
+    /// assert!(db.is_match("foo bar", &mut s).unwrap());
+    /// assert!(!db.is_match("bar", &mut s).unwrap());
+    /// ```
+    pub fn is_match<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef) -> Result<bool> {
+        self.scan(data, scratch, |_, _, _, _| Matching::Terminate)
+            .map(ScanOutcome::is_terminated)
+    }
+
+    /// Returns the match which ends first, the scan is terminated on the first match.
+    ///
+    /// Unlike `regex`, which returns the leftmost-first match, Hyperscan reports a match as soon as it ends,
+    /// so the match of `fo+` in `foo` ends after `fo`. The start of match is only accurate with `SOM_LEFTMOST`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, Match};
+    /// let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    ///
+    /// assert_eq!(db.find("a foo", &mut s).unwrap(), Some(Match { id: 0, from: 2, to: 4 }));
+    /// assert_eq!(db.find("bar", &mut s).unwrap(), None);
+    /// ```
+    pub fn find<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef) -> Result<Option<Match>> {
+        let mut found = None;
+
+        self.scan(data, scratch, |id, from, to, _| {
+            found = Some(Match { id, from, to });
+
+            Matching::Terminate
+        })?;
+
+        Ok(found)
+    }
+
+    /// Returns all the matches of the data, ordered by their end offset.
+    ///
+    /// Unlike the non-overlapping matches of `regex`, Hyperscan reports every end offset of every pattern,
+    /// so the matches may overlap, e.g. `fo+` matches `foo` twice, ending after `fo` and after `foo`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    ///
+    /// let ends = db.find_iter("foo", &mut s).unwrap().map(|m| m.from..m.to).collect::<Vec<_>>();
+    ///
+    /// assert_eq!(ends, vec![0..2, 0..3]);
+    /// ```
+    pub fn find_iter<T: AsRef<[u8]>>(&self, data: T, scratch: &mut ScratchRef) -> Result<alloc::vec::IntoIter<Match>> {
+        let mut matches = self.scan_matches(data, scratch)?;
+
+        // Hyperscan reports the matches of a block scan in the order of their end offset
+        matches.sort_by_key(|m| m.to);
+
+        Ok(matches.into_iter())
+    }
+
+    /// Returns an iterator over the matches of the data, in the order reported by Hyperscan.
+    ///
+    /// Hyperscan drives the match handler until the end of the scan, so the matches are scanned in batches:
+    /// the scan is terminated once a batch of matches is buffered, and the next batch rescans the data
+    /// from its start, skipping the matches already returned. Each batch doubles the size of the previous one,
+    /// so the data is scanned about `log2(n / 256)` times for `n` matches, instead of once per batch,
+    /// and a batch holds at most as many matches as all the batches before it. Only the first scan
+    /// is reported to the metrics sink.
+    ///
+    /// A dropped iterator doesn't scan the rest of the data, so `scan_iter` suits the consumers which stop early.
+    /// To collect all the matches, `scan_matches` scans the data once.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::{prelude::*, Match};
+    /// let db: BlockDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let data = "test ".repeat(1_000_000);
+    ///
+    /// let first = db.scan_iter(&data, &mut s).next().unwrap().unwrap();
+    ///
+    /// assert_eq!(first, Match { id: 0, from: 0, to: 4 });
+    /// ```
+    pub fn scan_iter<'a, T: AsRef<[u8]>>(&'a self, data: T, scratch: &'a mut ScratchRef) -> ScanIter<'a, T> {
+        ScanIter {
+            db: self,
+            filter: None,
+            data,
+            scratch,
+            matches: VecDeque::new(),
+            batch: SCAN_ITER_BATCH,
+            returned: 0,
+            done: false,
+        }
+    }
 
+    /// Scan the data with a C match event handler and its context, e.g. a match sink written in C or C++.
+    ///
+    /// # Safety
+    ///
+    /// The handler is called by Hyperscan as is, without the closure and panic handling of the other scans:
+    ///
+    /// - the handler must not unwind, a panic must be caught before returning to Hyperscan,
+    /// - the context must be valid for the handler, and outlive the call,
+    /// - the handler must not scan with the same scratch space or stream.
+    ///
+    /// The termination requested by the handler, with a non-zero return, is returned as `ScanOutcome::Terminated`.
+    /// The scan isn't reported to the metrics sink nor traced.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// unsafe extern "C" fn count(_: u32, _: u64, _: u64, _: u32, ctx: *mut libc::c_void) -> libc::c_int {
+    ///     *ctx.cast::<usize>() += 1;
+    ///     0
+    /// }
+    ///
+    /// let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let mut matches = 0usize;
+    ///
+    /// unsafe { db.scan_raw("foo foo", &mut s, Some(count), &mut matches as *mut usize as *mut _) }.unwrap();
+    ///
+    /// assert_eq!(matches, 2);
+    /// ```
+    pub unsafe fn scan_raw<T: AsRef<[u8]>>(
+        &self,
+        data: T,
+        scratch: &mut ScratchRef,
+        handler: ffi::match_event_handler,
+        ctx: *mut libc::c_void,
+    ) -> Result<ScanOutcome> {
+        let data = data.as_ref();
+
+        ScanOutcome::of(
             ffi::hs_scan(
                 self.as_ptr(),
                 data.as_ptr() as *const c_char,
-                data.len() as u32,
+                scan_len(data.len())?,
                 0,
                 scratch.as_ptr(),
-                callback,
-                userdata,
+                handler,
+                ctx,
             )
-            .ok()
+            .ok(),
+        )
+    }
+
+    /// The non-generic scan of the data, instantiated once per match event handler.
+    pub(crate) fn scan_metered<F>(
+        &self,
+        data: &[u8],
+        scratch: &mut ScratchRef,
+        mut on_match_event: F,
+        sink: Option<&dyn MetricsSink>,
+    ) -> Result<ScanOutcome>
+    where
+        F: MatchEventHandler,
+    {
+        let len = scan_len(data.len())?;
+
+        unsafe {
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            let (res, matches) = instrumented(&mut on_match_event, sink, data.len(), |callback, userdata| {
+                ffi::hs_scan(
+                    self.as_ptr(),
+                    data.as_ptr() as *const c_char,
+                    len,
+                    0,
+                    scratch.as_ptr(),
+                    callback,
+                    userdata,
+                )
+                .ok()
+            });
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                target: "hyperscan",
+                mode = "block",
+                bytes = data.len(),
+                matches,
+                outcome = ScanStatus::of(&res).as_str(),
+                "scan"
+            );
+
+            res
         }
     }
 }
@@ -148,52 +758,151 @@ impl DatabaseRef<Vectored> {
     /// ```rust
     /// # use hyperscan::prelude::*;
     /// let db: VectoredDatabase = pattern!{"test"; CASELESS|SOM_LEFTMOST}.build().unwrap();
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     ///
     /// let mut matches = vec![];
     ///
-    /// db.scan(vec!["foo", "test", "bar"], &s, |id, from, to, _| {
+    /// db.scan(vec!["foo", "test", "bar"], &mut s, |id, from, to, _| {
     ///     matches.push(from..to);
     ///     Matching::Continue
     /// }).unwrap();
     ///
     /// assert_eq!(matches, vec![3..7]);
     /// ```
-    pub fn scan<I, T, F>(&self, data: I, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub fn scan<I, T, F>(&self, data: I, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
     where
         I: IntoIterator<Item = T>,
         T: AsRef<[u8]>,
         F: MatchEventHandler,
     {
-        let (ptrs, lens): (Vec<_>, Vec<_>) = data
-            .into_iter()
-            .map(|buf| {
-                let buf = buf.as_ref();
+        self.scan_metered(data, scratch, on_match_event, metrics::global_sink())
+    }
+
+    /// Scan the blocks of data, and returns all the matches in the order reported by Hyperscan.
+    ///
+    /// The returned vector doesn't allocate when there is no match.
+    pub fn scan_matches<I, T>(&self, data: I, scratch: &mut ScratchRef) -> Result<Vec<Match>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut matches = Vec::new();
 
-                (buf.as_ptr() as *const i8, buf.len() as c_uint)
-            })
-            .unzip();
+        self.scan(data, scratch, Match::collect(&mut matches))?;
+
+        Ok(matches)
+    }
+
+    /// Scan the blocks of data, and returns all the matches with the start offset of the `SOM_LEFTMOST` patterns,
+    /// the patterns must be the ones compiled into the database.
+    #[cfg(feature = "compile")]
+    pub fn scan_som_matches<I, T>(
+        &self,
+        data: I,
+        scratch: &mut ScratchRef,
+        patterns: &crate::compile::Patterns,
+    ) -> Result<Vec<SomMatch>>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut matches = Vec::new();
+
+        self.scan(data, scratch, collect_som(&mut matches, patterns))?;
+
+        Ok(matches)
+    }
+
+    pub(crate) fn scan_metered<I, T, F>(
+        &self,
+        data: I,
+        scratch: &mut ScratchRef,
+        mut on_match_event: F,
+        sink: Option<&dyn MetricsSink>,
+    ) -> Result<ScanOutcome>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+        F: MatchEventHandler,
+    {
+        let mut stack_ptrs = [ptr::null::<c_char>(); VECTORED_STACK_BLOCKS];
+        let mut stack_lens = [0 as c_uint; VECTORED_STACK_BLOCKS];
+        let mut heap: Option<(Vec<*const c_char>, Vec<c_uint>)> = None;
+        let mut count = 0;
+        let mut bytes = 0;
+
+        for buf in data {
+            let buf = buf.as_ref();
+            let (ptr, len) = (buf.as_ptr() as *const c_char, buf.len() as c_uint);
+
+            bytes += buf.len();
+
+            if count < VECTORED_STACK_BLOCKS {
+                stack_ptrs[count] = ptr;
+                stack_lens[count] = len;
+            } else {
+                let (ptrs, lens) = heap.get_or_insert_with(|| (stack_ptrs.to_vec(), stack_lens.to_vec()));
+
+                ptrs.push(ptr);
+                lens.push(len);
+            }
+
+            count += 1;
+        }
+
+        // the offsets of the matches are reported in the concatenated blocks
+        scan_len(bytes)?;
+
+        let blocks = scan_len(count)?;
+
+        let (ptrs, lens) = match heap {
+            Some((ref ptrs, ref lens)) => (ptrs.as_slice(), lens.as_slice()),
+            None => (&stack_ptrs[..count], &stack_lens[..count]),
+        };
 
         unsafe {
-            let (callback, userdata) = on_match_event.split();
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            let (res, matches) = instrumented(&mut on_match_event, sink, bytes, |callback, userdata| {
+                ffi::hs_scan_vector(
+                    self.as_ptr(),
+                    ptrs.as_ptr(),
+                    lens.as_ptr(),
+                    blocks,
+                    0,
+                    scratch.as_ptr(),
+                    callback,
+                    userdata,
+                )
+                .ok()
+            });
 
-            ffi::hs_scan_vector(
-                self.as_ptr(),
-                ptrs.as_slice().as_ptr() as *const *const c_char,
-                lens.as_slice().as_ptr() as *const _,
-                ptrs.len() as u32,
-                0,
-                scratch.as_ptr(),
-                callback,
-                userdata,
-            )
-            .ok()
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                target: "hyperscan",
+                mode = "vectored",
+                bytes,
+                matches,
+                outcome = ScanStatus::of(&res).as_str(),
+                "scan"
+            );
+
+            res
         }
     }
 }
 
+#[cfg(feature = "std")]
 const SCAN_BUF_SIZE: usize = 4096;
 
+/// The pointers and lengths of the first blocks of a vectored scan are kept on the stack.
+const VECTORED_STACK_BLOCKS: usize = 16;
+
+/// Hyperscan scans at most `u32::MAX` bytes in a call.
+fn scan_len(len: usize) -> Result<c_uint> {
+    c_uint::try_from(len).map_err(|_| Error::DataTooLarge(len as u64))
+}
+
+#[cfg(feature = "std")]
 impl DatabaseRef<Streaming> {
     /// Pattern matching takes place for stream-mode pattern databases.
     ///
@@ -208,11 +917,11 @@ impl DatabaseRef<Streaming> {
     /// buf.push_str("baaab");
     ///
     /// let db: StreamingDatabase = pattern! { "a+"; SOM_LEFTMOST }.build().unwrap();
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let mut cur = Cursor::new(buf.as_bytes());
     /// let mut matches = vec![];
     ///
-    /// db.scan(&mut cur, &s, |_, from, to, _| {
+    /// db.scan(&mut cur, &mut s, |_, from, to, _| {
     ///     matches.push((from, to));
     ///
     ///     Matching::Continue
@@ -221,25 +930,82 @@ impl DatabaseRef<Streaming> {
     ///
     /// assert_eq!(matches, vec![(4095, 4096), (4095, 4097), (4095, 4098)]);
     /// ```
-    pub fn scan<R, F>(&self, reader: &mut R, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub fn scan<R, F>(&self, reader: &mut R, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
     where
         R: Read,
         F: MatchEventHandler,
     {
+        self.scan_reader(reader, scratch, SCAN_BUF_SIZE, on_match_event)
+    }
+
+    /// Scan the data read from a file or a socket as a stream, in chunks of up to `buf_size` bytes.
+    ///
+    /// A stream is opened, the chunks are read into a reused buffer and scanned as they are read,
+    /// and the stream is closed at the end of data, to report the matches at the end of data.
+    /// The short reads are scanned as is, and the interrupted reads are retried.
+    ///
+    /// A zero-byte read is never scanned, and is the end of data: the `Read` contract reserves `Ok(0)`
+    /// from a non-empty buffer for the end of file, which a file keeps returning on each read,
+    /// so it can't be skipped without reading forever. A reader without data available yet blocks,
+    /// or returns `ErrorKind::WouldBlock` when it's non-blocking, which is returned as `Error::Io`;
+    /// such a reader should be scanned with `async_scan`, or with `Stream::scan` as its data arrives.
+    ///
+    /// When the match handler terminates the scan, no more data is read, and `ScanOutcome::Terminated`
+    /// is returned. A read error is returned as `Error::Io`,
+    /// and an empty buffer as `HsError::Invalid`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: StreamingDatabase = pattern! {"foo$"; SOM_LEFTMOST}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let mut matches = vec![];
+    ///
+    /// db.scan_reader(&b"foo bar foo"[..], &mut s, 4, |_, from, to, _| {
+    ///     matches.push((from, to));
+    ///     Matching::Continue
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(matches, vec![(8, 11)]);
+    /// ```
+    pub fn scan_reader<R, F>(
+        &self,
+        mut reader: R,
+        scratch: &mut ScratchRef,
+        buf_size: usize,
+        mut on_match_event: F,
+    ) -> Result<ScanOutcome>
+    where
+        R: Read,
+        F: MatchEventHandler,
+    {
+        if buf_size == 0 {
+            return Err(crate::HsError::Invalid.into());
+        }
+
         let stream = self.open_stream()?;
-        let mut buf = [0; SCAN_BUF_SIZE];
+        let mut buf = alloc::vec![0; buf_size];
 
-        let (callback, userdata) = unsafe { on_match_event.split() };
+        let handler = unsafe { SplitHandler::of(&mut on_match_event) };
 
-        while let Ok(len) = reader.read(&mut buf[..]) {
-            if len == 0 {
-                break;
-            }
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            };
 
-            stream.scan(&buf[..len], scratch, (callback, userdata))?;
+            if stream.scan(&buf[..len], scratch, handler)?.is_terminated() {
+                return Ok(ScanOutcome::Terminated);
+            }
         }
 
-        stream.close(scratch, (callback, userdata))
+        stream.close(scratch, handler)?;
+
+        Ok(ScanOutcome::Completed)
     }
 
     /// Pattern matching takes place for stream-mode pattern databases using AsyncRead.
@@ -256,12 +1022,12 @@ impl DatabaseRef<Streaming> {
     /// buf.push_str("baaab");
     ///
     /// let db: StreamingDatabase = pattern! { "a+"; SOM_LEFTMOST }.build().unwrap();
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let mut cur = Cursor::new(buf.as_bytes());
     /// let mut matches = vec![];
     ///
     /// tokio_test::block_on(async {
-    ///     db.async_scan(&mut cur, &s, |_, from, to, _| {
+    ///     db.async_scan(&mut cur, &mut s, |_, from, to, _| {
     ///         matches.push((from, to));
     ///
     ///         Matching::Continue
@@ -271,7 +1037,12 @@ impl DatabaseRef<Streaming> {
     /// assert_eq!(matches, vec![(4095, 4096), (4095, 4097), (4095, 4098)]);
     /// ```
     #[cfg(feature = "async")]
-    pub async fn async_scan<R, F>(&self, reader: &mut R, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub async fn async_scan<R, F>(
+        &self,
+        reader: &mut R,
+        scratch: &mut ScratchRef,
+        mut on_match_event: F,
+    ) -> Result<ScanOutcome>
     where
         R: AsyncRead + Unpin,
         F: MatchEventHandler,
@@ -279,17 +1050,21 @@ impl DatabaseRef<Streaming> {
         let stream = self.open_stream()?;
         let mut buf = [0; SCAN_BUF_SIZE];
 
-        let (callback, userdata) = unsafe { on_match_event.split() };
+        let handler = unsafe { SplitHandler::of(&mut on_match_event) };
 
         while let Ok(len) = reader.read(&mut buf[..]).await {
             if len == 0 {
                 break;
             }
 
-            stream.scan(&buf[..len], scratch, (callback, userdata))?;
+            if stream.scan(&buf[..len], scratch, handler)?.is_terminated() {
+                return Ok(ScanOutcome::Terminated);
+            }
         }
 
-        stream.close(scratch, (callback, userdata))
+        stream.close(scratch, handler)?;
+
+        Ok(ScanOutcome::Completed)
     }
 }
 
@@ -305,7 +1080,7 @@ impl StreamRef {
     /// # use hyperscan::prelude::*;
     /// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
     ///
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let st = db.open_stream().unwrap();
     ///
     /// let data = vec!["foo t", "es", "t bar"];
@@ -318,33 +1093,669 @@ impl StreamRef {
     /// };
     ///
     /// for d in data {
-    ///     st.scan(d, &s, &mut callback).unwrap();
+    ///     st.scan(d, &mut s, &mut callback).unwrap();
     /// }
     ///
-    /// st.close(&s, callback).unwrap();
+    /// st.close(&mut s, callback).unwrap();
     ///
     /// assert_eq!(matches, vec![(4, 8)]);
     /// ```
-    pub fn scan<T, F>(&self, data: T, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub fn scan<T, F>(&self, data: T, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
     where
         T: AsRef<[u8]>,
         F: MatchEventHandler,
     {
-        let data = data.as_ref();
+        self.scan_metered(data.as_ref(), scratch, on_match_event, metrics::global_sink())
+    }
 
-        unsafe {
-            let (callback, userdata) = on_match_event.split();
+    /// Write the data to the stream with a C match event handler and its context.
+    ///
+    /// The termination of the stream isn't tracked by `Stream::is_terminated`.
+    ///
+    /// # Safety
+    ///
+    /// The handler is called by Hyperscan as is, without the closure and panic handling of the other scans:
+    ///
+    /// - the handler must not unwind, a panic must be caught before returning to Hyperscan,
+    /// - the context must be valid for the handler, and outlive the call,
+    /// - the handler must not scan with the same scratch space or stream.
+    ///
+    /// The termination requested by the handler, with a non-zero return, is returned as `ScanOutcome::Terminated`.
+    /// The scan isn't reported to the metrics sink nor traced.
+    pub unsafe fn scan_raw<T: AsRef<[u8]>>(
+        &self,
+        data: T,
+        scratch: &mut ScratchRef,
+        handler: ffi::match_event_handler,
+        ctx: *mut libc::c_void,
+    ) -> Result<ScanOutcome> {
+        let data = data.as_ref();
 
+        ScanOutcome::of(
             ffi::hs_scan_stream(
                 self.as_ptr(),
                 data.as_ptr() as *const c_char,
-                data.len() as u32,
+                scan_len(data.len())?,
                 0,
                 scratch.as_ptr(),
-                callback,
-                userdata,
+                handler,
+                ctx,
             )
-            .ok()
+            .ok(),
+        )
+    }
+
+    /// The non-generic scan of the data, instantiated once per match event handler.
+    pub(crate) fn scan_metered<F>(
+        &self,
+        data: &[u8],
+        scratch: &mut ScratchRef,
+        mut on_match_event: F,
+        sink: Option<&dyn MetricsSink>,
+    ) -> Result<ScanOutcome>
+    where
+        F: MatchEventHandler,
+    {
+        let len = scan_len(data.len())?;
+
+        unsafe {
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            let (res, matches) = instrumented(&mut on_match_event, sink, data.len(), |callback, userdata| {
+                ffi::hs_scan_stream(
+                    self.as_ptr(),
+                    data.as_ptr() as *const c_char,
+                    len,
+                    0,
+                    scratch.as_ptr(),
+                    callback,
+                    userdata,
+                )
+                .ok()
+            });
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                target: "hyperscan",
+                mode = "stream",
+                bytes = data.len(),
+                matches,
+                outcome = ScanStatus::of(&res).as_str(),
+                "scan"
+            );
+
+            res
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::prelude::*;
+
+    fn panicked<T: fmt::Debug, F: FnOnce() -> Result<T>>(f: F) -> String {
+        let payload = panic::catch_unwind(AssertUnwindSafe(f)).expect_err("the handler should panic");
+
+        payload.downcast::<&str>().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_scan_matches() {
+        let patterns: Patterns = "1:/fo+/L\n2:/o+b/L\n3:/bar/".parse().unwrap();
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let m = |id, from, to| Match { id, from, to };
+
+        assert_eq!(
+            db.scan_matches("foobar", &mut s).unwrap(),
+            vec![m(1, 0, 2), m(1, 0, 3), m(2, 1, 4), m(3, 0, 6)]
+        );
+
+        let matches = db.scan_matches("baz", &mut s).unwrap();
+
+        assert!(matches.is_empty());
+        assert_eq!(matches.capacity(), 0);
+
+        let db: VectoredDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert_eq!(
+            db.scan_matches(["fo", "ob", "ar"], &mut s).unwrap(),
+            vec![m(1, 0, 2), m(1, 0, 3), m(2, 1, 4), m(3, 0, 6)]
+        );
+    }
+
+    #[test]
+    fn test_vectored_scan() {
+        let db: VectoredDatabase = pattern! {"foobar"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let m = |from, to| Match { id: 0, from, to };
+
+        // the match spans two adjacent blocks, and the empty blocks
+        let header: &[u8] = b"xfoo";
+        let payload: &[u8] = b"bar foobar";
+
+        assert_eq!(
+            db.scan_matches([header, payload], &mut s).unwrap(),
+            vec![m(1, 7), m(11, 17)]
+        );
+        assert_eq!(
+            db.scan_matches([header, b"", b"", payload], &mut s).unwrap(),
+            vec![m(1, 7), m(11, 17)]
+        );
+
+        // the blocks after the first ones are kept on the heap
+        let blocks = "x".repeat(VECTORED_STACK_BLOCKS * 2) + "foobar";
+        let blocks = blocks.as_bytes().chunks(1).collect::<Vec<_>>();
+        let off = VECTORED_STACK_BLOCKS as u64 * 2;
+
+        assert_eq!(db.scan_matches(&blocks, &mut s).unwrap(), vec![m(off, off + 6)]);
+    }
+
+    #[test]
+    fn test_scan_len() {
+        assert_eq!(scan_len(0).unwrap(), 0);
+        assert_eq!(scan_len(u32::MAX as usize).unwrap(), u32::MAX);
+
+        // the length is checked before the scan, without allocating the data
+        if usize::BITS > 32 {
+            assert_eq!(scan_len(u32::MAX as usize + 1), Err(Error::DataTooLarge(1 << 32)));
+            assert_eq!(scan_len(usize::MAX), Err(Error::DataTooLarge(usize::MAX as u64)));
+        }
+    }
+
+    #[test]
+    fn test_closure_handlers() {
+        let data = b"foo bar foo".to_vec();
+        let db: BlockDatabase = pattern! {"foo|bar"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut words = vec![];
+        let mut count = 0;
+
+        // the closure captures the state mutably and borrows the scanned data
+        db.scan(&data, &mut s, |_, from, to, _| {
+            words.push(&data[from as usize..to as usize]);
+            count += 1;
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(words, vec![&b"foo"[..], b"bar", b"foo"]);
+        assert_eq!(count, 3);
+
+        let mut ends = vec![];
+
+        assert_eq!(
+            db.scan(&data, &mut s, |_, _, to, _| {
+                ends.push(to);
+                Matching::Terminate
+            })
+            .unwrap(),
+            ScanOutcome::Terminated
+        );
+        assert_eq!(ends, vec![3]);
+
+        let db: VectoredDatabase = pattern! {"foo|bar"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut ends = vec![];
+
+        assert_eq!(
+            db.scan(data.chunks(2), &mut s, |_, _, to, _| {
+                ends.push(to);
+                if to < 7 {
+                    Matching::Continue
+                } else {
+                    Matching::Terminate
+                }
+            })
+            .unwrap(),
+            ScanOutcome::Terminated
+        );
+        assert_eq!(ends, vec![3, 7]);
+
+        let db: StreamingDatabase = pattern! {"foo|bar$"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+        let mut ranges = vec![];
+
+        for chunk in data[..7].chunks(2) {
+            st.scan(chunk, &mut s, |_, from, to, _| {
+                ranges.push(from..to);
+                Matching::Continue
+            })
+            .unwrap();
+        }
+
+        // the match at the end of the stream is reported by the handler of close
+        st.close(&mut s, |_, from, to, _| {
+            ranges.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(ranges, vec![0..3, 4..7]);
+    }
+
+    #[test]
+    fn test_handler_panic() {
+        let boom = |_, _, _, _| -> Matching { panic!("boom") };
+
+        let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert_eq!(panicked(|| db.scan("foo", &mut s, boom)), "boom");
+
+        // the scratch space is released, and the next scan runs as usual
+        assert_eq!(db.scan_matches("foo", &mut s).unwrap().len(), 1);
+
+        let db: VectoredDatabase = pattern! {"foo"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert_eq!(panicked(|| db.scan(["fo", "o"], &mut s, boom)), "boom");
+
+        let db: StreamingDatabase = pattern! {"foo$"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        assert_eq!(panicked(|| st.scan("foo", &mut s, boom)), "boom");
+        assert_eq!(panicked(|| st.reset(&mut s, boom)), "boom");
+
+        st.scan("foo", &mut s, Matching::Continue).unwrap();
+
+        assert_eq!(panicked(|| st.close(&mut s, boom)), "boom");
+
+        // a panic of a nested scan is carried over both scans
+        let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+        let (mut outer, mut inner) = (db.alloc_scratch().unwrap(), db.alloc_scratch().unwrap());
+
+        assert_eq!(
+            panicked(|| db.scan("foo", &mut outer, |_, _, _, _| {
+                let _ = db.scan("foo", &mut inner, boom);
+
+                Matching::Continue
+            })),
+            "boom"
+        );
+    }
+
+    #[test]
+    fn test_scan_terminated() {
+        fn ids(matches: &mut Vec<u32>) -> impl FnMut(u32, u64, u64, u32) -> Matching + '_ {
+            move |id, _, _, _| {
+                matches.push(id);
+                Matching::Terminate
+            }
+        }
+
+        let patterns: Patterns = "1:/foo/\n2:/bar/".parse().unwrap();
+        let mut matches = vec![];
+
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert!(db.scan("foo bar", &mut s, ids(&mut matches)).unwrap().is_terminated());
+        assert!(!db.scan("baz", &mut s, ids(&mut matches)).unwrap().is_terminated());
+        assert_eq!(matches, vec![1]);
+
+        let db: VectoredDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert!(db
+            .scan(["fo", "o bar"], &mut s, ids(&mut matches))
+            .unwrap()
+            .is_terminated());
+        assert!(!db.scan(["ba", "z"], &mut s, ids(&mut matches)).unwrap().is_terminated());
+        assert_eq!(matches, vec![1, 1]);
+
+        let db: StreamingDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        assert!(!st.scan("fo", &mut s, ids(&mut matches)).unwrap().is_terminated());
+        assert!(!st.is_terminated());
+        assert!(st.scan("o ba", &mut s, ids(&mut matches)).unwrap().is_terminated());
+        assert!(st.is_terminated());
+
+        // the terminated stream doesn't scan the next writes
+        assert!(st.scan("r", &mut s, ids(&mut matches)).unwrap().is_terminated());
+        assert_eq!(matches, vec![1, 1, 1]);
+
+        let fork = st.try_clone().unwrap();
+
+        assert!(fork.is_terminated());
+
+        st.reset(&mut s, ()).unwrap();
+
+        assert!(!st.is_terminated());
+        assert!(st.scan("bar", &mut s, ids(&mut matches)).unwrap().is_terminated());
+        assert_eq!(matches, vec![1, 1, 1, 2]);
+    }
+
+    #[test]
+    fn test_scan_som_matches() {
+        let patterns: Patterns = "1:/fo+/L\n2:/o+b/\n3:/bar/L".parse().unwrap();
+        let m = |id, start, end| SomMatch { id, start, end };
+        let expected = vec![m(1, Some(0), 2), m(1, Some(0), 3), m(2, None, 4), m(3, Some(3), 6)];
+
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert_eq!(db.scan_som_matches("foobar", &mut s, &patterns).unwrap(), expected);
+
+        let db: VectoredDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert_eq!(
+            db.scan_som_matches(["fo", "ob", "ar"], &mut s, &patterns).unwrap(),
+            expected
+        );
+
+        assert_eq!(Match { id: 2, from: 0, to: 4 }.with_som(&patterns), Some(m(2, None, 4)));
+        assert_eq!(Match { id: 4, from: 0, to: 4 }.with_som(&patterns), None);
+    }
+
+    #[test]
+    fn test_scan_iter() {
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let data = "foo bar ".repeat(1000);
+
+        // more matches than a batch are returned in the order of `scan_matches`
+        let matches = db.scan_iter(&data, &mut s).collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(matches.len(), 2000);
+        assert_eq!(matches, db.scan_matches(&data, &mut s).unwrap());
+        assert_eq!(db.scan_iter("bar", &mut s).next(), None);
+
+        let mut iter = db.scan_iter(&data, &mut s);
+
+        assert_eq!(
+            iter.nth(SCAN_ITER_BATCH),
+            Some(Ok(Match {
+                id: 0,
+                from: 1024,
+                to: 1026
+            }))
+        );
+        // the second batch doubles the first one
+        assert_eq!(iter.returned, 3 * SCAN_ITER_BATCH);
+        assert_eq!(iter.batch, 4 * SCAN_ITER_BATCH);
+
+        // the 2000 matches are buffered by 4 scans, instead of one scan per batch of 256 matches
+        let mut iter = db.scan_iter(&data, &mut s);
+        let mut scans = 0;
+
+        while !iter.done {
+            iter.scan_batch().unwrap();
+            iter.matches.clear();
+            scans += 1;
+        }
+
+        assert_eq!(scans, 4);
+        assert_eq!(iter.returned, 2000);
+    }
+
+    #[test]
+    fn test_scan_iter_early_drop() {
+        let db: BlockDatabase = pattern! {"foo"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let data = b"foo ".repeat(16 * 1024 * 1024);
+        let mut iter = db.scan_iter(&data, &mut s);
+
+        assert_eq!(iter.next(), Some(Ok(Match { id: 0, from: 0, to: 3 })));
+
+        // only a batch of the matches was scanned and buffered
+        assert!(!iter.done);
+        assert_eq!(iter.returned, SCAN_ITER_BATCH);
+        assert!(iter.matches.capacity() < 2 * SCAN_ITER_BATCH);
+
+        drop(iter);
+
+        // the scratch space is released for the next scan
+        assert_eq!(db.scan_iter("foo", &mut s).count(), 1);
+    }
+
+    #[test]
+    fn test_find_like_regex() {
+        use regex::bytes::Regex;
+
+        for (pattern, data) in [
+            ("fo+", "a foo fooo"),
+            ("[0-9]+", "abc 123 4"),
+            ("bar", "foo"),
+            ("a|ab", "xab"),
+        ] {
+            let db: BlockDatabase = pattern!(pattern; SOM_LEFTMOST).build().unwrap();
+            let mut s = db.alloc_scratch().unwrap();
+            let re = Regex::new(pattern).unwrap();
+
+            assert_eq!(
+                db.is_match(data, &mut s).unwrap(),
+                re.is_match(data.as_bytes()),
+                "{}",
+                pattern
+            );
+
+            // both find the leftmost match, Hyperscan returns the shortest one
+            let found = db.find(data, &mut s).unwrap().map(|m| m.from as usize);
+
+            assert_eq!(found, re.find(data.as_bytes()).map(|m| m.start()), "{}", pattern);
+
+            // Hyperscan reports the overlapping matches, regex the non-overlapping ones
+            let ends = db
+                .find_iter(data, &mut s)
+                .unwrap()
+                .map(|m| m.to as usize)
+                .collect::<Vec<_>>();
+
+            assert!(ends.windows(2).all(|w| w[0] <= w[1]));
+            assert!(
+                re.find_iter(data.as_bytes()).all(|m| ends.contains(&m.end())),
+                "{}",
+                pattern
+            );
+        }
+
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert_eq!(db.find("foo", &mut s).unwrap(), Some(Match { id: 0, from: 0, to: 2 }));
+        assert_eq!(Regex::new("fo+").unwrap().find(b"foo").unwrap().end(), 3);
+        assert_eq!(db.find_iter("foo foo", &mut s).unwrap().count(), 4);
+        assert_eq!(Regex::new("fo+").unwrap().find_iter(b"foo foo").count(), 2);
+    }
+
+    #[test]
+    fn test_scan_raw() {
+        struct Sink {
+            ends: Vec<u64>,
+            limit: usize,
         }
+
+        unsafe extern "C" fn on_match(_: u32, _: u64, to: u64, _: u32, ctx: *mut libc::c_void) -> libc::c_int {
+            let sink = &mut *ctx.cast::<Sink>();
+
+            sink.ends.push(to);
+
+            (sink.ends.len() >= sink.limit) as _
+        }
+
+        let mut sink = Sink { ends: vec![], limit: 2 };
+        let ctx = &mut sink as *mut Sink as *mut libc::c_void;
+
+        let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        assert!(unsafe { db.scan_raw("foo foo foo", &mut s, Some(on_match), ctx) }
+            .unwrap()
+            .is_terminated());
+
+        let db: StreamingDatabase = pattern! {"foo"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        sink.limit = 10;
+
+        for chunk in &["f", "oo f", "oo"] {
+            unsafe { st.scan_raw(chunk, &mut s, Some(on_match), ctx) }.unwrap();
+        }
+
+        st.close(&mut s, ()).unwrap();
+
+        assert_eq!(sink.ends, vec![3, 7, 3, 7]);
+    }
+
+    #[test]
+    fn test_scan_reader() {
+        use std::fs::File;
+        use std::io::{self, BufWriter, Write};
+
+        struct Flaky<R> {
+            inner: R,
+            reads: usize,
+        }
+
+        // a reader returning short and interrupted reads
+        impl<R: Read> Read for Flaky<R> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                self.reads += 1;
+
+                match self.reads % 3 {
+                    0 => Err(io::ErrorKind::Interrupted.into()),
+                    1 => {
+                        let short = buf.len().min(7);
+
+                        self.inner.read(&mut buf[..short])
+                    }
+                    _ => self.inner.read(buf),
+                }
+            }
+        }
+
+        let patterns: Patterns = "1:/foo[0-9]+bar/L\n2:/b+$/L\n3:/x{3}/L".parse().unwrap();
+        let data = (0..10 * 1024 * 1024 / 16)
+            .map(|i| format!("{:08}", i * 7919 % 100_000_007))
+            .map(|n| match n.as_bytes()[7] {
+                b'0' => format!("foo{}bar", &n[..4]),
+                b'1' => "xxxx    ".to_owned(),
+                _ => n,
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+            + "bbb";
+        let path = std::env::temp_dir().join(format!("hyperscan-reader-{}.txt", std::process::id()));
+
+        {
+            let mut w = BufWriter::new(File::create(&path).unwrap());
+
+            w.write_all(data.as_bytes()).unwrap();
+        }
+
+        let block: BlockDatabase = patterns.build().unwrap();
+        let expected = block.scan_matches(&data, &mut block.alloc_scratch().unwrap()).unwrap();
+
+        let db: StreamingDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        db.scan_reader(File::open(&path).unwrap(), &mut s, 4096, Match::collect(&mut matches))
+            .unwrap();
+
+        assert!(expected.iter().any(|m| m.id == 2));
+        assert_eq!(matches, expected);
+
+        matches.clear();
+
+        let reader = Flaky {
+            inner: File::open(&path).unwrap(),
+            reads: 0,
+        };
+
+        db.scan_reader(reader, &mut s, 4096, Match::collect(&mut matches))
+            .unwrap();
+
+        assert_eq!(matches, expected);
+
+        std::fs::remove_file(&path).unwrap();
+
+        // the reader isn't read after the termination
+        let mut reader = Flaky {
+            inner: data.as_bytes(),
+            reads: 0,
+        };
+        let terminated = db
+            .scan_reader(&mut reader, &mut s, 4096, |_, _, _, _| Matching::Terminate)
+            .unwrap()
+            .is_terminated();
+
+        assert!(terminated);
+        assert!(reader.reads < data.len() / 4096);
+        assert!(db.scan_reader(&b"foo"[..], &mut s, 0, ()).is_err());
+    }
+
+    #[test]
+    fn test_scan_inputs() {
+        use std::borrow::Cow;
+
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let string = String::from("a foo");
+        let bytes = Vec::from("a foo");
+        let expected = db.scan_matches(b"a foo", &mut s).unwrap();
+
+        assert_eq!(db.scan_matches(&string, &mut s).unwrap(), expected);
+        assert_eq!(db.scan_matches(&bytes, &mut s).unwrap(), expected);
+        assert_eq!(db.scan_matches("a foo", &mut s).unwrap(), expected);
+        assert_eq!(db.scan_matches(Cow::from(&bytes[..]), &mut s).unwrap(), expected);
+        assert_eq!(db.scan_matches(&*Cow::from(&string[..]), &mut s).unwrap(), expected);
+        assert!(db.is_match(string, &mut s).unwrap());
+        assert_eq!(db.find(bytes, &mut s).unwrap(), expected.first().copied());
+
+        let db: StreamingDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let st = db.open_stream().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+
+        st.scan(String::from("a f"), &mut s, Match::collect(&mut matches))
+            .unwrap();
+        st.scan(Vec::from("o"), &mut s, Match::collect(&mut matches)).unwrap();
+        st.scan("o", &mut s, Match::collect(&mut matches)).unwrap();
+        st.close(&mut s, ()).unwrap();
+
+        assert_eq!(matches, expected);
+    }
+
+    #[test]
+    fn test_match_text() {
+        let text = "naïve café";
+        let db: BlockDatabase = pattern! {"[^ ]+"; UTF8 | SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let words = db
+            .find_iter(text, &mut s)
+            .unwrap()
+            .filter_map(|m| m.text(text))
+            .collect::<Vec<_>>();
+
+        assert!(words.contains(&"naïve") && words.contains(&"café"));
+
+        // without `UTF8`, a byte of a character matches
+        let db: BlockDatabase = pattern! {"\\xc3"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let m = db.find(text, &mut s).unwrap().unwrap();
+
+        assert_eq!(m, Match { id: 0, from: 2, to: 3 });
+        assert_eq!(m.text(text), None);
+        assert_eq!(
+            Match {
+                id: 0,
+                from: 0,
+                to: 100
+            }
+            .text(text),
+            None
+        );
     }
 }
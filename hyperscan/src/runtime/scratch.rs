@@ -1,12 +1,37 @@
-use std::mem::MaybeUninit;
-use std::ptr::NonNull;
+use core::mem::MaybeUninit;
+use core::ptr::NonNull;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
 
 use foreign_types::{foreign_type, ForeignType, ForeignTypeRef};
 
-use crate::{common::DatabaseRef, error::AsResult, ffi, Result};
+use crate::{
+    allocator,
+    common::{AnyDatabase, Database, DatabaseRef, Error as HsError},
+    error::AsResult,
+    ffi, Result,
+};
 
 foreign_type! {
     /// A large enough region of scratch space to support a given database.
+    ///
+    /// The scratch space doesn't refer to the databases it was allocated for,
+    /// so it may outlive them, and be reused with any database it is large enough for.
+    ///
+    /// The scans borrow the scratch space exclusively with `&mut ScratchRef`, so it can't be shared
+    /// by the concurrent scans of several threads, nor by a scan started from the match handler of another scan,
+    /// each scan should use its own scratch space, e.g. cloned from a prototype or from a `ScratchPool`.
+    ///
+    /// ```compile_fail
+    /// # use hyperscan::prelude::*;
+    /// let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    ///
+    /// std::thread::scope(|t| {
+    ///     t.spawn(|| db.scan("foo", &mut s, |_, _, _, _| Matching::Continue));
+    ///     t.spawn(|| db.scan("foo", &mut s, |_, _, _, _| Matching::Continue));
+    /// });
+    /// ```
     pub unsafe type Scratch: Send {
         type CType = ffi::hs_scratch_t;
 
@@ -33,26 +58,129 @@ impl Scratch {
     ///
     unsafe fn alloc<T>(db: &DatabaseRef<T>) -> Result<Scratch> {
         let mut s = MaybeUninit::zeroed();
+
+        allocator::mark_in_use();
         ffi::hs_alloc_scratch(db.as_ptr(), s.as_mut_ptr()).map(|_| Scratch::from_ptr(s.assume_init()))
     }
 
     /// Reallocate a "scratch" space for use by Hyperscan.
+    ///
+    /// Hyperscan frees the old scratch space when a larger one can't be allocated, and nulls the pointer,
+    /// so a clone is grown instead, and replaces the handle on success, the handle is left valid on failure.
     unsafe fn realloc<T>(&mut self, db: &DatabaseRef<T>) -> Result<()> {
-        let mut p = self.as_ptr();
+        let mut p = self.try_clone()?.into_ptr();
+        let res = ffi::hs_alloc_scratch(db.as_ptr(), &mut p).ok();
+        let grown = NonNull::new(p).map(|p| Scratch::from_ptr(p.as_ptr()));
 
-        ffi::hs_alloc_scratch(db.as_ptr(), &mut p).map(|_| {
-            self.0 = NonNull::new_unchecked(p);
-        })
+        if let Some(grown) = grown.filter(|_| res.is_ok()) {
+            *self = grown;
+        }
+
+        res
     }
 }
 
+impl Scratch {
+    /// Allocate a "scratch" space large enough for all the databases, whatever their modes.
+    ///
+    /// One scratch space per thread may be used with all the databases, instead of one per database.
+    /// `HsError::Invalid` is returned without any database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let block: BlockDatabase = pattern! {"foo"}.build().unwrap();
+    /// let streaming: StreamingDatabase = pattern! {"bar"}.build().unwrap();
+    /// let mut s = Scratch::for_databases(&[&block, &streaming]).unwrap();
+    ///
+    /// block.scan("foo", &mut s, |_, _, _, _| Matching::Continue).unwrap();
+    /// streaming.open_stream().unwrap().scan("bar", &mut s, |_, _, _, _| Matching::Continue).unwrap();
+    /// ```
+    pub fn for_databases(dbs: &[&dyn ScratchTarget]) -> Result<Scratch> {
+        let (first, rest) = dbs.split_first().ok_or(HsError::Invalid)?;
+        let mut s = first.alloc_scratch_for()?;
+
+        for db in rest {
+            db.realloc_scratch_for(&mut s)?;
+        }
+
+        Ok(s)
+    }
+}
+
+/// A database of any mode a "scratch" space may be allocated for.
+pub trait ScratchTarget {
+    /// Allocate a "scratch" space for the database.
+    fn alloc_scratch_for(&self) -> Result<Scratch>;
+
+    /// Reallocate the "scratch" space, so it is large enough for the database.
+    fn realloc_scratch_for(&self, s: &mut Scratch) -> Result<()>;
+}
+
+impl<T> ScratchTarget for DatabaseRef<T> {
+    fn alloc_scratch_for(&self) -> Result<Scratch> {
+        self.alloc_scratch()
+    }
+
+    fn realloc_scratch_for(&self, s: &mut Scratch) -> Result<()> {
+        self.realloc_scratch(s).map(|_| ())
+    }
+}
+
+impl<T> ScratchTarget for Database<T> {
+    fn alloc_scratch_for(&self) -> Result<Scratch> {
+        self.alloc_scratch()
+    }
+
+    fn realloc_scratch_for(&self, s: &mut Scratch) -> Result<()> {
+        self.realloc_scratch(s).map(|_| ())
+    }
+}
+
+impl ScratchTarget for AnyDatabase {
+    fn alloc_scratch_for(&self) -> Result<Scratch> {
+        match self {
+            AnyDatabase::Block(db) => db.alloc_scratch(),
+            AnyDatabase::Streaming(db) => db.alloc_scratch(),
+            AnyDatabase::Vectored(db) => db.alloc_scratch(),
+        }
+    }
+
+    fn realloc_scratch_for(&self, s: &mut Scratch) -> Result<()> {
+        match self {
+            AnyDatabase::Block(db) => db.realloc_scratch_for(s),
+            AnyDatabase::Streaming(db) => db.realloc_scratch_for(s),
+            AnyDatabase::Vectored(db) => db.realloc_scratch_for(s),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+thread_local! {
+    static THREAD_SCRATCH: RefCell<Option<Scratch>> = const { RefCell::new(None) };
+}
+
 impl ScratchRef {
     /// Provides the size of the given scratch space.
+    ///
+    /// The size grows when the scratch space is reallocated for a larger database, and never shrinks.
     pub fn size(&self) -> Result<usize> {
         let mut size = MaybeUninit::uninit();
 
         unsafe { ffi::hs_scratch_size(self.as_ptr(), size.as_mut_ptr()).map(|_| size.assume_init()) }
     }
+
+    /// Clone the scratch space, e.g. a prototype scratch space for each worker thread.
+    ///
+    /// Unlike `Clone`, a failed allocation is returned as `HsError::NoMem` instead of panicking.
+    pub fn try_clone(&self) -> Result<Scratch> {
+        let mut p = MaybeUninit::uninit();
+
+        allocator::mark_in_use();
+
+        unsafe { ffi::hs_clone_scratch(self.as_ptr(), p.as_mut_ptr()).map(|_| Scratch::from_ptr(p.assume_init())) }
+    }
 }
 
 impl<T> DatabaseRef<T> {
@@ -66,6 +194,48 @@ impl<T> DatabaseRef<T> {
         unsafe { s.realloc(self) }.map(|_| s)
     }
 
+    /// Call the closure with a "scratch" space cached by the current thread.
+    ///
+    /// The cached scratch space is reallocated if it is too small for the database,
+    /// and a nested call allocates a temporary scratch space.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: BlockDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+    /// let mut matches = vec![];
+    ///
+    /// db.with_thread_scratch(|s| {
+    ///     db.scan("foo test bar", s, |_, from, to, _| {
+    ///         matches.push(from..to);
+    ///         Matching::Continue
+    ///     })
+    /// })
+    /// .unwrap()
+    /// .unwrap();
+    ///
+    /// assert_eq!(matches, vec![4..8]);
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn with_thread_scratch<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut ScratchRef) -> R,
+    {
+        let mut scratch = match THREAD_SCRATCH.with(|cached| cached.borrow_mut().take()) {
+            Some(scratch) => scratch,
+            None => self.alloc_scratch()?,
+        };
+
+        self.realloc_scratch(&mut scratch)?;
+
+        let res = f(&mut scratch);
+
+        THREAD_SCRATCH.with(|cached| *cached.borrow_mut() = Some(scratch));
+
+        Ok(res)
+    }
+
     /// Allocate a "scratch" space for use by Hyperscan.
     #[deprecated = "use `alloc_scratch` instead"]
     pub fn alloc(&self) -> Result<Scratch> {
@@ -81,7 +251,10 @@ impl<T> DatabaseRef<T> {
 
 #[cfg(test)]
 pub mod tests {
+    use foreign_types::{ForeignType, ForeignTypeRef};
+
     use crate::prelude::*;
+    use crate::{AnyDatabase, BlockMode, DatabaseRef, Error, HsError};
 
     const SCRATCH_SIZE: usize = 2000;
 
@@ -103,4 +276,227 @@ pub mod tests {
 
         assert!(s2.size().unwrap() > s.size().unwrap());
     }
+
+    #[test]
+    fn test_scratch_size_grows() {
+        let small: BlockDatabase = "foo".parse().unwrap();
+        let large: Patterns = (0..200)
+            .map(|i| pattern! {format!("foo{}(bar|baz){{2,{}}}", i, i + 2); SOM_LEFTMOST})
+            .collect();
+        let large: StreamingDatabase = large.build().unwrap();
+
+        let mut s = small.alloc_scratch().unwrap();
+        let size = s.size().unwrap();
+
+        large.realloc_scratch(&mut s).unwrap();
+
+        assert!(s.size().unwrap() > size);
+        assert!(s.size().unwrap() >= large.alloc_scratch().unwrap().size().unwrap());
+
+        // the scratch space is never shrunk
+        small.realloc_scratch(&mut s).unwrap();
+
+        assert!(s.size().unwrap() > size);
+    }
+
+    #[test]
+    fn test_scratch_for_databases() {
+        let block: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let streaming: StreamingDatabase = (0..50)
+            .map(|i| pattern! {format!("bar{}", i)})
+            .collect::<Patterns>()
+            .build()
+            .unwrap();
+        let mut s = Scratch::for_databases(&[&block, &streaming]).unwrap();
+
+        assert!(s.size().unwrap() >= block.alloc_scratch().unwrap().size().unwrap());
+        assert!(s.size().unwrap() >= streaming.alloc_scratch().unwrap().size().unwrap());
+
+        let mut matches = vec![];
+
+        block
+            .scan("a foo", &mut s, |id, from, to, _| {
+                matches.push((id, from, to));
+                Matching::Continue
+            })
+            .unwrap();
+
+        let st = streaming.open_stream().unwrap();
+
+        for chunk in &["ba", "r42"] {
+            st.scan(chunk, &mut s, |id, from, to, _| {
+                matches.push((id, from, to));
+                Matching::Continue
+            })
+            .unwrap();
+        }
+
+        st.close(&mut s, Matching::Continue).unwrap();
+
+        assert_eq!(matches, vec![(0, 2, 4), (0, 2, 5), (4, 0, 4), (42, 0, 5)]);
+
+        let any = AnyDatabase::from(block);
+
+        assert!(Scratch::for_databases(&[&any, &*streaming]).is_ok());
+        assert_eq!(
+            Scratch::for_databases(&[]).err(),
+            Some(Error::Hyperscan(HsError::Invalid))
+        );
+    }
+
+    #[test]
+    fn test_scratch_grows_across_databases() {
+        let block: BlockDatabase = "foo".parse().unwrap();
+        let vectored: VectoredDatabase = (0..20)
+            .map(|i| pattern! {format!("baz{}", i)})
+            .collect::<Patterns>()
+            .build()
+            .unwrap();
+        let streaming: StreamingDatabase = (0..200)
+            .map(|i| pattern! {format!("foo{}(bar|baz){{2,{}}}", i, i + 2); SOM_LEFTMOST})
+            .collect::<Patterns>()
+            .build()
+            .unwrap();
+        let mut s = block.alloc_scratch().unwrap();
+        let mut size = s.size().unwrap();
+
+        vectored.realloc_scratch(&mut s).unwrap();
+
+        assert!(s.size().unwrap() >= size);
+        assert!(s.size().unwrap() >= vectored.alloc_scratch().unwrap().size().unwrap());
+
+        size = s.size().unwrap();
+
+        streaming.realloc_scratch(&mut s).unwrap();
+
+        assert!(s.size().unwrap() > size);
+        assert!(s.size().unwrap() >= streaming.alloc_scratch().unwrap().size().unwrap());
+
+        let mut matches = vec![];
+
+        block
+            .scan("foo", &mut s, |id, _, to, _| {
+                matches.push((id, to));
+                Matching::Continue
+            })
+            .unwrap();
+        vectored
+            .scan(vec!["ba", "z7"], &mut s, |id, _, to, _| {
+                matches.push((id, to));
+                Matching::Continue
+            })
+            .unwrap();
+
+        let st = streaming.open_stream().unwrap();
+
+        st.scan("foo1barbar", &mut s, |id, _, to, _| {
+            matches.push((id, to));
+            Matching::Continue
+        })
+        .unwrap();
+        st.close(&mut s, Matching::Continue).unwrap();
+
+        assert_eq!(matches, vec![(0, 3), (7, 4), (1, 10)]);
+    }
+
+    #[test]
+    fn test_scratch_realloc_failed() {
+        #[repr(align(64))]
+        struct Garbage([u8; 256]);
+
+        let garbage = Garbage([0; 256]);
+        let invalid = unsafe { DatabaseRef::<BlockMode>::from_ptr(garbage.0.as_ptr() as *mut _) };
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let size = s.size().unwrap();
+
+        assert!(invalid.realloc_scratch(&mut s).is_err());
+
+        // the scratch space is left valid and usable after the failed reallocation
+        assert_eq!(s.size().unwrap(), size);
+
+        let mut matches = vec![];
+
+        db.scan("foo", &mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![0..2, 0..3]);
+    }
+
+    #[test]
+    fn test_scratch_try_clone() {
+        let db: BlockDatabase = pattern! {"fo+"; SOM_LEFTMOST}.build().unwrap();
+        let s = db.alloc_scratch().unwrap();
+        let s2 = s.try_clone().unwrap();
+
+        assert_eq!(s2.size().unwrap(), s.size().unwrap());
+        assert_ne!(s2.as_ptr(), s.as_ptr());
+
+        let scan = |mut s: Scratch, data: &str| {
+            let mut matches = vec![];
+
+            for _ in 0..100 {
+                matches.clear();
+
+                db.scan(data, &mut s, |_, from, to, _| {
+                    matches.push(from..to);
+                    Matching::Continue
+                })
+                .unwrap();
+            }
+
+            matches
+        };
+
+        let (matches, matches2) = std::thread::scope(|t| {
+            // the scratch space isn't `Sync`, each thread owns its scratch space
+            let h = t.spawn(|| scan(s, "foo"));
+            let h2 = t.spawn(|| scan(s2, "a fooo"));
+
+            (h.join().unwrap(), h2.join().unwrap())
+        });
+
+        assert_eq!(matches, vec![0..2, 0..3]);
+        assert_eq!(matches2, vec![2..4, 2..5, 2..6]);
+    }
+
+    #[test]
+    fn test_scratch_outlives_database() {
+        let db: BlockDatabase = "test".parse().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        drop(db);
+
+        let db: BlockDatabase = "foo".parse().unwrap();
+        let mut matches = 0;
+
+        db.realloc_scratch(&mut s).unwrap();
+        db.scan("foobar", &mut s, |_, _, _, _| {
+            matches += 1;
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, 1);
+    }
+
+    #[test]
+    fn test_thread_scratch() {
+        let db: BlockDatabase = "test".parse().unwrap();
+        let db2: VectoredDatabase = "foobar".parse().unwrap();
+
+        let size = db.with_thread_scratch(|s| s.size().unwrap()).unwrap();
+
+        assert!(size > SCRATCH_SIZE);
+        assert!(db2.with_thread_scratch(|s| s.size().unwrap()).unwrap() > size);
+
+        let nested = db
+            .with_thread_scratch(|_| db.with_thread_scratch(|s| s.size().unwrap()).unwrap())
+            .unwrap();
+
+        assert!(nested > SCRATCH_SIZE);
+    }
 }
@@ -1,17 +1,33 @@
-use std::mem::MaybeUninit;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{ManuallyDrop, MaybeUninit};
+use core::ops::Deref;
+use core::ptr::{self, NonNull};
 
-use foreign_types::{foreign_type, ForeignType, ForeignTypeRef};
+use foreign_types::{ForeignType, ForeignTypeRef, Opaque};
 
+#[cfg(feature = "tracing")]
+use crate::metrics::ScanStatus;
+#[cfg(feature = "std")]
+use crate::runtime::scan::SplitHandler;
 use crate::{
-    common::{DatabaseRef, Streaming},
+    allocator,
+    common::{Database, DatabaseRef, Error as HsError, Streaming},
     error::AsResult,
-    ffi,
-    runtime::{MatchEventHandler, ScratchRef},
-    Result,
+    ffi, metrics,
+    runtime::{scan::instrumented, MatchEventHandler, ScratchRef},
+    unwind::guarded,
+    Error, Result, ScanOutcome,
 };
 
 impl DatabaseRef<Streaming> {
     /// Provides the size of the stream state allocated by a single stream opened against the given database.
+    ///
+    /// The size is known before any stream is opened, e.g. to budget the memory of the concurrent flows,
+    /// and grows with the SOM horizon of the `SOM_LEFTMOST` patterns.
     pub fn stream_size(&self) -> Result<usize> {
         let mut size = MaybeUninit::uninit();
 
@@ -19,34 +35,322 @@ impl DatabaseRef<Streaming> {
     }
 
     /// Open and initialise a stream.
-    pub fn open_stream(&self) -> Result<Stream> {
+    ///
+    /// The stream borrows the database, since the stream state refers to it.
+    pub fn open_stream(&self) -> Result<Stream<'_>> {
         let mut s = MaybeUninit::uninit();
 
-        unsafe { ffi::hs_open_stream(self.as_ptr(), 0, s.as_mut_ptr()).map(|_| Stream::from_ptr(s.assume_init())) }
+        allocator::mark_in_use();
+
+        let res =
+            unsafe { ffi::hs_open_stream(self.as_ptr(), 0, s.as_mut_ptr()).map(|_| Stream::of(self, s.assume_init())) };
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!(target: "hyperscan", ok = res.is_ok(), "stream opened");
+
+        res
+    }
+}
+
+impl Database<Streaming> {
+    /// Open and initialise a stream, which keeps the shared database alive.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use std::sync::Arc;
+    /// # use hyperscan::prelude::*;
+    /// let db: StreamingDatabase = pattern! {"test"}.build().unwrap();
+    /// let db = Arc::new(db);
+    ///
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let st = db.open_shared_stream().unwrap();
+    ///
+    /// drop(db);
+    ///
+    /// st.scan("test", &mut s, ()).unwrap();
+    /// st.close(&mut s, ()).unwrap();
+    /// ```
+    pub fn open_shared_stream(self: &Arc<Self>) -> Result<Stream<'static>> {
+        let stream = self.open_stream()?;
+
+        let stream = ManuallyDrop::new(stream);
+
+        Ok(Stream {
+            ptr: stream.ptr,
+            tag: stream.tag,
+            owner: Some(self.clone()),
+            terminated: Cell::new(false),
+            _db: PhantomData,
+        })
+    }
+}
+
+/// A pattern matching state can be maintained across multiple blocks of target data
+///
+/// The stream refers to the database it was opened against, so it either borrows the database,
+/// or shares its ownership with `Database::open_shared_stream`. The stream state is released when the stream is
+/// dropped, and the matches at the end of data are only reported by `Stream::close`.
+///
+/// Once a match handler terminates the scan, the stream stays terminated until it's reset, the next scans
+/// return `ScanOutcome::Terminated` without scanning the data, and `Stream::is_terminated` returns `true`.
+///
+/// A stream can't outlive its database:
+///
+/// ```rust,compile_fail
+/// # use hyperscan::prelude::*;
+/// let db: StreamingDatabase = pattern! {"test"}.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let st = db.open_stream().unwrap();
+///
+/// drop(db);
+///
+/// st.close(&mut s, ()).unwrap();
+/// ```
+pub struct Stream<'db> {
+    ptr: NonNull<ffi::hs_stream_t>,
+    tag: Option<u64>,
+    owner: Option<Arc<Database<Streaming>>>,
+    terminated: Cell<bool>,
+    _db: PhantomData<&'db DatabaseRef<Streaming>>,
+}
+
+/// A reference to a stream.
+pub struct StreamRef(Opaque);
+
+unsafe impl Send for Stream<'_> {}
+unsafe impl Send for StreamRef {}
+
+unsafe impl ForeignType for Stream<'_> {
+    type CType = ffi::hs_stream_t;
+    type Ref = StreamRef;
+
+    unsafe fn from_ptr(ptr: *mut ffi::hs_stream_t) -> Self {
+        Stream {
+            ptr: NonNull::new_unchecked(ptr),
+            tag: None,
+            owner: None,
+            terminated: Cell::new(false),
+            _db: PhantomData,
+        }
+    }
+
+    fn as_ptr(&self) -> *mut ffi::hs_stream_t {
+        self.ptr.as_ptr()
     }
 }
 
-foreign_type! {
-    /// A pattern matching state can be maintained across multiple blocks of target data
-    pub unsafe type Stream: Send {
-        type CType = ffi::hs_stream_t;
+unsafe impl ForeignTypeRef for StreamRef {
+    type CType = ffi::hs_stream_t;
+}
+
+impl Deref for Stream<'_> {
+    type Target = StreamRef;
 
-        fn drop = drop_stream;
-        fn clone = clone_stream;
+    fn deref(&self) -> &StreamRef {
+        unsafe { StreamRef::from_ptr(self.as_ptr()) }
     }
 }
 
-fn drop_stream(_s: *mut ffi::hs_stream_t) {}
+impl fmt::Debug for Stream<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Stream").field(&self.ptr).finish()
+    }
+}
+
+impl Drop for Stream<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::hs_close_stream(self.as_ptr(), ptr::null_mut(), None, ptr::null_mut());
+        }
+    }
+}
 
 /// Duplicate the given stream.
 ///
 /// The new stream will have the same state as the original including the current stream offset.
-unsafe fn clone_stream(s: *mut ffi::hs_stream_t) -> *mut ffi::hs_stream_t {
-    let mut p = MaybeUninit::uninit();
+impl Clone for Stream<'_> {
+    fn clone(&self) -> Self {
+        self.try_clone().expect("copy stream")
+    }
+}
+
+impl<'db> Stream<'db> {
+    /// Wrap a stream state opened against the database.
+    unsafe fn of(db: &'db DatabaseRef<Streaming>, ptr: *mut ffi::hs_stream_t) -> Self {
+        Stream {
+            ptr: NonNull::new_unchecked(ptr),
+            tag: Some(db.tag()),
+            owner: None,
+            terminated: Cell::new(false),
+            _db: PhantomData,
+        }
+    }
+
+    /// Duplicate the stream, e.g. to fork the state of a flow at a branch point.
+    ///
+    /// The streams are scanned independently, and a failed allocation is returned instead of panicking as `Clone`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: StreamingDatabase = pattern! {"foobar"}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let st = db.open_stream().unwrap();
+    ///
+    /// st.scan("foo", &mut s, ()).unwrap();
+    ///
+    /// let fork = st.try_clone().unwrap();
+    /// let mut ends = vec![];
+    ///
+    /// st.scan("bar", &mut s, |_, _, to, _| {
+    ///     ends.push(to);
+    ///     Matching::Continue
+    /// })
+    /// .unwrap();
+    /// fork.scan("baz", &mut s, |_, _, to, _| {
+    ///     ends.push(to);
+    ///     Matching::Continue
+    /// })
+    /// .unwrap();
+    ///
+    /// assert_eq!(ends, vec![6]);
+    /// ```
+    pub fn try_clone(&self) -> Result<Stream<'db>> {
+        let mut p = MaybeUninit::uninit();
+
+        unsafe {
+            ffi::hs_copy_stream(p.as_mut_ptr(), self.as_ptr()).ok()?;
+
+            Ok(Stream {
+                ptr: NonNull::new_unchecked(p.assume_init()),
+                tag: self.tag,
+                owner: self.owner.clone(),
+                terminated: self.terminated.clone(),
+                _db: PhantomData,
+            })
+        }
+    }
+
+    /// Returns `true` when a match handler terminated the scan of the stream.
+    ///
+    /// A terminated stream doesn't scan the data anymore, until it's reset.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: StreamingDatabase = pattern! {"foo"}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let st = db.open_stream().unwrap();
+    ///
+    /// assert!(st.scan("foo", &mut s, Matching::Terminate).unwrap().is_terminated());
+    /// assert!(st.is_terminated());
+    ///
+    /// // the next scans are terminated without calling the match handler
+    /// let mut matched = false;
+    /// let outcome = st
+    ///     .scan("foo", &mut s, |_, _, _, _| {
+    ///         matched = true;
+    ///         Matching::Continue
+    ///     })
+    ///     .unwrap();
+    ///
+    /// assert!(outcome.is_terminated() && !matched);
+    ///
+    /// st.reset(&mut s, ()).unwrap();
+    ///
+    /// assert!(!st.is_terminated());
+    /// ```
+    pub fn is_terminated(&self) -> bool {
+        self.terminated.get()
+    }
+
+    /// Write data to be scanned to the stream, and remember when the match handler terminates the scan.
+    ///
+    /// See `StreamRef::scan`.
+    pub fn scan<T, F>(&self, data: T, scratch: &mut ScratchRef, on_match_event: F) -> Result<ScanOutcome>
+    where
+        T: AsRef<[u8]>,
+        F: MatchEventHandler,
+    {
+        let outcome = (**self).scan(data, scratch, on_match_event)?;
+
+        if outcome.is_terminated() {
+            self.terminated.set(true);
+        }
+
+        Ok(outcome)
+    }
+
+    /// Reset the stream to an initial state, and clear its termination.
+    ///
+    /// See `StreamRef::reset`.
+    pub fn reset<F>(&self, scratch: &mut ScratchRef, on_match_event: F) -> Result<()>
+    where
+        F: MatchEventHandler,
+    {
+        self.terminated.set(false);
+        (**self).reset(scratch, on_match_event)
+    }
 
-    ffi::hs_copy_stream(p.as_mut_ptr(), s).expect("copy stream");
+    /// Duplicate the `from` stream state onto the stream, and clear its termination.
+    ///
+    /// A terminated `from` stream is only known as terminated after the next scan. See `StreamRef::reset_and_copy_stream`.
+    pub fn reset_and_copy_stream<F>(&self, from: &StreamRef, scratch: &mut ScratchRef, on_match_event: F) -> Result<()>
+    where
+        F: MatchEventHandler,
+    {
+        self.terminated.set(false);
+        (**self).reset_and_copy_stream(from, scratch, on_match_event)
+    }
 
-    p.assume_init()
+    /// Decompresses a compressed representation created by `Stream::compress` on top of the stream,
+    /// and clear its termination. The stream will first be reset (reporting any EOD matches).
+    ///
+    /// `HsError::Invalid` is returned when `buf` wasn't compressed by a stream opened against the same database.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
+    ///
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let st = db.open_stream().unwrap();
+    ///
+    /// let mut matches = vec![];
+    ///
+    /// let mut callback = |_, from, to, _| {
+    ///     matches.push((from, to));
+    ///
+    ///     Matching::Continue
+    /// };
+    ///
+    /// st.scan("foo t", &mut s, &mut callback).unwrap();
+    /// st.scan("es", &mut s, &mut callback).unwrap();
+    ///
+    /// let mut buf = [0; 8192];
+    /// let len = st.compress(&mut buf).unwrap();
+    /// st.scan("t bar", &mut s, &mut callback).unwrap();
+    ///
+    /// st.reset_and_expand(&buf[..len], &mut s, &mut callback).unwrap();
+    /// st.scan("t bar", &mut s, &mut callback).unwrap();
+    /// st.close(&mut s, &mut callback).unwrap();
+    ///
+    /// assert_eq!(matches, vec![(4, 8), (4, 8)]);
+    /// ```
+    pub fn reset_and_expand<F>(&self, buf: &[u8], scratch: &mut ScratchRef, on_match_event: F) -> Result<()>
+    where
+        F: MatchEventHandler,
+    {
+        let state = compressed_state(buf, self.tag)?;
+
+        self.terminated.set(false);
+
+        unsafe { (**self).reset_and_expand(state, scratch, on_match_event) }
+    }
 }
 
 impl StreamRef {
@@ -67,7 +371,7 @@ impl StreamRef {
     /// # use hyperscan::prelude::*;
     /// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
     ///
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let st = db.open_stream().unwrap();
     ///
     /// let data = vec!["foo t", "es", "t bar"];
@@ -80,27 +384,27 @@ impl StreamRef {
     /// };
     ///
     /// for d in &data {
-    ///     st.scan(d, &s, &mut callback).unwrap();
+    ///     st.scan(d, &mut s, &mut callback).unwrap();
     /// }
     ///
-    /// st.reset(&s, &mut callback).unwrap();
+    /// st.reset(&mut s, &mut callback).unwrap();
     ///
     /// for d in &data {
-    ///     st.scan(d, &s, &mut callback).unwrap();
+    ///     st.scan(d, &mut s, &mut callback).unwrap();
     /// }
     ///
-    /// st.close(&s, callback).unwrap();
+    /// st.close(&mut s, callback).unwrap();
     ///
     /// assert_eq!(matches, vec![(4, 8), (4, 8)]);
     /// ```
-    pub fn reset<F>(&self, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub fn reset<F>(&self, scratch: &mut ScratchRef, mut on_match_event: F) -> Result<()>
     where
         F: MatchEventHandler,
     {
         unsafe {
             let (callback, userdata) = on_match_event.split();
 
-            ffi::hs_reset_stream(self.as_ptr(), 0, scratch.as_ptr(), callback, userdata).ok()
+            guarded(|| ffi::hs_reset_stream(self.as_ptr(), 0, scratch.as_ptr(), callback, userdata).ok())
         }
     }
 
@@ -116,7 +420,7 @@ impl StreamRef {
     /// # use hyperscan::prelude::*;
     /// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
     ///
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let st = db.open_stream().unwrap();
     ///
     /// let mut matches = vec![];
@@ -127,58 +431,99 @@ impl StreamRef {
     ///     Matching::Continue
     /// };
     ///
-    /// st.scan("foo t", &s, &mut callback).unwrap();
-    /// st.scan("es", &s, &mut callback).unwrap();
+    /// st.scan("foo t", &mut s, &mut callback).unwrap();
+    /// st.scan("es", &mut s, &mut callback).unwrap();
     ///
     /// let st2 = db.open_stream().unwrap();
     ///
-    /// st2.scan("test", &s, &mut callback).unwrap();
-    /// st2.reset_and_copy_stream(&st, &s, &mut callback).unwrap();
-    /// st2.scan("t bar", &s, &mut callback).unwrap();
-    /// st2.close(&s, &mut callback).unwrap();
+    /// st2.scan("test", &mut s, &mut callback).unwrap();
+    /// st2.reset_and_copy_stream(&st, &mut s, &mut callback).unwrap();
+    /// st2.scan("t bar", &mut s, &mut callback).unwrap();
+    /// st2.close(&mut s, &mut callback).unwrap();
     ///
-    /// st.close(&s, Matching::Terminate).unwrap();
+    /// st.close(&mut s, Matching::Terminate).unwrap();
     ///
     /// assert_eq!(matches, vec![(0, 4), (4, 8)]);
     /// ```
-    pub fn reset_and_copy_stream<F>(&self, from: &StreamRef, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub fn reset_and_copy_stream<F>(
+        &self,
+        from: &StreamRef,
+        scratch: &mut ScratchRef,
+        mut on_match_event: F,
+    ) -> Result<()>
     where
         F: MatchEventHandler,
     {
         unsafe {
             let (callback, userdata) = on_match_event.split();
 
-            ffi::hs_reset_and_copy_stream(self.as_ptr(), from.as_ptr(), scratch.as_ptr(), callback, userdata).ok()
+            guarded(|| {
+                ffi::hs_reset_and_copy_stream(self.as_ptr(), from.as_ptr(), scratch.as_ptr(), callback, userdata).ok()
+            })
         }
     }
 }
 
-impl Stream {
+impl Stream<'_> {
     /// Close a stream.
     ///
     /// This function completes matching on the given stream and frees the memory associated with the stream state.
     /// After this call, the stream is invalid and can no longer be used.
     /// To reuse the stream state after completion, rather than closing it, the `StreamRef::reset` function can be used.
     ///
-    /// This function must be called for any stream created with `StreamingDatabase::open_stream`,
-    /// even if scanning has been terminated by a non-zero return from the match callback function.
-    pub fn close<F>(self, scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    /// This function should be called for any stream created with `StreamingDatabase::open_stream`,
+    /// even if scanning has been terminated by a non-zero return from the match callback function,
+    /// a dropped stream frees its state without reporting the matches at the end of data.
+    pub fn close<F>(self, scratch: &mut ScratchRef, mut on_match_event: F) -> Result<()>
     where
         F: MatchEventHandler,
     {
+        let mut stream = ManuallyDrop::new(self);
+
         unsafe {
-            let (callback, userdata) = on_match_event.split();
+            #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+            let (res, matches) = instrumented(&mut on_match_event, metrics::global_sink(), 0, |callback, userdata| {
+                ffi::hs_close_stream(stream.as_ptr(), scratch.as_ptr(), callback, userdata).ok()
+            });
+
+            // release the shared database after the stream state
+            ptr::drop_in_place(&mut stream.owner);
 
-            ffi::hs_close_stream(self.as_ptr(), scratch.as_ptr(), callback, userdata).ok()
+            #[cfg(feature = "tracing")]
+            tracing::debug!(
+                target: "hyperscan",
+                matches,
+                outcome = ScanStatus::of(&res).as_str(),
+                "stream closed"
+            );
+
+            // Hyperscan stops reporting the matches at the end of data, the stream is closed either way
+            res.map(|_| ())
         }
     }
 }
 
-impl StreamRef {
-    /// Creates a compressed representation of the provided stream in the buffer provided.
+/// The head of a compressed stream, followed by the identity of the database of the stream.
+const COMPRESSED_MAGIC: [u8; 4] = *b"hsz1";
+/// The size of the head of a compressed stream.
+const COMPRESSED_HEADER: usize = COMPRESSED_MAGIC.len() + 8;
+
+/// Returns the compressed state following the head, if it was compressed by a stream of the database of `tag`.
+fn compressed_state(buf: &[u8], tag: Option<u64>) -> Result<&[u8]> {
+    match (buf.split_at_checked(COMPRESSED_HEADER), tag) {
+        (Some((head, state)), Some(tag)) if head[..4] == COMPRESSED_MAGIC && head[4..] == tag.to_le_bytes() => {
+            Ok(state)
+        }
+        _ => Err(HsError::Invalid.into()),
+    }
+}
+
+impl Stream<'_> {
+    /// Creates a compressed representation of the stream in the buffer provided.
     ///
-    /// This compressed representation can be converted back into a stream state by using `expand()`
-    /// or `reset_and_expand()`.
+    /// The representation records the identity of the database of the stream, so it can only be converted back
+    /// into a stream state of the same database by using `DatabaseRef::expand_stream()`
+    /// or `Stream::reset_and_expand()`.
     ///
     /// # Examples
     ///
@@ -186,7 +531,7 @@ impl StreamRef {
     /// # use hyperscan::prelude::*;
     /// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
     ///
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let st = db.open_stream().unwrap();
     ///
     /// let mut matches = vec![];
@@ -197,19 +542,78 @@ impl StreamRef {
     ///     Matching::Continue
     /// };
     ///
-    /// st.scan("foo t", &s, &mut callback).unwrap();
-    /// st.scan("es", &s, &mut callback).unwrap();
+    /// st.scan("foo t", &mut s, &mut callback).unwrap();
+    /// st.scan("es", &mut s, &mut callback).unwrap();
     ///
     /// let mut buf = [0; 8192];
     /// let len = st.compress(&mut buf).unwrap();
-    /// st.close(&s, Matching::Terminate).unwrap();
+    /// st.close(&mut s, Matching::Terminate).unwrap();
     ///
     /// let st2 = db.expand_stream(&buf[..len]).unwrap();
-    /// st2.scan("t bar", &s, &mut callback).unwrap();
-    /// st2.close(&s, &mut callback).unwrap();
+    /// st2.scan("t bar", &mut s, &mut callback).unwrap();
+    /// st2.close(&mut s, &mut callback).unwrap();
     ///
     /// assert_eq!(matches, vec![(4, 8)]);
     /// ```
+    pub fn compress(&self, buf: &mut [u8]) -> Result<usize> {
+        let tag = self.tag.ok_or(HsError::Invalid)?;
+
+        if buf.len() < COMPRESSED_HEADER {
+            return Err(HsError::InsufficientSpace.into());
+        }
+
+        let (head, state) = buf.split_at_mut(COMPRESSED_HEADER);
+        let len = (**self).compress(state)?;
+
+        head[..4].copy_from_slice(&COMPRESSED_MAGIC);
+        head[4..].copy_from_slice(&tag.to_le_bytes());
+
+        Ok(COMPRESSED_HEADER + len)
+    }
+
+    /// Creates a compressed representation of the stream, appended to the vector.
+    ///
+    /// The vector is grown to the size required by the compressed stream, which is returned.
+    /// See `Stream::compress`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use hyperscan::prelude::*;
+    /// let db: StreamingDatabase = pattern! {"test"}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
+    /// let st = db.open_stream().unwrap();
+    ///
+    /// st.scan("foo te", &mut s, ()).unwrap();
+    ///
+    /// let mut buf = vec![];
+    /// let len = st.compress_into(&mut buf).unwrap();
+    ///
+    /// assert_eq!(buf.len(), len);
+    /// ```
+    pub fn compress_into(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let tag = self.tag.ok_or(HsError::Invalid)?;
+        let off = buf.len();
+
+        buf.extend_from_slice(&COMPRESSED_MAGIC);
+        buf.extend_from_slice(&tag.to_le_bytes());
+
+        match (**self).compress_into(buf) {
+            Ok(len) => Ok(COMPRESSED_HEADER + len),
+            Err(err) => {
+                buf.truncate(off);
+
+                Err(err)
+            }
+        }
+    }
+}
+
+impl StreamRef {
+    /// Creates the raw compressed representation of Hyperscan of the stream in the buffer provided.
+    ///
+    /// The raw representation doesn't record the database of the stream, it can only be converted back
+    /// into a stream state by the unsafe `StreamRef::reset_and_expand()`, `Stream::compress` should be preferred.
     pub fn compress(&self, buf: &mut [u8]) -> Result<usize> {
         let mut size = MaybeUninit::uninit();
 
@@ -220,52 +624,81 @@ impl StreamRef {
         }
     }
 
-    /// Decompresses a compressed representation created by `StreamRef::compress` on top of the stream.
+    /// Creates the raw compressed representation of Hyperscan of the stream, appended to the vector.
+    ///
+    /// The vector is grown to the size required by the compressed stream, which is returned.
+    /// See `StreamRef::compress`.
+    pub fn compress_into(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let off = buf.len();
+        let mut size = 0;
+
+        // probe the required size with the spare capacity, and retry with enough space
+        for _ in 0..2 {
+            buf.resize(off + size.max(buf.capacity() - off), 0);
+
+            unsafe {
+                match ffi::hs_compress_stream(
+                    self.as_ptr(),
+                    buf[off..].as_mut_ptr() as *mut _,
+                    buf.len() - off,
+                    &mut size,
+                )
+                .ok()
+                {
+                    Ok(()) => {
+                        buf.truncate(off + size);
+
+                        return Ok(size);
+                    }
+                    Err(Error::Hyperscan(HsError::InsufficientSpace)) => {}
+                    Err(err) => {
+                        buf.truncate(off);
+
+                        return Err(err);
+                    }
+                }
+            }
+        }
+
+        buf.truncate(off);
+
+        Err(HsError::InsufficientSpace.into())
+    }
+
+    /// Decompresses a raw compressed representation created by `StreamRef::compress` on top of the stream.
     /// The stream will first be reset (reporting any EOD matches).
     ///
-    /// Note: the stream must be opened against the same database as the compressed stream.
+    /// `Stream::reset_and_expand` checks the stream was compressed by a stream of the same database.
+    ///
+    /// # Safety
     ///
-    /// Note: `buf` must correspond to a complete compressed representation created by `StreamRef::compress` of a stream
-    /// that was opened against `db`. It is not always possible to detect misuse of this API and behaviour is undefined
-    /// if these properties are not satisfied.
+    /// `buf` must correspond to a complete compressed representation created by `StreamRef::compress` of a stream
+    /// that was opened against the same database as the stream. It is not always possible to detect misuse of this API
+    /// and behaviour is undefined if these properties are not satisfied.
     ///
     /// # Examples
     ///
     /// ```rust
     /// # use hyperscan::prelude::*;
-    /// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
-    ///
-    /// let s = db.alloc_scratch().unwrap();
+    /// let db: StreamingDatabase = pattern! {"test"}.build().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let st = db.open_stream().unwrap();
     ///
-    /// let mut matches = vec![];
-    ///
-    /// let mut callback = |_, from, to, _| {
-    ///     matches.push((from, to));
-    ///
-    ///     Matching::Continue
-    /// };
-    ///
-    /// st.scan("foo t", &s, &mut callback).unwrap();
-    /// st.scan("es", &s, &mut callback).unwrap();
+    /// st.scan("foo te", &mut s, ()).unwrap();
     ///
-    /// let mut buf = [0; 8192];
-    /// let len = st.compress(&mut buf).unwrap();
-    /// st.scan("t bar", &s, &mut callback).unwrap();
+    /// let mut buf = vec![];
+    /// (*st).compress_into(&mut buf).unwrap();
     ///
-    /// st.reset_and_expand(&buf[..len], &s, &mut callback).unwrap();
-    /// st.scan("t bar", &s, &mut callback).unwrap();
-    /// st.close(&s, &mut callback).unwrap();
-    ///
-    /// assert_eq!(matches, vec![(4, 8), (4, 8)]);
+    /// // the raw representation was compressed by a stream of the same database
+    /// unsafe { (*st).reset_and_expand(&buf, &mut s, ()).unwrap() };
     /// ```
-    pub fn reset_and_expand<F>(&self, buf: &[u8], scratch: &ScratchRef, mut on_match_event: F) -> Result<()>
+    pub unsafe fn reset_and_expand<F>(&self, buf: &[u8], scratch: &mut ScratchRef, mut on_match_event: F) -> Result<()>
     where
         F: MatchEventHandler,
     {
-        unsafe {
-            let (callback, userdata) = on_match_event.split();
+        let (callback, userdata) = on_match_event.split();
 
+        guarded(|| {
             ffi::hs_reset_and_expand_stream(
                 self.as_ptr(),
                 buf.as_ptr() as *const _,
@@ -275,16 +708,14 @@ impl StreamRef {
                 userdata,
             )
             .ok()
-        }
+        })
     }
 }
 
 impl DatabaseRef<Streaming> {
-    /// Decompresses a compressed representation created by `StreamRef::compress()` into a new stream.
+    /// Decompresses a compressed representation created by `Stream::compress()` into a new stream.
     ///
-    /// Note: `buf` must correspond to a complete compressed representation created by `StreamRef::compress()` of a stream
-    /// that was opened against `db`. It is not always possible to detect misuse of this API and behaviour is undefined
-    /// if these properties are not satisfied.
+    /// `HsError::Invalid` is returned when `buf` wasn't compressed by a stream opened against the database.
     ///
     /// # Examples
     ///
@@ -292,7 +723,7 @@ impl DatabaseRef<Streaming> {
     /// # use hyperscan::prelude::*;
     /// let db: StreamingDatabase = pattern! {"test"; SOM_LEFTMOST}.build().unwrap();
     ///
-    /// let s = db.alloc_scratch().unwrap();
+    /// let mut s = db.alloc_scratch().unwrap();
     /// let st = db.open_stream().unwrap();
     ///
     /// let mut matches = vec![];
@@ -303,26 +734,397 @@ impl DatabaseRef<Streaming> {
     ///     Matching::Continue
     /// };
     ///
-    /// st.scan("foo t", &s, &mut callback).unwrap();
-    /// st.scan("es", &s, &mut callback).unwrap();
+    /// st.scan("foo t", &mut s, &mut callback).unwrap();
+    /// st.scan("es", &mut s, &mut callback).unwrap();
     ///
     /// let mut buf = [0; 8192];
     /// let len = st.compress(&mut buf).unwrap();
-    /// st.close(&s, Matching::Terminate).unwrap();
+    /// st.close(&mut s, Matching::Terminate).unwrap();
     ///
     /// let st2 = db.expand_stream(&buf[..len]).unwrap();
-    /// st2.scan("t bar", &s, &mut callback).unwrap();
-    /// st2.close(&s, &mut callback).unwrap();
+    /// st2.scan("t bar", &mut s, &mut callback).unwrap();
+    /// st2.close(&mut s, &mut callback).unwrap();
     ///
     /// assert_eq!(matches, vec![(4, 8)]);
     /// ```
-    pub fn expand_stream(&self, buf: &[u8]) -> Result<Stream> {
+    pub fn expand_stream(&self, buf: &[u8]) -> Result<Stream<'_>> {
+        let buf = compressed_state(buf, Some(self.tag()))?;
         let mut stream = MaybeUninit::uninit();
 
         unsafe {
             ffi::hs_expand_stream(self.as_ptr(), stream.as_mut_ptr(), buf.as_ptr() as *const _, buf.len())
                 .ok()
-                .map(|_| Stream::from_ptr(stream.assume_init()))
+                .map(|_| Stream::of(self, stream.assume_init()))
+        }
+    }
+}
+
+/// A writer which scans the data written to a stream, e.g. with `io::copy` from a file, a socket or a decompressor.
+///
+/// A `Write` can't stop without failing, so the termination requested by the match event handler
+/// is returned as an `io::ErrorKind::Other` error, which wraps `HsError::ScanTerminated`.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::io;
+/// # use hyperscan::prelude::*;
+/// let db: StreamingDatabase = pattern! {"foo$"; SOM_LEFTMOST}.build().unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+/// let mut matches = vec![];
+/// let mut writer = db.open_stream().unwrap().writer(&mut s, |_, from, to, _| {
+///     matches.push(from..to);
+///     Matching::Continue
+/// });
+///
+/// io::copy(&mut &b"foo bar foo"[..], &mut writer).unwrap();
+/// writer.finish().unwrap();
+///
+/// assert_eq!(matches, vec![8..11]);
+/// ```
+#[cfg(feature = "std")]
+pub struct StreamWriter<'a, 'db, F> {
+    stream: Stream<'db>,
+    scratch: &'a mut ScratchRef,
+    on_match_event: F,
+}
+
+#[cfg(feature = "std")]
+impl<'db> Stream<'db> {
+    /// Wrap the stream in a writer, which scans the written data with the match event handler.
+    pub fn writer<F>(self, scratch: &mut ScratchRef, on_match_event: F) -> StreamWriter<'_, 'db, F>
+    where
+        F: MatchEventHandler,
+    {
+        StreamWriter {
+            stream: self,
+            scratch,
+            on_match_event,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: MatchEventHandler> StreamWriter<'_, '_, F> {
+    /// Close the stream, and report the matches at the end of data to the match event handler.
+    pub fn finish(mut self) -> Result<()> {
+        let handler = unsafe { SplitHandler::of(&mut self.on_match_event) };
+
+        self.stream.close(self.scratch, handler)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<F: MatchEventHandler> std::io::Write for StreamWriter<'_, '_, F> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // the handler is only called during the scan, while it is borrowed
+        let handler = unsafe { SplitHandler::of(&mut self.on_match_event) };
+
+        match self.stream.scan(buf, self.scratch, handler) {
+            Ok(ScanOutcome::Completed) => Ok(buf.len()),
+            Ok(ScanOutcome::Terminated) => Err(std::io::Error::other(Error::Hyperscan(HsError::ScanTerminated))),
+            Err(err) => Err(std::io::Error::other(err)),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Write};
+    use std::panic;
+
+    use crate::prelude::*;
+    use crate::{Error, HsError, Match, SomHorizon};
+
+    #[test]
+    fn test_stream_size() {
+        let db: StreamingDatabase = pattern! {"fo+bar"}.build().unwrap();
+        let size = db.stream_size().unwrap();
+
+        assert!(size > 0);
+
+        let som: StreamingDatabase = Database::builder()
+            .som_horizon(SomHorizon::Large)
+            .pattern(pattern! {"fo+bar"; SOM_LEFTMOST})
+            .build()
+            .unwrap();
+
+        assert!(som.stream_size().unwrap() > size);
+    }
+
+    #[test]
+    fn test_stream_offsets() {
+        let db: StreamingDatabase = pattern! {"foobar"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+        let mut matches = vec![];
+
+        for chunk in &["foo", "bar", " foo", "bar"] {
+            st.scan(chunk, &mut s, |_, from, to, _| {
+                matches.push(from..to);
+                Matching::Continue
+            })
+            .unwrap();
+        }
+
+        st.close(&mut s, Matching::Continue).unwrap();
+
+        assert_eq!(matches, vec![0..6, 7..13]);
+    }
+
+    #[test]
+    fn test_stream_close_end_of_data() {
+        let db: StreamingDatabase = pattern! {"bar$"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+        let mut ends = vec![];
+
+        for chunk in &["foo", "bar"] {
+            st.scan(chunk, &mut s, |_, _, to, _| {
+                ends.push(to);
+                Matching::Continue
+            })
+            .unwrap();
+        }
+
+        assert!(ends.is_empty());
+
+        st.close(&mut s, |_, _, to, _| {
+            ends.push(to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(ends, vec![6]);
+    }
+
+    #[test]
+    fn test_stream_fork() {
+        let db: StreamingDatabase = pattern! {"foobar"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        st.scan("xfoo", &mut s, Matching::Continue).unwrap();
+
+        let fork = st.try_clone().unwrap();
+        let copy = db.open_stream().unwrap();
+
+        copy.scan("foo", &mut s, Matching::Continue).unwrap();
+        copy.reset_and_copy_stream(&st, &mut s, Matching::Continue).unwrap();
+
+        let mut matches = vec![];
+
+        for (i, stream) in [&st, &fork, &copy].iter().enumerate() {
+            stream
+                .scan("bar", &mut s, |_, from, to, _| {
+                    matches.push((i, from..to));
+                    Matching::Continue
+                })
+                .unwrap();
         }
+
+        // the forks are independent
+        fork.scan("foobar", &mut s, |_, from, to, _| {
+            matches.push((1, from..to));
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![(0, 1..7), (1, 1..7), (2, 1..7), (1, 7..13)]);
+
+        for stream in [st, fork, copy] {
+            stream.close(&mut s, Matching::Continue).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_stream_compress_into() {
+        let db: StreamingDatabase = pattern! {"foobar"; SOM_LEFTMOST}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        st.scan("xxfoo", &mut s, Matching::Continue).unwrap();
+
+        let mut buf = b"header".to_vec();
+        let len = st.compress_into(&mut buf).unwrap();
+
+        assert_eq!(buf.len(), 6 + len);
+        assert!(len > 0);
+
+        drop(st);
+
+        let mut matches = vec![];
+        let st = db.expand_stream(&buf[6..]).unwrap();
+
+        st.scan("bar", &mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        // expand on top of another stream, which is reset
+        let st2 = db.open_stream().unwrap();
+
+        st2.scan("foo", &mut s, Matching::Continue).unwrap();
+        st2.reset_and_expand(&buf[6..], &mut s, Matching::Continue).unwrap();
+        st2.scan("xbar", &mut s, |_, from, to, _| {
+            matches.push(from..to);
+            Matching::Continue
+        })
+        .unwrap();
+
+        assert_eq!(matches, vec![2..8]);
+
+        // a too small buffer is grown
+        let mut small = Vec::with_capacity(1);
+
+        assert_eq!(st.compress_into(&mut small).unwrap(), small.len());
+        assert!(small.len() > 1);
+    }
+
+    #[test]
+    fn test_stream_expand_other_database() {
+        let db: StreamingDatabase = pattern! {"foobar"; SOM_LEFTMOST}.build().unwrap();
+        let other: StreamingDatabase = (0..20)
+            .map(|i| pattern! {format!("baz{}(qux)+", i)})
+            .collect::<Patterns>()
+            .build()
+            .unwrap();
+        let mut s = Scratch::for_databases(&[&db, &other]).unwrap();
+        let st = db.open_stream().unwrap();
+
+        st.scan("xxfoo", &mut s, Matching::Continue).unwrap();
+
+        let mut buf = vec![];
+
+        st.compress_into(&mut buf).unwrap();
+
+        // the stream compressed by a stream of another database isn't expanded
+        assert_eq!(
+            other.expand_stream(&buf).err(),
+            Some(Error::Hyperscan(HsError::Invalid))
+        );
+
+        let st2 = other.open_stream().unwrap();
+
+        assert_eq!(
+            st2.reset_and_expand(&buf, &mut s, Matching::Continue).err(),
+            Some(Error::Hyperscan(HsError::Invalid))
+        );
+
+        // nor a truncated or a raw compressed stream
+        assert!(db.expand_stream(&buf[..8]).is_err());
+
+        let mut raw = vec![];
+
+        (*st).compress_into(&mut raw).unwrap();
+
+        assert!(db.expand_stream(&raw).is_err());
+
+        // a deserialized copy of the database expands the stream
+        let copy = StreamingDatabase::deserialize(db.serialize().unwrap()).unwrap();
+        let mut matches = vec![];
+
+        copy.expand_stream(&buf)
+            .unwrap()
+            .scan("bar", &mut s, |_, from, to, _| {
+                matches.push(from..to);
+                Matching::Continue
+            })
+            .unwrap();
+
+        assert_eq!(matches, vec![2..8]);
+    }
+
+    #[test]
+    fn test_stream_writer() {
+        // a reader returning odd-sized chunks
+        struct Chunked<'a>(&'a [u8]);
+
+        impl io::Read for Chunked<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(997);
+
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+
+                Ok(n)
+            }
+        }
+
+        let mut data = vec![];
+
+        for i in 0..300_000 {
+            write!(data, "{} ", i).unwrap();
+
+            if i % 1000 == 999 {
+                write!(data, "foo{}bar ", i).unwrap();
+            }
+        }
+
+        assert!(data.len() > 1 << 20);
+
+        let patterns: Patterns = r"1:/foo\d+bar/L
+2:/99999/"
+            .parse()
+            .unwrap();
+        let block: BlockDatabase = patterns.build().unwrap();
+        let expected = block.scan_matches(&data, &mut block.alloc_scratch().unwrap()).unwrap();
+
+        assert!(expected.len() > 300);
+
+        let db: StreamingDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let mut matches = vec![];
+        let mut writer = db.open_stream().unwrap().writer(&mut s, |id, from, to, _| {
+            matches.push(Match { id, from, to });
+            Matching::Continue
+        });
+
+        assert_eq!(io::copy(&mut Chunked(&data), &mut writer).unwrap(), data.len() as u64);
+
+        writer.finish().unwrap();
+
+        assert_eq!(matches, expected);
+
+        // the termination is returned as an I/O error
+        let mut writer = db
+            .open_stream()
+            .unwrap()
+            .writer(&mut s, |_, _, _, _| Matching::Terminate);
+        let err = io::copy(&mut Chunked(&data), &mut writer).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::Other);
+        assert!(err
+            .into_inner()
+            .unwrap()
+            .downcast::<Error>()
+            .unwrap()
+            .is_scan_terminated());
+    }
+
+    #[test]
+    fn test_stream_dropped_on_panic() {
+        let db: StreamingDatabase = pattern! {"foo"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        // the unclosed stream is freed while unwinding, without reporting its matches
+        let res = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+            let st = db.open_stream().unwrap();
+
+            st.scan("foo", &mut s, Matching::Continue).unwrap();
+
+            panic!("scan failed");
+        }));
+
+        assert!(res.is_err());
+
+        let st = db.open_stream().unwrap();
+
+        drop(st);
     }
 }
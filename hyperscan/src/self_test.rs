@@ -0,0 +1,272 @@
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::{
+    common::{build_info, version_string, BlockDatabase, BuildInfo, StreamingDatabase},
+    compile::{Builder, Patterns, Platform},
+    runtime::Match,
+    Error, Result,
+};
+
+/// The embedded patterns, `$` is only matched at the end of data, when the stream is closed.
+const PATTERNS: &str = "1:/foo/\n2:/ba+r$/";
+const DATA: &str = "a foo and baar";
+const CHUNKS: &[&str] = &["a fo", "o and ba", "ar"];
+const EXPECTED: &[(u32, u64)] = &[(1, 5), (2, 14)];
+
+/// A stage of the [`self_test`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SelfTestStage {
+    /// The host supports the instruction set required by Hyperscan.
+    Platform,
+    /// The version of the linked library matches the headers used at build time.
+    Version,
+    /// The embedded patterns are compiled in block and streaming mode.
+    Compile,
+    /// The scratch space is allocated for the databases.
+    Scratch,
+    /// The block scan finds the expected matches.
+    BlockScan,
+    /// The stream scan finds the expected matches, across the chunks and at the end of data.
+    StreamScan,
+    /// The embedded Chimera pattern is compiled with the capture groups.
+    #[cfg(feature = "chimera")]
+    ChimeraCompile,
+    /// The Chimera scan finds the expected match and captures.
+    #[cfg(feature = "chimera")]
+    ChimeraScan,
+}
+
+impl fmt::Display for SelfTestStage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SelfTestStage::Platform => "platform",
+            SelfTestStage::Version => "version",
+            SelfTestStage::Compile => "compile",
+            SelfTestStage::Scratch => "scratch",
+            SelfTestStage::BlockScan => "block scan",
+            SelfTestStage::StreamScan => "stream scan",
+            #[cfg(feature = "chimera")]
+            SelfTestStage::ChimeraCompile => "chimera compile",
+            #[cfg(feature = "chimera")]
+            SelfTestStage::ChimeraScan => "chimera scan",
+        })
+    }
+}
+
+/// A stage which passed the [`self_test`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SelfTestStageReport {
+    /// The stage.
+    pub stage: SelfTestStage,
+    /// The time to run the stage.
+    pub duration: Duration,
+}
+
+/// The report of a passed [`self_test`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestReport {
+    /// The version string of the linked library.
+    pub version: &'static str,
+    /// How the library was built and linked.
+    pub build_info: BuildInfo,
+    /// The stages in the running order, all of them passed.
+    pub stages: Vec<SelfTestStageReport>,
+}
+
+impl SelfTestReport {
+    fn run<T, F>(&mut self, stage: SelfTestStage, f: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        let started = Instant::now();
+        let res = f().map_err(|err| match err {
+            Error::SelfTest { .. } => err,
+            err => Error::SelfTest {
+                stage,
+                reason: err.to_string(),
+            },
+        })?;
+
+        self.stages.push(SelfTestStageReport {
+            stage,
+            duration: started.elapsed(),
+        });
+
+        Ok(res)
+    }
+}
+
+fn expect<T: fmt::Debug + PartialEq>(stage: SelfTestStage, expected: T, found: T) -> Result<()> {
+    if expected == found {
+        Ok(())
+    } else {
+        Err(Error::SelfTest {
+            stage,
+            reason: format!("expected {:?}, found {:?}", expected, found),
+        })
+    }
+}
+
+/// Verify the linked Hyperscan library actually works, for example before a service takes traffic.
+///
+/// The stages check the platform, the version of the library, and compile, allocate the scratch space
+/// and scan a small embedded pattern set in block and streaming mode, with the known expected matches.
+/// With the `chimera` feature, a pattern with the capture groups is also compiled and scanned with Chimera.
+///
+/// This catches a broken installation, for example a wrong runtime library, a missing instruction set,
+/// or a missing PCRE library, at the startup instead of the first scan.
+/// The first stage which fails is returned as `Error::SelfTest`, with the stage and the reason.
+///
+/// # Examples
+///
+/// ```rust
+/// let report = hyperscan::self_test().unwrap();
+///
+/// for stage in &report.stages {
+///     println!("{}: passed in {:?}", stage.stage, stage.duration);
+/// }
+/// ```
+pub fn self_test() -> Result<SelfTestReport> {
+    let mut report = SelfTestReport {
+        version: version_string(),
+        build_info: build_info(),
+        stages: vec![],
+    };
+
+    let build_info = report.build_info;
+
+    report.run(SelfTestStage::Platform, Platform::is_valid)?;
+    report.run(SelfTestStage::Version, || build_info.verify())?;
+
+    let (block, streaming) = report.run(SelfTestStage::Compile, || {
+        let patterns: Patterns = PATTERNS.parse()?;
+        let block: BlockDatabase = patterns.build()?;
+        let streaming: StreamingDatabase = patterns.build()?;
+
+        Ok((block, streaming))
+    })?;
+    let mut scratch = report.run(SelfTestStage::Scratch, || {
+        let mut scratch = block.alloc_scratch()?;
+
+        streaming.realloc_scratch(&mut scratch)?;
+
+        Ok(scratch)
+    })?;
+
+    report.run(SelfTestStage::BlockScan, || {
+        let mut matches = vec![];
+
+        block.scan(DATA, &mut scratch, Match::collect(&mut matches))?;
+
+        expect(SelfTestStage::BlockScan, EXPECTED, ends(&matches).as_slice())
+    })?;
+    report.run(SelfTestStage::StreamScan, || {
+        let mut matches = vec![];
+        let stream = streaming.open_stream()?;
+
+        for chunk in CHUNKS {
+            stream.scan(chunk, &mut scratch, Match::collect(&mut matches))?;
+        }
+
+        stream.close(&mut scratch, Match::collect(&mut matches))?;
+
+        expect(SelfTestStage::StreamScan, EXPECTED, ends(&matches).as_slice())
+    })?;
+
+    #[cfg(feature = "chimera")]
+    chimera::self_test(&mut report)?;
+
+    Ok(report)
+}
+
+fn ends(matches: &[Match]) -> Vec<(u32, u64)> {
+    matches.iter().map(|m| (m.id, m.to)).collect()
+}
+
+#[cfg(feature = "chimera")]
+mod chimera {
+    use std::ops::Range;
+
+    use super::{expect, SelfTestReport, SelfTestStage};
+    use crate::{
+        chimera::{Builder, Capture, Matching, Pattern},
+        Result,
+    };
+
+    const PATTERN: &str = r"/(\w+)@(\w+)/";
+    const DATA: &str = "mail bob@example now";
+
+    pub fn self_test(report: &mut SelfTestReport) -> Result<()> {
+        let db = report.run(SelfTestStage::ChimeraCompile, || {
+            let pattern: Pattern = PATTERN.parse()?;
+
+            pattern.with_groups()
+        })?;
+
+        report.run(SelfTestStage::ChimeraScan, || {
+            let scratch = db.alloc_scratch()?;
+            let mut captures = vec![];
+
+            db.scan(
+                DATA,
+                &scratch,
+                |_, _, _, _, captured: Option<&[Capture]>| {
+                    captures.push(captured.map(|captured| captured.iter().map(Capture::range).collect::<Vec<_>>()));
+
+                    Matching::Continue
+                },
+                |_, _| Matching::Terminate,
+            )?;
+
+            let expected: Vec<Option<Vec<Range<usize>>>> = vec![Some(vec![5..16, 5..8, 9..16])];
+
+            expect(SelfTestStage::ChimeraScan, expected, captures)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test() {
+        let report = self_test().unwrap();
+        #[cfg(feature = "chimera")]
+        let last = SelfTestStage::ChimeraScan;
+        #[cfg(not(feature = "chimera"))]
+        let last = SelfTestStage::StreamScan;
+
+        assert_eq!(report.version, version_string());
+        assert_eq!(report.stages[0].stage, SelfTestStage::Platform);
+        assert_eq!(report.stages.last().unwrap().stage, last);
+    }
+
+    #[test]
+    fn test_stage_error() {
+        let mut report = self_test().unwrap();
+        let err = report
+            .run(SelfTestStage::BlockScan, || {
+                expect(SelfTestStage::BlockScan, EXPECTED, &[(1, 5)][..])
+            })
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "self test failed at the block scan stage, expected [(1, 5), (2, 14)], found [(1, 5)]"
+        );
+
+        let err = report
+            .run(SelfTestStage::Compile, || "1:/foo/Z".parse::<Patterns>().map(|_| ()))
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            Error::SelfTest {
+                stage: SelfTestStage::Compile,
+                reason: "invalid pattern flag: Z".to_owned(),
+            }
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+use std::ops::Deref;
+
+use crate::{
+    common::{Block, Database, Mode},
+    compile::{Builder, Pattern, Patterns, PlatformRef},
+    runtime::{IdFilter, Match, Matching, ScratchRef},
+    Error, Result, ScanOutcome,
+};
+
+/// A database which keeps a tag of each pattern, such as the rule it was loaded from,
+/// and reports the tag of the matched pattern with each match.
+///
+/// The tags are stored in the order of the patterns, and looked up by the pattern ID,
+/// so the IDs may be sparse. A pattern without ID uses its index, as the compiler does.
+///
+/// With the `serde` feature, the database is serialized with its tags, when they are serializable.
+///
+/// # Examples
+///
+/// ```rust
+/// # use hyperscan::{prelude::*, TaggedDatabase};
+/// let db: TaggedDatabase<&str> = TaggedDatabase::build(vec![
+///     (pattern! {10 => "foo"; CASELESS}, "low"),
+///     (pattern! {20 => "bar"; CASELESS}, "high"),
+/// ])
+/// .unwrap();
+/// let mut s = db.alloc_scratch().unwrap();
+///
+/// let matches = db.matches("FOObar", &mut s).unwrap();
+///
+/// assert_eq!(
+///     matches.iter().map(|&(m, tag)| (m.id, *tag)).collect::<Vec<_>>(),
+///     vec![(10, "low"), (20, "high")]
+/// );
+/// ```
+pub struct TaggedDatabase<T, M = Block> {
+    db: Database<M>,
+    tags: Vec<T>,
+    slots: HashMap<u32, usize>,
+}
+
+impl<T, M> Deref for TaggedDatabase<T, M> {
+    type Target = Database<M>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.db
+    }
+}
+
+impl<T, M: Mode> TaggedDatabase<T, M> {
+    /// Compile the tagged patterns for the host.
+    pub fn build<I>(tagged: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = (Pattern, T)>,
+    {
+        Self::for_platform(tagged, None)
+    }
+
+    /// Compile the tagged patterns for the target platform.
+    ///
+    /// The patterns with the same ID are rejected as `Error::InvalidOptions`, their tags would be ambiguous.
+    pub fn for_platform<I>(tagged: I, platform: Option<&PlatformRef>) -> Result<Self>
+    where
+        I: IntoIterator<Item = (Pattern, T)>,
+    {
+        let (patterns, tags): (Vec<Pattern>, Vec<T>) = tagged.into_iter().unzip();
+        let patterns = Patterns(patterns);
+        let slots = slots(patterns.ids().map(|id| id as u32))?;
+        let db = patterns.for_platform(platform)?;
+
+        Ok(TaggedDatabase { db, tags, slots })
+    }
+}
+
+impl<T, M> TaggedDatabase<T, M> {
+    /// Returns the tag of the pattern.
+    pub fn tag(&self, id: u32) -> Option<&T> {
+        self.slots.get(&id).map(|&slot| &self.tags[slot])
+    }
+
+    /// Returns the tags in the order of the patterns.
+    pub fn tags(&self) -> &[T] {
+        &self.tags
+    }
+
+    /// Wrap the match handler, which receives the matches with the tag of the matched pattern.
+    ///
+    /// The handler can be passed to any scan function of the database, for example to scan a stream.
+    pub fn tagged<'a, F>(&'a self, mut on_match: F) -> impl FnMut(u32, u64, u64, u32) -> Matching + 'a
+    where
+        F: FnMut(Match, &'a T) -> Matching + 'a,
+    {
+        move |id, from, to, _| match self.tag(id) {
+            Some(tag) => on_match(Match { id, from, to }, tag),
+            None => Matching::Continue,
+        }
+    }
+}
+
+impl<T, M> From<&TaggedDatabase<T, M>> for IdFilter {
+    /// Create a filter sized to the largest ID of the compiled patterns.
+    fn from(db: &TaggedDatabase<T, M>) -> IdFilter {
+        IdFilter::new(db.slots.keys().copied().max().unwrap_or_default())
+    }
+}
+
+impl<T> TaggedDatabase<T, Block> {
+    /// Scan the data, the match handler receives the matches with the tag of the matched pattern.
+    pub fn scan<'a, D, F>(&'a self, data: D, scratch: &mut ScratchRef, mut on_match: F) -> Result<ScanOutcome>
+    where
+        D: AsRef<[u8]>,
+        F: FnMut(Match, &'a T) -> Matching,
+    {
+        self.db.scan(data, scratch, |id, from, to, _| match self.tag(id) {
+            Some(tag) => on_match(Match { id, from, to }, tag),
+            None => Matching::Continue,
+        })
+    }
+
+    /// Scan the data, and returns all the matches with the tag of the matched pattern.
+    pub fn matches<D: AsRef<[u8]>>(&self, data: D, scratch: &mut ScratchRef) -> Result<Vec<(Match, &T)>> {
+        let mut matches = vec![];
+
+        self.scan(data, scratch, |m, tag| {
+            matches.push((m, tag));
+            Matching::Continue
+        })?;
+
+        Ok(matches)
+    }
+}
+
+fn slots<I: IntoIterator<Item = u32>>(ids: I) -> Result<HashMap<u32, usize>> {
+    let mut slots = HashMap::new();
+
+    for (slot, id) in ids.into_iter().enumerate() {
+        if slots.insert(id, slot).is_some() {
+            return Err(Error::InvalidOptions(format!("duplicate pattern id {}", id)));
+        }
+    }
+
+    Ok(slots)
+}
+
+#[cfg(feature = "serde")]
+mod serde_impl {
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{slots, TaggedDatabase};
+    use crate::common::{DatabaseRef, Mode, Serialized};
+
+    #[derive(Serialize)]
+    struct Ser<'a, T> {
+        database: &'a [u8],
+        tags: Vec<(u32, &'a T)>,
+    }
+
+    #[derive(Deserialize)]
+    struct De<T> {
+        database: Vec<u8>,
+        tags: Vec<(u32, T)>,
+    }
+
+    impl<T: Serialize, M> Serialize for TaggedDatabase<T, M> {
+        /// Serialize the database and the pattern IDs with their tags.
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            let database = DatabaseRef::serialize(&self.db).map_err(ser::Error::custom)?;
+            let mut tags = self.slots.iter().map(|(&id, &slot)| (slot, id)).collect::<Vec<_>>();
+
+            tags.sort_unstable();
+
+            Ser {
+                database: &database,
+                tags: tags.into_iter().map(|(slot, id)| (id, &self.tags[slot])).collect(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>, M: Mode> Deserialize<'de> for TaggedDatabase<T, M> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let De { database, tags } = De::<T>::deserialize(deserializer)?;
+            let db = database.deserialize().map_err(de::Error::custom)?;
+            let slots = slots(tags.iter().map(|&(id, _)| id)).map_err(de::Error::custom)?;
+
+            Ok(TaggedDatabase {
+                db,
+                tags: tags.into_iter().map(|(_, tag)| tag).collect(),
+                slots,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::Streaming;
+
+    #[derive(Clone, Debug, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Rule {
+        name: String,
+        severity: u8,
+    }
+
+    fn rule(name: &str, severity: u8) -> Rule {
+        Rule {
+            name: name.to_owned(),
+            severity,
+        }
+    }
+
+    fn tagged() -> Vec<(Pattern, Rule)> {
+        vec![
+            ("1000:/foo/".parse().unwrap(), rule("foo", 1)),
+            (pattern! {7 => "ba+r"; CASELESS}, rule("bar", 3)),
+            (pattern! {"baz"}, rule("baz", 2)),
+        ]
+    }
+
+    fn names<M>(db: &TaggedDatabase<Rule, M>, matches: &[(Match, &Rule)]) -> Vec<(u32, String)> {
+        matches
+            .iter()
+            .map(|(m, tag)| {
+                assert_eq!(db.tag(m.id), Some(*tag));
+
+                (m.id, tag.name.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_tagged_database() {
+        let db: TaggedDatabase<Rule> = TaggedDatabase::build(tagged()).unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let matches = db.matches("foo BAAR baz", &mut s).unwrap();
+
+        assert_eq!(
+            names(&db, &matches),
+            vec![(1000, "foo".to_owned()), (7, "bar".to_owned()), (2, "baz".to_owned())]
+        );
+        assert_eq!(db.tag(2), Some(&rule("baz", 2)));
+        assert_eq!(db.tag(1), None);
+        assert_eq!(db.tags().len(), 3);
+    }
+
+    #[test]
+    fn test_tagged_stream() {
+        let db: TaggedDatabase<Rule, Streaming> = TaggedDatabase::build(tagged()).unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+        let mut severities = vec![];
+
+        for chunk in &["fo", "o ba", "ar"] {
+            st.scan(
+                chunk,
+                &mut s,
+                db.tagged(|m, rule| {
+                    severities.push((m.id, m.to, rule.severity));
+                    Matching::Continue
+                }),
+            )
+            .unwrap();
+        }
+
+        st.close(&mut s, Matching::Continue).unwrap();
+
+        assert_eq!(severities, vec![(1000, 3, 1), (7, 8, 3)]);
+    }
+
+    #[test]
+    fn test_duplicate_ids() {
+        let err = TaggedDatabase::<_, Block>::build(vec![("1:/foo/".parse().unwrap(), 1), (pattern! {"bar"}, 2)])
+            .err()
+            .unwrap();
+
+        assert_eq!(err, Error::InvalidOptions("duplicate pattern id 1".to_owned()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serialize_tags() {
+        let db: TaggedDatabase<Rule> = TaggedDatabase::build(tagged()).unwrap();
+        let json = serde_json::to_string(&db).unwrap();
+        let db: TaggedDatabase<Rule> = serde_json::from_str(&json).unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let matches = db.matches("baz foo", &mut s).unwrap();
+
+        assert_eq!(
+            names(&db, &matches),
+            vec![(2, "baz".to_owned()), (1000, "foo".to_owned())]
+        );
+        assert_eq!(
+            db.tags(),
+            &tagged().into_iter().map(|(_, tag)| tag).collect::<Vec<_>>()[..]
+        );
+    }
+}
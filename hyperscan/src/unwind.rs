@@ -0,0 +1,64 @@
+//! Carry the panics of the match handlers over the C frames of Hyperscan.
+//!
+//! A panic can't unwind across an `extern "C"` function, so the trampolines call the handlers with [`catch`],
+//! which stashes the payload in a thread local and returns `None` to let the trampoline terminate the scan.
+//! The scan functions call [`resume`] after the C function returns, to resume the panic on the Rust side.
+//!
+//! Without the `std` feature the panics can't be caught, and the handlers are called as is.
+
+#[cfg(feature = "std")]
+use std::{
+    any::Any,
+    cell::Cell,
+    panic::{self, AssertUnwindSafe},
+};
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    static PANIC: Cell<Option<Box<dyn Any + Send>>> = Cell::new(None);
+}
+
+/// Call the handler, and returns `None` after stashing its panic.
+#[cfg(feature = "std")]
+pub(crate) fn catch<R, F: FnOnce() -> R>(f: F) -> Option<R> {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(res) => Some(res),
+        Err(payload) => {
+            PANIC.with(|stashed| {
+                // the scan is terminated after the first panic, keep it if another handler panics
+                let first = stashed.take().unwrap_or(payload);
+
+                stashed.set(Some(first));
+            });
+
+            None
+        }
+    }
+}
+
+/// Call the handler.
+#[cfg(not(feature = "std"))]
+pub(crate) fn catch<R, F: FnOnce() -> R>(f: F) -> Option<R> {
+    Some(f())
+}
+
+/// Resume the panic of a handler stashed while the C function was running.
+#[cfg(feature = "std")]
+pub(crate) fn resume() {
+    if let Some(payload) = PANIC.with(Cell::take) {
+        panic::resume_unwind(payload)
+    }
+}
+
+/// Nothing was stashed.
+#[cfg(not(feature = "std"))]
+pub(crate) fn resume() {}
+
+/// Call the C function with the trampolines, then resume the panic of a handler.
+pub(crate) fn guarded<T, F: FnOnce() -> T>(f: F) -> T {
+    let res = f();
+
+    resume();
+
+    res
+}
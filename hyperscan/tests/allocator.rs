@@ -0,0 +1,80 @@
+//! Assert the allocations of Hyperscan are accounted when routed through the Rust allocator.
+//!
+//! The allocator can only be installed before any database exists, so this test runs in its own process.
+#![cfg(feature = "compile")]
+
+use hyperscan::{
+    allocator::{self, Subsystem},
+    prelude::*,
+    Error,
+};
+
+fn stats(subsystem: Subsystem) -> allocator::AllocStats {
+    allocator::stats(subsystem).unwrap()
+}
+
+#[test]
+fn test_accounting() {
+    assert!(allocator::stats(Subsystem::Database).is_none());
+
+    allocator::install_with_accounting().unwrap();
+
+    for &subsystem in &Subsystem::ALL {
+        assert_eq!(stats(subsystem), Default::default());
+    }
+
+    let db: BlockDatabase = pattern! {"test"}.build().unwrap();
+    let database = stats(Subsystem::Database);
+
+    assert!(database.current >= db.size().unwrap());
+    assert_eq!(database.allocations, 1);
+
+    assert_eq!(allocator::install(), Err(Error::AllocatorInUse));
+
+    let mut s = db.alloc_scratch().unwrap();
+    let scratch = stats(Subsystem::Scratch);
+
+    assert!(scratch.current >= s.size().unwrap());
+
+    db.scan("foo test bar", &mut s, ()).unwrap();
+
+    assert_eq!(stats(Subsystem::Scratch), scratch);
+
+    let buf = db.serialize().unwrap();
+    let misc = stats(Subsystem::Misc);
+
+    assert!(misc.current >= buf.len());
+    assert_eq!(misc.allocations, 1);
+
+    drop(buf);
+    db.info().unwrap();
+    pattern! {"test"}.info().unwrap();
+
+    let misc = stats(Subsystem::Misc);
+
+    assert_eq!(misc.current, 0);
+    assert_eq!(misc.allocations, 3);
+
+    let streaming: StreamingDatabase = pattern! {"test"}.build().unwrap();
+    let mut s = streaming.alloc_scratch().unwrap();
+    let st = streaming.open_stream().unwrap();
+    let stream = stats(Subsystem::Stream);
+
+    assert!(stream.current > 0);
+    assert_eq!(stream.allocations, 1);
+
+    st.scan("foo te", &mut s, ()).unwrap();
+    st.scan("st", &mut s, ()).unwrap();
+    st.close(&mut s, ()).unwrap();
+
+    let stream = stats(Subsystem::Stream);
+
+    assert_eq!(stream.current, 0);
+    assert!(stream.peak > 0);
+
+    drop(streaming);
+    drop(db);
+
+    assert_eq!(stats(Subsystem::Database).current, 0);
+    assert_eq!(stats(Subsystem::Database).allocations, 2);
+}
@@ -0,0 +1,103 @@
+//! Scan the fixture tree under `tests/fixtures/corpus` with known matches.
+#![cfg(all(feature = "mmap", feature = "rayon"))]
+
+use std::path::{Path, PathBuf};
+
+use hyperscan::{
+    corpus::{scan_dir, FileReport, ScanOptions},
+    prelude::*,
+    Error,
+};
+
+const RULES: &str = "1:/foo/\n2:/bar/";
+
+type Summary = Vec<(String, Result<Vec<(u32, u64)>, Error>)>;
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus")
+}
+
+fn summary(reports: impl Iterator<Item = FileReport>) -> Summary {
+    reports
+        .map(|report| {
+            (
+                report
+                    .path
+                    .strip_prefix(root())
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace('\\', "/"),
+                report
+                    .result
+                    .map(|matches| matches.into_iter().map(|m| (m.id, m.to)).collect()),
+            )
+        })
+        .collect()
+}
+
+fn expected() -> Summary {
+    vec![
+        ("a.txt".to_owned(), Ok(vec![(1, 3), (2, 7)])),
+        ("nested/b.log".to_owned(), Ok(vec![(2, 3), (1, 7), (1, 11)])),
+        ("nested/empty.txt".to_owned(), Ok(vec![])),
+    ]
+}
+
+#[test]
+fn test_scan_dir() {
+    let patterns: Patterns = RULES.parse().unwrap();
+    let db: BlockDatabase = patterns.build().unwrap();
+
+    let reports = scan_dir(root(), &db, ScanOptions::default()).unwrap();
+
+    assert_eq!(summary(reports), expected());
+}
+
+#[test]
+fn test_scan_large_files_in_streaming_mode() {
+    let patterns: Patterns = RULES.parse().unwrap();
+    let db: BlockDatabase = patterns.build().unwrap();
+    let streaming: StreamingDatabase = patterns.build().unwrap();
+
+    let options = ScanOptions::default()
+        .stream_threshold(4)
+        .chunk_size(3)
+        .streaming(streaming);
+    let reports = scan_dir(root(), &db, options).unwrap();
+
+    assert_eq!(summary(reports), expected());
+
+    let reports = scan_dir(root(), &db, ScanOptions::default().stream_threshold(4)).unwrap();
+
+    assert_eq!(
+        summary(reports),
+        vec![
+            ("a.txt".to_owned(), Err(Error::FileTooLarge(8))),
+            ("nested/b.log".to_owned(), Err(Error::FileTooLarge(12))),
+            ("nested/empty.txt".to_owned(), Ok(vec![])),
+        ]
+    );
+}
+
+#[test]
+fn test_size_filters() {
+    let patterns: Patterns = RULES.parse().unwrap();
+    let db: BlockDatabase = patterns.build().unwrap();
+
+    let reports = scan_dir(root(), &db, ScanOptions::default().min_size(1).max_size(10)).unwrap();
+
+    assert_eq!(summary(reports), expected().into_iter().take(1).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_walk_error_is_reported() {
+    let db: BlockDatabase = pattern! {"foo"}.build().unwrap();
+
+    let reports = scan_dir(root().join("missing"), &db, ScanOptions::default())
+        .unwrap()
+        .collect::<Vec<_>>();
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].path, root().join("missing"));
+    assert!(matches!(reports[0].result, Err(Error::Io(_))));
+}
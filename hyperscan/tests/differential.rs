@@ -0,0 +1,101 @@
+//! Compare the matches of Hyperscan with the `regex` crate on random patterns and haystacks.
+//!
+//! The patterns are generated from a subset shared by both dialects, without anchors or assertions,
+//! so a match only depends on the bytes it covers. Hyperscan reports every end of match with the leftmost start
+//! (`SOM_LEFTMOST`), while `regex` reports the leftmost-first non-overlapping matches, so the results are normalized:
+//!
+//! - every `regex` match ends at a reported end,
+//! - a match is reported at an end if and only if `(?:p)$` matches the prefix before it,
+//! - the reported start is the leftmost one, where `^(?:p)$` matches the range.
+#![cfg(feature = "proptest")]
+
+use std::collections::BTreeSet;
+
+use hyperscan::prelude::*;
+use proptest::prelude::*;
+use regex::Regex;
+
+const LEAVES: &[&str] = &["a", "b", "c", "[ab]", "[^c]", "."];
+const QUANTIFIERS: &[&str] = &["?", "*", "+", "{2}", "{1,3}"];
+
+fn expr() -> impl Strategy<Value = String> {
+    prop::sample::select(LEAVES)
+        .prop_map(str::to_owned)
+        .prop_recursive(3, 16, 3, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 2..4).prop_map(|exprs| exprs.concat()),
+                (inner.clone(), inner.clone()).prop_map(|(lhs, rhs)| format!("(?:{}|{})", lhs, rhs)),
+                (inner, prop::sample::select(QUANTIFIERS)).prop_map(|(expr, q)| format!("(?:{}){}", expr, q)),
+            ]
+        })
+        .prop_filter("Hyperscan rejects the patterns matching the empty string", |expr| {
+            !Regex::new(expr).unwrap().is_match("")
+        })
+}
+
+fn haystack() -> impl Strategy<Value = String> {
+    "[abc]{0,24}"
+}
+
+fn hyperscan_matches(exprs: &[String], haystack: &str) -> Result<Vec<(u32, u64, u64)>, TestCaseError> {
+    let patterns = exprs
+        .iter()
+        .enumerate()
+        .map(|(id, expr)| Pattern {
+            id: Some(id),
+            ..pattern! { expr.as_str(); SOM_LEFTMOST }
+        })
+        .collect::<Patterns>();
+    let db: BlockDatabase = patterns
+        .build()
+        .map_err(|err| TestCaseError::fail(format!("compile {:?}: {}", exprs, err)))?;
+    let mut s = db.alloc_scratch().unwrap();
+    let mut matches = vec![];
+
+    db.scan(haystack, &mut s, |id, from, to, _| {
+        matches.push((id, from, to));
+        Matching::Continue
+    })
+    .unwrap();
+
+    Ok(matches)
+}
+
+proptest! {
+    #[test]
+    fn test_matches_agree_with_regex(exprs in prop::collection::vec(expr(), 1..4), haystack in haystack()) {
+        let matches = hyperscan_matches(&exprs, &haystack)?;
+        let ends = matches.iter().map(|&(id, _, to)| (id, to as usize)).collect::<BTreeSet<_>>();
+
+        prop_assert_eq!(ends.len(), matches.len(), "duplicated ends: {:?}", matches);
+
+        for (id, expr) in exprs.iter().enumerate() {
+            let id = id as u32;
+            let re = Regex::new(expr).unwrap();
+            let at_end = Regex::new(&format!("(?:{})$", expr)).unwrap();
+            let whole = Regex::new(&format!("^(?:{})$", expr)).unwrap();
+
+            for m in re.find_iter(&haystack) {
+                prop_assert!(ends.contains(&(id, m.end())), "/{}/ missed {:?}", expr, m);
+            }
+
+            for end in 0..=haystack.len() {
+                prop_assert_eq!(
+                    ends.contains(&(id, end)),
+                    at_end.is_match(&haystack[..end]),
+                    "/{}/ at {}", expr, end
+                );
+            }
+
+            for &(_, from, to) in matches.iter().filter(|m| m.0 == id) {
+                let (from, to) = (from as usize, to as usize);
+
+                prop_assert!(whole.is_match(&haystack[from..to]), "/{}/ at {}..{}", expr, from, to);
+                prop_assert!(
+                    (0..from).all(|start| !whole.is_match(&haystack[start..to])),
+                    "/{}/ at {}..{} is not leftmost", expr, from, to
+                );
+            }
+        }
+    }
+}
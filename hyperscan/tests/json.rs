@@ -0,0 +1,80 @@
+//! Snapshots of the JSON shape of the serialized matches and reports.
+#![cfg(feature = "serde")]
+
+use serde_json::json;
+
+use hyperscan::{prelude::*, Match};
+
+#[test]
+fn test_match() {
+    let m = Match { id: 2, from: 3, to: 6 };
+
+    assert_eq!(serde_json::to_value(m).unwrap(), json!({"id": 2, "from": 3, "to": 6}));
+}
+
+#[test]
+fn test_resolved_match() {
+    let patterns: Patterns = "3:/foo/\n/bar/".parse().unwrap();
+
+    assert_eq!(
+        serde_json::to_value(Match { id: 3, from: 0, to: 3 }.resolve(&patterns)).unwrap(),
+        json!({"id": 3, "from": 0, "to": 3, "pattern": "foo"})
+    );
+    // the pattern without ID is matched with its index
+    assert_eq!(
+        serde_json::to_value(Match { id: 1, from: 3, to: 6 }.resolve(&patterns)).unwrap(),
+        json!({"id": 1, "from": 3, "to": 6, "pattern": "bar"})
+    );
+    assert_eq!(Match { id: 0, from: 0, to: 3 }.resolve(&patterns), None);
+}
+
+#[cfg(feature = "bstr")]
+#[test]
+fn test_line_match() {
+    use hyperscan::lines::LineIndex;
+
+    let index = LineIndex::new(b"foo\r\nbar \xff\n");
+    let m = index.line_match(Match { id: 0, from: 5, to: 8 }).to_owned();
+
+    assert_eq!(
+        serde_json::to_value(m).unwrap(),
+        json!({"id": 0, "from": 5, "to": 8, "line_no": 2, "column": 1, "line": "bar \u{fffd}"})
+    );
+}
+
+#[cfg(all(feature = "mmap", feature = "rayon"))]
+#[test]
+fn test_file_report() {
+    use std::path::Path;
+
+    use hyperscan::corpus::{scan_dir, ScanOptions};
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/corpus");
+    let db: BlockDatabase = "1:/foo/".parse::<Patterns>().unwrap().build().unwrap();
+
+    let reports = scan_dir(&root, &db, ScanOptions::default().stream_threshold(10))
+        .unwrap()
+        .map(|report| serde_json::to_value(report).unwrap())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        reports,
+        vec![
+            json!({
+                "path": root.join("a.txt").to_string_lossy(),
+                "size": 8,
+                "matches": [{"id": 1, "from": 0, "to": 3}],
+            }),
+            json!({
+                "path": root.join("nested/b.log").to_string_lossy(),
+                "size": 12,
+                "error": "file of 12 bytes is too large to scan in block mode",
+            }),
+            json!({
+                "path": root.join("nested/empty.txt").to_string_lossy(),
+                "size": 0,
+                "matches": [],
+            }),
+        ]
+    );
+}
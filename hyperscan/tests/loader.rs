@@ -0,0 +1,127 @@
+//! Load the rule sets under `tests/fixtures/rules` with known problems.
+#![cfg(feature = "compile")]
+
+use std::path::{Path, PathBuf};
+
+use hyperscan::{
+    loader::{Diagnostic, FileCount, RuleSetLoader, WidthPolicy},
+    prelude::*,
+    Block, PatternFlags,
+};
+
+const POLICY: WidthPolicy = WidthPolicy {
+    min_width: 3,
+    max_width: Some(64),
+};
+
+fn root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/rules")
+}
+
+fn at(file: &str, line: usize, message: &str) -> Diagnostic {
+    Diagnostic {
+        path: Some(root().join(file)),
+        line: Some(line),
+        message: message.to_owned(),
+    }
+}
+
+#[test]
+fn test_report_all_errors() {
+    let report = RuleSetLoader::new()
+        .file(root().join("web.rules"))
+        .file(root().join("mail.rules"))
+        .file(root().join("missing.rules"))
+        .validate(POLICY)
+        .build::<Block>()
+        .err()
+        .unwrap();
+
+    assert!(!report.is_ok());
+    assert_eq!(report.errors.len(), 3);
+    assert_eq!(report.errors[0], at("web.rules", 4, "invalid pattern flag: Z"));
+    assert_eq!(report.errors[1].path, Some(root().join("missing.rules")));
+    assert_eq!(report.errors[1].line, None);
+    assert_eq!(
+        report.errors[2],
+        at(
+            "mail.rules",
+            3,
+            &format!(
+                "duplicate pattern id 2, first defined at {}:3",
+                root().join("web.rules").display()
+            )
+        )
+    );
+    assert_eq!(report.warnings, vec![at("web.rules", 3, "match width is unbounded")]);
+    assert_eq!(
+        report.files,
+        vec![
+            FileCount {
+                path: root().join("web.rules"),
+                patterns: 2,
+            },
+            FileCount {
+                path: root().join("mail.rules"),
+                patterns: 2,
+            },
+        ]
+    );
+    assert_eq!(report.compile_time, None);
+}
+
+#[test]
+fn test_build_with_warnings() {
+    let rules = RuleSetLoader::new()
+        .file(root().join("good.rules"))
+        .default_flags(PatternFlags::CASELESS)
+        .validate(POLICY)
+        .build::<Block>()
+        .unwrap();
+
+    assert_eq!(rules.patterns.len(), 2);
+    assert_eq!(rules.patterns[0].flags, PatternFlags::CASELESS);
+    assert!(rules.report.is_ok());
+    assert_eq!(
+        rules.report.warnings,
+        vec![at("good.rules", 2, "match width is unbounded")]
+    );
+    assert!(rules.report.compile_time.is_some());
+
+    let mut s = rules.database.alloc_scratch().unwrap();
+    let mut matches = vec![];
+
+    rules
+        .database
+        .scan("FOO barbar", &mut s, |id, _, _, _| {
+            matches.push(id);
+            Matching::Continue
+        })
+        .unwrap();
+
+    assert_eq!(matches, vec![1, 10, 10, 10]);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serialize_report() {
+    use serde_json::json;
+
+    let report = RuleSetLoader::new()
+        .file(root().join("web.rules"))
+        .validate(POLICY)
+        .build::<Block>()
+        .err()
+        .unwrap();
+    let path = root().join("web.rules").to_string_lossy().into_owned();
+
+    assert_eq!(
+        serde_json::to_value(report).unwrap(),
+        json!({
+            "errors": [{"path": path, "line": 4, "message": "invalid pattern flag: Z"}],
+            "warnings": [{"path": path, "line": 3, "message": "match width is unbounded"}],
+            "files": [{"path": path, "patterns": 2}],
+            "compile_time": null,
+        })
+    );
+}
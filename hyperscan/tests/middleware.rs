@@ -0,0 +1,185 @@
+//! Stream the HTTP bodies through the scanner, with a match spanning two frames.
+#![cfg(feature = "tower")]
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use http::{HeaderMap, Request, Response, StatusCode};
+use http_body::{Body, Frame};
+use http_body_util::{BodyExt, Full};
+use hyperscan::{
+    middleware::{Policy, ScanBody, ScanBodyError, ScanLayer, Verdict},
+    prelude::*,
+    Match,
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+struct Frames(VecDeque<Frame<Bytes>>);
+
+impl Body for Frames {
+    type Data = Bytes;
+    type Error = Infallible;
+
+    fn poll_frame(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Result<Frame<Bytes>, Infallible>>> {
+        Poll::Ready(self.0.pop_front().map(Ok))
+    }
+}
+
+fn trailers() -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+
+    trailers.insert("x-checksum", "42".parse().unwrap());
+    trailers
+}
+
+fn frames() -> Frames {
+    Frames(
+        vec![
+            Frame::data(Bytes::from("hello wo")),
+            Frame::data(Bytes::from("rld!")),
+            Frame::trailers(trailers()),
+        ]
+        .into(),
+    )
+}
+
+fn scan<P: Policy>(policy: P) -> ScanBody<Frames, P> {
+    let db: StreamingDatabase = "1:/world/\n2:/!$/".parse::<Patterns>().unwrap().build().unwrap();
+    let pool = ScratchPool::new(&db).unwrap();
+
+    ScanBody::new(frames(), Arc::new(db), Arc::new(pool), policy)
+}
+
+#[test]
+fn test_continue() {
+    let matches = Arc::new(Mutex::new(vec![]));
+    let body = scan({
+        let matches = matches.clone();
+
+        move |m: Match| {
+            matches.lock().unwrap().push((m.id, m.to));
+
+            Verdict::Continue
+        }
+    });
+
+    let collected = tokio_test::block_on(body.collect()).unwrap();
+
+    assert_eq!(*matches.lock().unwrap(), vec![(1, 11), (2, 12)]);
+    assert_eq!(collected.trailers(), Some(&trailers()));
+    assert_eq!(collected.to_bytes(), "hello world!");
+}
+
+#[test]
+fn test_strip() {
+    let body = scan(|m: Match| if m.id == 1 { Verdict::Strip } else { Verdict::Continue });
+
+    let collected = tokio_test::block_on(body.collect()).unwrap();
+
+    assert_eq!(collected.trailers(), None);
+    assert_eq!(collected.to_bytes(), "hello wo");
+}
+
+#[test]
+fn test_abort() {
+    let body = scan(|m: Match| {
+        if m.id == 1 {
+            Verdict::Abort(StatusCode::FORBIDDEN)
+        } else {
+            Verdict::Continue
+        }
+    });
+
+    let err = tokio_test::block_on(body.collect()).unwrap_err();
+
+    assert_eq!(err.status(), Some(StatusCode::FORBIDDEN));
+
+    // the match at the end of data is reported when the stream is closed, before the trailers
+    let mut body = scan(|m: Match| {
+        if m.id == 2 {
+            Verdict::Abort(StatusCode::BAD_REQUEST)
+        } else {
+            Verdict::Continue
+        }
+    });
+
+    tokio_test::block_on(async {
+        assert_eq!(body.frame().await.unwrap().unwrap().into_data().unwrap(), "hello wo");
+        assert_eq!(body.frame().await.unwrap().unwrap().into_data().unwrap(), "rld!");
+        assert!(matches!(
+            body.frame().await,
+            Some(Err(ScanBodyError::Aborted(StatusCode::BAD_REQUEST)))
+        ));
+        assert!(body.frame().await.is_none());
+    });
+}
+
+#[test]
+fn test_early_drop() {
+    let mut body = scan(|_: Match| Verdict::Continue);
+
+    tokio_test::block_on(async {
+        assert_eq!(body.frame().await.unwrap().unwrap().into_data().unwrap(), "hello wo");
+    });
+
+    drop(body);
+}
+
+/// Respond with the request body, or the status of the aborted request body.
+#[derive(Clone)]
+struct Echo;
+
+impl<B> Service<Request<B>> for Echo
+where
+    B: Body<Data = Bytes, Error = ScanBodyError<Infallible>> + Send + 'static,
+{
+    type Response = Response<Full<Bytes>>;
+    type Error = Infallible;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Infallible>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: Request<B>) -> Self::Future {
+        Box::pin(async move {
+            Ok(match req.into_body().collect().await {
+                Ok(collected) => Response::new(Full::new(collected.to_bytes())),
+                Err(err) => {
+                    let mut res = Response::new(Full::default());
+
+                    *res.status_mut() = err.status().unwrap();
+                    res
+                }
+            })
+        })
+    }
+}
+
+#[test]
+fn test_layer() {
+    let db: StreamingDatabase = pattern! {"world"}.build().unwrap();
+    let layer = ScanLayer::new(db, |_: Match| Verdict::Abort(StatusCode::FORBIDDEN)).unwrap();
+
+    let mut service = layer.clone().responses(false).layer(Echo);
+    let res = tokio_test::block_on(service.call(Request::new(frames()))).unwrap();
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    // scan the responses only
+    let mut service = layer.requests(false).layer(Echo);
+    let res = tokio_test::block_on(service.call(Request::new(Frames(vec![Frame::data(Bytes::from("hello"))].into()))))
+        .unwrap();
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        tokio_test::block_on(res.into_body().collect()).unwrap().to_bytes(),
+        "hello"
+    );
+}
@@ -0,0 +1,39 @@
+//! Scan with a serialized database in a `#![no_std]` crate.
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+use hyperscan::{metrics::ScanCounters, BlockDatabase, Matching, Result, Serialized, StreamingDatabase};
+
+/// Deserialize a block database, and collect the matches of the data.
+pub fn scan_block(serialized: &[u8], data: &[u8]) -> Result<Vec<(u32, u64)>> {
+    let db: BlockDatabase = serialized.deserialize()?;
+    let mut s = db.alloc_scratch()?;
+    let mut matches = Vec::new();
+
+    db.scan(data, &mut s, |id, _, to, _| {
+        matches.push((id, to));
+
+        Matching::Continue
+    })?;
+
+    Ok(matches)
+}
+
+/// Deserialize a streaming database, and count the matches of the chunks.
+pub fn scan_stream(serialized: &[u8], chunks: &[&[u8]]) -> Result<u64> {
+    let db: StreamingDatabase = serialized.deserialize()?;
+    let mut s = db.alloc_scratch()?;
+    let st = db.open_stream()?;
+    let counters = ScanCounters::new();
+
+    for chunk in chunks {
+        st.metered(&counters).scan(chunk, &mut s, ())?;
+    }
+
+    st.close(&mut s, ())?;
+
+    Ok(counters.snapshot().matches)
+}
@@ -0,0 +1,66 @@
+//! Load the `hsbench` style pattern file under `tests/fixtures`.
+#![cfg(all(feature = "compile", feature = "v5", hs_version_gte_5_1))]
+
+use std::path::{Path, PathBuf};
+
+use hyperscan::{prelude::*, Error, PatternFileOptions, PatternFlags};
+
+fn fixture() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/hsbench.patterns")
+}
+
+#[test]
+fn test_load_pattern_file() {
+    let patterns = Patterns::from_file(fixture()).unwrap();
+
+    assert_eq!(patterns.len(), 289);
+    assert_eq!(patterns[0].id, Some(2));
+    assert_eq!(patterns[0].flags, PatternFlags::CASELESS | PatternFlags::MULTILINE);
+    assert_eq!(patterns[4].ext.min_offset(), Some(44));
+    assert_eq!(patterns.last().unwrap().flags, PatternFlags::COMBINATION);
+
+    let db: BlockDatabase = patterns.build().unwrap();
+    let mut s = db.alloc_scratch().unwrap();
+    let mut matches = vec![];
+
+    db.scan("a needle in a haystack", &mut s, |id, _, _, _| {
+        matches.push(id);
+        Matching::Continue
+    })
+    .unwrap();
+
+    assert_eq!(matches, vec![303]);
+}
+
+#[test]
+fn test_duplicate_ids() {
+    let rules = "1:/foo/\n# 1:/bar/\n2:/bar/\n\n1:/baz/i\n";
+
+    assert_eq!(
+        Patterns::from_reader(rules.as_bytes()).unwrap_err(),
+        Error::InvalidLine {
+            line: 5,
+            text: "1:/baz/i".to_owned(),
+            reason: "duplicate pattern id 1, first defined at line 1".to_owned(),
+        }
+    );
+
+    let options = PatternFileOptions {
+        allow_duplicate_ids: true,
+    };
+    let patterns = Patterns::from_reader_with(rules.as_bytes(), options).unwrap();
+
+    assert_eq!(patterns.len(), 3);
+    assert_eq!(patterns[2].id, Some(1));
+}
+
+#[test]
+fn test_invalid_line() {
+    let err = Patterns::from_reader("1:/foo/\n  2:/ba(r/Z  \n".as_bytes()).unwrap_err();
+
+    assert_eq!(err.to_string(), "line 2: invalid pattern flag: Z, `2:/ba(r/Z`");
+    assert!(matches!(
+        Patterns::from_file(fixture().with_extension("missing")),
+        Err(Error::Io(_))
+    ));
+}
@@ -0,0 +1,28 @@
+//! Link a runtime-only configuration against `libhs_runtime`, which must not reference any compile time symbols.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+#[ignore = "build the crate again with cargo, run with `--ignored`"]
+fn test_runtime_only_link() {
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    let target_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("runtime-only");
+
+    let status = Command::new(cargo)
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .env("CARGO_TARGET_DIR", target_dir)
+        .args([
+            "build",
+            "--example",
+            "runtime_only",
+            "--no-default-features",
+            "--features",
+            "runtime,latest,static",
+        ])
+        .status()
+        .expect("run cargo");
+
+    assert!(status.success(), "runtime-only build failed with {}", status);
+}
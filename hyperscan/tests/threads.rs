@@ -0,0 +1,46 @@
+//! Share a database between threads, each thread scans with its own scratch space.
+#![cfg(feature = "compile")]
+
+use std::sync::Arc;
+use std::thread;
+
+use hyperscan::prelude::*;
+
+const THREADS: usize = 8;
+
+#[test]
+fn test_scan_from_threads() {
+    let patterns: Patterns = "0:/foo/L\n1:/ba+r/L".parse().unwrap();
+    let db: Arc<BlockDatabase> = Arc::new(patterns.build().unwrap());
+
+    let handles = (0..THREADS)
+        .map(|i| {
+            let db = Arc::clone(&db);
+
+            thread::spawn(move || {
+                let mut s = db.alloc_scratch().unwrap();
+                let data = format!("{} foo {} baar", "x".repeat(i), i);
+                let mut matches = vec![];
+
+                for _ in 0..100 {
+                    matches.clear();
+
+                    db.scan(&data, &mut s, |id, from, to, _| {
+                        matches.push((id, from, to));
+                        Matching::Continue
+                    })
+                    .unwrap();
+                }
+
+                (i, matches)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    for handle in handles {
+        let (i, matches) = handle.join().unwrap();
+        let i = i as u64;
+
+        assert_eq!(matches, vec![(0, i + 1, i + 4), (1, i + 7, i + 11)]);
+    }
+}
@@ -0,0 +1,115 @@
+//! Assert the spans and events reported with the `tracing` feature.
+#![cfg(feature = "tracing")]
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+use hyperscan::prelude::*;
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Subscriber,
+};
+use tracing_subscriber::{layer::Context, prelude::*, registry::LookupSpan, Layer};
+
+type Fields = BTreeMap<String, String>;
+
+#[derive(Default)]
+struct Recorder(Fields);
+
+impl Visit for Recorder {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name().to_owned(), format!("{:?}", value));
+    }
+}
+
+#[derive(Clone, Default)]
+struct TestLayer {
+    spans: Arc<Mutex<Vec<(String, Fields)>>>,
+    events: Arc<Mutex<Vec<Fields>>>,
+}
+
+impl<S> Layer<S> for TestLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, _id: &span::Id, _ctx: Context<'_, S>) {
+        let mut recorder = Recorder::default();
+
+        attrs.record(&mut recorder);
+
+        self.spans
+            .lock()
+            .unwrap()
+            .push((attrs.metadata().name().to_owned(), recorder.0));
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut recorder = Recorder::default();
+
+        event.record(&mut recorder);
+
+        if event.metadata().target() == "hyperscan" {
+            self.events.lock().unwrap().push(recorder.0);
+        }
+    }
+}
+
+#[test]
+fn test_compile_and_scan_events() {
+    let layer = TestLayer::default();
+    let subscriber = tracing_subscriber::registry().with(layer.clone());
+
+    tracing::subscriber::with_default(subscriber, || {
+        let patterns: Patterns = "1:/foo/\n2:/bar/".parse().unwrap();
+        let db: BlockDatabase = patterns.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+
+        db.scan("foo bar foo", &mut s, |_, _, _, _| Matching::Continue).unwrap();
+
+        let db: StreamingDatabase = pattern! {"test"}.build().unwrap();
+        let mut s = db.alloc_scratch().unwrap();
+        let st = db.open_stream().unwrap();
+
+        st.scan("foo te", &mut s, ()).unwrap();
+        st.scan("st", &mut s, ()).unwrap();
+        st.close(&mut s, ()).unwrap();
+    });
+
+    let spans = layer.spans.lock().unwrap();
+
+    assert_eq!(spans.len(), 2);
+    assert_eq!(spans[0].0, "compile");
+    assert_eq!(spans[0].1["patterns"], "2");
+    assert_eq!(spans[0].1["mode"], "\"Block\"");
+    assert_eq!(spans[1].1["patterns"], "1");
+
+    let events = layer.events.lock().unwrap();
+    let messages = events.iter().map(|e| e["message"].as_str()).collect::<Vec<_>>();
+
+    assert_eq!(
+        messages,
+        vec![
+            "compiled",
+            "scan",
+            "compiled",
+            "stream opened",
+            "scan",
+            "scan",
+            "stream closed"
+        ]
+    );
+
+    assert!(events[0]["size"].parse::<usize>().unwrap() > 0);
+
+    assert_eq!(events[1]["mode"], "\"block\"");
+    assert_eq!(events[1]["bytes"], "11");
+    assert_eq!(events[1]["matches"], "3");
+    assert_eq!(events[1]["outcome"], "\"ok\"");
+
+    assert_eq!(events[4]["matches"], "0");
+    assert_eq!(events[5]["mode"], "\"stream\"");
+    assert_eq!(events[5]["bytes"], "2");
+    assert_eq!(events[5]["matches"], "1");
+    assert_eq!(events[6]["matches"], "0");
+}